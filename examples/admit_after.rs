@@ -0,0 +1,43 @@
+#[cfg(feature = "full")]
+use memoize::{memoize, FrequencySketch};
+
+#[cfg(feature = "full")]
+static mut CALLS: usize = 0;
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 2, AdmitAfter: 2)]
+fn thumbnail(id: u64) -> String {
+    unsafe { CALLS += 1 };
+    format!("thumbnail-{}", id)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // Recurring keys are cached on their second request...
+    assert_eq!(thumbnail(1), "thumbnail-1");
+    assert_eq!(memoized_size_thumbnail(), 0);
+    assert_eq!(thumbnail(1), "thumbnail-1");
+    assert_eq!(memoized_size_thumbnail(), 1);
+    assert_eq!(thumbnail(1), "thumbnail-1");
+    assert_eq!(unsafe { CALLS }, 2);
+
+    // ...while a long tail of one-off keys doesn't evict them.
+    for id in 100..200 {
+        thumbnail(id);
+    }
+    assert_eq!(memoized_size_thumbnail(), 1);
+    assert_eq!(thumbnail(1), "thumbnail-1");
+    assert_eq!(unsafe { CALLS }, 102);
+
+    let mut sketch = FrequencySketch::new();
+    assert_eq!(sketch.record("a"), 1);
+    assert_eq!(sketch.record("a"), 2);
+    assert_eq!(sketch.estimate("a"), 2);
+    sketch.clear();
+    assert_eq!(sketch.estimate("a"), 0);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+static mut CALLS: u32 = 0;
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 4, Eviction: ARC)]
+fn triple(n: u32) -> u32 {
+    unsafe { CALLS += 1 };
+    3 * n
+}
+
+#[cfg(feature = "full")]
+fn calls() -> u32 {
+    unsafe { CALLS }
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // A hot-spot phase...
+    for _ in 0..3 {
+        assert_eq!(triple(1), 3);
+        assert_eq!(triple(2), 6);
+    }
+    assert_eq!(calls(), 2);
+
+    // ...followed by a scan, which only replaces entries that were used once.
+    for n in 100..120 {
+        assert_eq!(triple(n), 3 * n);
+    }
+    assert_eq!(calls(), 22);
+    assert_eq!(triple(1), 3);
+    assert_eq!(triple(2), 6);
+    assert_eq!(calls(), 22);
+    assert_eq!(memoized_size_triple(), 4);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
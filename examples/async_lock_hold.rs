@@ -0,0 +1,57 @@
+#[cfg(feature = "tokio")]
+use memoize::memoize;
+#[cfg(feature = "tokio")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+#[memoize(SharedCache)]
+async fn slow(n: u64) -> u64 {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    n * 2
+}
+
+#[cfg(feature = "tokio")]
+#[memoize(SharedCache)]
+async fn double(n: u64) -> u64 {
+    n * 2
+}
+
+// Runs on a single thread, so that a task holding the store while it waits would stall the others.
+#[cfg(feature = "tokio")]
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    assert_eq!(slow(1).await, 2);
+
+    // While a miss awaits the function, the store is free: hits are served right away.
+    let miss = tokio::spawn(slow(2));
+    tokio::task::yield_now().await;
+    let started = std::time::Instant::now();
+    assert_eq!(slow(1).await, 2);
+    assert!(started.elapsed() < Duration::from_millis(50));
+    assert_eq!(memoized_size_slow().await, 1);
+    assert_eq!(miss.await.unwrap(), 4);
+    assert_eq!(memoized_size_slow().await, 2);
+
+    // Bulk lookups lock the store for a bounded number of keys at a time, and let other tasks run in
+    // between.
+    static TICKS: AtomicUsize = AtomicUsize::new(0);
+    let ticker = tokio::spawn(async {
+        loop {
+            TICKS.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+        }
+    });
+    let values = memoized_get_or_compute_many_double(0..1000).await;
+    assert_eq!(values.len(), 1000);
+    assert_eq!(values[999], 1998);
+    assert!(TICKS.load(Ordering::SeqCst) >= 1000 / memoize::MAINTENANCE_CHUNK);
+    ticker.abort();
+    assert_eq!(memoized_size_double().await, 1000);
+}
+
+#[cfg(not(feature = "tokio"))]
+fn main() {
+    println!("Use the \"tokio\" feature to execute this example");
+}
@@ -0,0 +1,35 @@
+use memoize::memoize;
+use std::time::Duration;
+
+static mut CALLS: usize = 0;
+
+#[memoize(SharedCache)]
+async fn fetch(id: u64) -> String {
+    unsafe { CALLS += 1 };
+    smol::Timer::after(Duration::from_millis(10)).await;
+    format!("user-{}", id)
+}
+
+fn main() {
+    smol::block_on(async {
+        let tasks: Vec<_> = (0..4).map(|id| smol::spawn(fetch(id % 2))).collect();
+        for (id, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await, format!("user-{}", id % 2));
+        }
+        assert_eq!(fetch(0).await, "user-0");
+        assert!(unsafe { CALLS } <= 4);
+
+        #[cfg(any(feature = "tokio", feature = "async"))]
+        {
+            assert_eq!(memoized_size_fetch().await, 2);
+            memoized_flush_fetch().await;
+            assert_eq!(memoized_size_fetch().await, 0);
+        }
+        #[cfg(not(any(feature = "tokio", feature = "async")))]
+        {
+            assert_eq!(memoized_size_fetch(), 2);
+            memoized_flush_fetch();
+            assert_eq!(memoized_size_fetch(), 0);
+        }
+    });
+}
@@ -0,0 +1,51 @@
+use memoize::memoize;
+use std::time::Duration;
+
+static mut CALLS: usize = 0;
+
+#[memoize(SharedCache)]
+async fn fetch(id: u64) -> String {
+    unsafe { CALLS += 1 };
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    format!("user-{}", id)
+}
+
+#[memoize(ThreadLocal)]
+async fn local(n: u64) -> u64 {
+    tokio::task::yield_now().await;
+    n * 2
+}
+
+#[tokio::main]
+async fn main() {
+    let tasks: Vec<_> = (0..4)
+        .map(|_| tokio::spawn(async { fetch(1).await }))
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), "user-1");
+    }
+    assert_eq!(fetch(1).await, "user-1");
+    assert!(unsafe { CALLS } <= 4);
+
+    // With the `tokio` (or `async`) feature, helpers that lock the shared store are async as well.
+    #[cfg(any(feature = "tokio", feature = "async"))]
+    {
+        assert_eq!(memoized_size_fetch().await, 1);
+        memoized_flush_fetch().await;
+        assert_eq!(memoized_size_fetch().await, 0);
+    }
+    #[cfg(not(any(feature = "tokio", feature = "async")))]
+    {
+        assert_eq!(memoized_size_fetch(), 1);
+        memoized_flush_fetch();
+        assert_eq!(memoized_size_fetch(), 0);
+    }
+
+    assert_eq!(local(21).await, 42);
+    assert_eq!(local(21).await, 42);
+    assert_eq!(memoized_size_local(), 1);
+    assert_eq!(
+        memoized_get_or_compute_many_local(vec![1, 21]).await,
+        vec![2, 42]
+    );
+}
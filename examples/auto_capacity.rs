@@ -0,0 +1,48 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+#[memoize(AutoCapacity: 16..=1024)]
+fn page(n: u32) -> u32 {
+    n * 2
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 512, AutoCapacity: 32..=512)]
+fn rare(n: u32) -> u32 {
+    n + 1
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // A working set of 300 keys thrashes a cache of 16 entries, which grows until it fits.
+    for _ in 0..20 {
+        for n in 0..300 {
+            page(n);
+        }
+    }
+    assert!(memoized_size_page() >= 300);
+    let before = memoized_stats_page();
+    for n in 0..300 {
+        page(n);
+    }
+    assert_eq!(memoized_stats_page().hits - before.hits, 300);
+
+    // A handful of hot keys only need a fraction of the initial capacity, which shrinks (on the
+    // next miss), so that fewer than the 8 + 450 keys requested fit afterwards.
+    for _ in 0..2000 {
+        for n in 0..8 {
+            rare(n);
+        }
+    }
+    assert_eq!(memoized_size_rare(), 8);
+    for n in 100..550 {
+        rare(n);
+    }
+    assert!(memoized_size_rare() < 458);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,35 @@
+use memoize::memoize;
+use std::cell::Cell;
+use std::rc::Rc;
+
+trait Logger {
+    fn log(&self, message: &str);
+}
+
+struct CountingLogger(Cell<usize>);
+
+impl Logger for CountingLogger {
+    fn log(&self, _message: &str) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+// Neither logger is part of the key.
+#[memoize(AutoIgnoreDyn)]
+fn square(x: u64, logger: &dyn Logger, fallback: Rc<dyn Logger>) -> u64 {
+    logger.log("computing");
+    fallback.log("computing");
+    x * x
+}
+
+fn main() {
+    let logger = CountingLogger(Cell::new(0));
+    let fallback = Rc::new(CountingLogger(Cell::new(0)));
+
+    assert_eq!(square(3, &logger, fallback.clone()), 9);
+    assert_eq!(square(3, &logger, fallback.clone()), 9);
+    assert_eq!(square(4, &logger, fallback.clone()), 16);
+    assert_eq!(logger.0.get(), 2);
+    assert_eq!(fallback.0.get(), 2);
+    assert_eq!(memoized_size_square(), 2);
+}
@@ -0,0 +1,50 @@
+#[cfg(feature = "tokio")]
+use memoize::memoize;
+#[cfg(feature = "tokio")]
+use std::sync::Mutex;
+
+// The batches of keys requested from the backend.
+#[cfg(feature = "tokio")]
+static BATCHES: Mutex<Vec<Vec<u32>>> = Mutex::new(Vec::new());
+
+// One round trip for many users, e.g. `SELECT ... WHERE id IN (...)`.
+#[cfg(feature = "tokio")]
+async fn load_users(ids: Vec<u32>) -> Vec<String> {
+    BATCHES.lock().unwrap().push(ids.clone());
+    ids.iter().map(|id| format!("user {}", id)).collect()
+}
+
+#[cfg(feature = "tokio")]
+#[memoize(SharedCache, BatchWith: load_users, BatchWindow: std::time::Duration::from_millis(20))]
+async fn user(id: u32) -> String {
+    unreachable!("user {} is loaded by load_users", id)
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::main]
+async fn main() {
+    // Concurrent misses, e.g. while resolving a list of posts and their authors, are loaded in one
+    // batch; each key is only loaded once.
+    let authors = futures::future::join_all(vec![3, 1, 4, 1, 5].into_iter().map(user)).await;
+    assert_eq!(authors, ["user 3", "user 1", "user 4", "user 1", "user 5"]);
+    assert_eq!(*BATCHES.lock().unwrap(), vec![vec![3, 1, 4, 5]]);
+
+    // The values are cached one by one.
+    assert_eq!(memoized_size_user().await, 4);
+    assert_eq!(user(4).await, "user 4");
+    let more = futures::future::join_all(vec![4, 9, 2].into_iter().map(user)).await;
+    assert_eq!(more, ["user 4", "user 9", "user 2"]);
+    assert_eq!(BATCHES.lock().unwrap()[1], vec![9, 2]);
+
+    // Tasks on other threads join the batch as well.
+    let tasks: Vec<_> = (10..14).map(|id| tokio::spawn(user(id))).collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(BATCHES.lock().unwrap().len(), 3);
+}
+
+#[cfg(not(feature = "tokio"))]
+fn main() {
+    println!("Use the \"tokio\" feature to execute this example");
+}
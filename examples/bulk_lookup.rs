@@ -0,0 +1,28 @@
+use memoize::memoize;
+
+#[memoize(SharedCache)]
+fn render(id: u32) -> String {
+    format!("<li>{}</li>", id)
+}
+
+fn main() {
+    assert_eq!(render(1), "<li>1</li>");
+    assert_eq!(render(3), "<li>3</li>");
+
+    // One lock acquisition for the whole page.
+    assert_eq!(
+        memoized_get_many_render(1..=3),
+        vec![
+            Some("<li>1</li>".to_string()),
+            None,
+            Some("<li>3</li>".to_string())
+        ]
+    );
+
+    // The same, but missing items are rendered (and cached) as well.
+    assert_eq!(
+        memoized_get_or_compute_many_render(vec![1, 2, 3]),
+        vec!["<li>1</li>", "<li>2</li>", "<li>3</li>"]
+    );
+    assert_eq!(memoized_size_render(), 3);
+}
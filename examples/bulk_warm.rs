@@ -0,0 +1,26 @@
+use memoize::memoize;
+
+#[memoize(SharedCache)]
+fn describe(id: u32, verbose: bool) -> String {
+    if verbose {
+        format!("item number {}", id)
+    } else {
+        format!("#{}", id)
+    }
+}
+
+#[memoize]
+fn double(n: u64) -> u64 {
+    n * 2
+}
+
+fn main() {
+    // E.g. the result of a database query at boot.
+    let hot_items = vec![(1, true), (2, false), (3, true)];
+    memoized_warm_describe(hot_items);
+    assert_eq!(memoized_size_describe(), 3);
+    assert_eq!(describe(2, false), "#2");
+
+    memoized_warm_double(0..100);
+    assert_eq!(memoized_size_double(), 100);
+}
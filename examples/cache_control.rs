@@ -0,0 +1,90 @@
+#[cfg(feature = "full")]
+use memoize::{memoize, CacheControl};
+
+#[cfg(feature = "full")]
+#[memoize]
+fn length(text: String) -> usize {
+    text.len()
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 100)]
+fn product(a: u64, b: u64) -> u64 {
+    a * b
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache)]
+fn words(text: &'static str) -> Vec<&'static str> {
+    text.split(' ').collect()
+}
+
+#[cfg(all(feature = "full", feature = "tokio"))]
+#[memoize(SharedCache)]
+async fn fetch(id: u32) -> String {
+    format!("resource {}", id)
+}
+
+// A generic admin report, over caches of all kinds of keys and values.
+#[cfg(feature = "full")]
+fn report(caches: &[Box<dyn CacheControl>]) -> Vec<(&'static str, usize)> {
+    caches
+        .iter()
+        .map(|cache| (cache.name(), cache.size()))
+        .collect()
+}
+
+#[cfg(feature = "full")]
+fn caches() -> Vec<Box<dyn CacheControl>> {
+    #[allow(unused_mut)]
+    let mut caches: Vec<Box<dyn CacheControl>> = vec![
+        Box::new(memoized_cache_handle_length()),
+        Box::new(memoized_cache_handle_product()),
+        Box::new(memoized_cache_handle_words()),
+    ];
+    #[cfg(feature = "tokio")]
+    caches.push(Box::new(memoized_cache_handle_fetch()));
+    caches
+}
+
+#[cfg(all(feature = "full", feature = "tokio"))]
+#[tokio::main]
+async fn main() {
+    fetch(1).await;
+    fetch(2).await;
+    run();
+    // Handles can be used from async code as well.
+    let caches = caches();
+    assert_eq!(caches[3].size(), 2);
+    caches[3].flush();
+    assert_eq!(memoized_size_fetch().await, 0);
+}
+
+#[cfg(all(feature = "full", not(feature = "tokio")))]
+fn main() {
+    run();
+}
+
+#[cfg(feature = "full")]
+fn run() {
+    length("memoize".to_string());
+    product(6, 7);
+    product(2, 3);
+    words("a b c");
+
+    let caches = caches();
+    assert_eq!(
+        report(&caches)[..3],
+        [("length", 1), ("product", 2), ("words", 1)]
+    );
+    for cache in &caches[..3] {
+        cache.flush();
+    }
+    assert!(report(&caches)[..3].iter().all(|(_, size)| *size == 0));
+    assert_eq!(caches[1].stats().misses, 2);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
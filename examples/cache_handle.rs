@@ -0,0 +1,80 @@
+#[cfg(feature = "full")]
+use memoize::{memoize, CacheControl};
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache)]
+fn greeting(name: String) -> String {
+    format!("Hello, {}!", name)
+}
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 10)]
+fn area(width: u32, height: u32) -> u32 {
+    width * height
+}
+
+// Administrative code only knows about `CacheControl`.
+#[cfg(feature = "full")]
+struct AdminState<C: CacheControl> {
+    greetings: C,
+}
+
+#[cfg(feature = "full")]
+fn report(cache: &impl CacheControl) -> String {
+    let stats = cache.stats();
+    format!(
+        "{}: {} entries, {} hits, {} misses",
+        cache.name(),
+        cache.size(),
+        stats.hits,
+        stats.misses
+    )
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    let state = AdminState {
+        greetings: memoized_cache_handle_greeting(),
+    };
+
+    greeting("Alice".to_string());
+    greeting("Bob".to_string());
+    greeting("Alice".to_string());
+    assert_eq!(
+        report(&state.greetings),
+        "greeting: 2 entries, 1 hits, 2 misses"
+    );
+
+    // With the `serde` feature, single entries can be removed by their serialized key.
+    #[cfg(feature = "serde")]
+    {
+        let key = bincode::serialize("Bob").unwrap();
+        assert!(state.greetings.remove_serialized(&key));
+        assert!(!state.greetings.remove_serialized(&key));
+        assert_eq!(state.greetings.size(), 1);
+    }
+
+    state.greetings.flush();
+    assert_eq!(state.greetings.size(), 0);
+
+    // Keys of several arguments are serialized as a tuple.
+    area(2, 3);
+    area(4, 5);
+    let areas = memoized_cache_handle_area();
+    #[cfg(feature = "serde")]
+    {
+        assert!(areas.remove_serialized(&bincode::serialize(&(2u32, 3u32)).unwrap()));
+        assert_eq!(areas.size(), 1);
+    }
+    // Otherwise, nothing is removed.
+    #[cfg(not(feature = "serde"))]
+    {
+        assert!(!areas.remove_serialized(&[2, 0, 0, 0, 3, 0, 0, 0]));
+        assert_eq!(areas.size(), 2);
+    }
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
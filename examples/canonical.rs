@@ -0,0 +1,53 @@
+use memoize::memoize;
+use std::collections::{HashMap, HashSet};
+
+static mut CALLS: usize = 0;
+
+#[memoize(Canonical: tags)]
+fn search(query: String, tags: &HashSet<String>) -> usize {
+    unsafe { CALLS += 1 };
+    query.len() + tags.len()
+}
+
+#[memoize(SharedCache, Canonical: weights)]
+fn total(weights: HashMap<String, u32>) -> u32 {
+    unsafe { CALLS += 1 };
+    weights.values().sum()
+}
+
+#[memoize(Canonical: ids)]
+fn count_distinct(ids: &[u32]) -> usize {
+    unsafe { CALLS += 1 };
+    ids.iter().collect::<HashSet<_>>().len()
+}
+
+fn main() {
+    let a: HashSet<String> = ["red", "green", "blue"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    // Built in a different order, and with a different capacity.
+    let mut b = HashSet::with_capacity(100);
+    for tag in &["blue", "red", "green"] {
+        b.insert(tag.to_string());
+    }
+    assert_eq!(search("cars".to_string(), &a), 7);
+    assert_eq!(search("cars".to_string(), &b), 7);
+    assert_eq!(unsafe { CALLS }, 1);
+
+    let weights: HashMap<String, u32> = vec![("a".to_string(), 1), ("b".to_string(), 2)]
+        .into_iter()
+        .collect();
+    let reversed: HashMap<String, u32> = vec![("b".to_string(), 2), ("a".to_string(), 1)]
+        .into_iter()
+        .collect();
+    assert_eq!(total(weights), 3);
+    assert_eq!(total(reversed), 3);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_total(), 1);
+
+    assert_eq!(count_distinct(&[3, 1, 3]), 2);
+    assert_eq!(count_distinct(&[1, 3, 3]), 2);
+    assert_eq!(count_distinct(&[1, 3]), 2);
+    assert_eq!(unsafe { CALLS }, 4);
+}
@@ -0,0 +1,64 @@
+use memoize::memoize;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// Whether the upstream service is down, and how often it was called.
+static DOWN: AtomicBool = AtomicBool::new(true);
+static CALLS: AtomicU32 = AtomicU32::new(0);
+
+fn upstream(id: u32) -> Result<String, String> {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    if DOWN.load(Ordering::SeqCst) {
+        Err(format!(
+            "upstream unavailable (request {})",
+            CALLS.load(Ordering::SeqCst)
+        ))
+    } else {
+        Ok(format!("item {}", id))
+    }
+}
+
+#[memoize(SharedCache, CircuitBreaker: { failures: 3, cooldown: Duration::from_millis(50) })]
+fn item(id: u32) -> Result<String, String> {
+    upstream(id)
+}
+
+#[memoize(CircuitBreaker: { failures: 2, cooldown: Duration::from_millis(50), per_key: true })]
+fn detail(id: u32) -> Result<String, String> {
+    if id == 0 {
+        Err("no such item".to_string())
+    } else {
+        Ok(format!("detail {}", id))
+    }
+}
+
+fn main() {
+    // Three consecutive failures, for any keys, open the circuit.
+    for id in 0..3 {
+        assert!(item(id).is_err());
+    }
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+    // Short-circuited with the last error, without calling upstream.
+    assert_eq!(item(7), Err("upstream unavailable (request 3)".to_string()));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+    // After the cooldown, one call tries again; as it fails, the circuit opens again.
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(item(7), Err("upstream unavailable (request 4)".to_string()));
+    assert!(item(8).is_err());
+    assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+    // A success closes the circuit. Errors were not cached.
+    DOWN.store(false, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(item(7), Ok("item 7".to_string()));
+    assert_eq!(item(1), Ok("item 1".to_string()));
+    assert_eq!(memoized_size_item(), 2);
+
+    // Per key, a failing key doesn't affect the others.
+    assert!(detail(0).is_err());
+    assert!(detail(0).is_err());
+    assert_eq!(detail(1), Ok("detail 1".to_string()));
+    assert_eq!(detail(0), Err("no such item".to_string()));
+}
@@ -0,0 +1,64 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+static mut CALLS: u32 = 0;
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 4, Eviction: ClockPro)]
+fn double(n: u32) -> u32 {
+    unsafe { CALLS += 1 };
+    2 * n
+}
+
+// Hits of a shared cache only take the read lock of its store.
+#[cfg(feature = "full")]
+#[memoize(SharedCache, PublicStore, Capacity: 4, Eviction: ClockPro)]
+fn triple(n: u32) -> u32 {
+    3 * n
+}
+
+#[cfg(feature = "full")]
+fn calls() -> u32 {
+    unsafe { CALLS }
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // A hot spot...
+    for _ in 0..3 {
+        assert_eq!(double(1), 2);
+        assert_eq!(double(2), 4);
+    }
+    assert_eq!(calls(), 2);
+
+    // ...survives a scan over many keys, which would flush an LRU cache of the same capacity.
+    for n in 100..120 {
+        assert_eq!(double(n), 2 * n);
+    }
+    assert_eq!(calls(), 22);
+    assert_eq!(double(1), 2);
+    assert_eq!(double(2), 4);
+    assert_eq!(calls(), 22);
+    assert_eq!(memoized_size_double(), 4);
+
+    // While a reader holds the store, hits of other threads run concurrently (with a `Mutex`, they
+    // would wait for the reader).
+    assert_eq!(triple(1), 3);
+    let reader = MEMOIZED_MAPPING_TRIPLE.read().unwrap();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| assert_eq!(triple(1), 3));
+        }
+    });
+    assert_eq!(reader.len(), 1);
+    drop(reader);
+    // Misses take the write lock.
+    assert_eq!(triple(2), 6);
+    assert_eq!(memoized_size_triple(), 2);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
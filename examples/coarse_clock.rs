@@ -0,0 +1,52 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// A hot function, whose hits only read the coarse clock.
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 100, TimeToLive: Duration::from_millis(50), CoarseClock)]
+fn quote(symbol: u32) -> Instant {
+    let _ = symbol;
+    Instant::now()
+}
+
+#[cfg(feature = "full")]
+#[memoize(ExpireAfterAccess: Duration::from_millis(50), CoarseClock)]
+fn session(user: u32) -> Instant {
+    let _ = user;
+    Instant::now()
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // The coarse clock follows the precise one, within a few ticks.
+    let precise = Instant::now();
+    let coarse = memoize::coarse_now();
+    assert!(precise.saturating_duration_since(coarse) < 10 * memoize::COARSE_CLOCK_RESOLUTION);
+    thread::sleep(Duration::from_millis(20));
+    assert!(memoize::coarse_now() > coarse);
+
+    let first = quote(1);
+    for _ in 0..1000 {
+        assert_eq!(quote(1), first);
+    }
+    thread::sleep(Duration::from_millis(70));
+    assert_ne!(quote(1), first);
+
+    let first = session(1);
+    for _ in 0..3 {
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(session(1), first);
+    }
+    thread::sleep(Duration::from_millis(70));
+    assert_ne!(session(1), first);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
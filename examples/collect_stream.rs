@@ -0,0 +1,46 @@
+#[cfg(any(feature = "async", feature = "tokio"))]
+mod collect_stream {
+    use futures::stream::{self, Stream, StreamExt};
+    use memoize::memoize;
+
+    static mut FETCHES: usize = 0;
+
+    // Fetches all pages of a paginated listing.
+    #[memoize(CollectStream)]
+    pub fn list_pages(prefix: String) -> impl Stream<Item = String> {
+        unsafe { FETCHES += 1 };
+        stream::iter(1..=3).then(move |page| {
+            let prefix = prefix.clone();
+            async move { format!("{}/page-{}", prefix, page) }
+        })
+    }
+
+    pub async fn main() {
+        let expected = vec!["items/page-1", "items/page-2", "items/page-3"];
+        let pages: Vec<String> = list_pages("items".to_string()).collect().await;
+        assert_eq!(pages, expected);
+        assert_eq!(unsafe { FETCHES }, 1);
+
+        // Replayed from the cache.
+        let pages: Vec<String> = list_pages("items".to_string()).collect().await;
+        assert_eq!(pages, expected);
+        assert_eq!(unsafe { FETCHES }, 1);
+
+        // Streams which are not consumed completely are not cached.
+        let first: Vec<String> = list_pages("other".to_string()).take(1).collect().await;
+        assert_eq!(first, vec!["other/page-1"]);
+        let pages: Vec<String> = list_pages("other".to_string()).collect().await;
+        assert_eq!(pages.len(), 3);
+        assert_eq!(unsafe { FETCHES }, 3);
+    }
+}
+
+#[cfg(any(feature = "async", feature = "tokio"))]
+fn main() {
+    futures::executor::block_on(collect_stream::main());
+}
+
+#[cfg(not(any(feature = "async", feature = "tokio")))]
+fn main() {
+    println!("Use the \"async\" feature to execute this example");
+}
@@ -0,0 +1,58 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "full")]
+static LOGINS: AtomicU32 = AtomicU32::new(0);
+
+// Small values, whose entries would otherwise mostly consist of timestamps.
+#[cfg(feature = "full")]
+#[memoize(TimeToLive: Duration::from_millis(100), ExpireAfterAccess: Duration::from_millis(40), CompactEntries)]
+fn session(user: u32) -> u32 {
+    let _ = user;
+    LOGINS.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 10, TimeToLive: Duration::from_millis(50), CompactEntries, CoarseClock)]
+fn shared_session(user: u32) -> Instant {
+    let _ = user;
+    Instant::now()
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // An `Entry` tracking the access time holds two `Instant`s.
+    assert!(
+        std::mem::size_of::<memoize::CompactEntry<u32>>()
+            < std::mem::size_of::<memoize::Entry<u32, std::cell::Cell<Instant>>>() / 3
+    );
+
+    // Read often enough, but written too long ago.
+    assert_eq!(session(1), 1);
+    for _ in 0..4 {
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(session(1), 1);
+    }
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(session(1), 2);
+
+    // Not read for too long.
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(session(1), 3);
+
+    let first = shared_session(1);
+    assert_eq!(shared_session(1), first);
+    thread::sleep(Duration::from_millis(70));
+    assert_ne!(shared_session(1), first);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,31 @@
+#![cfg_attr(feature = "nightly", feature(core_intrinsics, const_eval_select))]
+#![cfg_attr(feature = "nightly", allow(internal_features))]
+
+#[cfg(feature = "nightly")]
+mod nightly {
+    use memoize::memoize;
+
+    #[memoize(KeepConst)]
+    pub const fn triangle(n: u64) -> u64 {
+        n * (n + 1) / 2
+    }
+
+    // Evaluated by the compiler, bypassing the cache.
+    pub const TEN: u64 = triangle(4);
+}
+
+#[cfg(feature = "nightly")]
+fn main() {
+    use nightly::*;
+
+    assert_eq!(TEN, 10);
+    assert_eq!(memoized_size_triangle(), 0);
+    assert_eq!(triangle(4), 10);
+    assert_eq!(triangle(100), 5050);
+    assert_eq!(memoized_size_triangle(), 2);
+}
+
+#[cfg(not(feature = "nightly"))]
+fn main() {
+    println!("Use the \"nightly\" feature (and a nightly compiler) to execute this example");
+}
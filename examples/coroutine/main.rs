@@ -0,0 +1,45 @@
+#![cfg_attr(feature = "nightly", feature(coroutines, coroutine_trait))]
+
+// The coroutines live in their own file, which isn't parsed without the `nightly` feature: the
+// `yield` syntax is rejected by stable compilers even in disabled code.
+#[cfg(feature = "nightly")]
+mod words;
+
+#[cfg(feature = "nightly")]
+fn main() {
+    use std::ops::{Coroutine, CoroutineState};
+    use std::pin::pin;
+    use words::*;
+
+    fn drain<C: Coroutine<Yield = String, Return = usize>>(coroutine: C) -> (Vec<String>, usize) {
+        let mut coroutine = pin!(coroutine);
+        let mut yielded = vec![];
+        loop {
+            match coroutine.as_mut().resume(()) {
+                CoroutineState::Yielded(word) => yielded.push(word),
+                CoroutineState::Complete(count) => return (yielded, count),
+            }
+        }
+    }
+
+    let expected = (
+        vec!["to".to_string(), "be".to_string(), "or".to_string()],
+        3,
+    );
+    assert_eq!(drain(words("to be or".to_string())), expected);
+    // Replayed from the cache.
+    assert_eq!(drain(words("to be or".to_string())), expected);
+    assert_eq!(unsafe { PARSES }, 1);
+    assert_eq!(memoized_size_words(), 1);
+
+    assert_eq!(drain(shared_words("to be or".to_string())), expected);
+    assert_eq!(drain(shared_words("to be or".to_string())), expected);
+    assert_eq!(drain(shared_words("not".to_string())).1, 1);
+    assert_eq!(unsafe { PARSES }, 2);
+    assert_eq!(memoized_size_shared_words(), 2);
+}
+
+#[cfg(not(feature = "nightly"))]
+fn main() {
+    println!("Use the \"nightly\" feature (and a nightly compiler) to execute this example");
+}
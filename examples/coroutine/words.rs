@@ -0,0 +1,24 @@
+use memoize::memoize;
+use std::ops::Coroutine;
+
+pub static mut PARSES: u32 = 0;
+
+// Yields the words of a sentence, and returns their count.
+#[memoize(CollectCoroutine)]
+pub fn words(sentence: String) -> impl Coroutine<Yield = String, Return = usize> {
+    unsafe { PARSES += 1 };
+    #[coroutine]
+    static move || {
+        let mut count = 0;
+        for word in sentence.split_whitespace() {
+            yield word.to_string();
+            count += 1;
+        }
+        count
+    }
+}
+
+#[memoize(SharedCache, Capacity: 10, CollectCoroutine)]
+pub fn shared_words(sentence: String) -> impl Coroutine<Yield = String, Return = usize> {
+    words(sentence)
+}
@@ -0,0 +1,27 @@
+use memoize::memoize;
+
+// Neither `Clone`, `Hash` nor `Eq`, but with a stable `Debug` representation.
+#[derive(Debug)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+static mut CALLS: usize = 0;
+
+// `DebugKeys` always emits a deprecation warning, as a reminder that it's a last resort.
+#[allow(deprecated)]
+#[memoize(DebugKeys)]
+fn distance(a: Point, b: Point) -> f32 {
+    unsafe { CALLS += 1 };
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn main() {
+    let origin = || Point { x: 0.0, y: 0.0 };
+    assert_eq!(distance(origin(), Point { x: 3.0, y: 4.0 }), 5.0);
+    assert_eq!(distance(origin(), Point { x: 3.0, y: 4.0 }), 5.0);
+    assert_eq!(distance(origin(), Point { x: 6.0, y: 8.0 }), 10.0);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_distance(), 2);
+}
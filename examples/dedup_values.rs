@@ -0,0 +1,35 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+// Many spellings normalize to the same (large) table.
+#[memoize(DedupValues)]
+fn normalize(input: String) -> Vec<String> {
+    unsafe { CALLS += 1 };
+    let word = input.trim().to_lowercase();
+    (0..1000).map(|i| format!("{}-{}", word, i)).collect()
+}
+
+#[memoize(SharedCache, DedupValues)]
+fn parity(n: u64) -> String {
+    if n.is_multiple_of(2) { "even" } else { "odd" }.to_string()
+}
+
+fn main() {
+    let a = normalize("Hello".to_string());
+    let b = normalize(" hello ".to_string());
+    let c = normalize("HELLO".to_string());
+    assert_eq!(a, b);
+    assert_eq!(b, c);
+    assert_eq!(normalize("Hello".to_string()), a);
+    assert_eq!(unsafe { CALLS }, 3);
+    assert_eq!(memoized_size_normalize(), 3);
+
+    for n in 0..100 {
+        assert_eq!(parity(n), if n.is_multiple_of(2) { "even" } else { "odd" });
+    }
+    assert_eq!(parity(7), "odd");
+    assert_eq!(memoized_size_parity(), 100);
+    memoized_flush_parity();
+    assert_eq!(parity(8), "even");
+}
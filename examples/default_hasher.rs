@@ -0,0 +1,31 @@
+use memoize::memoize;
+
+// With the `fxhash` or `ahash` feature, the caches of all functions use that hasher.
+#[memoize]
+fn double(n: u64) -> u64 {
+    n * 2
+}
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 8)]
+fn triple(n: u64) -> u64 {
+    n * 3
+}
+
+fn main() {
+    let hasher = std::any::type_name::<memoize::DefaultHashBuilder>();
+    println!("Caches use {}", hasher);
+    if cfg!(feature = "fxhash") {
+        assert!(hasher.contains("FxBuildHasher"));
+    }
+
+    assert_eq!(double(21), 42);
+    assert_eq!(double(21), 42);
+    assert_eq!(memoized_size_double(), 1);
+
+    #[cfg(feature = "full")]
+    {
+        assert_eq!(triple(3), 9);
+        assert_eq!(memoized_size_triple(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+use memoize::memoize;
+use std::thread;
+
+#[memoize(ThreadLocal)]
+fn thread_local_square(n: u64) -> u64 {
+    n * n
+}
+
+mod cached {
+    pub static mut CALLS: usize = 0;
+
+    // Imports `#[memoize]`, with these defaults.
+    memoize::defaults!(SharedCache, Ignore: verbose);
+
+    #[memoize]
+    pub fn square(n: u64, verbose: bool) -> u64 {
+        unsafe { CALLS += 1 };
+        if verbose {
+            println!("Computing {}²", n);
+        }
+        n * n
+    }
+
+    #[memoize]
+    pub fn cube(n: u64, verbose: bool) -> u64 {
+        if verbose {
+            println!("Computing {}³", n);
+        }
+        n * n * n
+    }
+
+    // Overrides a default.
+    #[memoize(ThreadLocal)]
+    pub fn double(n: u64, verbose: bool) -> u64 {
+        if verbose {
+            println!("Computing {} * 2", n);
+        }
+        n * 2
+    }
+
+    // Submodules importing the items of the module use its defaults.
+    pub mod nested {
+        use super::*;
+
+        #[memoize]
+        pub fn half(n: u64, verbose: bool) -> u64 {
+            if verbose {
+                println!("Computing {} / 2", n);
+            }
+            n / 2
+        }
+    }
+}
+
+// Not affected by the defaults of `cached`.
+mod other {
+    use memoize::memoize;
+
+    #[memoize]
+    pub fn describe(n: u64, verbose: bool) -> String {
+        if verbose {
+            format!("{} ({} bits)", n, 64 - n.leading_zeros())
+        } else {
+            n.to_string()
+        }
+    }
+}
+
+fn main() {
+    thread::spawn(|| {
+        assert_eq!(cached::square(3, true), 9);
+        assert_eq!(cached::cube(2, false), 8);
+        assert_eq!(cached::double(2, false), 4);
+        assert_eq!(cached::nested::half(8, true), 4);
+        assert_eq!(thread_local_square(3), 9);
+    })
+    .join()
+    .unwrap();
+
+    // The cache is shared, and `verbose` isn't part of the key.
+    assert_eq!(cached::square(3, false), 9);
+    assert_eq!(unsafe { cached::CALLS }, 1);
+    assert_eq!(cached::memoized_size_cube(), 1);
+    assert_eq!(cached::memoized_size_double(), 0);
+    assert_eq!(cached::nested::memoized_size_half(), 1);
+    assert_eq!(memoized_size_thread_local_square(), 0);
+
+    assert_eq!(other::describe(4, true), "4 (3 bits)");
+    assert_eq!(other::describe(4, false), "4");
+    assert_eq!(other::memoized_size_describe(), 2);
+}
@@ -0,0 +1,61 @@
+#[cfg(feature = "encryption")]
+mod encrypted {
+    use memoize::memoize;
+    use std::process::Command;
+
+    static mut CALLS: usize = 0;
+
+    // In a real application, the key would come from a keyring or secret store.
+    fn key() -> [u8; 32] {
+        *b"an example key, 32 bytes long!!!"
+    }
+
+    #[memoize(
+        SharedCache,
+        Persist: std::env::var("MEMOIZE_EXAMPLE_CACHE").unwrap(),
+        PersistKey: key
+    )]
+    pub fn profile(user: String) -> String {
+        unsafe { CALLS += 1 };
+        format!("{}'s secret address", user)
+    }
+
+    pub fn main() {
+        if std::env::var("MEMOIZE_EXAMPLE_CACHE").is_ok() {
+            profile("alice".to_string());
+            return;
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "memoize-example-encrypted-{}.bin",
+            std::process::id()
+        ));
+        let status = Command::new(std::env::current_exe().unwrap())
+            .env("MEMOIZE_EXAMPLE_CACHE", &path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // Neither keys nor values are stored in plaintext.
+        let contents = std::fs::read(&path).unwrap();
+        let contains = |text: &str| contents.windows(text.len()).any(|w| w == text.as_bytes());
+        assert!(!contains("alice"));
+        assert!(!contains("secret"));
+
+        // But they can be read back with the key.
+        std::env::set_var("MEMOIZE_EXAMPLE_CACHE", &path);
+        assert_eq!(profile("alice".to_string()), "alice's secret address");
+        assert_eq!(unsafe { CALLS }, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn main() {
+    encrypted::main();
+}
+
+#[cfg(not(feature = "encryption"))]
+fn main() {
+    println!("Use the \"encryption\" feature to execute this example");
+}
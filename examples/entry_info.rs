@@ -0,0 +1,45 @@
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::time::Duration;
+use std::time::Instant;
+
+#[memoize(SharedCache, EntryInfo)]
+fn exchange_rate(from: String, to: String) -> f64 {
+    if from == to {
+        1.0
+    } else {
+        1.1
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(EntryInfo, TimeToLive: Duration::from_secs(60))]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    let start = Instant::now();
+    assert!(memoized_entry_info_exchange_rate("EUR".to_string(), "USD".to_string()).is_none());
+
+    exchange_rate("EUR".to_string(), "USD".to_string());
+    let info = memoized_entry_info_exchange_rate("EUR".to_string(), "USD".to_string()).unwrap();
+    assert_eq!(info.hits, 0);
+    assert!(info.inserted >= start);
+    assert_eq!(info.accessed, info.inserted);
+    assert_eq!(info.expires, None);
+
+    exchange_rate("EUR".to_string(), "USD".to_string());
+    exchange_rate("EUR".to_string(), "USD".to_string());
+    let info = memoized_entry_info_exchange_rate("EUR".to_string(), "USD".to_string()).unwrap();
+    assert_eq!(info.hits, 2);
+    assert!(info.accessed >= info.inserted);
+
+    #[cfg(feature = "full")]
+    {
+        square(4);
+        square(4);
+        assert_eq!(memoized_entry_info_square(4).unwrap().hits, 1);
+        assert!(memoized_entry_info_square(5).is_none());
+    }
+}
@@ -0,0 +1,32 @@
+use memoize::{memoize, CacheEventKind};
+
+#[memoize(Instrument)]
+fn length(s: String) -> usize {
+    s.len()
+}
+
+fn main() {
+    // Not recorded, as there are no subscribers yet.
+    length("uncounted".to_string());
+
+    let events = memoize::subscribe_events();
+    length("hello".to_string());
+    length("hello".to_string());
+    memoized_flush_length();
+
+    let events: Vec<_> = events.try_iter().collect();
+    let kinds: Vec<_> = events.iter().map(|event| event.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            CacheEventKind::Miss,
+            CacheEventKind::Insert,
+            CacheEventKind::Hit,
+            CacheEventKind::Flush,
+        ]
+    );
+    assert!(events.iter().all(|event| event.function == "length"));
+    // All events about the same key carry the same hash.
+    assert_eq!(events[0].key_hash, events[2].key_hash);
+    assert_eq!(events[3].key_hash, None);
+}
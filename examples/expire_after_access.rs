@@ -0,0 +1,41 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// Keeps values for at most 200 ms, and only as long as they are read every 50 ms.
+#[cfg(feature = "full")]
+#[memoize(ExpireAfterWrite: Duration::from_millis(200), ExpireAfterAccess: Duration::from_millis(50))]
+fn now(key: u32) -> Instant {
+    let _ = key;
+    Instant::now()
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    let first = now(1);
+    for _ in 0..3 {
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(now(1), first);
+    }
+
+    // Not accessed for too long.
+    thread::sleep(Duration::from_millis(60));
+    let second = now(1);
+    assert_ne!(second, first);
+
+    // Accessed often enough, but written too long ago.
+    for _ in 0..6 {
+        thread::sleep(Duration::from_millis(40));
+        now(1);
+    }
+    assert_ne!(now(1), second);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
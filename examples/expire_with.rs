@@ -0,0 +1,38 @@
+use memoize::memoize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+struct Token {
+    serial: u32,
+    expires_at: Option<Instant>,
+}
+
+static mut SERIAL: u32 = 0;
+
+// Tokens are only valid for 50 ms, except for the admin's which never expires.
+#[memoize(ExpireWith: |token: &Token| token.expires_at)]
+fn token(user: &'static str) -> Token {
+    let serial = unsafe {
+        SERIAL += 1;
+        SERIAL
+    };
+    let expires_at = if user == "admin" {
+        None
+    } else {
+        Some(Instant::now() + Duration::from_millis(50))
+    };
+    Token { serial, expires_at }
+}
+
+fn main() {
+    assert_eq!(token("alice").serial, 1);
+    assert_eq!(token("admin").serial, 2);
+    assert_eq!(token("alice").serial, 1);
+
+    thread::sleep(Duration::from_millis(60));
+
+    // Alice's token has expired and is recomputed, the admin's is still cached.
+    assert_eq!(token("alice").serial, 3);
+    assert_eq!(token("admin").serial, 2);
+}
@@ -0,0 +1,68 @@
+#[cfg(feature = "serde")]
+use memoize::memoize;
+#[cfg(feature = "serde")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "serde")]
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "serde")]
+#[memoize(SharedCache, Export)]
+fn distance(from: u32, to: u32) -> u64 {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    (from as u64).abs_diff(to as u64) * 10
+}
+
+#[cfg(feature = "serde")]
+#[memoize(Capacity: 3, Export)]
+fn name(id: u32) -> String {
+    format!("city-{}", id)
+}
+
+#[cfg(feature = "serde")]
+fn worker() {
+    // The worker starts with the entries computed by its parent.
+    let exported = memoize::read_frame(std::io::stdin()).unwrap();
+    assert_eq!(memoized_import_distance(&exported).unwrap(), 10);
+    assert_eq!(memoized_size_distance(), 10);
+    assert_eq!(distance(0, 7), 70);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    use std::process::{Command, Stdio};
+
+    if std::env::var_os("MEMOIZE_EXPORT_WORKER").is_some() {
+        return worker();
+    }
+
+    for to in 0..10 {
+        distance(0, to);
+    }
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .env("MEMOIZE_EXPORT_WORKER", "1")
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    memoize::write_frame(child.stdin.take().unwrap(), &memoized_export_distance()).unwrap();
+    assert!(child.wait().unwrap().success());
+
+    // LRU caches keep their order: 1 is the least recently used entry after the import.
+    for id in [1, 2, 3] {
+        name(id);
+    }
+    let exported = memoized_export_name();
+    memoized_flush_name();
+    assert_eq!(memoized_import_name(&exported).unwrap(), 3);
+    assert_eq!(memoized_lru_back_name(), Some(1));
+    assert_eq!(memoized_lru_front_name(), Some(3));
+
+    assert!(memoized_import_name(b"not an export").is_err());
+    assert!(memoized_import_name(&exported[..exported.len() - 1]).is_err());
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
@@ -0,0 +1,30 @@
+use memoize::memoize;
+
+static mut CALLS: u32 = 0;
+
+/// Attributes exporting a function, like `#[wasm_bindgen]` for JavaScript or `#[export_name]` for C,
+/// are applied to the memoized function, so that the exported function is the memoized one.
+#[memoize]
+#[export_name = "memoize_example_collatz_steps"]
+pub extern "C" fn collatz_steps(n: u64) -> u32 {
+    unsafe { CALLS += 1 };
+    let (mut n, mut steps) = (n, 0);
+    while n != 1 {
+        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+        steps += 1;
+    }
+    steps
+}
+
+extern "C" {
+    fn memoize_example_collatz_steps(n: u64) -> u32;
+}
+
+fn main() {
+    // Called through the exported symbol, as foreign code would.
+    assert_eq!(unsafe { memoize_example_collatz_steps(27) }, 111);
+    assert_eq!(unsafe { memoize_example_collatz_steps(27) }, 111);
+    assert_eq!(collatz_steps(27), 111);
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(memoized_size_collatz_steps(), 1);
+}
@@ -0,0 +1,45 @@
+use memoize::memoize;
+
+// Neither `Clone` nor `Hash`.
+struct RequestContext {
+    locale: String,
+    request_id: u64,
+}
+
+static mut CALLS: usize = 0;
+
+#[memoize(Extract: ctx => ctx.locale: String)]
+fn format_price(cents: u64, ctx: &RequestContext) -> String {
+    unsafe { CALLS += 1 };
+    let separator = if ctx.locale == "de" { "," } else { "." };
+    format!("{}{}{:02}", cents / 100, separator, cents % 100)
+}
+
+#[memoize(SharedCache, Extract: ctx => ctx.locale.len(): usize, Extract: id => id % 10: u64)]
+fn label(ctx: &RequestContext, id: u64) -> String {
+    format!("{}-{}", ctx.locale.len(), id % 10)
+}
+
+fn main() {
+    let en = RequestContext {
+        locale: "en".to_string(),
+        request_id: 1,
+    };
+    let de = RequestContext {
+        locale: "de".to_string(),
+        request_id: 2,
+    };
+    assert_eq!(format_price(1234, &en), "12.34");
+    assert_eq!(format_price(1234, &de), "12,34");
+    let other_request = RequestContext {
+        locale: "en".to_string(),
+        request_id: 3,
+    };
+    assert_eq!(format_price(1234, &other_request), "12.34");
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(en.request_id + de.request_id + other_request.request_id, 6);
+
+    assert_eq!(label(&en, 13), "2-3");
+    assert_eq!(label(&de, 23), "2-3");
+    assert_eq!(memoized_size_label(), 1);
+}
@@ -0,0 +1,48 @@
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+#[memoize(SharedCache)]
+fn report(user: u32, month: u8) -> String {
+    format!("report of {} for {}", user, month)
+}
+
+#[cfg(feature = "full")]
+#[memoize(TimeToLive: Duration::from_secs(60))]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    for user in 1..=3 {
+        report(user, 3);
+        report(user, 4);
+    }
+    let mut march = memoized_find_report(|(_, month)| *month == 3);
+    march.sort();
+    assert_eq!(
+        march,
+        vec![
+            ((1, 3), "report of 1 for 3".to_string()),
+            ((2, 3), "report of 2 for 3".to_string()),
+            ((3, 3), "report of 3 for 3".to_string()),
+        ]
+    );
+    assert!(memoized_find_report(|(_, month)| *month == 5).is_empty());
+
+    // Expired entries are skipped.
+    #[cfg(feature = "full")]
+    {
+        square(3);
+        square(10);
+        assert_eq!(memoized_find_square(|n| *n < 5), vec![(3, 9)]);
+        // A thread-local store is only borrowed for reading, so the predicate may look at the cache as
+        // well (the lock of a shared store isn't reentrant).
+        if !cfg!(feature = "shared") {
+            assert_eq!(
+                memoized_find_square(|_| memoized_size_square() == 2).len(),
+                2
+            );
+        }
+    }
+}
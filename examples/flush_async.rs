@@ -0,0 +1,62 @@
+#[cfg(feature = "tokio")]
+mod flush_async {
+    use memoize::memoize;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    // The version of the configuration in the backing store.
+    static VERSION: AtomicU32 = AtomicU32::new(1);
+
+    #[memoize(SharedCache)]
+    pub async fn config(name: String) -> String {
+        let version = VERSION.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        format!("{} v{}", name, version)
+    }
+
+    #[memoize(SharedCache, SharedFuture)]
+    pub async fn shared_config(name: String) -> String {
+        config(name).await
+    }
+
+    pub async fn main() {
+        // A computation is running while the configuration changes and the cache is flushed.
+        let running = tokio::spawn(config("db".to_string()));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        VERSION.store(2, Ordering::SeqCst);
+        memoized_flush_config_async().await;
+        // The flush waited for the computation, whose outdated value was not cached.
+        assert!(running.is_finished());
+        assert_eq!(running.await.unwrap(), "db v1");
+        assert_eq!(memoized_size_config().await, 0);
+        assert_eq!(config("db".to_string()).await, "db v2");
+        assert_eq!(memoized_size_config().await, 1);
+
+        // Shared futures started before the flush aren't joined by later callers.
+        let running = tokio::spawn(shared_config("cache".to_string()));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        VERSION.store(3, Ordering::SeqCst);
+        memoized_flush_config_async().await;
+        memoized_flush_shared_config_async().await;
+        assert!(running.is_finished());
+        assert_eq!(running.await.unwrap(), "cache v2");
+        assert_eq!(shared_config("cache".to_string()).await, "cache v3");
+
+        // Without running computations, the flush completes right away.
+        tokio::time::timeout(Duration::from_millis(10), memoized_flush_config_async())
+            .await
+            .unwrap();
+        assert_eq!(memoized_size_config().await, 0);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::main]
+async fn main() {
+    flush_async::main().await;
+}
+
+#[cfg(not(feature = "tokio"))]
+fn main() {
+    println!("Use the \"tokio\" feature to execute this example");
+}
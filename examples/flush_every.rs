@@ -0,0 +1,41 @@
+use memoize::memoize;
+use std::thread;
+use std::time::Duration;
+
+static mut CALLS: usize = 0;
+
+#[memoize(SharedCache, FlushEvery: Duration::from_millis(100))]
+fn exchange_rate(currency: String) -> f64 {
+    unsafe { CALLS += 1 };
+    if currency == "EUR" {
+        1.1
+    } else {
+        1.0
+    }
+}
+
+#[memoize(FlushEvery: Duration::from_millis(100))]
+fn local(n: u32) -> u32 {
+    n + 1
+}
+
+fn main() {
+    assert_eq!(exchange_rate("EUR".to_string()), 1.1);
+    assert_eq!(exchange_rate("USD".to_string()), 1.0);
+    assert_eq!(exchange_rate("EUR".to_string()), 1.1);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_exchange_rate(), 2);
+
+    thread::sleep(Duration::from_millis(150));
+    // The first call after the period flushes everything.
+    assert_eq!(exchange_rate("EUR".to_string()), 1.1);
+    assert_eq!(unsafe { CALLS }, 3);
+    assert_eq!(memoized_size_exchange_rate(), 1);
+
+    assert_eq!(local(1), 2);
+    assert_eq!(local(2), 3);
+    assert_eq!(memoized_size_local(), 2);
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(local(1), 2);
+    assert_eq!(memoized_size_local(), 1);
+}
@@ -0,0 +1,79 @@
+#[cfg(feature = "schedule")]
+use memoize::memoize;
+#[cfg(feature = "schedule")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "schedule")]
+static BALANCE_LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "schedule")]
+#[memoize(SharedCache)]
+fn account_balance(account: u64) -> u64 {
+    BALANCE_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+    account * 100
+}
+
+#[cfg(feature = "schedule")]
+fn checkout(account: u64) -> u64 {
+    let _flush = memoize::flush_request(&["account_balance"]);
+    // Within the request, the balance is only looked up once.
+    let before = account_balance(account);
+    let after = account_balance(account);
+    before + after
+}
+
+#[cfg(all(feature = "schedule", feature = "tower"))]
+async fn serve_with_layer() {
+    use memoize::tower::{FlushTagsLayer, Layer, Service};
+    use std::convert::Infallible;
+    use std::future::{poll_fn, ready, Ready};
+    use std::task::{Context, Poll};
+
+    #[derive(Clone)]
+    struct Checkout;
+
+    impl Service<u64> for Checkout {
+        type Response = u64;
+        type Error = Infallible;
+        type Future = Ready<Result<u64, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, account: u64) -> Self::Future {
+            ready(Ok(account_balance(account) + account_balance(account)))
+        }
+    }
+
+    let mut service = FlushTagsLayer::new(["account_balance"]).layer(Checkout);
+    let lookups = BALANCE_LOOKUPS.load(Ordering::SeqCst);
+    for _ in 0..2 {
+        poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+        assert_eq!(service.call(7).await.unwrap(), 1400);
+        assert_eq!(memoized_size_account_balance(), 0);
+    }
+    assert_eq!(BALANCE_LOOKUPS.load(Ordering::SeqCst), lookups + 2);
+}
+
+#[cfg(feature = "schedule")]
+fn main() {
+    assert_eq!(checkout(1), 200);
+    assert_eq!(checkout(1), 200);
+    // Once per request, and nothing is left behind.
+    assert_eq!(BALANCE_LOOKUPS.load(Ordering::SeqCst), 2);
+    assert_eq!(memoized_size_account_balance(), 0);
+
+    memoize::flush_tags(&["account_balance", "flush_request::account_balance"]);
+
+    #[cfg(feature = "tower")]
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(serve_with_layer());
+}
+
+#[cfg(not(feature = "schedule"))]
+fn main() {
+    println!("Use the \"schedule\" feature to execute this example");
+}
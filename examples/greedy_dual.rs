@@ -0,0 +1,41 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::{thread, time::Duration};
+
+#[cfg(feature = "full")]
+static mut CALLS: u32 = 0;
+
+// Simulates a computation whose duration depends on the input.
+#[cfg(feature = "full")]
+#[memoize(Capacity: 2, Eviction: GreedyDual)]
+fn slow_square(n: u64, millis: u64) -> u64 {
+    unsafe { CALLS += 1 };
+    thread::sleep(Duration::from_millis(millis));
+    n * n
+}
+
+#[cfg(feature = "full")]
+fn calls() -> u32 {
+    unsafe { CALLS }
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    assert_eq!(slow_square(1, 50), 1);
+    assert_eq!(slow_square(2, 0), 4);
+    assert_eq!(slow_square(3, 0), 9);
+    assert_eq!(calls(), 3);
+
+    // The cheap entry for 2 was evicted rather than the expensive one for 1, although the latter
+    // is the least recently used entry.
+    assert_eq!(slow_square(1, 50), 1);
+    assert_eq!(calls(), 3);
+    assert_eq!(slow_square(2, 0), 4);
+    assert_eq!(calls(), 4);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
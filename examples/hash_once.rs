@@ -0,0 +1,75 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+#[cfg(feature = "full")]
+static HASHED: AtomicUsize = AtomicUsize::new(0);
+
+// A large key, which counts how often it is hashed.
+#[cfg(feature = "full")]
+#[derive(Clone, PartialEq, Eq)]
+struct Document(String);
+
+#[cfg(feature = "full")]
+impl Hash for Document {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        HASHED.fetch_add(1, Ordering::SeqCst);
+        self.0.hash(state);
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(HashOnce)]
+fn word_count(document: Document) -> usize {
+    document.0.split_whitespace().count()
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, HashOnce, TimeToLive: Duration::from_millis(50))]
+fn shared_word_count(document: Document) -> usize {
+    word_count(document)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    let text = Document("the quick brown fox jumps over the lazy dog ".repeat(100));
+
+    // A miss hashes the key once, for both the lookup and the insertion.
+    assert_eq!(word_count(text.clone()), 900);
+    assert_eq!(HASHED.load(Ordering::SeqCst), 1);
+    assert_eq!(word_count(text.clone()), 900);
+    assert_eq!(HASHED.load(Ordering::SeqCst), 2);
+    assert_eq!(word_count(Document("hello world".to_string())), 2);
+    assert_eq!(HASHED.load(Ordering::SeqCst), 3);
+    assert_eq!(memoized_size_word_count(), 2);
+
+    // Other operations on the cache work as usual.
+    assert_eq!(
+        memoized_get_many_word_count(vec![text.clone()]),
+        vec![Some(900)]
+    );
+    memoized_flush_word_count();
+    assert_eq!(memoized_size_word_count(), 0);
+
+    HASHED.store(0, Ordering::SeqCst);
+    assert_eq!(shared_word_count(text.clone()), 900);
+    // The miss of the shared cache hashed the key once, and so did the one of `word_count()`.
+    assert_eq!(HASHED.load(Ordering::SeqCst), 2);
+    assert_eq!(shared_word_count(text.clone()), 900);
+    assert_eq!(HASHED.load(Ordering::SeqCst), 3);
+
+    // Expired entries are replaced in place.
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(shared_word_count(text.clone()), 900);
+    assert_eq!(memoized_size_shared_word_count(), 1);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
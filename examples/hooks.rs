@@ -0,0 +1,50 @@
+use memoize::{memoize, Hooks};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static HITS: AtomicUsize = AtomicUsize::new(0);
+static MISSES: AtomicUsize = AtomicUsize::new(0);
+static INSERTS: AtomicUsize = AtomicUsize::new(0);
+static FLUSHES: AtomicUsize = AtomicUsize::new(0);
+
+struct Counters;
+
+impl Hooks for Counters {
+    fn on_hit(&self, _function: &'static str) {
+        HITS.fetch_add(1, Ordering::Relaxed);
+    }
+    fn on_miss(&self, _function: &'static str) {
+        MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+    fn on_insert(&self, _function: &'static str) {
+        INSERTS.fetch_add(1, Ordering::Relaxed);
+    }
+    fn on_flush(&self, function: &'static str) {
+        assert_eq!(function, "double");
+        FLUSHES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[memoize]
+fn double(n: u32) -> u32 {
+    2 * n
+}
+
+#[memoize(SharedCache)]
+fn triple(n: u32) -> u32 {
+    3 * n
+}
+
+fn main() {
+    memoize::set_hooks(Counters);
+
+    // The same hooks instrument all memoized functions.
+    double(1);
+    double(1);
+    triple(1);
+    memoized_flush_double();
+
+    assert_eq!(HITS.load(Ordering::Relaxed), 1);
+    assert_eq!(MISSES.load(Ordering::Relaxed), 2);
+    assert_eq!(INSERTS.load(Ordering::Relaxed), 2);
+    assert_eq!(FLUSHES.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+// Whether the configuration service is reachable.
+#[cfg(feature = "full")]
+static UP: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "full")]
+static CALLS: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(feature = "full")]
+fn fetch(key: &str) -> Result<String, String> {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    if UP.load(Ordering::SeqCst) {
+        Ok(format!("value of {}", key))
+    } else {
+        Err("connection refused".to_string())
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(IgnoreErrors)]
+fn config(key: String) -> Result<String, String> {
+    fetch(&key)
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 10, IgnoreErrors)]
+fn shared_config(key: String) -> Result<String, String> {
+    fetch(&key)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    // Transient errors are returned, but not cached: the next call tries again.
+    assert!(config("timeout".to_string()).is_err());
+    assert!(config("timeout".to_string()).is_err());
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    assert_eq!(memoized_size_config(), 0);
+
+    UP.store(true, Ordering::SeqCst);
+    assert_eq!(
+        config("timeout".to_string()),
+        Ok("value of timeout".to_string())
+    );
+    assert_eq!(
+        config("timeout".to_string()),
+        Ok("value of timeout".to_string())
+    );
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+    assert_eq!(memoized_size_config(), 1);
+
+    // The same goes for shared caches, and for values computed in bulk.
+    UP.store(false, Ordering::SeqCst);
+    let keys = vec!["retries".to_string(), "port".to_string()];
+    assert!(memoized_get_or_compute_many_shared_config(keys.clone())
+        .iter()
+        .all(Result::is_err));
+    assert!(shared_config("retries".to_string()).is_err());
+    assert_eq!(memoized_size_shared_config(), 0);
+
+    UP.store(true, Ordering::SeqCst);
+    assert!(memoized_get_or_compute_many_shared_config(keys)
+        .iter()
+        .all(Result::is_ok));
+    assert!(shared_config("retries".to_string()).is_ok());
+    assert_eq!(memoized_size_shared_config(), 2);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 8);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,57 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+#[cfg(feature = "full")]
+#[derive(Clone, Debug, PartialEq)]
+struct Document {
+    folder: u32,
+    title: String,
+}
+
+#[cfg(feature = "full")]
+#[memoize(IndexBy: |_id, document| document.folder: u32)]
+fn document(id: u32) -> Document {
+    Document {
+        folder: id % 3,
+        title: format!("document {}", id),
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 4, TimeToLive: Duration::from_secs(60), IndexBy: |_id, document| document.folder: u32)]
+fn shared_document(id: u32) -> Document {
+    document(id)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    for id in 0..9 {
+        document(id);
+    }
+    assert_eq!(memoized_size_document(), 9);
+
+    // Folder 1 was renamed: all of its cached documents are invalidated.
+    assert_eq!(memoized_remove_by_index_document(&1), 3);
+    assert_eq!(memoized_size_document(), 6);
+    assert_eq!(memoized_remove_by_index_document(&1), 0);
+    assert_eq!(memoized_find_document(|id| id % 3 == 1).len(), 0);
+
+    // The index follows the cache, also when entries are evicted.
+    for id in 0..12 {
+        shared_document(id);
+    }
+    assert_eq!(memoized_size_shared_document(), 4);
+    // Documents 8 to 11 are left, in folders 2, 0, 1 and 2.
+    assert_eq!(memoized_remove_by_index_shared_document(&2), 2);
+    assert_eq!(memoized_remove_by_index_shared_document(&0), 1);
+    assert_eq!(memoized_size_shared_document(), 1);
+    memoized_flush_shared_document();
+    assert_eq!(memoized_remove_by_index_shared_document(&1), 0);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
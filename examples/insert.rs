@@ -0,0 +1,45 @@
+use memoize::memoize;
+
+static mut FETCHES: usize = 0;
+
+// Fetching a page also yields its neighbors, which are cached right away.
+fn fetch_pages_around(n: u32) -> (String, String, String) {
+    unsafe { FETCHES += 1 };
+    (
+        format!("page {}", n.saturating_sub(1)),
+        format!("page {}", n),
+        format!("page {}", n + 1),
+    )
+}
+
+#[memoize]
+fn page(n: u32) -> String {
+    let (previous, current, next) = fetch_pages_around(n);
+    if n > 0 {
+        memoized_insert_page(n - 1, previous);
+    }
+    memoized_insert_page(n + 1, next);
+    current
+}
+
+#[memoize(SharedCache)]
+fn distance(from: u32, to: u32) -> u32 {
+    // Distances are symmetric.
+    memoized_insert_distance((to, from), from.abs_diff(to));
+    from.abs_diff(to)
+}
+
+fn main() {
+    assert_eq!(page(5), "page 5");
+    assert_eq!(page(4), "page 4");
+    assert_eq!(page(6), "page 6");
+    assert_eq!(unsafe { FETCHES }, 1);
+    assert_eq!(memoized_size_page(), 3);
+
+    distance(3, 10);
+    assert_eq!(memoized_get_many_distance(vec![(10, 3)]), vec![Some(7)]);
+
+    // Values can also be inserted from outside.
+    memoized_insert_page(100, "cached page 100".to_string());
+    assert_eq!(page(100), "cached page 100");
+}
@@ -0,0 +1,34 @@
+use memoize::memoize;
+use std::path::PathBuf;
+
+static mut CALLS: usize = 0;
+
+#[memoize]
+fn greet(name: impl Into<String>, excited: bool) -> String {
+    unsafe { CALLS += 1 };
+    let name = name.into();
+    if excited {
+        format!("Hello, {}!", name)
+    } else {
+        format!("Hello, {}.", name)
+    }
+}
+
+#[memoize(SharedCache)]
+fn extension(path: impl Into<PathBuf>) -> Option<String> {
+    path.into()
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+}
+
+fn main() {
+    assert_eq!(greet("World", true), "Hello, World!");
+    // A `String` hits the entry stored for the equal `&str`.
+    assert_eq!(greet(String::from("World"), true), "Hello, World!");
+    assert_eq!(greet('W', false), "Hello, W.");
+    assert_eq!(unsafe { CALLS }, 2);
+
+    assert_eq!(extension("archive.tar.gz"), Some("gz".to_string()));
+    assert_eq!(extension(PathBuf::from("README")), None);
+    assert_eq!(memoized_size_extension(), 2);
+}
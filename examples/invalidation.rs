@@ -0,0 +1,93 @@
+#[cfg(all(feature = "invalidation", unix))]
+use memoize::memoize;
+
+#[cfg(all(feature = "invalidation", unix))]
+#[memoize(SharedCache, Subscribe)]
+fn price(product: u32) -> u64 {
+    product as u64 * 100
+}
+
+#[cfg(all(feature = "invalidation", unix))]
+#[memoize(SharedCache, Subscribe)]
+fn stock(product: u32, warehouse: String) -> u32 {
+    product + warehouse.len() as u32
+}
+
+// Waits until `done` holds, as messages from other processes arrive asynchronously.
+#[cfg(all(feature = "invalidation", unix))]
+fn wait_for(done: impl Fn() -> bool) {
+    let start = std::time::Instant::now();
+    while !done() {
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[cfg(all(feature = "invalidation", unix))]
+fn worker(dir: &std::path::Path) {
+    memoize::set_invalidation_bus(memoize::UnixSocketBus::join(dir).unwrap());
+    for product in 0..3 {
+        price(product);
+    }
+    stock(1, "north".to_string());
+    println!("ready");
+
+    // Invalidations published by the parent process.
+    wait_for(|| memoized_size_price() == 2);
+    assert_eq!(memoized_find_price(|product| *product == 1), vec![]);
+    wait_for(|| memoized_size_stock() == 0);
+}
+
+#[cfg(all(feature = "invalidation", unix))]
+fn main() {
+    use std::io::BufRead;
+    use std::process::{Command, Stdio};
+
+    let dir = std::env::temp_dir().join(format!("memoize-bus-{}", std::process::id()));
+    if let Some(dir) = std::env::var_os("MEMOIZE_BUS_WORKER") {
+        return worker(dir.as_ref());
+    }
+
+    // Entries inserted before the first call are invalidated as well.
+    memoized_insert_price(7, 700);
+    memoize::publish_key_invalidation("price", &7u32);
+    assert_eq!(memoized_size_price(), 0);
+    memoized_insert_stock((7, "east".to_string()), 11);
+    memoize::publish_invalidation("invalidation::stock");
+    assert_eq!(memoized_size_stock(), 0);
+
+    memoize::set_invalidation_bus(memoize::UnixSocketBus::join(&dir).unwrap());
+    price(1);
+    price(2);
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .env("MEMOIZE_BUS_WORKER", &dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut ready = String::new();
+    std::io::BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut ready)
+        .unwrap();
+    assert_eq!(ready, "ready\n");
+
+    // Invalidations apply to this process right away, and to the worker once it receives them.
+    memoize::publish_key_invalidation("price", &1u32);
+    assert_eq!(memoized_size_price(), 1);
+    memoize::publish_invalidation("invalidation::stock");
+    assert!(child.wait().unwrap().success());
+
+    // Keys of several arguments are published as tuples.
+    stock(1, "north".to_string());
+    stock(1, "south".to_string());
+    memoize::publish_key_invalidation("stock", &(1u32, "north".to_string()));
+    assert_eq!(memoized_size_stock(), 1);
+
+    // Malformed messages are ignored.
+    memoize::deliver_invalidation(b"\x01garbage");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(not(all(feature = "invalidation", unix)))]
+fn main() {
+    println!("Use the \"invalidation\" feature (on Unix) to execute this example");
+}
@@ -0,0 +1,41 @@
+use memoize::memoize;
+use std::sync::Arc;
+
+// Neither `Hash` nor `Eq` (floats), and expensive to hash anyway.
+struct Model {
+    weights: Vec<f64>,
+}
+
+static mut CALLS: usize = 0;
+
+#[memoize(KeyByPtr: model)]
+fn evaluate(model: Arc<Model>, input: u32) -> f64 {
+    unsafe { CALLS += 1 };
+    model.weights.iter().map(|w| w * input as f64).sum()
+}
+
+#[memoize(SharedCache, KeyByPtr: model)]
+fn total(model: &Arc<Model>) -> f64 {
+    model.weights.iter().sum()
+}
+
+fn main() {
+    let model = Arc::new(Model {
+        weights: vec![0.5; 1000],
+    });
+    assert_eq!(evaluate(model.clone(), 2), 1000.0);
+    assert_eq!(evaluate(model.clone(), 2), 1000.0);
+    assert_eq!(unsafe { CALLS }, 1);
+
+    // Equal contents, but a different allocation: a different key.
+    let copy = Arc::new(Model {
+        weights: model.weights.clone(),
+    });
+    assert_eq!(evaluate(copy, 2), 1000.0);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_evaluate(), 2);
+
+    assert_eq!(total(&model), 500.0);
+    assert_eq!(total(&model), 500.0);
+    assert_eq!(memoized_size_total(), 1);
+}
@@ -0,0 +1,44 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+fn trim(text: String) -> String {
+    text.trim().to_string()
+}
+
+#[memoize(
+    KeyPipeline: query => [trim, |q| q.to_lowercase()],
+    KeyPipeline: radius => [f64::abs, |r| (r * 10.0).round() as i64]: i64
+)]
+fn search(query: String, radius: f64) -> String {
+    unsafe { CALLS += 1 };
+    format!("{} within {:.1}", query.trim().to_lowercase(), radius.abs())
+}
+
+struct Request {
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+// A reference argument is projected onto an owned key, whose type has to be given.
+#[memoize(SharedCache, KeyPipeline: request => [|r| r.path.clone(), trim]: String)]
+fn route(request: &Request) -> usize {
+    request.path.trim().len()
+}
+
+fn main() {
+    assert_eq!(search("Rust".to_string(), 1.0), "rust within 1.0");
+    assert_eq!(search("  rust ".to_string(), -1.01), "rust within 1.0");
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(search("rust".to_string(), 2.0), "rust within 2.0");
+    assert_eq!(memoized_size_search(), 2);
+
+    let request = |path: &str| Request {
+        path: path.to_string(),
+        headers: vec![("accept".to_string(), "*/*".to_string())],
+    };
+    assert_eq!(request("/").headers.len(), 1);
+    assert_eq!(route(&request("/index ")), 6);
+    assert_eq!(route(&request(" /index")), 6);
+    assert_eq!(memoized_size_route(), 1);
+}
@@ -0,0 +1,76 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+#[cfg(feature = "full")]
+#[derive(Clone, Debug)]
+struct Rate {
+    value: f64,
+    // The version of the upstream data set the rate was computed from.
+    source: u32,
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, PublicStore)]
+fn rate(from: String, to: String) -> Rate {
+    let source = if from == "EUR" { 1 } else { 2 };
+    Rate {
+        value: (from.len() * 10 + to.len()) as f64,
+        source,
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(TimeToLive: Duration::from_millis(50))]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    for (from, to) in [("EUR", "USD"), ("EUR", "CHF"), ("USD", "CHF")] {
+        rate(from.to_string(), to.to_string());
+    }
+
+    // Data set 1 turned out to be wrong: find the keys computed from it, and remove them.
+    let mut stale = memoized_keys_where_rate(|rate| rate.source == 1);
+    stale.sort();
+    assert_eq!(
+        stale,
+        [
+            ("EUR".to_string(), "CHF".to_string()),
+            ("EUR".to_string(), "USD".to_string()),
+        ]
+    );
+    let mut store = MEMOIZED_MAPPING_RATE.lock().unwrap();
+    for key in &stale {
+        store.remove(key);
+    }
+    drop(store);
+    assert_eq!(memoized_size_rate(), 1);
+    assert_eq!(memoized_keys_where_rate(|rate| rate.value > 0.0).len(), 1);
+
+    // Only fresh entries are considered.
+    for n in 0..10 {
+        square(n);
+    }
+    let mut large = memoized_keys_where_square(|square| *square > 50);
+    large.sort();
+    assert_eq!(large, [8, 9]);
+    // A thread-local store is only borrowed for reading, so the predicate may look at the cache as
+    // well (the lock of a shared store isn't reentrant).
+    if !cfg!(feature = "shared") {
+        assert_eq!(
+            memoized_keys_where_square(|_| memoized_size_square() == 10).len(),
+            10
+        );
+    }
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(memoized_keys_where_square(|_| true).is_empty());
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,41 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 3)]
+fn page(n: u32) -> String {
+    format!("page {}", n)
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 2)]
+fn user(id: u32, detailed: bool) -> String {
+    format!("user {} ({})", id, detailed)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    assert_eq!(memoized_lru_front_page(), None);
+    page(1);
+    page(2);
+    page(3);
+    assert_eq!(memoized_lru_front_page(), Some(3));
+    assert_eq!(memoized_lru_back_page(), Some(1));
+    // A hit makes the entry the most recently used one.
+    page(1);
+    assert_eq!(memoized_lru_front_page(), Some(1));
+    assert_eq!(memoized_lru_back_page(), Some(2));
+    // The least recently used entry is the next to be evicted.
+    page(4);
+    assert_eq!(memoized_lru_back_page(), Some(3));
+
+    user(1, false);
+    user(2, true);
+    assert_eq!(memoized_lru_front_user(), Some((2, true)));
+    assert_eq!(memoized_lru_back_user(), Some((1, false)));
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
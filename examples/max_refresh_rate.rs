@@ -0,0 +1,43 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "full")]
+use std::thread;
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+#[cfg(feature = "full")]
+static QUERIES: AtomicU32 = AtomicU32::new(0);
+
+// An expensive aggregate, which should be fresh, but never recomputed more than every 100 ms.
+#[cfg(feature = "full")]
+#[memoize(
+    SharedCache,
+    TimeToLive: Duration::from_millis(10),
+    MaxRefreshRate: Duration::from_millis(100)
+)]
+fn revenue(region: String) -> u32 {
+    QUERIES.fetch_add(1, Ordering::SeqCst) + region.len() as u32
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    assert_eq!(revenue("emea".to_string()), 4);
+
+    // Expired, but computed too recently to be recomputed: the existing value is served.
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(revenue("emea".to_string()), 4);
+    assert_eq!(QUERIES.load(Ordering::SeqCst), 1);
+
+    // Once the rate allows it, the expired value is recomputed.
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(revenue("emea".to_string()), 5);
+    assert_eq!(revenue("emea".to_string()), 5);
+    assert_eq!(QUERIES.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,34 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+#[memoize(MaxValueWeight: 100, Weigher: |body: &Vec<u8>| body.len())]
+fn download(size: usize) -> Vec<u8> {
+    unsafe { CALLS += 1 };
+    vec![0; size]
+}
+
+#[memoize(SharedCache, PerElement, MaxValueWeight: 3, Weigher: |name: &String| name.len())]
+fn names(ids: &[u32]) -> Vec<String> {
+    ids.iter().map(|id| "x".repeat(*id as usize)).collect()
+}
+
+fn main() {
+    assert_eq!(download(10).len(), 10);
+    assert_eq!(download(10).len(), 10);
+    assert_eq!(unsafe { CALLS }, 1);
+
+    // Too heavy to be cached, but returned anyway.
+    assert_eq!(download(1000).len(), 1000);
+    assert_eq!(download(1000).len(), 1000);
+    assert_eq!(unsafe { CALLS }, 3);
+    assert_eq!(memoized_size_download(), 1);
+
+    assert_eq!(names(&[1, 5, 3]), vec!["x", "xxxxx", "xxx"]);
+    assert_eq!(memoized_size_names(), 2);
+    assert_eq!(
+        memoized_get_or_compute_many_download(vec![10, 500]).len(),
+        2
+    );
+    assert_eq!(memoized_size_download(), 1);
+}
@@ -0,0 +1,53 @@
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+use memoize::memoize;
+
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+#[memoize(SharedCache)]
+fn render(page: u32) -> Vec<u8> {
+    vec![page as u8; 4096]
+}
+
+#[cfg(all(feature = "full", any(feature = "jemalloc", feature = "mimalloc")))]
+#[memoize(SharedCache, Capacity: 100)]
+fn thumbnail(page: u32) -> Vec<u8> {
+    vec![page as u8; 256]
+}
+
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+fn main() {
+    for page in 0..100 {
+        render(page);
+    }
+    assert!(memoize::shrinkable_caches()
+        .iter()
+        .any(|path| path.ends_with("::render")));
+    assert!(memoize::resident_memory().is_some());
+
+    // Every registered cache gives up the same share of its entries.
+    assert!(memoize::shrink_caches(0.25) >= 25);
+    assert_eq!(memoized_size_render(), 75);
+
+    #[cfg(feature = "full")]
+    {
+        for page in 0..10 {
+            thumbnail(page);
+        }
+        // Keep the first page recently used; LRU caches evict their least recently used entries.
+        thumbnail(0);
+        memoize::shrink_caches(0.5);
+        assert_eq!(memoized_size_thumbnail(), 5);
+        assert_eq!(memoized_lru_back_thumbnail(), Some(6));
+        assert_eq!(memoized_lru_front_thumbnail(), Some(0));
+    }
+
+    // Without a high-water mark, nothing happens; above it, caches shrink proportionally.
+    assert_eq!(memoize::check_memory(), 0);
+    memoize::set_memory_high_water_mark(1);
+    memoize::check_memory();
+    assert_eq!(memoized_size_render(), 0);
+}
+
+#[cfg(not(any(feature = "jemalloc", feature = "mimalloc")))]
+fn main() {
+    println!("Use the \"jemalloc\" or \"mimalloc\" feature to execute this example");
+}
@@ -0,0 +1,33 @@
+use memoize::memoize;
+use std::thread;
+use std::time::Duration;
+
+static mut CALLS: usize = 0;
+
+// Only slow inputs are worth caching.
+#[memoize(MinComputeTime: Duration::from_millis(20))]
+fn plan(complexity: u64) -> u64 {
+    unsafe { CALLS += 1 };
+    if complexity > 10 {
+        thread::sleep(Duration::from_millis(30));
+    }
+    complexity * 2
+}
+
+fn main() {
+    assert_eq!(plan(1), 2);
+    assert_eq!(plan(1), 2);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_plan(), 0);
+
+    assert_eq!(plan(50), 100);
+    assert_eq!(plan(50), 100);
+    assert_eq!(unsafe { CALLS }, 3);
+    assert_eq!(memoized_size_plan(), 1);
+
+    assert_eq!(
+        memoized_get_or_compute_many_plan(vec![2, 50, 60]),
+        vec![4, 100, 120]
+    );
+    assert_eq!(memoized_size_plan(), 2);
+}
@@ -0,0 +1,58 @@
+use memoize::{memoize, BloomFilter, NegativeFilter};
+
+static mut CALLS: usize = 0;
+
+#[memoize(SharedCache, NegativeFilter: false, NegativeFilterCapacity: 1000)]
+fn is_known(hash: u64) -> bool {
+    unsafe { CALLS += 1 };
+    hash % 100 == 7
+}
+
+#[memoize(NegativeFilter: None)]
+fn owner(hash: u64) -> Option<String> {
+    unsafe { CALLS += 1 };
+    (hash == 7).then(|| "alice".to_string())
+}
+
+fn main() {
+    assert!(!is_known(1));
+    assert!(!is_known(1));
+    assert!(is_known(107));
+    assert!(is_known(107));
+    assert_eq!(unsafe { CALLS }, 2);
+    // Only the positive result takes up an entry.
+    assert_eq!(memoized_size_is_known(), 1);
+
+    memoized_flush_is_known();
+    assert!(!is_known(1));
+    assert_eq!(unsafe { CALLS }, 3);
+
+    assert_eq!(owner(3), None);
+    assert_eq!(owner(3), None);
+    assert_eq!(owner(7).as_deref(), Some("alice"));
+    assert_eq!(owner(7).as_deref(), Some("alice"));
+    assert_eq!(unsafe { CALLS }, 5);
+    assert_eq!(memoized_size_owner(), 1);
+
+    let mut filter = BloomFilter::new(100);
+    for n in 0..100u32 {
+        filter.insert(&n);
+    }
+    assert!((0..100u32).all(|n| filter.contains(&n)));
+    let false_positives = (1000..2000u32).filter(|n| filter.contains(n)).count();
+    assert!(false_positives < 50, "{} false positives", false_positives);
+    assert_eq!(filter.len(), 100);
+    // A full filter starts over.
+    filter.insert(&100u32);
+    assert_eq!(filter.len(), 1);
+    assert!(!filter.contains(&0u32));
+
+    // The filter used by `NegativeFilter` confirms the hits of its Bloom filter, so that it has no
+    // false positives.
+    let mut filter = NegativeFilter::new(100);
+    for n in 0..100u32 {
+        filter.insert(&n);
+    }
+    assert!((0..100u32).all(|n| filter.contains(&n)));
+    assert!(!(1000..2000u32).any(|n| filter.contains(&n)));
+}
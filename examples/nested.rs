@@ -0,0 +1,39 @@
+//! Memoized functions can be defined inside other functions; the cache and the generated helpers
+//! are then local to the enclosing block.
+
+use memoize::memoize;
+
+fn outer(n: u64) -> u64 {
+    #[memoize]
+    fn inner(n: u64) -> u64 {
+        n * 2
+    }
+    inner(n)
+}
+
+fn other(n: u64) -> u64 {
+    // Same name, but a different cache.
+    #[memoize(SharedCache, WriteBack: 2, Warm: [(7)])]
+    fn inner(n: u64) -> u64 {
+        n * 3
+    }
+    let result = inner(n);
+    memoized_write_back_inner();
+    assert_eq!(memoized_size_inner(), 2);
+    result
+}
+
+fn main() {
+    assert_eq!(outer(1), 2);
+    assert_eq!(other(1), 3);
+
+    let closure = |n: u64| {
+        #[memoize(Precompute: [(1)])]
+        const fn square(n: u64) -> u64 {
+            n * n
+        }
+        square(n)
+    };
+    assert_eq!(closure(1), 1);
+    assert_eq!(closure(2), 4);
+}
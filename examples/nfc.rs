@@ -0,0 +1,50 @@
+#[cfg(feature = "nfc")]
+use memoize::memoize;
+
+#[cfg(feature = "nfc")]
+static mut CALLS: usize = 0;
+
+#[cfg(feature = "nfc")]
+#[memoize(Nfc)]
+fn word_count(text: String, language: u8) -> usize {
+    unsafe { CALLS += 1 };
+    let _ = language;
+    text.split_whitespace().count()
+}
+
+#[cfg(feature = "nfc")]
+fn fold_case(text: String) -> String {
+    text.to_lowercase()
+}
+
+// `Nfc` is applied before the `NormalizeKey` function.
+#[cfg(feature = "nfc")]
+#[memoize(SharedCache, Nfc, NormalizeKey: fold_case)]
+fn shout(text: String) -> String {
+    text.to_uppercase()
+}
+
+#[cfg(feature = "nfc")]
+fn main() {
+    // A precomposed "é", and an "e" followed by a combining acute accent.
+    let composed = "caf\u{e9} cr\u{e8}me".to_string();
+    let decomposed = "cafe\u{301} cre\u{300}me".to_string();
+    assert_ne!(composed, decomposed);
+
+    assert_eq!(word_count(composed.clone(), 1), 2);
+    assert_eq!(word_count(decomposed.clone(), 1), 2);
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(word_count(decomposed.clone(), 2), 2);
+    assert_eq!(memoized_size_word_count(), 2);
+
+    assert_eq!(memoize::nfc(decomposed.clone()), composed);
+
+    shout(composed.clone());
+    shout(decomposed.to_uppercase());
+    assert_eq!(memoized_size_shout(), 1);
+}
+
+#[cfg(not(feature = "nfc"))]
+fn main() {
+    println!("Use the \"nfc\" feature to execute this example");
+}
@@ -0,0 +1,34 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+fn fold_case(word: String) -> String {
+    word.trim().to_lowercase()
+}
+
+#[memoize(NormalizeKey: fold_case)]
+fn definition(word: String) -> String {
+    unsafe { CALLS += 1 };
+    format!("definition of {}", word.trim().to_lowercase())
+}
+
+fn round_down((name, year): (String, i32)) -> (String, i32) {
+    (name.to_uppercase(), year - year % 10)
+}
+
+#[memoize(SharedCache, NormalizeKey: round_down)]
+fn decade_report(name: String, year: i32) -> String {
+    format!("{} in the {}s", name.to_uppercase(), year - year % 10)
+}
+
+fn main() {
+    assert_eq!(definition("Memo".to_string()), "definition of memo");
+    assert_eq!(definition(" memo ".to_string()), "definition of memo");
+    assert_eq!(definition("MEMO".to_string()), "definition of memo");
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(memoized_size_definition(), 1);
+
+    assert_eq!(decade_report("rust".to_string(), 2015), "RUST in the 2010s");
+    assert_eq!(decade_report("Rust".to_string(), 2019), "RUST in the 2010s");
+    assert_eq!(memoized_size_decade_report(), 1);
+}
@@ -0,0 +1,25 @@
+// Run with `--features=opentelemetry`. Without an installed meter provider, the counters are no-ops;
+// in an application, install the provider (e.g. an OTLP exporter) before the first memoized call.
+#[cfg(feature = "opentelemetry")]
+use memoize::{memoize, Stats};
+
+#[cfg(feature = "opentelemetry")]
+#[memoize(Capacity: 2)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+#[cfg(feature = "opentelemetry")]
+fn main() {
+    // Counted in `memoize.hits`, `memoize.misses` and `memoize.evictions`, with the attribute
+    // `function = "square"`: one hit, four misses and two evictions.
+    for n in [1, 2, 2, 3, 1] {
+        assert_eq!(square(n), n * n);
+    }
+    assert_eq!(memoized_stats_square(), Stats { hits: 1, misses: 4 });
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+fn main() {
+    println!("Use the \"opentelemetry\" feature to execute this example");
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Tier {
+    Free,
+    Premium,
+}
+
+#[cfg(feature = "full")]
+static mut CALLS: u32 = 0;
+
+// Each tier has its own capacity of two entries.
+#[cfg(feature = "full")]
+#[memoize(Capacity: 2, PartitionBy: |key: &(Tier, u32)| key.0: Tier)]
+fn render(tier: Tier, page: u32) -> String {
+    unsafe { CALLS += 1 };
+    format!("{:?} page {}", tier, page)
+}
+
+#[cfg(feature = "full")]
+fn calls() -> u32 {
+    unsafe { CALLS }
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    render(Tier::Premium, 1);
+    render(Tier::Premium, 2);
+    assert_eq!(calls(), 2);
+
+    // Lots of free pages only evict other free pages.
+    for page in 0..10 {
+        render(Tier::Free, page);
+    }
+    assert_eq!(calls(), 12);
+    assert_eq!(render(Tier::Premium, 1), "Premium page 1");
+    assert_eq!(render(Tier::Premium, 2), "Premium page 2");
+    assert_eq!(calls(), 12);
+    assert_eq!(memoized_size_render(), 4);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
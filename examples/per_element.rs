@@ -0,0 +1,31 @@
+use memoize::memoize;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Item {
+    id: u64,
+}
+
+#[memoize(PerElement, Ignore: calls)]
+fn lookup(ids: &[u64], calls: &mut Vec<Vec<u64>>) -> Vec<Item> {
+    calls.push(ids.to_vec());
+    ids.iter().map(|&id| Item { id }).collect()
+}
+
+fn main() {
+    let mut calls = vec![];
+    assert_eq!(
+        lookup(&[1, 2], &mut calls),
+        vec![Item { id: 1 }, Item { id: 2 }]
+    );
+    // Only the missing id is looked up, and the results are merged in order.
+    assert_eq!(
+        lookup(&[3, 2, 1], &mut calls),
+        vec![Item { id: 3 }, Item { id: 2 }, Item { id: 1 }]
+    );
+    assert_eq!(calls, vec![vec![1, 2], vec![3]]);
+    assert_eq!(memoized_size_lookup(), 3);
+    assert_eq!(
+        memoized_get_many_lookup(vec![1, 4]),
+        vec![Some(Item { id: 1 }), None]
+    );
+}
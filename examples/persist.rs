@@ -0,0 +1,97 @@
+#[cfg(feature = "serde")]
+mod persist {
+    use memoize::memoize;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    static mut CALLS: usize = 0;
+
+    pub fn path() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "memoize-example-persist-{}.bin",
+            std::process::id()
+        ))
+    }
+
+    // The cache file is given by the parent process, which shares it with its children.
+    #[memoize(SharedCache, Persist: std::env::var("MEMOIZE_EXAMPLE_CACHE").unwrap())]
+    pub fn square(n: u64) -> u64 {
+        unsafe { CALLS += 1 };
+        n * n
+    }
+
+    // Another version of the function, which must not load the entries of the first one.
+    #[memoize(
+        SharedCache,
+        Persist: std::env::var("MEMOIZE_EXAMPLE_CACHE").unwrap(),
+        PersistSalt: "v2"
+    )]
+    pub fn square_v2(n: u64) -> u64 {
+        unsafe { CALLS += 1 };
+        n * n
+    }
+
+    pub fn main() {
+        if std::env::var("MEMOIZE_EXAMPLE_CACHE").is_ok() {
+            // A child process: computes (or finds) some squares, which are written to the file
+            // when it exits.
+            for n in 0..10 {
+                assert_eq!(square(n), n * n);
+            }
+            return;
+        }
+
+        // The file starts with an entry, and a record cut off by a process killed while writing it,
+        // which is removed before other records are appended.
+        let path = path();
+        std::env::set_var("MEMOIZE_EXAMPLE_CACHE", &path);
+        assert_eq!(square(100), 10000);
+        memoize::flush_persistence();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[100, 0, 0, 0, 1, 2]).unwrap();
+
+        // Run several invocations concurrently, like a build system would.
+        let children: Vec<_> = (0..4)
+            .map(|_| {
+                Command::new(std::env::current_exe().unwrap())
+                    .env("MEMOIZE_EXAMPLE_CACHE", &path)
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for mut child in children {
+            assert!(child.wait().unwrap().success());
+        }
+
+        // All results are found on disk.
+        for n in 0..10 {
+            assert_eq!(square(n), n * n);
+        }
+        assert_eq!(unsafe { CALLS }, 1);
+        assert_eq!(memoized_size_square(), 11);
+
+        // Flushing also clears the file.
+        let len = std::fs::metadata(&path).unwrap().len();
+        memoized_flush_square();
+        assert!(std::fs::metadata(&path).unwrap().len() < len);
+
+        // Files written for another fingerprint (of the signature and options) are not loaded.
+        assert_eq!(square(3), 9);
+        memoize::flush_persistence();
+        assert_eq!(square_v2(3), 9);
+        assert_eq!(unsafe { CALLS }, 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    persist::main();
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
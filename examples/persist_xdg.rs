@@ -0,0 +1,38 @@
+#[cfg(feature = "serde")]
+mod persist_xdg {
+    use memoize::memoize;
+
+    #[memoize(PersistXdg: "memoize-example")]
+    pub fn greeting(name: String) -> String {
+        format!("Hello, {}!", name)
+    }
+
+    pub fn main() {
+        // Keep the example's cache out of the user's cache directory.
+        let dir = std::env::temp_dir().join(format!("memoize-example-xdg-{}", std::process::id()));
+        std::env::set_var("XDG_CACHE_HOME", &dir);
+        std::env::set_var("HOME", &dir);
+        std::env::set_var("LOCALAPPDATA", &dir);
+
+        assert_eq!(greeting("World".to_string()), "Hello, World!");
+
+        let file = memoize::cache_file("memoize-example", "persist_xdg::persist_xdg::greeting");
+        assert!(file.starts_with(&dir));
+        assert!(file.ends_with("memoize-example/persist_xdg.persist_xdg.greeting.bin"));
+        // New entries are written in batches, or when explicitly flushed.
+        let len = std::fs::metadata(&file).unwrap().len();
+        memoize::flush_persistence();
+        assert!(std::fs::metadata(&file).unwrap().len() > len);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    persist_xdg::main();
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
@@ -0,0 +1,25 @@
+use memoize::memoize;
+
+// The results for the listed inputs are computed by the compiler.
+#[memoize(Precompute: [(0), (1), (2), (10)])]
+const fn triangle(n: u64) -> u64 {
+    n * (n + 1) / 2
+}
+
+#[memoize(Precompute: [(2, 3), (3, 4)])]
+const fn area(width: u32, height: u32) -> u32 {
+    width * height
+}
+
+fn main() {
+    // Served from the precomputed table, the runtime cache is not touched.
+    assert_eq!(triangle(10), 55);
+    assert_eq!(memoized_size_triangle(), 0);
+    // Other inputs are memoized as usual.
+    assert_eq!(triangle(4), 10);
+    assert_eq!(memoized_size_triangle(), 1);
+
+    assert_eq!(area(2, 3), 6);
+    assert_eq!(area(3, 2), 6);
+    assert_eq!(memoized_size_area(), 1);
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+
+#[cfg(feature = "full")]
+static mut CALLS: u32 = 0;
+
+// Results for large inputs are expensive to recompute, so they are evicted last.
+#[cfg(feature = "full")]
+#[memoize(Capacity: 2, Priority: |n: &u64, _steps: &u32| if *n >= 1000 { 1 } else { 0 })]
+fn collatz_steps(n: u64) -> u32 {
+    unsafe { CALLS += 1 };
+    let (mut n, mut steps) = (n, 0);
+    while n != 1 {
+        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+        steps += 1;
+    }
+    steps
+}
+
+#[cfg(feature = "full")]
+fn calls() -> u32 {
+    unsafe { CALLS }
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    assert_eq!(collatz_steps(9999), 91);
+    assert_eq!(collatz_steps(7), 16);
+    assert_eq!(collatz_steps(9), 19);
+    assert_eq!(calls(), 3);
+
+    // 7 was evicted rather than 9999, although 9999 is the least recently used entry.
+    assert_eq!(collatz_steps(9999), 91);
+    assert_eq!(calls(), 3);
+    assert_eq!(collatz_steps(7), 16);
+    assert_eq!(calls(), 4);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
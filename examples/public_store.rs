@@ -0,0 +1,42 @@
+use memoize::memoize;
+
+mod prices {
+    use memoize::memoize;
+
+    #[memoize(SharedCache, PublicStore)]
+    pub fn price(item: u32) -> u64 {
+        item as u64 * 40
+    }
+}
+
+#[memoize(ThreadLocal, PublicStore)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    for item in 1..=5 {
+        prices::price(item);
+    }
+    let mut expensive: Vec<u32> = prices::MEMOIZED_MAPPING_PRICE
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, price)| **price > 100)
+        .map(|(item, _)| *item)
+        .collect();
+    expensive.sort();
+    assert_eq!(expensive, vec![3, 4, 5]);
+
+    // A custom eviction: drop the entries of odd items.
+    prices::MEMOIZED_MAPPING_PRICE
+        .lock()
+        .unwrap()
+        .retain(|item, _| item % 2 == 0);
+    assert_eq!(prices::memoized_size_price(), 2);
+
+    square(3);
+    square(4);
+    let total: u64 = MEMOIZED_MAPPING_SQUARE.with(|cache| cache.borrow().values().sum());
+    assert_eq!(total, 25);
+}
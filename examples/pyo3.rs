@@ -0,0 +1,62 @@
+#[cfg(feature = "pyo3")]
+mod python {
+    use memoize::memoize;
+    use pyo3::prelude::*;
+    use pyo3::types::PyAny;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    // The GIL token isn't part of the key, which is `(text, separator)`.
+    #[memoize(Capacity: 100)]
+    #[pyfunction]
+    #[pyo3(signature = (text, separator = " ".to_string()))]
+    fn tokenize(_py: Python<'_>, text: String, separator: String) -> Vec<String> {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        text.split(separator.as_str()).map(str::to_string).collect()
+    }
+
+    // The argument attribute is applied by `#[pyfunction]`: any sized Python object is accepted, and
+    // its length is the key.
+    #[memoize]
+    #[pyfunction]
+    fn blocks(#[pyo3(from_py_with = Bound::<'_, PyAny>::len)] len: usize) -> usize {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        len.div_ceil(4)
+    }
+
+    pub fn main() {
+        Python::with_gil(|py| {
+            let tokenize = wrap_pyfunction!(tokenize, py).unwrap();
+            let blocks = wrap_pyfunction!(blocks, py).unwrap();
+            pyo3::py_run!(
+                py,
+                tokenize blocks,
+                r#"
+assert tokenize("a b") == ["a", "b"]
+assert tokenize("a b", separator=" ") == ["a", "b"]
+assert tokenize("a,b", ",") == ["a", "b"]
+assert blocks([1, 2, 3, 4, 5]) == 2
+assert blocks("hello") == 2
+"#
+            );
+        });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+        assert_eq!(memoized_size_tokenize(), 2);
+
+        // Called from Rust, with another GIL token, the entry is found as well.
+        let tokens = Python::with_gil(|py| tokenize(py, "a b".to_string(), " ".to_string()));
+        assert_eq!(tokens, vec!["a", "b"]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+    }
+}
+
+#[cfg(feature = "pyo3")]
+fn main() {
+    python::main();
+}
+
+#[cfg(not(feature = "pyo3"))]
+fn main() {
+    println!("Use the \"pyo3\" feature to execute this example");
+}
@@ -0,0 +1,34 @@
+use memoize::memoize;
+use std::time::Duration;
+
+static mut CALLS: usize = 0;
+
+// Temperatures are bucketed to a tenth of a degree.
+#[memoize(Quantize: temperature => (temperature * 10.0).round() as i64)]
+fn viscosity(temperature: f64, pressure: u32) -> f64 {
+    unsafe { CALLS += 1 };
+    ((temperature * 10.0).round() / 10.0) * pressure as f64
+}
+
+#[memoize(SharedCache, Quantize: age => age.as_secs() / 60: u64)]
+fn freshness(age: Duration) -> &'static str {
+    if age.as_secs() < 60 {
+        "fresh"
+    } else {
+        "stale"
+    }
+}
+
+fn main() {
+    assert_eq!(viscosity(20.01, 2), 40.0);
+    assert_eq!(viscosity(19.98, 2), 40.0);
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(viscosity(20.2, 2), 40.4);
+    assert_eq!(viscosity(20.01, 3), 60.0);
+    assert_eq!(memoized_size_viscosity(), 3);
+
+    assert_eq!(freshness(Duration::from_secs(5)), "fresh");
+    assert_eq!(freshness(Duration::from_secs(59)), "fresh");
+    assert_eq!(freshness(Duration::from_secs(61)), "stale");
+    assert_eq!(memoized_size_freshness(), 2);
+}
@@ -0,0 +1,46 @@
+#[cfg(feature = "rayon")]
+mod rayon_shared {
+    use memoize::memoize;
+    use memoize::rayon::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[memoize(RayonShared, WriteBack: 16)]
+    pub fn score(item: u64) -> u64 {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        item * item
+    }
+
+    pub fn total(items: &[u64]) -> u64 {
+        items.par_iter().map(|item| score(*item % 100)).sum()
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn main() {
+    use rayon_shared::*;
+    use std::sync::atomic::Ordering;
+
+    let items: Vec<u64> = (0..10_000).collect();
+    let expected: u64 = items.iter().map(|item| (item % 100) * (item % 100)).sum();
+    assert_eq!(total(&items), expected);
+
+    // Publish the buffers of all workers.
+    memoize::rayon::broadcast(|_| memoized_write_back_score());
+    assert_eq!(memoized_size_score(), 100);
+
+    // Workers now find all results in the shared cache.
+    let calls = CALLS.load(Ordering::Relaxed);
+    assert_eq!(total(&items), expected);
+    assert_eq!(CALLS.load(Ordering::Relaxed), calls);
+
+    // Outside of rayon, the shared cache is used directly.
+    assert_eq!(score(1_000), 1_000_000);
+    assert_eq!(memoized_size_score(), 101);
+}
+
+#[cfg(not(feature = "rayon"))]
+fn main() {
+    println!("Use the \"rayon\" feature to execute this example");
+}
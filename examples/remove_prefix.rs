@@ -0,0 +1,28 @@
+use memoize::memoize;
+
+#[memoize(SharedCache)]
+fn permission(user: String, resource: u32) -> bool {
+    resource.is_multiple_of(2) || user == "admin"
+}
+
+#[memoize]
+fn report(user: u32, month: u8, detailed: bool) -> String {
+    format!("report of {} for {} ({})", user, month, detailed)
+}
+
+fn main() {
+    for resource in 0..4 {
+        permission("alice".to_string(), resource);
+        permission("bob".to_string(), resource);
+    }
+    assert_eq!(memoized_size_permission(), 8);
+    assert_eq!(memoized_remove_prefix_permission(&"alice".to_string()), 4);
+    assert_eq!(memoized_size_permission(), 4);
+    assert_eq!(memoized_remove_prefix_permission(&"alice".to_string()), 0);
+
+    report(1, 1, false);
+    report(1, 2, true);
+    report(2, 1, false);
+    assert_eq!(memoized_remove_prefix_report(&1), 2);
+    assert_eq!(memoized_size_report(), 1);
+}
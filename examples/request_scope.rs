@@ -0,0 +1,65 @@
+#[cfg(feature = "tower")]
+mod request_scope {
+    use memoize::memoize;
+    use memoize::tower::{Layer, RequestScopeLayer, Service};
+    use std::convert::Infallible;
+    use std::future::{poll_fn, Future};
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    pub static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[memoize(RequestScoped)]
+    async fn load_user(id: u64) -> String {
+        LOOKUPS.fetch_add(1, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        format!("user-{}", id)
+    }
+
+    /// A handler looking up the same user several times.
+    #[derive(Clone)]
+    struct ShowUser;
+
+    impl Service<u64> for ShowUser {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<String, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, id: u64) -> Self::Future {
+            Box::pin(async move {
+                let name = load_user(id).await;
+                let greeting = format!("Hello, {}", load_user(id).await);
+                Ok(format!("{} ({})", greeting, name))
+            })
+        }
+    }
+
+    pub async fn serve(requests: Vec<u64>) {
+        let mut service = RequestScopeLayer.layer(ShowUser);
+        for id in requests {
+            poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+            let response = service.call(id).await.unwrap();
+            assert_eq!(response, format!("Hello, user-{} (user-{})", id, id));
+        }
+    }
+}
+
+#[cfg(feature = "tower")]
+#[tokio::main]
+async fn main() {
+    use std::sync::atomic::Ordering;
+
+    request_scope::serve(vec![1, 2, 1]).await;
+    // One lookup per request.
+    assert_eq!(request_scope::LOOKUPS.load(Ordering::SeqCst), 3);
+}
+
+#[cfg(not(feature = "tower"))]
+fn main() {
+    println!("Use the \"tower\" feature to execute this example");
+}
@@ -0,0 +1,78 @@
+#[cfg(feature = "full")]
+use memoize::{memoize, Hooks};
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(feature = "full")]
+static EVICTED: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(feature = "full")]
+struct EvictionCounter;
+
+#[cfg(feature = "full")]
+impl Hooks for EvictionCounter {
+    fn on_evict(&self, _function: &'static str) {
+        EVICTED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(Capacity: 2)]
+fn page(n: u32) -> String {
+    format!("page {}", n)
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 4)]
+fn user(id: u32) -> String {
+    format!("user {}", id)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    memoize::set_hooks(EvictionCounter);
+
+    // Growing the cache keeps its entries, and makes room for more.
+    page(1);
+    page(2);
+    memoized_resize_page(4);
+    for n in 3..=4 {
+        page(n);
+    }
+    assert_eq!(memoized_size_page(), 4);
+    assert_eq!(EVICTED.load(Ordering::SeqCst), 0);
+
+    // Shrinking it evicts the least recently used entries.
+    page(1);
+    memoized_resize_page(2);
+    assert_eq!(memoized_size_page(), 2);
+    assert_eq!(memoized_lru_back_page(), Some(4));
+    assert_eq!(memoized_lru_front_page(), Some(1));
+    assert_eq!(EVICTED.load(Ordering::SeqCst), 2);
+
+    // Other threads keep the capacity given to `#[memoize]`.
+    std::thread::spawn(|| {
+        for n in 1..=3 {
+            page(n);
+        }
+        assert_eq!(memoized_size_page(), 2);
+    })
+    .join()
+    .unwrap();
+
+    for id in 0..4 {
+        user(id);
+    }
+    memoized_resize_user(1);
+    assert_eq!(memoized_size_user(), 1);
+    assert_eq!(memoized_lru_front_user(), Some(3));
+    // The capacity is at least one.
+    memoized_resize_user(0);
+    user(4);
+    assert_eq!(memoized_size_user(), 1);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,50 @@
+use memoize::memoize;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+struct Response {
+    body: String,
+    etag: u32,
+    max_age: Option<Duration>,
+}
+
+// The version of each document on the origin server.
+static mut VERSION: u32 = 1;
+static mut FETCHES: u32 = 0;
+static mut VALIDATIONS: u32 = 0;
+
+// A cheap conditional request, like `If-None-Match`.
+fn not_modified(_url: &String, response: &Response) -> bool {
+    unsafe { VALIDATIONS += 1 };
+    response.etag == unsafe { VERSION }
+}
+
+#[memoize(MaxAge: |response: &Response| response.max_age, Revalidate: not_modified)]
+fn fetch(url: String) -> Response {
+    unsafe { FETCHES += 1 };
+    let etag = unsafe { VERSION };
+    Response {
+        body: format!("{} (version {})", url, etag),
+        etag,
+        max_age: Some(Duration::from_millis(50)),
+    }
+}
+
+fn main() {
+    assert_eq!(fetch("/index".to_string()).body, "/index (version 1)");
+    assert_eq!(fetch("/index".to_string()).etag, 1);
+    assert_eq!(unsafe { (FETCHES, VALIDATIONS) }, (1, 0));
+
+    // Stale, but still current: the cached response is renewed.
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(fetch("/index".to_string()).etag, 1);
+    assert_eq!(fetch("/index".to_string()).etag, 1);
+    assert_eq!(unsafe { (FETCHES, VALIDATIONS) }, (1, 1));
+
+    // Stale and modified: the response is fetched again.
+    unsafe { VERSION = 2 };
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(fetch("/index".to_string()).body, "/index (version 2)");
+    assert_eq!(unsafe { (FETCHES, VALIDATIONS) }, (2, 2));
+}
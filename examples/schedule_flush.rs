@@ -0,0 +1,51 @@
+#[cfg(feature = "schedule")]
+use memoize::memoize;
+
+#[cfg(feature = "schedule")]
+#[memoize(SharedCache)]
+fn exchange_rate(currency: String) -> f64 {
+    if currency == "EUR" {
+        1.1
+    } else {
+        1.0
+    }
+}
+
+#[cfg(feature = "schedule")]
+#[memoize]
+fn local(n: u32) -> u32 {
+    n + 1
+}
+
+#[cfg(feature = "schedule")]
+fn main() {
+    // Entries inserted before the first call are flushed as well.
+    memoized_insert_exchange_rate("GBP".to_string(), 1.3);
+    memoize::flush_by_tag("exchange_rate");
+    assert_eq!(memoized_size_exchange_rate(), 0);
+
+    memoize::schedule_flush("exchange_rate", "30 2 * * *").unwrap();
+    memoize::schedule_flush("schedule_flush::local", "*/15 0-6 1,15 * 1-5").unwrap();
+    memoize::schedule_flush("local", "@daily").unwrap();
+    assert!(memoize::schedule_flush("local", "61 * * * *").is_err());
+    assert!(memoize::schedule_flush("local", "* * *").is_err());
+
+    assert_eq!(exchange_rate("EUR".to_string()), 1.1);
+    assert_eq!(exchange_rate("USD".to_string()), 1.0);
+    assert_eq!(memoized_size_exchange_rate(), 2);
+    memoize::flush_by_tag("exchange_rate");
+    assert_eq!(memoized_size_exchange_rate(), 0);
+
+    assert_eq!(local(1), 2);
+    assert_eq!(local(2), 3);
+    assert_eq!(memoized_size_local(), 2);
+    // Thread-local caches are flushed on their next call.
+    memoize::flush_by_tag("schedule_flush::local");
+    assert_eq!(local(1), 2);
+    assert_eq!(memoized_size_local(), 1);
+}
+
+#[cfg(not(feature = "schedule"))]
+fn main() {
+    println!("Use the \"schedule\" feature to execute this example");
+}
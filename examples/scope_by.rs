@@ -0,0 +1,48 @@
+use memoize::memoize;
+use std::cell::Cell;
+
+thread_local! {
+    static TENANT: Cell<u32> = const { Cell::new(0) };
+}
+
+fn current_tenant() -> u32 {
+    TENANT.with(|tenant| tenant.get())
+}
+
+fn as_tenant<T>(tenant: u32, f: impl FnOnce() -> T) -> T {
+    TENANT.with(|t| t.set(tenant));
+    f()
+}
+
+static mut CALLS: usize = 0;
+
+#[memoize(SharedCache, ScopeBy: current_tenant(): u32)]
+fn dashboard(user: String) -> String {
+    unsafe { CALLS += 1 };
+    format!("dashboard of {} for tenant {}", user, current_tenant())
+}
+
+#[memoize(ScopeBy: current_tenant(): u32, Ignore: verbose)]
+fn greeting(verbose: bool) -> String {
+    let _ = verbose;
+    format!("hello tenant {}", current_tenant())
+}
+
+fn main() {
+    let a = as_tenant(1, || dashboard("bob".to_string()));
+    let b = as_tenant(2, || dashboard("bob".to_string()));
+    assert_eq!(a, "dashboard of bob for tenant 1");
+    assert_eq!(b, "dashboard of bob for tenant 2");
+    assert_eq!(as_tenant(1, || dashboard("bob".to_string())), a);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_dashboard(), 2);
+    // Keys include the scope.
+    assert_eq!(
+        memoized_get_many_dashboard(vec![(2, "bob".to_string()), (3, "bob".to_string())]),
+        vec![Some(b), None]
+    );
+
+    assert_eq!(as_tenant(7, || greeting(true)), "hello tenant 7");
+    assert_eq!(as_tenant(8, || greeting(false)), "hello tenant 8");
+    assert_eq!(memoized_size_greeting(), 2);
+}
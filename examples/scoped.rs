@@ -0,0 +1,46 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+#[memoize(Scoped)]
+fn exchange_rate(from: &'static str, to: &'static str) -> f64 {
+    unsafe { CALLS += 1 };
+    match (from, to) {
+        ("EUR", "USD") => 1.1,
+        ("USD", "EUR") => 0.9,
+        _ => 1.0,
+    }
+}
+
+fn process(batch: &[(&'static str, f64)]) -> f64 {
+    batch
+        .iter()
+        .map(|(currency, amount)| amount * exchange_rate(currency, "USD"))
+        .sum()
+}
+
+fn main() {
+    let batches = [
+        vec![("EUR", 10.0), ("EUR", 20.0), ("USD", 5.0)],
+        vec![("EUR", 1.0), ("USD", 2.0)],
+    ];
+    for batch in &batches {
+        memoize::scope(|| {
+            process(batch);
+            assert_eq!(memoized_size_exchange_rate(), 2);
+        });
+    }
+    // Each batch looked up each rate once.
+    assert_eq!(unsafe { CALLS }, 4);
+
+    // Nested scopes have caches of their own.
+    memoize::scope(|| {
+        exchange_rate("EUR", "USD");
+        memoize::scope(|| assert_eq!(memoized_size_exchange_rate(), 0));
+        assert_eq!(memoized_size_exchange_rate(), 1);
+    });
+
+    // Outside of a scope, nothing is cached.
+    exchange_rate("USD", "EUR");
+    assert_eq!(memoized_size_exchange_rate(), 0);
+}
@@ -0,0 +1,102 @@
+#[cfg(feature = "serde")]
+use memoize::{memoize, SecondaryStore};
+#[cfg(feature = "serde")]
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::sync::Mutex;
+
+// Stands in for Redis, or a directory on disk.
+#[cfg(feature = "serde")]
+struct External(Mutex<BTreeMap<Vec<u8>, Vec<u8>>>);
+
+#[cfg(feature = "serde")]
+impl SecondaryStore for External {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        self.0.lock().unwrap().insert(key.to_vec(), value.to_vec());
+    }
+}
+
+#[cfg(feature = "serde")]
+static EXTERNAL: External = External(Mutex::new(BTreeMap::new()));
+
+#[cfg(feature = "serde")]
+static mut CALLS: usize = 0;
+
+#[cfg(feature = "serde")]
+#[memoize(SharedCache, Secondary: &EXTERNAL)]
+fn render(page: u32, lang: String) -> String {
+    unsafe { CALLS += 1 };
+    format!("<h1>{} ({})</h1>", page, lang)
+}
+
+#[cfg(feature = "serde")]
+#[memoize(Secondary: &EXTERNAL)]
+fn square(n: u64) -> u64 {
+    unsafe { CALLS += 1 };
+    n * n
+}
+
+// Writes to slow stores can be taken off the hot path.
+#[cfg(feature = "serde")]
+struct Slow(Mutex<Vec<Vec<u8>>>);
+
+#[cfg(feature = "serde")]
+impl SecondaryStore for Slow {
+    fn get(&self, _key: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn put(&self, key: &[u8], _value: &[u8]) {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        self.0.lock().unwrap().push(key.to_vec());
+    }
+}
+
+#[cfg(feature = "serde")]
+static SLOW: Slow = Slow(Mutex::new(Vec::new()));
+
+#[cfg(feature = "serde")]
+#[memoize(SharedCache, Secondary: &SLOW, WriteBehind)]
+fn cube(n: u64) -> u64 {
+    n * n * n
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    assert_eq!(render(1, "en".to_string()), "<h1>1 (en)</h1>");
+    assert_eq!(unsafe { CALLS }, 1);
+    // Written through to the external store.
+    assert_eq!(EXTERNAL.0.lock().unwrap().len(), 1);
+
+    // After the in-memory cache is gone (e.g. in a new process), the external store answers.
+    memoized_flush_render();
+    assert_eq!(render(1, "en".to_string()), "<h1>1 (en)</h1>");
+    assert_eq!(unsafe { CALLS }, 1);
+    assert_eq!(memoized_size_render(), 1);
+
+    assert_eq!(square(4), 16);
+    assert_eq!(square(4), 16);
+    assert_eq!(unsafe { CALLS }, 2);
+    // Both functions share the store, under different keys.
+    assert_eq!(EXTERNAL.0.lock().unwrap().len(), 2);
+    memoized_flush_square();
+    assert_eq!(square(4), 16);
+    assert_eq!(unsafe { CALLS }, 2);
+
+    let start = std::time::Instant::now();
+    for n in 0..5 {
+        assert_eq!(cube(n), n * n * n);
+    }
+    assert!(start.elapsed() < std::time::Duration::from_millis(100));
+    memoize::flush_write_behind();
+    assert_eq!(SLOW.0.lock().unwrap().len(), 5);
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
@@ -0,0 +1,57 @@
+#[cfg(feature = "serde")]
+use memoize::memoize;
+
+#[cfg(feature = "serde")]
+static mut CALLS: usize = 0;
+
+#[cfg(feature = "serde")]
+#[memoize(SharedCache, Seed)]
+fn distance(from: u32, to: u32) -> u64 {
+    unsafe { CALLS += 1 };
+    (from as u64).abs_diff(to as u64) * 10
+}
+
+#[cfg(feature = "serde")]
+#[memoize(Seed)]
+fn name(id: u32) -> String {
+    format!("city-{}", id)
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    let dir = std::env::temp_dir().join(format!("memoize-seed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let routes = dir.join("routes.seed");
+
+    // Written offline, e.g. by a batch job. Seeded values don't need to be what the function
+    // would compute.
+    memoize::write_seed(
+        &routes,
+        (0..10u32).map(|to| ((0u32, to), 1000u64 + to as u64)),
+    )
+    .unwrap();
+    assert_eq!(memoized_load_seed_distance(&routes).unwrap(), 10);
+    assert_eq!(memoized_size_distance(), 10);
+    assert_eq!(distance(0, 3), 1003);
+    assert_eq!(distance(5, 3), 20);
+    assert_eq!(unsafe { CALLS }, 1);
+
+    let names = dir.join("names.seed");
+    memoize::write_seed(&names, vec![(1u32, "Paris".to_string())]).unwrap();
+    assert_eq!(memoized_load_seed_name(&names).unwrap(), 1);
+    assert_eq!(name(1), "Paris");
+    assert_eq!(name(2), "city-2");
+
+    let pairs: Vec<(u32, String)> = memoize::read_seed(&names).unwrap();
+    assert_eq!(pairs, vec![(1, "Paris".to_string())]);
+    assert!(memoized_load_seed_name(dir.join("missing.seed")).is_err());
+    std::fs::write(dir.join("garbage.seed"), b"garbage!").unwrap();
+    assert!(memoized_load_seed_name(dir.join("garbage.seed")).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
@@ -0,0 +1,56 @@
+#[cfg(feature = "serde")]
+use memoize::memoize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// Like a third-party type: neither `Clone`, `Hash` nor `Eq` (floats can't be hashed).
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct Config {
+    name: String,
+    factor: f64,
+}
+
+#[cfg(feature = "serde")]
+static mut CALLS: usize = 0;
+
+#[cfg(feature = "serde")]
+#[memoize(SerdeKeys)]
+fn simulate(config: Config, steps: u32) -> f64 {
+    unsafe { CALLS += 1 };
+    config.factor * steps as f64
+}
+
+#[cfg(feature = "serde")]
+#[memoize(SharedCache, SerdeKeys, Ignore: log)]
+fn describe(config: &Config, log: &mut Vec<String>) -> String {
+    log.push(config.name.clone());
+    format!("{} x{}", config.name, config.factor)
+}
+
+#[cfg(feature = "serde")]
+fn config(name: &str, factor: f64) -> Config {
+    Config {
+        name: name.to_string(),
+        factor,
+    }
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    assert_eq!(simulate(config("a", 1.5), 2), 3.0);
+    assert_eq!(simulate(config("a", 1.5), 2), 3.0);
+    assert_eq!(simulate(config("a", 2.5), 2), 5.0);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_simulate(), 2);
+
+    let mut log = vec![];
+    assert_eq!(describe(&config("b", 0.5), &mut log), "b x0.5");
+    assert_eq!(describe(&config("b", 0.5), &mut log), "b x0.5");
+    assert_eq!(log, vec!["b"]);
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
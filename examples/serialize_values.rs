@@ -0,0 +1,29 @@
+#[cfg(feature = "serde")]
+use memoize::memoize;
+
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Histogram {
+    buckets: Vec<u32>,
+}
+
+#[cfg(feature = "serde")]
+#[memoize(SerializeValues)]
+fn histogram(n: u32) -> Histogram {
+    Histogram {
+        buckets: (0..n).collect(),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn main() {
+    assert_eq!(histogram(4).buckets, vec![0, 1, 2, 3]);
+    assert_eq!(histogram(4).buckets, vec![0, 1, 2, 3]);
+    // The length prefix and four u32 buckets.
+    assert_eq!(memoized_bytes_histogram(), 8 + 4 * 4);
+}
+
+#[cfg(not(feature = "serde"))]
+fn main() {
+    println!("Use the \"serde\" feature to execute this example");
+}
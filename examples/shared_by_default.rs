@@ -0,0 +1,35 @@
+use memoize::memoize;
+use std::thread;
+
+// Shared with the `shared` feature.
+#[memoize]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+#[memoize(ThreadLocal)]
+fn cube(n: u64) -> u64 {
+    n * n * n
+}
+
+#[memoize(SharedCache)]
+fn double(n: u64) -> u64 {
+    n * 2
+}
+
+fn main() {
+    thread::spawn(|| {
+        square(2);
+        cube(2);
+        double(2);
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(
+        memoized_size_square(),
+        if cfg!(feature = "shared") { 1 } else { 0 }
+    );
+    assert_eq!(memoized_size_cube(), 0);
+    assert_eq!(memoized_size_double(), 1);
+}
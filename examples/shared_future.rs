@@ -0,0 +1,105 @@
+#[cfg(any(feature = "async", feature = "tokio"))]
+mod shared_future {
+    use memoize::memoize;
+    use std::time::Duration;
+
+    static mut CALLS: usize = 0;
+
+    #[memoize(SharedCache, SharedFuture)]
+    pub async fn download(url: String) -> Vec<u8> {
+        unsafe { CALLS += 1 };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        url.into_bytes()
+    }
+
+    static mut ATTEMPTS: usize = 0;
+
+    // Failed futures are not cached.
+    #[memoize(SharedFuture)]
+    pub async fn connect(host: String) -> Result<String, String> {
+        unsafe { ATTEMPTS += 1 };
+        if unsafe { ATTEMPTS } < 3 {
+            Err(format!("{} is unreachable", host))
+        } else {
+            Ok(format!("connected to {}", host))
+        }
+    }
+
+    static mut PANICS: usize = 0;
+
+    #[memoize(SharedCache, SharedFuture)]
+    pub async fn flaky(n: u64) -> u64 {
+        unsafe { PANICS += 1 };
+        if unsafe { PANICS } == 1 {
+            panic!("first attempt fails");
+        }
+        n
+    }
+
+    static mut REPORTS: usize = 0;
+
+    #[memoize(SharedCache, SharedFuture)]
+    pub async fn report(id: u64) -> String {
+        unsafe { REPORTS += 1 };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        format!("report {}", id)
+    }
+
+    pub async fn main() {
+        // All tasks start before the first download completes, and still share it.
+        let tasks: Vec<_> = (0..8)
+            .map(|_| tokio::spawn(download("https://example.com".to_string())))
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), b"https://example.com");
+        }
+        assert_eq!(unsafe { CALLS }, 1);
+
+        // The cache holds the shared futures, which can also be awaited directly.
+        let shared = memoized_shared_download("https://example.com".to_string());
+        assert_eq!(shared.await, b"https://example.com");
+        assert_eq!(memoized_size_download(), 1);
+        assert_eq!(unsafe { CALLS }, 1);
+
+        assert!(connect("db".to_string()).await.is_err());
+        assert!(connect("db".to_string()).await.is_err());
+        assert_eq!(connect("db".to_string()).await.unwrap(), "connected to db");
+        assert_eq!(connect("db".to_string()).await.unwrap(), "connected to db");
+        assert_eq!(unsafe { ATTEMPTS }, 3);
+
+        // A panicking future is discarded as well, and retried by the next caller.
+        std::panic::set_hook(Box::new(|_| {}));
+        assert!(tokio::spawn(flaky(1)).await.is_err());
+        let _ = std::panic::take_hook();
+        assert_eq!(flaky(1).await, 1);
+        assert_eq!(flaky(1).await, 1);
+        assert_eq!(unsafe { PANICS }, 2);
+
+        // A computation abandoned by the caller that started it is not kept in the cache; a caller
+        // waiting for it meanwhile still gets the value, and later callers compute it again.
+        let peer = tokio::spawn(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            report(1).await
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(20), report(1))
+            .await
+            .is_err());
+        assert_eq!(memoized_size_report(), 0);
+        assert_eq!(peer.await.unwrap(), "report 1");
+        assert_eq!(memoized_size_report(), 0);
+        assert_eq!(report(1).await, "report 1");
+        assert_eq!(report(1).await, "report 1");
+        assert_eq!(unsafe { REPORTS }, 2);
+    }
+}
+
+#[cfg(any(feature = "async", feature = "tokio"))]
+#[tokio::main]
+async fn main() {
+    shared_future::main().await;
+}
+
+#[cfg(not(any(feature = "async", feature = "tokio")))]
+fn main() {
+    println!("Use the \"async\" feature to execute this example");
+}
@@ -0,0 +1,31 @@
+use memoize::memoize;
+
+static mut CALLS: usize = 0;
+
+#[memoize]
+fn checksum(data: &[u8]) -> u32 {
+    unsafe { CALLS += 1 };
+    data.iter().map(|&byte| byte as u32).sum()
+}
+
+#[memoize(SharedCache)]
+fn weighted_sum(values: &[i64], weight: i64) -> i64 {
+    values.iter().map(|value| value * weight).sum()
+}
+
+fn main() {
+    let data = vec![1, 2, 3];
+    assert_eq!(checksum(&data), 6);
+    // Equal slices hit the same entry, wherever they are borrowed from.
+    assert_eq!(checksum(&[1, 2, 3]), 6);
+    assert_eq!(checksum(&data[..2]), 3);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_checksum(), 2);
+
+    assert_eq!(weighted_sum(&[1, 2, 3], 2), 12);
+    assert_eq!(weighted_sum(&[1, 2, 3], 3), 18);
+    assert_eq!(
+        memoized_get_many_weighted_sum(vec![(vec![1, 2, 3], 2), (vec![1, 2], 2)]),
+        vec![Some(12), None]
+    );
+}
@@ -0,0 +1,37 @@
+use memoize::memoize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+static mut CALLS: usize = 0;
+
+// Integer keys are copied, without needing an option.
+#[memoize]
+fn grid(cell: (u8, u8), scale: [u16; 2]) -> u32 {
+    unsafe { CALLS += 1 };
+    cell.0 as u32 * scale[0] as u32 + cell.1 as u32 * scale[1] as u32
+}
+
+#[memoize(SmallKey)]
+fn manhattan(from: Point, to: Point) -> i32 {
+    unsafe { CALLS += 1 };
+    (from.x - to.x).abs() + (from.y - to.y).abs()
+}
+
+fn main() {
+    assert_eq!(grid((1, 2), [10, 100]), 210);
+    assert_eq!(grid((1, 2), [10, 100]), 210);
+    assert_eq!(unsafe { CALLS }, 1);
+
+    let origin = Point { x: 0, y: 0 };
+    let target = Point { x: 3, y: -4 };
+    assert_eq!(manhattan(origin, target), 7);
+    assert_eq!(manhattan(origin, target), 7);
+    assert_eq!(unsafe { CALLS }, 2);
+    assert_eq!(memoized_size_manhattan(), 1);
+    // The arguments are still usable after the call.
+    assert_eq!(origin, Point { x: 0, y: 0 });
+}
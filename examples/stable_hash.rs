@@ -0,0 +1,29 @@
+use memoize::{memoize, StableHasher};
+
+#[memoize(Instrument)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    // The output is fixed for a given version, on all platforms and in all builds.
+    assert_eq!(StableHasher::VERSION, 1);
+    assert_eq!(memoize::stable_hash(&0u8), 0xaf63_bd4c_8601_b7df);
+    assert_eq!(memoize::stable_hash(&1usize), memoize::stable_hash(&1u64));
+    assert_eq!(
+        memoize::stable_hash("key"),
+        memoize::stable_hash(&"key".to_string())
+    );
+    assert_ne!(memoize::stable_hash(&(1, 2)), memoize::stable_hash(&(2, 1)));
+
+    let events = memoize::subscribe_events();
+    square(3);
+    let miss = events.recv().unwrap();
+    assert_eq!(miss.key_hash, Some(memoize::stable_hash(&3u64)));
+
+    #[cfg(feature = "serde")]
+    {
+        let hash = memoize::stable_key_hash(&(String::from("user"), 42u32));
+        assert_eq!(hash, memoize::stable_key_hash(&("user", 42u32)));
+    }
+}
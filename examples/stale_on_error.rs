@@ -0,0 +1,115 @@
+#[cfg(feature = "full")]
+use memoize::{memoize, Hooks};
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+#[cfg(feature = "full")]
+use std::thread;
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+// Whether the upstream service is down.
+#[cfg(feature = "full")]
+static DOWN: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "full")]
+static CALLS: AtomicU32 = AtomicU32::new(0);
+#[cfg(feature = "full")]
+static REPORTED: AtomicU32 = AtomicU32::new(0);
+#[cfg(feature = "full")]
+static STALE: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(feature = "full")]
+struct StaleCounter;
+
+#[cfg(feature = "full")]
+impl Hooks for StaleCounter {
+    fn on_stale(&self, _function: &'static str) {
+        STALE.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "full")]
+fn report(city: &String, error: &Result<String, String>) {
+    REPORTED.fetch_add(1, Ordering::SeqCst);
+    println!("serving a stale forecast for {}: {:?}", city, error);
+}
+
+#[cfg(feature = "full")]
+fn upstream(city: &str) -> Result<String, String> {
+    let call = CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+    if DOWN.load(Ordering::SeqCst) {
+        Err("weather service unavailable".to_string())
+    } else {
+        Ok(format!("forecast for {} #{}", city, call))
+    }
+}
+
+#[cfg(feature = "full")]
+#[memoize(TimeToLive: Duration::from_millis(50), StaleOnError: report)]
+fn forecast(city: String) -> Result<String, String> {
+    upstream(&city)
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, TimeToLive: Duration::from_millis(50), StaleOnError)]
+fn shared_forecast(city: String) -> Result<String, String> {
+    upstream(&city)
+}
+
+#[cfg(feature = "full")]
+fn main() {
+    memoize::set_hooks(StaleCounter);
+
+    assert_eq!(
+        forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #1".to_string())
+    );
+
+    // The service goes down after the value expired: the expired value is served.
+    DOWN.store(true, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(
+        forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #1".to_string())
+    );
+    assert_eq!(REPORTED.load(Ordering::SeqCst), 1);
+    assert_eq!(STALE.load(Ordering::SeqCst), 1);
+
+    // The error was not cached: the next call tries again.
+    assert_eq!(
+        forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #1".to_string())
+    );
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+    // Without a previous value, the error is returned.
+    assert!(forecast("Bergen".to_string()).is_err());
+
+    // Once the service is back, the value is refreshed.
+    DOWN.store(false, Ordering::SeqCst);
+    assert_eq!(
+        forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #5".to_string())
+    );
+    assert_eq!(
+        forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #5".to_string())
+    );
+
+    // Shared caches work the same.
+    assert_eq!(
+        shared_forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #6".to_string())
+    );
+    DOWN.store(true, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(
+        shared_forecast("Oslo".to_string()),
+        Ok("forecast for Oslo #6".to_string())
+    );
+    assert_eq!(STALE.load(Ordering::SeqCst), 3);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
@@ -0,0 +1,33 @@
+use memoize::{memoize, Stats};
+use std::thread;
+
+#[memoize(ThreadLocal)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    // Every thread has its own cache, and its own counters.
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                for n in 0..10 {
+                    square(n);
+                    square(n);
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    square(1);
+
+    assert_eq!(
+        memoized_stats_square(),
+        Stats {
+            hits: 40,
+            misses: 41
+        }
+    );
+}
@@ -0,0 +1,41 @@
+use memoize::memoize;
+
+/// A rich value, which is deliberately not `Clone`.
+#[derive(Debug, PartialEq)]
+struct Report {
+    title: String,
+    lines: Vec<String>,
+}
+
+/// The compact form stored in the cache.
+fn encode(report: &Report) -> String {
+    let mut s = report.title.clone();
+    for line in &report.lines {
+        s.push('\n');
+        s.push_str(line);
+    }
+    s
+}
+
+fn decode(s: &str) -> Report {
+    let mut parts = s.split('\n').map(String::from);
+    Report {
+        title: parts.next().unwrap(),
+        lines: parts.collect(),
+    }
+}
+
+#[memoize(StoreWith: encode: String, LoadWith: decode)]
+fn report(n: usize) -> Report {
+    Report {
+        title: format!("Report {}", n),
+        lines: (0..n).map(|i| format!("line {}", i)).collect(),
+    }
+}
+
+fn main() {
+    let first = report(2);
+    assert_eq!(first.lines, vec!["line 0", "line 1"]);
+    assert_eq!(report(2), first);
+    assert_eq!(memoized_size_report(), 1);
+}
@@ -0,0 +1,57 @@
+#[cfg(feature = "tokio")]
+mod task_local {
+    use memoize::memoize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[memoize(TaskLocal)]
+    pub async fn permissions(user: u64) -> Vec<String> {
+        LOOKUPS.fetch_add(1, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        vec![format!("read:{}", user)]
+    }
+
+    #[memoize(TaskLocal)]
+    pub fn display_name(user: u64) -> String {
+        format!("user #{}", user)
+    }
+
+    // A request handler, in which all lookups for the same user are deduplicated.
+    pub async fn handle(user: u64) -> usize {
+        let mut checks = 0;
+        for _ in 0..3 {
+            checks += permissions(user).await.len();
+        }
+        assert_eq!(display_name(user), format!("user #{}", user));
+        assert_eq!(memoized_size_display_name(), 1);
+        checks
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::main]
+async fn main() {
+    use std::sync::atomic::Ordering;
+    use task_local::*;
+
+    let requests: Vec<_> = (0..4)
+        .map(|user| tokio::spawn(memoize::task_scope(handle(user % 2))))
+        .collect();
+    for request in requests {
+        assert_eq!(request.await.unwrap(), 3);
+    }
+    // One lookup per request, as every request has its own cache.
+    assert_eq!(LOOKUPS.load(Ordering::SeqCst), 4);
+
+    // Outside of a scope, nothing is cached.
+    permissions(7).await;
+    permissions(7).await;
+    assert_eq!(LOOKUPS.load(Ordering::SeqCst), 6);
+    assert_eq!(memoized_size_permissions(), 0);
+}
+
+#[cfg(not(feature = "tokio"))]
+fn main() {
+    println!("Use the \"tokio\" feature to execute this example");
+}
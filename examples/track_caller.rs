@@ -0,0 +1,53 @@
+#[cfg(feature = "full")]
+use memoize::memoize;
+#[cfg(feature = "full")]
+use std::panic::{self, Location};
+#[cfg(feature = "full")]
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "full")]
+use std::time::Duration;
+
+/// Checks a port number, and panics at the call site if it is invalid.
+#[cfg(feature = "full")]
+#[memoize]
+#[track_caller]
+fn check_port(port: u32) -> u16 {
+    if port > u16::MAX as u32 {
+        panic!("invalid port {}", port);
+    }
+    port as u16
+}
+
+#[cfg(feature = "full")]
+#[memoize(SharedCache, Capacity: 10, TimeToLive: Duration::from_secs(60))]
+#[track_caller]
+fn caller_line(key: u32) -> u32 {
+    let _ = key;
+    Location::caller().line()
+}
+
+#[cfg(feature = "full")]
+static PANIC_LINE: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(feature = "full")]
+fn main() {
+    // The original function sees the caller of the memoized function.
+    let (line, caller) = (line!(), caller_line(1));
+    assert_eq!(caller, line);
+
+    // Panics report the call site, not the generated code.
+    assert_eq!(check_port(8080), 8080);
+    panic::set_hook(Box::new(|info| {
+        let location = info.location().unwrap();
+        assert_eq!(location.file(), file!());
+        PANIC_LINE.store(location.line(), Ordering::SeqCst);
+    }));
+    let (line, result) = (line!(), panic::catch_unwind(|| check_port(1 << 20)));
+    assert!(result.is_err());
+    assert_eq!(PANIC_LINE.load(Ordering::SeqCst), line);
+}
+
+#[cfg(not(feature = "full"))]
+fn main() {
+    println!("Use the \"full\" feature to execute this example");
+}
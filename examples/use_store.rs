@@ -0,0 +1,72 @@
+use memoize::{memoize, CacheStore};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, Mutex};
+
+// One store shared by two functions of the same signature, and hand-written code.
+static LENGTHS: LazyLock<Mutex<HashMap<String, usize>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[memoize(SharedCache, UseStore: LENGTHS)]
+fn byte_length(text: String) -> usize {
+    text.len()
+}
+
+#[memoize(SharedCache, UseStore: LENGTHS)]
+fn char_length(text: String) -> usize {
+    text.chars().count()
+}
+
+// A custom store, which keeps its keys ordered.
+#[derive(Default)]
+struct OrderedStore(BTreeMap<u64, u64>);
+
+impl CacheStore<u64, u64> for OrderedStore {
+    fn get(&mut self, key: &u64) -> Option<&u64> {
+        self.0.get(key)
+    }
+
+    fn insert(&mut self, key: u64, value: u64) {
+        self.0.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &u64) -> Option<u64> {
+        self.0.remove(key)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+thread_local! {
+    static SQUARES: RefCell<OrderedStore> = RefCell::new(OrderedStore::default());
+}
+
+#[memoize(UseStore: SQUARES)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    assert_eq!(byte_length("héllo".to_string()), 6);
+    // Shares the entry computed by `byte_length`.
+    assert_eq!(char_length("héllo".to_string()), 6);
+    assert_eq!(char_length("world".to_string()), 5);
+    assert_eq!(LENGTHS.lock().unwrap().len(), 2);
+    assert_eq!(memoized_size_byte_length(), 2);
+    LENGTHS.lock().unwrap().remove("héllo");
+    assert_eq!(char_length("héllo".to_string()), 5);
+
+    for n in [3, 1, 2] {
+        square(n);
+    }
+    let keys: Vec<u64> = SQUARES.with(|store| store.borrow().0.keys().copied().collect());
+    assert_eq!(keys, vec![1, 2, 3]);
+    memoized_flush_square();
+    assert_eq!(memoized_size_square(), 0);
+}
@@ -0,0 +1,61 @@
+use memoize::memoize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+struct Document {
+    body: String,
+    version: u32,
+}
+
+// The version counter of each document at the source, which is cheap to query.
+static VERSION: AtomicU32 = AtomicU32::new(1);
+static LOADS: AtomicU32 = AtomicU32::new(0);
+static CHECKS: AtomicU32 = AtomicU32::new(0);
+
+fn current(_id: &u32, document: &Document) -> bool {
+    CHECKS.fetch_add(1, Ordering::SeqCst);
+    document.version == VERSION.load(Ordering::SeqCst)
+}
+
+#[memoize(ValidateWith: current)]
+fn load(id: u32) -> Document {
+    LOADS.fetch_add(1, Ordering::SeqCst);
+    let version = VERSION.load(Ordering::SeqCst);
+    Document {
+        body: format!("document {} (version {})", id, version),
+        version,
+    }
+}
+
+#[memoize(SharedCache, ValidateWith: current, ValidateEvery: Duration::from_millis(50))]
+fn load_shared(id: u32) -> Document {
+    load(id)
+}
+
+fn main() {
+    assert_eq!(load(1).version, 1);
+    assert_eq!(load(1).version, 1);
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    assert_eq!(CHECKS.load(Ordering::SeqCst), 1);
+
+    // The source changed: the next hit is turned into a miss.
+    VERSION.store(2, Ordering::SeqCst);
+    assert_eq!(load(1).body, "document 1 (version 2)");
+    assert_eq!(LOADS.load(Ordering::SeqCst), 2);
+    assert_eq!(memoized_stats_load().misses, 2);
+
+    // With `ValidateEvery`, hits within the period are served without asking.
+    CHECKS.store(0, Ordering::SeqCst);
+    assert_eq!(load_shared(1).version, 2);
+    for _ in 0..5 {
+        assert_eq!(load_shared(1).version, 2);
+    }
+    VERSION.store(3, Ordering::SeqCst);
+    assert_eq!(load_shared(1).version, 2);
+    assert_eq!(CHECKS.load(Ordering::SeqCst), 1);
+
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(load_shared(1).version, 3);
+}
@@ -0,0 +1,25 @@
+use memoize::memoize;
+
+#[memoize(Warm: [(1, 1), (2, 3)])]
+fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[memoize(SharedCache, Warm: [("hot".to_string())])]
+fn shout(s: String) -> String {
+    s.to_uppercase()
+}
+
+fn main() {
+    // The first call also computes the warm list.
+    assert_eq!(add(5, 5), 10);
+    assert_eq!(memoized_size_add(), 3);
+    memoized_flush_add();
+    assert_eq!(memoized_size_add(), 0);
+    // The warm list is computed again after a flush.
+    assert_eq!(add(1, 1), 2);
+    assert_eq!(memoized_size_add(), 2);
+
+    assert_eq!(shout("hot".to_string()), "HOT");
+    assert_eq!(memoized_size_shout(), 1);
+}
@@ -0,0 +1,23 @@
+#[cfg(feature = "startup")]
+use memoize::memoize;
+
+#[cfg(feature = "startup")]
+#[memoize(SharedCache, Warm: [(10), (20)], WarmAtStartup)]
+fn slow_square(n: u64) -> u64 {
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    n * n
+}
+
+#[cfg(feature = "startup")]
+fn main() {
+    assert_eq!(memoized_size_slow_square(), 0);
+    // Compute all registered warm lists before serving traffic.
+    memoize::warm_all();
+    assert_eq!(memoized_size_slow_square(), 2);
+    assert_eq!(slow_square(10), 100);
+}
+
+#[cfg(not(feature = "startup"))]
+fn main() {
+    println!("Use the \"startup\" feature to execute this example");
+}
@@ -0,0 +1,51 @@
+use memoize::memoize;
+use std::sync::mpsc;
+use std::thread;
+
+// Each thread buffers up to 8 new entries before merging them into the shared cache.
+#[memoize(SharedCache, WriteBack: 8)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {
+    let workers: Vec<_> = (0..4)
+        .map(|t| {
+            thread::spawn(move || {
+                for n in 0..20 {
+                    assert_eq!(square(t * 100 + n), (t * 100 + n) * (t * 100 + n));
+                }
+                // Publish whatever is still buffered.
+                memoized_write_back_square();
+            })
+        })
+        .collect();
+    for w in workers {
+        w.join().unwrap();
+    }
+    assert_eq!(memoized_size_square(), 80);
+
+    assert_eq!(square(3), 9);
+    // Buffered entries are only visible to the shared cache after a write-back.
+    assert_eq!(memoized_size_square(), 80);
+    memoized_flush_square();
+    assert_eq!(memoized_size_square(), 0);
+
+    // A flush also discards what other threads have buffered, rather than letting them write it
+    // back later.
+    let (buffered, is_buffered) = mpsc::channel();
+    let (flushed, is_flushed) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        square(4);
+        buffered.send(()).unwrap();
+        is_flushed.recv().unwrap();
+        memoized_write_back_square();
+        square(5);
+    });
+    is_buffered.recv().unwrap();
+    memoized_flush_square();
+    flushed.send(()).unwrap();
+    // On exit, the worker publishes only the entry it computed after the flush.
+    worker.join().unwrap();
+    assert_eq!(memoized_size_square(), 1);
+}
@@ -8,10 +8,85 @@ use quote::{self, ToTokens};
 mod kw {
     syn::custom_keyword!(Capacity);
     syn::custom_keyword!(TimeToLive);
+    syn::custom_keyword!(ExpireAfterWrite);
+    syn::custom_keyword!(ExpireAfterAccess);
     syn::custom_keyword!(SharedCache);
     syn::custom_keyword!(CustomHasher);
     syn::custom_keyword!(HasherInit);
     syn::custom_keyword!(Ignore);
+    syn::custom_keyword!(WriteBack);
+    syn::custom_keyword!(WriteBackEvery);
+    syn::custom_keyword!(Precompute);
+    syn::custom_keyword!(Warm);
+    syn::custom_keyword!(WarmAtStartup);
+    syn::custom_keyword!(PerElement);
+    syn::custom_keyword!(StoreWith);
+    syn::custom_keyword!(LoadWith);
+    syn::custom_keyword!(SerializeValues);
+    syn::custom_keyword!(ExpireWith);
+    syn::custom_keyword!(Priority);
+    syn::custom_keyword!(Eviction);
+    syn::custom_keyword!(PartitionBy);
+    syn::custom_keyword!(Instrument);
+    syn::custom_keyword!(SharedFuture);
+    syn::custom_keyword!(CollectStream);
+    syn::custom_keyword!(CollectCoroutine);
+    syn::custom_keyword!(MaxAge);
+    syn::custom_keyword!(Revalidate);
+    syn::custom_keyword!(Persist);
+    syn::custom_keyword!(PersistXdg);
+    syn::custom_keyword!(PersistSalt);
+    syn::custom_keyword!(PersistKey);
+    syn::custom_keyword!(SmallKey);
+    syn::custom_keyword!(DedupValues);
+    syn::custom_keyword!(MaxValueWeight);
+    syn::custom_keyword!(Weigher);
+    syn::custom_keyword!(MinComputeTime);
+    syn::custom_keyword!(AdmitAfter);
+    syn::custom_keyword!(NegativeFilter);
+    syn::custom_keyword!(NegativeFilterCapacity);
+    syn::custom_keyword!(FlushEvery);
+    syn::custom_keyword!(Seed);
+    syn::custom_keyword!(Secondary);
+    syn::custom_keyword!(WriteBehind);
+    syn::custom_keyword!(ScopeBy);
+    syn::custom_keyword!(Extract);
+    syn::custom_keyword!(Canonical);
+    syn::custom_keyword!(SerdeKeys);
+    syn::custom_keyword!(DebugKeys);
+    syn::custom_keyword!(NormalizeKey);
+    syn::custom_keyword!(Nfc);
+    syn::custom_keyword!(KeyByPtr);
+    syn::custom_keyword!(Quantize);
+    syn::custom_keyword!(KeyPipeline);
+    syn::custom_keyword!(PublicStore);
+    syn::custom_keyword!(UseStore);
+    syn::custom_keyword!(EntryInfo);
+    syn::custom_keyword!(AutoCapacity);
+    syn::custom_keyword!(AutoIgnoreDyn);
+    syn::custom_keyword!(KeepConst);
+    syn::custom_keyword!(ThreadLocal);
+    syn::custom_keyword!(RayonShared);
+    syn::custom_keyword!(TaskLocal);
+    syn::custom_keyword!(Scoped);
+    syn::custom_keyword!(RequestScoped);
+    syn::custom_keyword!(Export);
+    syn::custom_keyword!(Subscribe);
+    syn::custom_keyword!(ValidateWith);
+    syn::custom_keyword!(ValidateEvery);
+    syn::custom_keyword!(BatchWith);
+    syn::custom_keyword!(BatchWindow);
+    syn::custom_keyword!(CircuitBreaker);
+    syn::custom_keyword!(MaxRefreshRate);
+    syn::custom_keyword!(StaleOnError);
+    syn::custom_keyword!(IndexBy);
+    syn::custom_keyword!(CoarseClock);
+    syn::custom_keyword!(CompactEntries);
+    syn::custom_keyword!(HashOnce);
+    syn::custom_keyword!(IgnoreErrors);
+    syn::custom_keyword!(failures);
+    syn::custom_keyword!(cooldown);
+    syn::custom_keyword!(per_key);
     syn::custom_punctuation!(Colon, :);
 }
 
@@ -19,20 +94,162 @@ mod kw {
 struct CacheOptions {
     lru_max_entries: Option<usize>,
     time_to_live: Option<Expr>,
+    expire_after_access: Option<Expr>,
     shared_cache: bool,
     custom_hasher: Option<Path>,
     custom_hasher_initializer: Option<ExprCall>,
     ignore: Vec<syn::Ident>,
+    write_back: Option<usize>,
+    write_back_every: Option<Expr>,
+    precompute: Vec<Expr>,
+    warm: Vec<Expr>,
+    warm_at_startup: bool,
+    per_element: bool,
+    store_with: Option<(Path, syn::Type)>,
+    load_with: Option<Path>,
+    serialize_values: bool,
+    expire_with: Option<Expr>,
+    priority: Option<Expr>,
+    eviction: Option<syn::Ident>,
+    partition_by: Option<(Expr, syn::Type)>,
+    instrument: bool,
+    shared_future: bool,
+    collect_stream: bool,
+    collect_coroutine: bool,
+    max_age: Option<Expr>,
+    revalidate: Option<Expr>,
+    persist: Option<Expr>,
+    persist_xdg: Option<syn::LitStr>,
+    persist_salt: Option<syn::LitStr>,
+    persist_key: Option<Expr>,
+    small_key: bool,
+    dedup_values: bool,
+    max_value_weight: Option<Expr>,
+    weigher: Option<Expr>,
+    min_compute_time: Option<Expr>,
+    admit_after: Option<u8>,
+    negative_filter: Option<Expr>,
+    negative_filter_capacity: Option<usize>,
+    flush_every: Option<Expr>,
+    seed: bool,
+    secondary: Option<Expr>,
+    write_behind: bool,
+    scope_by: Option<(Expr, syn::Type)>,
+    extract: Vec<(syn::Ident, Expr, syn::Type)>,
+    canonical: Vec<syn::Ident>,
+    serde_keys: bool,
+    debug_keys: bool,
+    normalize_key: Option<Path>,
+    nfc: bool,
+    key_by_ptr: Vec<syn::Ident>,
+    quantize: Vec<(syn::Ident, Expr, syn::Type)>,
+    key_pipeline: Vec<(syn::Ident, Vec<Expr>, Option<syn::Type>)>,
+    public_store: bool,
+    use_store: Option<Path>,
+    entry_info: bool,
+    auto_capacity: Option<(usize, usize)>,
+    auto_ignore_dyn: bool,
+    keep_const: bool,
+    thread_local: bool,
+    rayon_shared: bool,
+    task_local: bool,
+    scoped: bool,
+    export: bool,
+    subscribe: bool,
+    validate_with: Option<Expr>,
+    validate_every: Option<Expr>,
+    batch_with: Option<Expr>,
+    batch_window: Option<Expr>,
+    circuit_breaker: Option<(u32, Expr, bool)>,
+    max_refresh_rate: Option<Expr>,
+    // With `StaleOnError`, the optional function reporting the errors.
+    stale_on_error: Option<Option<Expr>>,
+    index_by: Option<(Expr, syn::Type)>,
+    coarse_clock: bool,
+    compact_entries: bool,
+    hash_once: bool,
+    ignore_errors: bool,
 }
 
 #[derive(Clone)]
 enum CacheOption {
     LRUMaxEntries(usize),
     TimeToLive(Expr),
+    ExpireAfterAccess(Expr),
     SharedCache,
     CustomHasher(Path),
     HasherInit(ExprCall),
     Ignore(syn::Ident),
+    WriteBack(usize),
+    WriteBackEvery(Expr),
+    Precompute(Vec<Expr>),
+    Warm(Vec<Expr>),
+    WarmAtStartup,
+    PerElement,
+    StoreWith(Path, syn::Type),
+    LoadWith(Path),
+    SerializeValues,
+    ExpireWith(Expr),
+    Priority(Expr),
+    Eviction(syn::Ident),
+    PartitionBy(Expr, syn::Type),
+    Instrument,
+    SharedFuture,
+    CollectStream,
+    CollectCoroutine,
+    MaxAge(Expr),
+    Revalidate(Expr),
+    Persist(Expr),
+    PersistXdg(syn::LitStr),
+    PersistSalt(syn::LitStr),
+    PersistKey(Expr),
+    SmallKey,
+    DedupValues,
+    MaxValueWeight(Expr),
+    Weigher(Expr),
+    MinComputeTime(Expr),
+    AdmitAfter(u8),
+    NegativeFilter(Expr),
+    NegativeFilterCapacity(usize),
+    FlushEvery(Expr),
+    Seed,
+    Secondary(Expr),
+    WriteBehind,
+    ScopeBy(Expr, syn::Type),
+    Extract(syn::Ident, Expr, syn::Type),
+    Canonical(syn::Ident),
+    SerdeKeys,
+    DebugKeys,
+    NormalizeKey(Path),
+    Nfc,
+    KeyByPtr(syn::Ident),
+    Quantize(syn::Ident, Expr, syn::Type),
+    KeyPipeline(syn::Ident, Vec<Expr>, Option<syn::Type>),
+    PublicStore,
+    UseStore(Path),
+    EntryInfo,
+    AutoCapacity(usize, usize),
+    AutoIgnoreDyn,
+    KeepConst,
+    ThreadLocal,
+    RayonShared,
+    TaskLocal,
+    Scoped,
+    RequestScoped,
+    Export,
+    Subscribe,
+    ValidateWith(Expr),
+    ValidateEvery(Expr),
+    BatchWith(Expr),
+    BatchWindow(Expr),
+    CircuitBreaker(u32, Expr, bool),
+    MaxRefreshRate(Expr),
+    StaleOnError(Option<Expr>),
+    IndexBy(Expr, syn::Type),
+    CoarseClock,
+    CompactEntries,
+    HashOnce,
+    IgnoreErrors,
 }
 
 // To extend option parsing, add functionality here.
@@ -64,6 +281,28 @@ impl parse::Parse for CacheOption {
 
             return Ok(CacheOption::TimeToLive(cap));
         }
+        if la.peek(kw::ExpireAfterWrite) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: ExpireAfterWrite specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::ExpireAfterWrite>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let ttl: syn::Expr = input.parse()?;
+            return Ok(CacheOption::TimeToLive(ttl));
+        }
+        if la.peek(kw::ExpireAfterAccess) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: ExpireAfterAccess specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::ExpireAfterAccess>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let tti: syn::Expr = input.parse()?;
+            return Ok(CacheOption::ExpireAfterAccess(tti));
+        }
         if la.peek(kw::SharedCache) {
             input.parse::<kw::SharedCache>().unwrap();
             return Ok(CacheOption::SharedCache);
@@ -86,6 +325,587 @@ impl parse::Parse for CacheOption {
             let ignore_ident = input.parse::<syn::Ident>().unwrap();
             return Ok(CacheOption::Ignore(ignore_ident));
         }
+        if la.peek(kw::WriteBackEvery) {
+            input.parse::<kw::WriteBackEvery>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let every: syn::Expr = input.parse()?;
+            return Ok(CacheOption::WriteBackEvery(every));
+        }
+        if la.peek(kw::WriteBack) {
+            input.parse::<kw::WriteBack>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let threshold: syn::LitInt = input.parse()?;
+            return Ok(CacheOption::WriteBack(threshold.base10_parse()?));
+        }
+        if la.peek(kw::Precompute) {
+            input.parse::<kw::Precompute>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let inputs: syn::ExprArray = input.parse()?;
+            return Ok(CacheOption::Precompute(inputs.elems.into_iter().collect()));
+        }
+        if la.peek(kw::Warm) {
+            input.parse::<kw::Warm>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let inputs: syn::ExprArray = input.parse()?;
+            return Ok(CacheOption::Warm(inputs.elems.into_iter().collect()));
+        }
+        if la.peek(kw::WarmAtStartup) {
+            #[cfg(not(feature = "startup"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: WarmAtStartup specified, but the feature 'startup' is not enabled! To fix this, compile with `--features=startup`.",
+            ));
+
+            input.parse::<kw::WarmAtStartup>().unwrap();
+            return Ok(CacheOption::WarmAtStartup);
+        }
+        if la.peek(kw::PerElement) {
+            input.parse::<kw::PerElement>().unwrap();
+            return Ok(CacheOption::PerElement);
+        }
+        if la.peek(kw::StoreWith) {
+            input.parse::<kw::StoreWith>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let encode: syn::Path = input.parse()?;
+            // The stored type can't be inferred, as it is part of the type of the cache.
+            input.parse::<kw::Colon>()?;
+            let stored_type: syn::Type = input.parse()?;
+            return Ok(CacheOption::StoreWith(encode, stored_type));
+        }
+        if la.peek(kw::LoadWith) {
+            input.parse::<kw::LoadWith>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let decode: syn::Path = input.parse()?;
+            return Ok(CacheOption::LoadWith(decode));
+        }
+        if la.peek(kw::SerializeValues) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: SerializeValues specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::SerializeValues>().unwrap();
+            return Ok(CacheOption::SerializeValues);
+        }
+        if la.peek(kw::ExpireWith) {
+            input.parse::<kw::ExpireWith>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let expire_with: syn::Expr = input.parse()?;
+            return Ok(CacheOption::ExpireWith(expire_with));
+        }
+        if la.peek(kw::Priority) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Priority specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::Priority>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let priority: syn::Expr = input.parse()?;
+            return Ok(CacheOption::Priority(priority));
+        }
+        if la.peek(kw::Eviction) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Eviction specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::Eviction>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let policy: syn::Ident = input.parse()?;
+            if policy != "GreedyDual" && policy != "ClockPro" && policy != "ARC" {
+                return Err(syn::Error::new(
+                    policy.span(),
+                    "memoize error: unknown eviction policy, expected `GreedyDual`, `ClockPro` or `ARC`",
+                ));
+            }
+            return Ok(CacheOption::Eviction(policy));
+        }
+        if la.peek(kw::PartitionBy) {
+            input.parse::<kw::PartitionBy>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let partition: syn::Expr = input.parse()?;
+            // The partition type can't be inferred, as it is part of the type of the cache.
+            input.parse::<kw::Colon>()?;
+            let partition_type: syn::Type = input.parse()?;
+            return Ok(CacheOption::PartitionBy(partition, partition_type));
+        }
+        if la.peek(kw::Instrument) {
+            input.parse::<kw::Instrument>().unwrap();
+            return Ok(CacheOption::Instrument);
+        }
+        if la.peek(kw::SharedFuture) {
+            #[cfg(not(any(feature = "async", feature = "tokio")))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: SharedFuture specified, but neither the feature 'async' nor 'tokio' is enabled! To fix this, compile with `--features=async`.",
+            ));
+
+            input.parse::<kw::SharedFuture>().unwrap();
+            return Ok(CacheOption::SharedFuture);
+        }
+        if la.peek(kw::CollectStream) {
+            #[cfg(not(any(feature = "async", feature = "tokio")))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: CollectStream specified, but neither the feature 'async' nor 'tokio' is enabled! To fix this, compile with `--features=async`.",
+            ));
+
+            input.parse::<kw::CollectStream>().unwrap();
+            return Ok(CacheOption::CollectStream);
+        }
+        if la.peek(kw::CollectCoroutine) {
+            #[cfg(not(feature = "nightly"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: CollectCoroutine specified, but the feature 'nightly' is not enabled! To fix this, compile with `--features=nightly`.",
+            ));
+
+            input.parse::<kw::CollectCoroutine>().unwrap();
+            return Ok(CacheOption::CollectCoroutine);
+        }
+        if la.peek(kw::MaxAge) {
+            input.parse::<kw::MaxAge>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let max_age: syn::Expr = input.parse()?;
+            return Ok(CacheOption::MaxAge(max_age));
+        }
+        if la.peek(kw::Revalidate) {
+            input.parse::<kw::Revalidate>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let revalidate: syn::Expr = input.parse()?;
+            return Ok(CacheOption::Revalidate(revalidate));
+        }
+        if la.peek(kw::Persist) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Persist specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::Persist>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let path: syn::Expr = input.parse()?;
+            return Ok(CacheOption::Persist(path));
+        }
+        if la.peek(kw::PersistXdg) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: PersistXdg specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::PersistXdg>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let tool: syn::LitStr = input.parse()?;
+            return Ok(CacheOption::PersistXdg(tool));
+        }
+        if la.peek(kw::PersistSalt) {
+            input.parse::<kw::PersistSalt>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let salt: syn::LitStr = input.parse()?;
+            return Ok(CacheOption::PersistSalt(salt));
+        }
+        if la.peek(kw::PersistKey) {
+            #[cfg(not(feature = "encryption"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: PersistKey specified, but the feature 'encryption' is not enabled! To fix this, compile with `--features=encryption`.",
+            ));
+
+            input.parse::<kw::PersistKey>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let key: syn::Expr = input.parse()?;
+            return Ok(CacheOption::PersistKey(key));
+        }
+        if la.peek(kw::SmallKey) {
+            input.parse::<kw::SmallKey>().unwrap();
+            return Ok(CacheOption::SmallKey);
+        }
+        if la.peek(kw::DedupValues) {
+            input.parse::<kw::DedupValues>().unwrap();
+            return Ok(CacheOption::DedupValues);
+        }
+        if la.peek(kw::MaxValueWeight) {
+            input.parse::<kw::MaxValueWeight>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let max_weight: syn::Expr = input.parse()?;
+            return Ok(CacheOption::MaxValueWeight(max_weight));
+        }
+        if la.peek(kw::Weigher) {
+            input.parse::<kw::Weigher>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let weigher: syn::Expr = input.parse()?;
+            return Ok(CacheOption::Weigher(weigher));
+        }
+        if la.peek(kw::MinComputeTime) {
+            input.parse::<kw::MinComputeTime>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let min_time: syn::Expr = input.parse()?;
+            return Ok(CacheOption::MinComputeTime(min_time));
+        }
+        if la.peek(kw::AdmitAfter) {
+            input.parse::<kw::AdmitAfter>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let requests: syn::LitInt = input.parse()?;
+            return Ok(CacheOption::AdmitAfter(requests.base10_parse()?));
+        }
+        if la.peek(kw::NegativeFilter) {
+            input.parse::<kw::NegativeFilter>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let negative: syn::Expr = input.parse()?;
+            return Ok(CacheOption::NegativeFilter(negative));
+        }
+        if la.peek(kw::NegativeFilterCapacity) {
+            input.parse::<kw::NegativeFilterCapacity>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let capacity: syn::LitInt = input.parse()?;
+            return Ok(CacheOption::NegativeFilterCapacity(
+                capacity.base10_parse()?,
+            ));
+        }
+        if la.peek(kw::FlushEvery) {
+            input.parse::<kw::FlushEvery>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let every: syn::Expr = input.parse()?;
+            return Ok(CacheOption::FlushEvery(every));
+        }
+        if la.peek(kw::Seed) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Seed specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::Seed>().unwrap();
+            return Ok(CacheOption::Seed);
+        }
+        if la.peek(kw::Secondary) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Secondary specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::Secondary>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let store: syn::Expr = input.parse()?;
+            return Ok(CacheOption::Secondary(store));
+        }
+        if la.peek(kw::WriteBehind) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: WriteBehind specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::WriteBehind>().unwrap();
+            return Ok(CacheOption::WriteBehind);
+        }
+        if la.peek(kw::ScopeBy) {
+            input.parse::<kw::ScopeBy>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let scope: syn::Expr = input.parse()?;
+            // Like the partition type, the scope type is part of the type of the cache.
+            input.parse::<kw::Colon>()?;
+            let scope_type: syn::Type = input.parse()?;
+            return Ok(CacheOption::ScopeBy(scope, scope_type));
+        }
+        if la.peek(kw::Extract) {
+            input.parse::<kw::Extract>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let argument: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=>]>()?;
+            let extract: syn::Expr = input.parse()?;
+            // The type of the extracted value is part of the type of the cache.
+            input.parse::<kw::Colon>()?;
+            let extract_type: syn::Type = input.parse()?;
+            return Ok(CacheOption::Extract(argument, extract, extract_type));
+        }
+        if la.peek(kw::Canonical) {
+            input.parse::<kw::Canonical>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let argument: syn::Ident = input.parse()?;
+            return Ok(CacheOption::Canonical(argument));
+        }
+        if la.peek(kw::SerdeKeys) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: SerdeKeys specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::SerdeKeys>().unwrap();
+            return Ok(CacheOption::SerdeKeys);
+        }
+        if la.peek(kw::DebugKeys) {
+            input.parse::<kw::DebugKeys>().unwrap();
+            return Ok(CacheOption::DebugKeys);
+        }
+        if la.peek(kw::NormalizeKey) {
+            input.parse::<kw::NormalizeKey>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let normalize: Path = input.parse()?;
+            return Ok(CacheOption::NormalizeKey(normalize));
+        }
+        if la.peek(kw::Nfc) {
+            #[cfg(not(feature = "nfc"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Nfc specified, but the feature 'nfc' is not enabled! To fix this, compile with `--features=nfc`.",
+            ));
+
+            input.parse::<kw::Nfc>().unwrap();
+            return Ok(CacheOption::Nfc);
+        }
+        if la.peek(kw::KeyByPtr) {
+            input.parse::<kw::KeyByPtr>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let argument: syn::Ident = input.parse()?;
+            return Ok(CacheOption::KeyByPtr(argument));
+        }
+        if la.peek(kw::Quantize) {
+            input.parse::<kw::Quantize>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let argument: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=>]>()?;
+            let quantize: syn::Expr = input.parse()?;
+            // The type of the bucket is part of the type of the cache; it can be omitted after a cast.
+            let bucket_type: syn::Type = if input.peek(kw::Colon) {
+                input.parse::<kw::Colon>().unwrap();
+                input.parse()?
+            } else if let syn::Expr::Cast(cast) = &quantize {
+                (*cast.ty).clone()
+            } else {
+                return Err(syn::Error::new(
+                    quantize.span(),
+                    "memoize error: Quantize needs the type of the bucket, e.g. `Quantize: x => bucket(x): i64`",
+                ));
+            };
+            return Ok(CacheOption::Quantize(argument, quantize, bucket_type));
+        }
+        if la.peek(kw::KeyPipeline) {
+            input.parse::<kw::KeyPipeline>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let argument: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=>]>()?;
+            let steps;
+            syn::bracketed!(steps in input);
+            let steps =
+                syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated(&steps)?;
+            // The type of the final value, if the steps don't preserve the type of the argument.
+            let key_type = if input.peek(kw::Colon) {
+                input.parse::<kw::Colon>().unwrap();
+                Some(input.parse()?)
+            } else {
+                None
+            };
+            return Ok(CacheOption::KeyPipeline(
+                argument,
+                steps.into_iter().collect(),
+                key_type,
+            ));
+        }
+        if la.peek(kw::PublicStore) {
+            input.parse::<kw::PublicStore>().unwrap();
+            return Ok(CacheOption::PublicStore);
+        }
+        if la.peek(kw::UseStore) {
+            input.parse::<kw::UseStore>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let store: Path = input.parse()?;
+            return Ok(CacheOption::UseStore(store));
+        }
+        if la.peek(kw::EntryInfo) {
+            input.parse::<kw::EntryInfo>().unwrap();
+            return Ok(CacheOption::EntryInfo);
+        }
+        if la.peek(kw::AutoCapacity) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: AutoCapacity specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::AutoCapacity>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let min: syn::LitInt = input.parse()?;
+            input.parse::<syn::Token![..=]>()?;
+            let max: syn::LitInt = input.parse()?;
+            let (min, max) = (min.base10_parse()?, max.base10_parse()?);
+            if min == 0 || min > max {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "memoize error: AutoCapacity needs a range `min..=max` with 0 < min <= max",
+                ));
+            }
+            return Ok(CacheOption::AutoCapacity(min, max));
+        }
+        if la.peek(kw::AutoIgnoreDyn) {
+            input.parse::<kw::AutoIgnoreDyn>().unwrap();
+            return Ok(CacheOption::AutoIgnoreDyn);
+        }
+        if la.peek(kw::KeepConst) {
+            #[cfg(not(feature = "nightly"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: KeepConst specified, but the feature 'nightly' is not enabled! To fix this, compile with `--features=nightly`.",
+            ));
+
+            input.parse::<kw::KeepConst>().unwrap();
+            return Ok(CacheOption::KeepConst);
+        }
+        if la.peek(kw::ThreadLocal) {
+            input.parse::<kw::ThreadLocal>().unwrap();
+            return Ok(CacheOption::ThreadLocal);
+        }
+        if la.peek(kw::RayonShared) {
+            #[cfg(not(feature = "rayon"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: RayonShared specified, but the feature 'rayon' is not enabled! To fix this, compile with `--features=rayon`.",
+            ));
+
+            input.parse::<kw::RayonShared>().unwrap();
+            return Ok(CacheOption::RayonShared);
+        }
+        if la.peek(kw::TaskLocal) {
+            #[cfg(not(feature = "tokio"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: TaskLocal specified, but the feature 'tokio' is not enabled! To fix this, compile with `--features=tokio`.",
+            ));
+
+            input.parse::<kw::TaskLocal>().unwrap();
+            return Ok(CacheOption::TaskLocal);
+        }
+        if la.peek(kw::Scoped) {
+            input.parse::<kw::Scoped>().unwrap();
+            return Ok(CacheOption::Scoped);
+        }
+        if la.peek(kw::RequestScoped) {
+            #[cfg(not(feature = "tower"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: RequestScoped specified, but the feature 'tower' is not enabled! To fix this, compile with `--features=tower`.",
+            ));
+
+            input.parse::<kw::RequestScoped>().unwrap();
+            return Ok(CacheOption::RequestScoped);
+        }
+        if la.peek(kw::Export) {
+            #[cfg(not(feature = "serde"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Export specified, but the feature 'serde' is not enabled! To fix this, compile with `--features=serde`.",
+            ));
+
+            input.parse::<kw::Export>().unwrap();
+            return Ok(CacheOption::Export);
+        }
+        if la.peek(kw::Subscribe) {
+            #[cfg(not(feature = "invalidation"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: Subscribe specified, but the feature 'invalidation' is not enabled! To fix this, compile with `--features=invalidation`.",
+            ));
+
+            input.parse::<kw::Subscribe>().unwrap();
+            return Ok(CacheOption::Subscribe);
+        }
+        if la.peek(kw::ValidateWith) {
+            input.parse::<kw::ValidateWith>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let validate: syn::Expr = input.parse()?;
+            return Ok(CacheOption::ValidateWith(validate));
+        }
+        if la.peek(kw::ValidateEvery) {
+            input.parse::<kw::ValidateEvery>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let every: syn::Expr = input.parse()?;
+            return Ok(CacheOption::ValidateEvery(every));
+        }
+        if la.peek(kw::BatchWith) {
+            #[cfg(not(feature = "tokio"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: BatchWith specified, but the feature 'tokio' is not enabled! To fix this, compile with `--features=tokio`.",
+            ));
+
+            input.parse::<kw::BatchWith>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let load_many: syn::Expr = input.parse()?;
+            return Ok(CacheOption::BatchWith(load_many));
+        }
+        if la.peek(kw::BatchWindow) {
+            input.parse::<kw::BatchWindow>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let window: syn::Expr = input.parse()?;
+            return Ok(CacheOption::BatchWindow(window));
+        }
+        if la.peek(kw::MaxRefreshRate) {
+            input.parse::<kw::MaxRefreshRate>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let per: syn::Expr = input.parse()?;
+            return Ok(CacheOption::MaxRefreshRate(per));
+        }
+        if la.peek(kw::StaleOnError) {
+            input.parse::<kw::StaleOnError>().unwrap();
+            if input.peek(kw::Colon) {
+                input.parse::<kw::Colon>().unwrap();
+                let report: syn::Expr = input.parse()?;
+                return Ok(CacheOption::StaleOnError(Some(report)));
+            }
+            return Ok(CacheOption::StaleOnError(None));
+        }
+        if la.peek(kw::IndexBy) {
+            input.parse::<kw::IndexBy>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let index_by: syn::Expr = input.parse()?;
+            // The type of the secondary key is part of the type of the index.
+            input.parse::<kw::Colon>()?;
+            let index_type: syn::Type = input.parse()?;
+            return Ok(CacheOption::IndexBy(index_by, index_type));
+        }
+        if la.peek(kw::CoarseClock) {
+            input.parse::<kw::CoarseClock>().unwrap();
+            return Ok(CacheOption::CoarseClock);
+        }
+        if la.peek(kw::CompactEntries) {
+            input.parse::<kw::CompactEntries>().unwrap();
+            return Ok(CacheOption::CompactEntries);
+        }
+        if la.peek(kw::HashOnce) {
+            #[cfg(not(feature = "full"))]
+            return Err(syn::Error::new(input.span(),
+            "memoize error: HashOnce specified, but the feature 'full' is not enabled! To fix this, compile with `--features=full`.",
+            ));
+
+            input.parse::<kw::HashOnce>().unwrap();
+            return Ok(CacheOption::HashOnce);
+        }
+        if la.peek(kw::IgnoreErrors) {
+            input.parse::<kw::IgnoreErrors>().unwrap();
+            return Ok(CacheOption::IgnoreErrors);
+        }
+        if la.peek(kw::CircuitBreaker) {
+            input.parse::<kw::CircuitBreaker>().unwrap();
+            input.parse::<kw::Colon>().unwrap();
+            let fields;
+            syn::braced!(fields in input);
+            let (mut failures, mut cooldown, mut per_key) = (None, None, false);
+            while !fields.is_empty() {
+                let field = fields.lookahead1();
+                if field.peek(kw::failures) {
+                    fields.parse::<kw::failures>().unwrap();
+                    fields.parse::<kw::Colon>()?;
+                    let n: syn::LitInt = fields.parse()?;
+                    failures = Some(n.base10_parse::<u32>()?);
+                } else if field.peek(kw::cooldown) {
+                    fields.parse::<kw::cooldown>().unwrap();
+                    fields.parse::<kw::Colon>()?;
+                    cooldown = Some(fields.parse::<syn::Expr>()?);
+                } else if field.peek(kw::per_key) {
+                    fields.parse::<kw::per_key>().unwrap();
+                    fields.parse::<kw::Colon>()?;
+                    per_key = fields.parse::<syn::LitBool>()?.value;
+                } else {
+                    return Err(field.error());
+                }
+                if !fields.is_empty() {
+                    fields.parse::<syn::Token![,]>()?;
+                }
+            }
+            return match (failures, cooldown) {
+                (Some(failures), Some(cooldown)) if failures > 0 => {
+                    Ok(CacheOption::CircuitBreaker(failures, cooldown, per_key))
+                }
+                _ => Err(syn::Error::new(
+                    input.span(),
+                    "memoize error: CircuitBreaker needs `failures` (at least 1) and a `cooldown`, e.g. `CircuitBreaker: { failures: 5, cooldown: Duration::from_secs(30) }`",
+                )),
+            };
+        }
         Err(la.error())
     }
 }
@@ -94,59 +914,290 @@ impl parse::Parse for CacheOptions {
     fn parse(input: parse::ParseStream) -> syn::Result<Self> {
         let f: syn::punctuated::Punctuated<CacheOption, syn::Token![,]> =
             input.parse_terminated(CacheOption::parse, syn::Token![,])?;
+        Ok(Self::from_options(f))
+    }
+}
+
+impl CacheOptions {
+    fn from_options(options: impl IntoIterator<Item = CacheOption>) -> Self {
         let mut opts = Self::default();
 
-        for opt in f {
+        for opt in options {
             match opt {
                 CacheOption::LRUMaxEntries(cap) => opts.lru_max_entries = Some(cap),
                 CacheOption::TimeToLive(sec) => opts.time_to_live = Some(sec),
+                CacheOption::ExpireAfterAccess(tti) => opts.expire_after_access = Some(tti),
                 CacheOption::CustomHasher(hasher) => opts.custom_hasher = Some(hasher),
                 CacheOption::HasherInit(init) => opts.custom_hasher_initializer = Some(init),
                 CacheOption::SharedCache => opts.shared_cache = true,
                 CacheOption::Ignore(ident) => opts.ignore.push(ident),
+                CacheOption::WriteBack(threshold) => opts.write_back = Some(threshold),
+                CacheOption::WriteBackEvery(every) => opts.write_back_every = Some(every),
+                CacheOption::Precompute(inputs) => opts.precompute.extend(inputs),
+                CacheOption::Warm(inputs) => opts.warm.extend(inputs),
+                CacheOption::WarmAtStartup => opts.warm_at_startup = true,
+                CacheOption::PerElement => opts.per_element = true,
+                CacheOption::StoreWith(encode, ty) => opts.store_with = Some((encode, ty)),
+                CacheOption::LoadWith(decode) => opts.load_with = Some(decode),
+                CacheOption::SerializeValues => opts.serialize_values = true,
+                CacheOption::ExpireWith(expire_with) => opts.expire_with = Some(expire_with),
+                CacheOption::Priority(priority) => opts.priority = Some(priority),
+                CacheOption::Eviction(policy) => opts.eviction = Some(policy),
+                CacheOption::PartitionBy(partition, ty) => {
+                    opts.partition_by = Some((partition, ty))
+                }
+                CacheOption::Instrument => opts.instrument = true,
+                CacheOption::SharedFuture => opts.shared_future = true,
+                CacheOption::CollectStream => opts.collect_stream = true,
+                CacheOption::CollectCoroutine => opts.collect_coroutine = true,
+                CacheOption::MaxAge(max_age) => opts.max_age = Some(max_age),
+                CacheOption::Revalidate(revalidate) => opts.revalidate = Some(revalidate),
+                CacheOption::Persist(path) => opts.persist = Some(path),
+                CacheOption::PersistXdg(tool) => opts.persist_xdg = Some(tool),
+                CacheOption::PersistSalt(salt) => opts.persist_salt = Some(salt),
+                CacheOption::PersistKey(key) => opts.persist_key = Some(key),
+                CacheOption::SmallKey => opts.small_key = true,
+                CacheOption::DedupValues => opts.dedup_values = true,
+                CacheOption::MaxValueWeight(max_weight) => opts.max_value_weight = Some(max_weight),
+                CacheOption::Weigher(weigher) => opts.weigher = Some(weigher),
+                CacheOption::MinComputeTime(min_time) => opts.min_compute_time = Some(min_time),
+                CacheOption::AdmitAfter(requests) => opts.admit_after = Some(requests),
+                CacheOption::NegativeFilter(negative) => opts.negative_filter = Some(negative),
+                CacheOption::NegativeFilterCapacity(capacity) => {
+                    opts.negative_filter_capacity = Some(capacity)
+                }
+                CacheOption::FlushEvery(every) => opts.flush_every = Some(every),
+                CacheOption::Seed => opts.seed = true,
+                CacheOption::Secondary(store) => opts.secondary = Some(store),
+                CacheOption::WriteBehind => opts.write_behind = true,
+                CacheOption::ScopeBy(scope, ty) => opts.scope_by = Some((scope, ty)),
+                CacheOption::Extract(argument, extract, ty) => {
+                    opts.extract.push((argument, extract, ty))
+                }
+                CacheOption::Canonical(argument) => opts.canonical.push(argument),
+                CacheOption::SerdeKeys => opts.serde_keys = true,
+                CacheOption::DebugKeys => opts.debug_keys = true,
+                CacheOption::NormalizeKey(normalize) => opts.normalize_key = Some(normalize),
+                CacheOption::Nfc => opts.nfc = true,
+                CacheOption::KeyByPtr(argument) => opts.key_by_ptr.push(argument),
+                CacheOption::Quantize(argument, quantize, ty) => {
+                    opts.quantize.push((argument, quantize, ty))
+                }
+                CacheOption::KeyPipeline(argument, steps, ty) => {
+                    opts.key_pipeline.push((argument, steps, ty))
+                }
+                CacheOption::PublicStore => opts.public_store = true,
+                CacheOption::UseStore(store) => opts.use_store = Some(store),
+                CacheOption::EntryInfo => opts.entry_info = true,
+                CacheOption::AutoCapacity(min, max) => opts.auto_capacity = Some((min, max)),
+                CacheOption::AutoIgnoreDyn => opts.auto_ignore_dyn = true,
+                CacheOption::KeepConst => opts.keep_const = true,
+                CacheOption::ThreadLocal => opts.thread_local = true,
+                CacheOption::RayonShared => opts.rayon_shared = true,
+                CacheOption::TaskLocal => opts.task_local = true,
+                CacheOption::Scoped => opts.scoped = true,
+                CacheOption::RequestScoped => opts.task_local = true,
+                CacheOption::Export => opts.export = true,
+                CacheOption::Subscribe => opts.subscribe = true,
+                CacheOption::ValidateWith(validate) => opts.validate_with = Some(validate),
+                CacheOption::ValidateEvery(every) => opts.validate_every = Some(every),
+                CacheOption::BatchWith(load_many) => opts.batch_with = Some(load_many),
+                CacheOption::BatchWindow(window) => opts.batch_window = Some(window),
+                CacheOption::CircuitBreaker(failures, cooldown, per_key) => {
+                    opts.circuit_breaker = Some((failures, cooldown, per_key))
+                }
+                CacheOption::MaxRefreshRate(per) => opts.max_refresh_rate = Some(per),
+                CacheOption::StaleOnError(report) => opts.stale_on_error = Some(report),
+                CacheOption::IndexBy(index_by, ty) => opts.index_by = Some((index_by, ty)),
+                CacheOption::CoarseClock => opts.coarse_clock = true,
+                CacheOption::CompactEntries => opts.compact_entries = true,
+                CacheOption::HashOnce => opts.hash_once = true,
+                CacheOption::IgnoreErrors => opts.ignore_errors = true,
             }
         }
-        Ok(opts)
+        opts
     }
 }
 
-// This implementation of the storage backend does not depend on any more crates.
-#[cfg(not(feature = "full"))]
-mod store {
-    use crate::CacheOptions;
-    use proc_macro::TokenStream;
+/// An option, with the tokens it was parsed from.
+struct SourcedOption {
+    tokens: proc_macro2::TokenStream,
+    option: CacheOption,
+}
 
-    /// Returns tokenstreams (for quoting) of the store type and an expression to initialize it.
-    pub(crate) fn construct_cache(
-        _options: &CacheOptions,
-        key_type: proc_macro2::TokenStream,
-        value_type: proc_macro2::TokenStream,
-    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-        // This is the unbounded default.
-        if let Some(hasher) = &_options.custom_hasher {
-            return (
-                quote::quote! { #hasher<#key_type, #value_type> },
-                quote::quote! { #hasher::new() },
-            );
-        } else {
-            (
-                quote::quote! { std::collections::HashMap<#key_type, #value_type> },
-                quote::quote! { std::collections::HashMap::new() },
-            )
+impl parse::Parse for SourcedOption {
+    fn parse(input: parse::ParseStream) -> syn::Result<Self> {
+        let begin = input.cursor();
+        let option = input.parse()?;
+        let mut tokens = proc_macro2::TokenStream::new();
+        let mut cursor = begin;
+        while cursor != input.cursor() {
+            let (tree, next) = cursor.token_tree().unwrap();
+            tokens.extend(std::iter::once(tree));
+            cursor = next;
         }
-    }
-
-    /// Returns names of methods as TokenStreams to insert and get (respectively) elements from a
-    /// store.
-    pub(crate) fn cache_access_methods(
-        _options: &CacheOptions,
-    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-        (quote::quote! { insert }, quote::quote! { get })
+        Ok(SourcedOption { tokens, option })
     }
 }
 
-// This implementation of the storage backend also depends on the `lru` crate.
-#[cfg(feature = "full")]
+type OptionList = syn::punctuated::Punctuated<SourcedOption, syn::Token![,]>;
+
+/// Parses the options of a `#[memoize]` attribute, preceded by the options of `defaults!` that
+/// aren't given explicitly.
+///
+/// Also returns the merged options as a string, which e.g. the fingerprint of `Persist` is computed
+/// from.
+fn parse_options(
+    defaults: proc_macro2::TokenStream,
+    attr: proc_macro2::TokenStream,
+) -> syn::Result<(CacheOptions, String)> {
+    let explicit = parse::Parser::parse2(OptionList::parse_terminated, attr)?;
+    let defaults = parse::Parser::parse2(OptionList::parse_terminated, defaults)?;
+    let overridden: Vec<_> = explicit.iter().map(|o| option_kind(&o.option)).collect();
+    let merged: Vec<SourcedOption> = defaults
+        .into_iter()
+        .filter(|o| !overridden.contains(&option_kind(&o.option)))
+        .chain(explicit)
+        .collect();
+    let source = merged
+        .iter()
+        .map(|o| o.tokens.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok((
+        CacheOptions::from_options(merged.into_iter().map(|o| o.option)),
+        source,
+    ))
+}
+
+/// Options of the same kind override each other: the same option, or `SharedCache` and `ThreadLocal`.
+fn option_kind(option: &CacheOption) -> std::mem::Discriminant<CacheOption> {
+    match option {
+        CacheOption::ThreadLocal => std::mem::discriminant(&CacheOption::SharedCache),
+        option => std::mem::discriminant(option),
+    }
+}
+
+/**
+ * `memoize::defaults!(SharedCache, TimeToLive: Duration::from_secs(60));` sets default options for
+ * all `#[memoize]` functions of the module it is called in. A function can override a default by
+ * giving the same option itself, e.g. `#[memoize(TimeToLive: ...)]`.
+ *
+ * It imports the `#[memoize]` attribute into the module, which must not import it otherwise (so
+ * neither with `use memoize::memoize;` nor with `use memoize::*;`). Submodules importing the
+ * items of the module with `use super::*;` use the same defaults, while other modules aren't
+ * affected; `#[memoize::memoize]` ignores them as well.
+ */
+#[proc_macro]
+pub fn defaults(input: TokenStream) -> TokenStream {
+    let input = proc_macro2::TokenStream::from(input);
+    if let Err(e) = parse::Parser::parse2(OptionList::parse_terminated, input.clone()) {
+        return e.to_compile_error().into();
+    }
+    // The imported attribute passes each function to `__memoize_defaults!`, which adds the
+    // defaults; both are found through the module's scope.
+    quote::quote! {
+        #[doc(hidden)]
+        macro_rules! __memoize_defaults_rules {
+            ([$($options:tt)*] $($item:tt)*) => {
+                #[::memoize::__memoize_with_defaults([#input] $($options)*)]
+                $($item)*
+            };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        use __memoize_defaults_rules as __memoize_defaults;
+        #[allow(unused_imports)]
+        use ::memoize::__memoize_defaulted as memoize;
+    }
+    .into()
+}
+
+/// The `#[memoize]` attribute imported by `defaults!`.
+#[doc(hidden)]
+#[proc_macro_attribute]
+pub fn memoize_defaulted(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = proc_macro2::TokenStream::from(attr);
+    let item = proc_macro2::TokenStream::from(item);
+    quote::quote! {
+        __memoize_defaults! { [#attr] #item }
+    }
+    .into()
+}
+
+/// `#[memoize]`, with the options of `defaults!` in brackets before the explicit ones.
+#[doc(hidden)]
+#[proc_macro_attribute]
+pub fn memoize_with_defaults(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut attr = proc_macro2::TokenStream::from(attr).into_iter();
+    let defaults = match attr.next() {
+        Some(proc_macro2::TokenTree::Group(group))
+            if group.delimiter() == proc_macro2::Delimiter::Bracket =>
+        {
+            group.stream()
+        }
+        _ => {
+            return quote::quote! { compile_error!("memoize: malformed defaults"); }.into();
+        }
+    };
+    expand(defaults, attr.collect(), item)
+}
+
+/// Whether the `fxhash` or `ahash` feature replaces the hasher of the default stores (`HashMap` and
+/// `LruCache`) by `memoize::DefaultHashBuilder`.
+const DEFAULT_HASHER: bool = cfg!(feature = "fxhash") || cfg!(feature = "ahash");
+
+// This implementation of the storage backend does not depend on any more crates.
+#[cfg(not(feature = "full"))]
+mod store {
+    use crate::CacheOptions;
+    use proc_macro::TokenStream;
+
+    /// Returns tokenstreams (for quoting) of the store type and an expression to initialize it.
+    pub(crate) fn construct_cache(
+        _options: &CacheOptions,
+        key_type: proc_macro2::TokenStream,
+        value_type: proc_macro2::TokenStream,
+        _bounded_store: Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)>,
+    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        // This is the unbounded default.
+        if let Some(hasher) = &_options.custom_hasher {
+            (
+                quote::quote! { #hasher<#key_type, #value_type> },
+                quote::quote! { #hasher::new() },
+            )
+        } else if crate::DEFAULT_HASHER {
+            (
+                quote::quote! { std::collections::HashMap<#key_type, #value_type, ::memoize::DefaultHashBuilder> },
+                quote::quote! { std::collections::HashMap::default() },
+            )
+        } else {
+            (
+                quote::quote! { std::collections::HashMap<#key_type, #value_type> },
+                quote::quote! { std::collections::HashMap::new() },
+            )
+        }
+    }
+
+    /// Returns names of methods as TokenStreams to insert, get and remove (respectively) elements
+    /// from a store.
+    pub(crate) fn cache_access_methods(
+        _options: &CacheOptions,
+    ) -> (
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+    ) {
+        (
+            quote::quote! { insert },
+            quote::quote! { get },
+            quote::quote! { remove },
+        )
+    }
+}
+
+// This implementation of the storage backend also depends on the `lru` crate.
+#[cfg(feature = "full")]
 mod store {
     use crate::CacheOptions;
     use proc_macro::TokenStream;
@@ -156,15 +1207,15 @@ mod store {
     ///
     /// First return value: Type of store ("Container<K,V>").
     /// Second return value: Initializer syntax ("Container::<K,V>::new()").
+    ///
+    /// `bounded_store` is given if a bounded cache should use another eviction policy than LRU: it is
+    /// the path of the store type, and the arguments its `new()` takes after the capacity.
     pub(crate) fn construct_cache(
         options: &CacheOptions,
         key_type: proc_macro2::TokenStream,
         value_type: proc_macro2::TokenStream,
+        bounded_store: Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)>,
     ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-        let value_type = match options.time_to_live {
-            None => quote::quote! {#value_type},
-            Some(_) => quote::quote! {(std::time::Instant, #value_type)},
-        };
         // This is the unbounded default.
         match options.lru_max_entries {
             None => {
@@ -181,17 +1232,45 @@ mod store {
                         );
                     }
                 }
+                // `PrehashedMap` uses `memoize::DefaultHashBuilder` as well.
+                if options.hash_once {
+                    return (
+                        quote::quote! { ::memoize::PrehashedMap<#key_type, #value_type> },
+                        quote::quote! { ::memoize::PrehashedMap::new() },
+                    );
+                }
+                if crate::DEFAULT_HASHER {
+                    return (
+                        quote::quote! { std::collections::HashMap<#key_type, #value_type, ::memoize::DefaultHashBuilder> },
+                        quote::quote! { std::collections::HashMap::default() },
+                    );
+                }
                 (
                     quote::quote! { std::collections::HashMap<#key_type, #value_type> },
                     quote::quote! { std::collections::HashMap::new() },
                 )
             }
             Some(cap) => {
-                if let Some(_) = &options.custom_hasher {
+                if options.custom_hasher.is_some() {
                     (
                         quote::quote! { compile_error!("Cannot use LRU cache and a custom hasher at the same time") },
                         quote::quote! { std::collections::HashMap::new() },
                     )
+                } else if let Some((store, args)) = bounded_store {
+                    (
+                        quote::quote! { #store<#key_type, #value_type> },
+                        quote::quote! { #store::new(std::num::NonZeroUsize::new(#cap).unwrap() #args) },
+                    )
+                } else if crate::DEFAULT_HASHER {
+                    (
+                        quote::quote! { ::memoize::lru::LruCache<#key_type, #value_type, ::memoize::DefaultHashBuilder> },
+                        quote::quote! {
+                            ::memoize::lru::LruCache::with_hasher(
+                                std::num::NonZeroUsize::new(#cap).unwrap(),
+                                ::memoize::DefaultHashBuilder::default(),
+                            )
+                        },
+                    )
                 } else {
                     (
                         quote::quote! { ::memoize::lru::LruCache<#key_type, #value_type> },
@@ -202,15 +1281,27 @@ mod store {
         }
     }
 
-    /// Returns names of methods as TokenStreams to insert and get (respectively) elements from a
-    /// store.
+    /// Returns names of methods as TokenStreams to insert, get and remove (respectively) elements
+    /// from a store.
     pub(crate) fn cache_access_methods(
         options: &CacheOptions,
-    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    ) -> (
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+    ) {
         // This is the unbounded default.
         match options.lru_max_entries {
-            None => (quote::quote! { insert }, quote::quote! { get }),
-            Some(_) => (quote::quote! { put }, quote::quote! { get }),
+            None => (
+                quote::quote! { insert },
+                quote::quote! { get },
+                quote::quote! { remove },
+            ),
+            Some(_) => (
+                quote::quote! { put },
+                quote::quote! { get },
+                quote::quote! { pop },
+            ),
         }
     }
 }
@@ -247,8 +1338,362 @@ mod store {
  *
  * Parameters can be ignored by the cache using the `Ignore` parameter. `Ignore` can be specified
  * multiple times, once per each parameter. `Ignore`d parameters do not need to implement [`Clone`]
- * or [`Hash`]. 
- * 
+ * or [`Hash`].
+ *
+ * Arguments of type `impl Into<T>` are converted to `T` before the lookup; `T` is stored in the key
+ * and passed on to the function (as `T: Into<T>`), so that callers can pass any convertible value.
+ *
+ * Slice arguments `&[T]` are stored as a `Vec<T>`, which is only built when a result is inserted. If
+ * the slice is the whole key, lookups compare it borrowed, so hits don't allocate (unless the store is
+ * a `Persist`ed, partitioned, prioritized or custom one). `memoized_warm_<function name>()` and
+ * `memoized_get_or_compute_many_<function name>()` aren't generated for such functions.
+ *
+ * `#[memoize(AutoIgnoreDyn)]` ignores all arguments that are trait objects, like `&dyn Trait`,
+ * `Box<dyn Trait>` or `Arc<dyn Trait>`, as if they were given to `Ignore`: they can't be hashed, and
+ * are usually dependencies (a logger, a client) rather than inputs.
+ *
+ * `#[memoize(Extract: ctx => ctx.locale: String)]` keeps the argument `ctx` out of the key, like
+ * `Ignore`, but includes a value extracted from it instead (a clone of the expression, whose type
+ * must be given after a colon). Only the extracted value needs to implement [`Clone`] and [`Hash`].
+ *
+ * `#[memoize(Canonical: tags)]` allows arguments of unordered collections, like a `HashSet` or a
+ * `HashMap` (or a reference to one): the key holds their canonical representation, a sorted `Vec`,
+ * so that logically equal collections hit the same entry despite different iteration orders. The
+ * argument needs to implement `memoize::Canonicalize`; for `Vec`s and slices, which implement it as
+ * well, the order of the elements is then ignored.
+ *
+ * `#[memoize(Quantize: x => (x * 100.0).round() as i64)]` rounds an argument into a discrete bucket
+ * before it enters the key, so that continuous inputs (floats, timestamps) close to each other share
+ * an entry; the first call within a bucket determines the cached result. The expression refers to the
+ * argument by its name, and must not move it. The type of the bucket is taken from a trailing cast, or
+ * given explicitly after a colon, as in `Quantize: t => t.as_secs() / 60: u64`.
+ *
+ * These transformations can be combined per argument with `KeyPipeline`: in
+ * `#[memoize(KeyPipeline: query => [trim, |q| q.to_lowercase(), memoize::nfc])]`, a clone of the
+ * argument is passed through each step in order (functions or closures taking the previous value),
+ * and the final value enters the key. It is of the type of the argument, unless another type is given
+ * after a colon, as in `KeyPipeline: x => [f64::abs, |x| (x * 100.0) as i64]: i64` (which is
+ * required for reference arguments).
+ *
+ * `#[memoize(KeyByPtr: config)]` keys an argument of type `Arc<T>` (or `&Arc<T>`) by the identity of
+ * the value it points to instead of its contents, as a `memoize::PtrKey<T>`: the key is then built
+ * in constant time, and `T` doesn't need to implement [`Hash`], [`Eq`] or [`Clone`]. This suits large,
+ * immutable, shared inputs, for which a different allocation is a different input. The key only holds
+ * a weak reference, so it doesn't keep the value alive, and an entry is never hit again once all
+ * `Arc`s of its argument are dropped.
+ *
+ * `#[memoize(NormalizeKey: normalize)]` passes the key through `fn normalize(key: K) -> K` before
+ * every lookup and insertion, so that semantically equal arguments (e.g. differing in case or
+ * surrounding whitespace) share one entry. The key `K` is the memoized argument, or a tuple of the
+ * memoized arguments in order if there are several; `memoized_get_or_compute_many_<function name>()`
+ * isn't generated then.
+ *
+ * With the `nfc` feature, `#[memoize(Nfc)]` stores the arguments of type `String` in Unicode
+ * Normalization Form C (before any `NormalizeKey`), so that canonically equivalent texts in different
+ * normal forms share one entry. `memoize::nfc` can also be used as a `NormalizeKey` function itself.
+ *
+ * With the `serde` feature, `#[memoize(SerdeKeys)]` builds keys by serializing the arguments (with
+ * `bincode`) instead of storing them, so that they only need to implement `serde::Serialize`, rather
+ * than [`Clone`], [`Hash`] and [`Eq`]; this unlocks memoization for third-party types lacking these
+ * implementations. Keys are then `Vec<u8>`s, and `memoized_get_or_compute_many_<function name>()`
+ * isn't generated.
+ *
+ * As a last resort for quick experiments, `#[memoize(DebugKeys)]` keys the cache on the `Debug`
+ * representation of the arguments (a `String` built with `format!("{:?}", ...)`), so that they only
+ * need to implement [`Debug`](std::fmt::Debug). This is only correct if that output is stable, and
+ * differs for unequal arguments (which it doesn't for, e.g., truncated outputs); every use is hence
+ * reported by a deprecation warning, which can be silenced with `#[allow(deprecated)]`.
+ *
+ * Caches are thread-local by default. The `shared` feature makes every cache shared instead, as if it
+ * was given `SharedCache`, unless it is marked `#[memoize(ThreadLocal)]`; conversely, the
+ * `thread-local` feature keeps every cache thread-local unless it is marked `SharedCache`, including
+ * those with `RayonShared`. The two features cannot be enabled together.
+ *
+ * `#[memoize(Scoped)]` bounds the lifetime of a cache: within `memoize::scope(|| ...)`, calls on the
+ * same thread use a cache owned by the scope, which is dropped when the closure returns (nested scopes
+ * each have their own caches). Outside of a scope, nothing is cached. Batch jobs can thus memoize
+ * without their caches outliving the batch.
+ *
+ * With the `tokio` feature, `#[memoize(TaskLocal)]` keeps a cache per tokio task instead: a future
+ * run by `memoize::task_scope(future)` (e.g. a request handler) gets fresh caches for all `TaskLocal`
+ * functions, which are dropped when it completes. Outside of a scope, nothing is cached. This suits
+ * request-scoped derived data in async services. The other generated functions, like
+ * `memoized_flush_<function name>()`, act on the cache of the current scope.
+ *
+ * With the `tower` feature, `memoize::tower::RequestScopeLayer` runs every request of a tower service
+ * (e.g. an axum router) in a task scope, and `#[memoize(RequestScoped)]`, a synonym of `TaskLocal`,
+ * deduplicates calls within a request.
+ *
+ * With `SharedCache`, `#[memoize(SharedCache, WriteBack: 64)]` makes each thread collect newly
+ * computed entries in a thread-local buffer, and merge them into the shared cache in a single lock
+ * acquisition once 64 entries have accumulated (or, with `WriteBackEvery: Duration::from_millis(100)`,
+ * once the buffer is older than the given duration). `memoized_write_back_<function name>()`
+ * publishes the current thread's buffer immediately; buffers are also published when a thread exits.
+ * `memoized_flush_<function name>()` discards the entries buffered by all threads.
+ *
+ * With the `rayon` feature, `#[memoize(RayonShared)]` suits functions called from rayon's parallel
+ * iterators: the cache is shared, but each worker thread of a rayon pool collects its entries in its
+ * own buffer, which it consults first, and publishes it to the shared cache like `WriteBack` (by
+ * default, every 64 entries or 100 ms). Other threads access the shared cache directly. Workers thus
+ * don't compute and hold their own copies of the same results, nor start with cold caches.
+ *
+ * For a `const fn`, `#[memoize(Precompute: [(0), (1), (2)])]` evaluates the function for the listed
+ * inputs at compile time and bakes the results into a static table, which is consulted before the
+ * runtime cache. Each input lists all arguments of the function as a tuple.
+ *
+ * A memoized `const fn` is no longer `const`, as its cache only exists at runtime. With the `nightly`
+ * feature, `#[memoize(KeepConst)]` keeps it `const` instead (which requires
+ * `#![feature(core_intrinsics, const_eval_select)]` in the crate): in const contexts, the original
+ * function is evaluated, while calls at runtime are memoized.
+ *
+ * Attributes exporting a function, like `#[wasm_bindgen]`, `#[export_name = "..."]` or
+ * `#[no_mangle]`, are moved from the original function to the memoized one (and doc comments are
+ * copied), so that JavaScript or C callers go through the cache, whether they are written before or
+ * after `#[memoize]`. Caches without expiration or cost measurement don't read the clock, so they work
+ * on `wasm32-unknown-unknown`, where `std::time::Instant` is not available.
+ *
+ * The same goes for PyO3's `#[pyfunction]` and its `#[pyo3(...)]` options, which keep their order,
+ * and `#[pyo3(...)]` attributes of arguments stay on the memoized function only. `#[memoize]` must
+ * come first, so that it sees `#[pyfunction]`: the `Python<'py>` token argument is then left out of
+ * the key.
+ *
+ * `#[track_caller]` is kept on the original function and added to the memoized one, so that panics
+ * and `Location::caller()` in the original function report the call site of the memoized function.
+ *
+ * The memoized function of a synchronous function is `#[inline]`, and only looks up the cache:
+ * computing and inserting a value on a miss happens out of line, in a `#[cold]` function, which keeps
+ * hits cheap in tight loops. Functions with `#[track_caller]` handle misses inline, as the caller
+ * location isn't passed through.
+ *
+ * `#[memoize(Warm: [(1, 2), (3, 4)])]` computes the listed inputs as soon as the cache is first
+ * touched (and again after a flush), which avoids latency spikes on the first requests for a known set
+ * of hot keys. Like with `Precompute`, each input lists all arguments of the function.
+ *
+ * With the `startup` feature, `WarmAtStartup` registers the `Warm` list of a function at program
+ * startup, and `memoize::warm_all()` (typically called from `main`) computes all registered warm lists
+ * before serving traffic.
+ *
+ * Unless arguments are `Ignore`d, `memoized_warm_<function name>(inputs)` computes and inserts all
+ * inputs of an iterator of argument tuples, e.g. to prime a cache from a database query at boot. With
+ * the `rayon` feature and `SharedCache`, the inputs are computed in parallel.
+ *
+ * `memoized_get_many_<function name>(keys)` looks up an iterator of key tuples (the non-ignored
+ * arguments) while taking the lock only once, returning `None` for uncached keys.
+ * `memoized_get_or_compute_many_<function name>(keys)` additionally computes (and caches) the missing
+ * values; it is only generated if no argument is `Ignore`d.
+ *
+ * Functions of the shape `fn lookup(ids: &[u64]) -> Vec<Item>` (returning one value per element,
+ * in order) can use `PerElement`: the cache then maps single ids to single items, and the original
+ * function is only called with the ids that are not cached yet.
+ *
+ * Functions defined inside other functions (or closures) can be memoized as well. Their cache and
+ * the generated helper functions are items of the enclosing block, and thus local to it.
+ *
+ * The cached representation of values can differ from the returned type:
+ * `#[memoize(StoreWith: encode: Compact, LoadWith: decode)]` stores `encode(&value)` (of type
+ * `Compact`), and returns `decode(&stored)` on cache hits. The return type then doesn't need to be
+ * [`Clone`].
+ *
+ * `#[memoize(DedupValues)]` is meant for functions mapping many keys to few distinct, large values:
+ * the cache stores values as `Arc`s, and equal values computed for different keys share a single
+ * allocation. The return type needs to implement [`Clone`], [`Hash`] and [`Eq`], and be `Send` and
+ * `Sync`.
+ *
+ * `#[memoize(MaxValueWeight: 1 << 20, Weigher: |value| value.len())]` keeps outliers out of the
+ * cache: `Weigher` takes a function (or non-capturing closure) from a reference to the return value
+ * to its weight as a `usize`, and values weighing more than `MaxValueWeight` are returned, but not
+ * cached.
+ *
+ * `#[memoize(MinComputeTime: Duration::from_millis(5))]` measures how long the original function
+ * takes, and only caches values whose computation took at least as long, so that cheap calls don't
+ * take up the slots of a `Capacity`-bounded cache needed by expensive ones.
+ *
+ * With `#[memoize(AdmitAfter: 2)]`, a value is only cached once its key has been requested at least
+ * the given number of times (up to 255), so that one-off keys of a long-tail workload don't displace
+ * recurring ones. Requests are counted approximately by a fixed-size `memoize::FrequencySketch`,
+ * shared by all threads, in which old counts decay over time.
+ *
+ * For functions whose result is mostly negative (e.g. "is this hash known?"),
+ * `#[memoize(NegativeFilter: false)]` doesn't cache the keys with a negative result (the given
+ * value; the return type needs to implement [`PartialEq`]) in the cache itself, but in a
+ * `memoize::NegativeFilter`, which takes about 10 bytes per key: a Bloom filter rejects most other
+ * keys, and the keys it lets through are confirmed by their 64-bit hash. Keys found in the filter
+ * return the negative value without calling the function. It is cleared whenever it has taken
+ * `NegativeFilterCapacity` keys (100 000 by default).
+ *
+ * With the `serde` feature, `SerializeValues` stores values serialized to bytes (using `bincode`)
+ * instead of their native type. The return type then needs to implement `serde::Serialize` and
+ * `serde::Deserialize`, but not [`Clone`], and `memoized_bytes_<function name>()` returns the number
+ * of bytes used by the cached values.
+ *
+ * Also with the `serde` feature, `#[memoize(Persist: path)]` keeps the cache in the file at `path` (any
+ * expression implementing `AsRef<std::path::Path>`), using `memoize::DiskStore`: entries are read from
+ * the file when the cache is first used, and new entries are appended to it, so that results are
+ * shared by all processes using the same file (e.g. parallel invocations of a CLI tool). Accesses are
+ * protected by advisory file locks. New entries are written in batches, and when the process exits
+ * normally; entries which are still buffered are lost if it is killed or aborts, unless
+ * `memoize::flush_persistence()` has written them. Arguments and return values need to implement
+ * `serde::Serialize` and `serde::Deserialize`; `Persist` can't be combined with `Capacity` or expiring
+ * entries. The file is stamped with a fingerprint of the function's signature and the `memoize`
+ * options, and files with another fingerprint are discarded rather than loaded, so that data written
+ * by an incompatible version of the function is never deserialized. Changes which the fingerprint
+ * can't see (like new fields of the returned type) can be signalled by changing
+ * `PersistSalt: "some version"`.
+ *
+ * With the `encryption` feature, `PersistKey: key` encrypts all persisted records (keys and values)
+ * with ChaCha20-Poly1305, e.g. for cached API responses containing user data. `key` is a function
+ * returning the 256-bit key as `[u8; 32]`, which is called at runtime when the cache is first used;
+ * records which can't be decrypted with the key are ignored.
+ *
+ * `#[memoize(PersistXdg: "toolname")]` persists the cache in the platform's cache directory instead
+ * (`$XDG_CACHE_HOME` or `~/.cache` on Linux, `~/Library/Caches` on macOS, `%LOCALAPPDATA%` on
+ * Windows), in a file per function below a `toolname` directory, which is created as needed. See
+ * `memoize::cache_file()`.
+ *
+ * Also with the `serde` feature, `#[memoize(Seed)]` generates `memoized_load_seed_<function name>(path)`,
+ * which loads precomputed entries from a file written by `memoize::write_seed()` (e.g. by an offline
+ * batch job) into the cache, and returns their number. Unlike `Persist`, the file is only read. For
+ * thread-local caches, the entries are loaded into the cache of the calling thread.
+ *
+ * Also with the `serde` feature, `#[memoize(Export)]` generates `memoized_export_<function name>()`,
+ * which serializes the cached (and still fresh) entries into a `Vec<u8>`, and
+ * `memoized_import_<function name>(&bytes)`, which inserts them into the cache (of the calling thread,
+ * for thread-local caches) and returns their number, so that a warmed parent process can hand its
+ * cache to the workers it spawns. `memoize::write_frame()` and `memoize::read_frame()` send the bytes
+ * over a pipe or socket, prefixed with their length.
+ *
+ * Also with the `serde` feature, `#[memoize(Secondary: &STORE)]` composes the in-memory cache with
+ * an external store, like Redis or a directory on disk: on a miss, the value is looked up in the
+ * `memoize::SecondaryStore` before it is computed, and computed values are written through to it.
+ * The expression is evaluated on each miss, and has to be a reference to the store; keys and values
+ * are serialized with `bincode`. Neither persisted files nor secondary stores depend on `std`'s
+ * hashers, which may change between Rust releases; stores which need fixed-size keys can use the
+ * versioned `memoize::stable_key_hash`.
+ *
+ * With the `invalidation` feature, `#[memoize(SharedCache, Subscribe)]` keeps the caches of several
+ * processes (e.g. the instances of a service) coherent: `memoize::publish_invalidation("function_name")`
+ * flushes the caches of the functions with the given name (or path), and
+ * `memoize::publish_key_invalidation("function_name", &key)` removes the entry of a key (the tuple of
+ * memoized arguments), in this process and, through the bus set with `memoize::set_invalidation_bus()`,
+ * in every other process. Buses implement `memoize::InvalidationBus`, e.g. on Redis pub/sub, and pass
+ * the messages they receive to `memoize::deliver_invalidation()`; on Unix, `memoize::UnixSocketBus`
+ * connects the processes on one host through sockets in a shared directory. Keys are serialized with
+ * `bincode`.
+ *
+ * With `WriteBehind`, computed values are not written to the secondary store by the calling thread,
+ * but queued for a background thread, so that the memoized function never waits for disk or network
+ * I/O. The store then needs to be `Sync` and referenced by a `&'static`. Queued values are lost if the
+ * process exits before they are written; `memoize::flush_write_behind()` waits until they are.
+ *
+ * Values can also expire based on their content: `#[memoize(ExpireWith: |response| response.expires_at)]`
+ * takes a function (or non-capturing closure) from a reference to the return value to an
+ * `Option<std::time::Instant>`; the cached value is recomputed once that instant has passed.
+ *
+ * For memoized API clients, `#[memoize(MaxAge: |response| response.max_age)]` takes the freshness
+ * lifetime from the value itself (a function from a reference to the return value to an
+ * `Option<std::time::Duration>`, like the `max-age` of a `Cache-Control` header; without one, a value
+ * is stale right away). `Revalidate: validator` is consulted before recomputing a stale value: it takes
+ * a reference to the arguments (as a tuple, or the bare argument for single-argument functions) and to
+ * the stale value, and if it returns `true` (e.g. after a conditional request with the value's `ETag`),
+ * the stale value is renewed and returned instead. `Revalidate` also works with `ExpireWith`,
+ * `TimeToLive` and `ExpireAfterAccess`.
+ *
+ * For values that are expensive to recompute, like aggregates behind a dashboard,
+ * `MaxRefreshRate: Duration::from_secs(60)` recomputes each key at most once per minute: an expired
+ * value which was computed less than a minute ago is still served. It requires `TimeToLive`,
+ * `ExpireAfterAccess`, `ExpireWith` or `MaxAge`.
+ *
+ * For near-real-time freshness without short lifetimes, `ValidateWith: validator` is consulted on
+ * every hit, with the same arguments as `Revalidate`: if it returns `false` (e.g. because a cheap
+ * version counter or `ETag` of the source has changed), the entry is removed and the value is
+ * recomputed. `ValidateEvery: Duration::from_secs(1)` validates each entry at most once per period,
+ * and serves it without asking in between.
+ *
+ * `#[memoize(IgnoreErrors)]` only caches the `Ok` results of a function returning a `Result`: `Err`s
+ * are returned, but the next call computes the value again, so that transient failures aren't
+ * memoized.
+ *
+ * For functions returning a `Result`, `#[memoize(CircuitBreaker: { failures: 5, cooldown:
+ * Duration::from_secs(30) })]` stops calling a failing dependency: after 5 consecutive `Err`s, calls
+ * return the last `Err` without calling the function until the cooldown has passed. Then one call
+ * tries again, and either closes the circuit, or opens it for another cooldown. Failures are counted
+ * for all keys together, or for each key with `per_key: true`. `Err`s are not cached.
+ *
+ * To stay available while a dependency is down, `#[memoize(TimeToLive: ..., StaleOnError)]` serves the
+ * previous (expired) `Ok` value when recomputing it returns an `Err`. The `Err` is not cached, so the
+ * next call tries again, and it is reported to the `on_stale` hook and to the optional function
+ * given as `StaleOnError: report`, which takes a reference to the arguments and to the `Err` result.
+ * It requires a function returning a `Result`, and `TimeToLive`, `ExpireAfterAccess`, `ExpireWith`
+ * or `MaxAge`.
+ *
+ * `#[memoize(FlushEvery: Duration::from_secs(3600))]` clears the whole cache periodically, as a blunt
+ * freshness guarantee for data which doesn't need per-entry expiration. The period starts with the
+ * first call, and is checked on every call (for thread-local caches, per thread).
+ *
+ * With the `schedule` feature, `memoize::schedule_flush("function_name", "0 2 * * *")` flushes the
+ * caches of the functions with the given name (or path) on a cron-like schedule, e.g. after a nightly
+ * data refresh, and `memoize::flush_by_tag("function_name")` flushes them right away.
+ * `memoize::flush_request(&["tag", ...])` flushes a group of tags at the boundaries of a request (right
+ * away, and when the returned guard is dropped), and with the `tower` feature,
+ * `memoize::tower::FlushTagsLayer` does so around every request of a tower service.
+ *
+ * `async fn`s can be memoized as well; the cache is only locked while looking up or inserting a
+ * value, never while the original function is awaited. With the `tokio` feature, the store of a
+ * `SharedCache` async function is guarded by a `tokio::sync::Mutex` (with the `async` feature, by the
+ * runtime-agnostic `async_lock::Mutex`, for async-std or smol), so that tasks waiting for the lock
+ * yield to the executor instead of blocking its worker thread; the helper functions which lock
+ * the store (`memoized_flush_<function name>()`, `memoized_size_<function name>()`, ...) are then
+ * `async` as well. Bulk lookups (`memoized_get_or_compute_many_<function name>()`) lock the async
+ * store for a bounded number of keys at a time (`memoize::MAINTENANCE_CHUNK`), and yield to the
+ * executor in between. `WriteBack` and `WarmAtStartup` are not supported for async functions.
+ *
+ * With `#[memoize(SharedFuture)]` (and the `async` or `tokio` feature), an async function's cache
+ * stores the shared future of each computation rather than its result: the first caller creates the
+ * future, and later callers, even those arriving before it has completed, await the same future
+ * instead of starting the work again. The completed value stays cached under the usual rules
+ * (`Capacity`, `TimeToLive`, ...). `memoized_shared_<function name>(...)` returns the shared future
+ * itself, and the return type needs to be [`Clone`]. Failed computations are not kept: if the
+ * future panics, or the function returns a `Result` and the future resolves to an `Err`, the entry is
+ * removed, and the next caller retries. Neither are abandoned computations: if the caller that
+ * created the future stops awaiting it before it completes (e.g. because its task was cancelled), the
+ * entry is removed; callers already awaiting the future complete it themselves, rather than waiting
+ * forever, and later callers start over. Without `SharedFuture`, values of async functions are only
+ * inserted once the caller computing them has awaited them to the end.
+ *
+ * Async functions also get `async fn memoized_flush_<function name>_async()`, which flushes the cache
+ * and then waits until all computations running at that point have completed or were cancelled.
+ * Values computed by these computations are not cached, and shared futures created before the flush
+ * are not joined by later callers, so that callers after the flush get fresh results.
+ *
+ * With the `tokio` feature, `#[memoize(BatchWith: load_many)]` batches the misses of an async
+ * function, like a DataLoader: the keys missed by concurrent calls within a short window (1 ms, or
+ * `BatchWindow: Duration::from_millis(5)`) are loaded by a single call of `load_many`, an async
+ * function taking a `Vec` of keys (tuples of the memoized arguments, or bare arguments of
+ * single-argument functions) and returning a `Vec` of their values, in the same order. Each value is
+ * cached under its key. The body of the memoized function is not called on misses.
+ *
+ * `#[memoize(CollectStream)]` memoizes a function returning `impl Stream<Item = T>` (with the `async`
+ * or `tokio` feature): the items are collected while the stream is consumed, and once it is
+ * exhausted, the cache holds them as a `Vec<T>`. Later calls return a stream replaying the cached
+ * items. A stream which is dropped before its end is not cached. `T` needs to be [`Clone`].
+ *
+ * Similarly, with the `nightly` feature, `#[memoize(CollectCoroutine)]` memoizes a function returning
+ * `impl Coroutine<Yield = Y, Return = R>` (which requires `#![feature(coroutine_trait)]` in the
+ * crate). On a miss, the coroutine is run to completion right away, and the cache holds the yielded
+ * values and the return value as a `(Vec<Y>, R)`; every call returns a coroutine replaying them. `Y`
+ * and `R` need to be [`Clone`].
+ *
+ * Keys made of primitive `Copy` types (integers, `bool`, `char`, floats, and small arrays and tuples
+ * of them) are copied rather than cloned when they are looked up and stored. Other small `Copy` key
+ * types, like a `Copy` struct of a few fields, get the same treatment with `#[memoize(SmallKey)]`.
+ *
+ * `#[memoize(ScopeBy: current_tenant(): TenantId)]` mixes ambient context into every key: the
+ * expression is evaluated at the start of each call (e.g. reading a task-local or thread-local
+ * tenant id), and its value, whose type must be given after a colon, becomes the first component of
+ * the key. Multi-tenant services thus can't leak cached results across tenants, even if the tenant
+ * isn't an argument of the function. `memoized_get_many_<function name>()` then takes keys including
+ * the scope, and `memoized_get_or_compute_many_<function name>()` isn't generated.
+ *
  * See the `examples` for concrete applications.
  *
  * *The following descriptions need the `full` feature enabled.*
@@ -259,204 +1704,4983 @@ mod store {
  * `#[memoize(TimeToLive: Duration::from_secs(2))]`. In that case, cached value will be actual
  * no longer than duration provided and refreshed with next request. If you prefer chrono::Duration,
  * it can be also used: `#[memoize(TimeToLive: chrono::Duration::hours(9).to_std().unwrap()]`
+ * `ExpireAfterWrite` is a synonym of `TimeToLive`. `#[memoize(ExpireAfterAccess: Duration::from_secs(60))]`
+ * instead expires values that haven't been read for the given duration; both can be combined, in
+ * which case a value expires as soon as either duration has passed.
+ *
+ * `#[memoize(TimeToLive: Duration::from_secs(1), CoarseClock)]` checks expiration against
+ * `memoize::coarse_now()` rather than `Instant::now()`: a clock that is cheap to read, as it is only
+ * updated by a background thread every `memoize::COARSE_CLOCK_RESOLUTION` (1 ms). Hits are faster,
+ * but entries may be served a few milliseconds past their expiration. The thread, named
+ * `memoize-clock`, is started by the first lookup, and parks once the clock hasn't been read for
+ * 100 ms; the next lookup reads the time itself and wakes it up again.
+ *
+ * Entries of caches with `TimeToLive` or `ExpireAfterAccess` are `memoize::Entry`s, which only
+ * track the metadata their options need, in a 16-byte `Instant` per timestamp.
+ * `#[memoize(TimeToLive: ..., CompactEntries)]` stores `memoize::CompactEntry`s instead, with
+ * timestamps in `u32` milliseconds since the epoch of the cache (per thread for thread-local
+ * caches). An epoch runs out after about 49 days, at which point the cache is cleared on the next
+ * insertion and a new epoch starts. `CompactEntries` can't be combined with options needing other
+ * metadata: `ExpireWith`, `MaxAge`, `EntryInfo`, `ValidateEvery`, `Revalidate` and
+ * `Eviction: GreedyDual`, nor with `UseStore`.
+ *
+ * With a `Capacity`, `#[memoize(Capacity: 100, Priority: |key, value| ...)]` ranks entries by a `u8`
+ * computed from the key tuple and the return value when they are inserted. A full cache evicts the
+ * least recently used of its lowest-priority entries, so expensive-to-recompute results can be
+ * protected regardless of recency.
+ *
+ * `#[memoize(Capacity: 100, Eviction: GreedyDual)]` measures how long each value took to compute,
+ * and makes a full cache preferentially keep the entries that were expensive to compute (using the
+ * GreedyDual policy, under which unused entries still age out eventually).
+ *
+ * `Eviction: ClockPro` selects the CLOCK-Pro policy instead, which approximates LIRS: entries that
+ * are used again soon after being inserted become hot, and a scan over many new keys only evicts
+ * cold entries. Cache hits don't reorder any list, they only mark an entry as referenced, so that a
+ * `SharedCache` store is a `RwLock` (with `PublicStore`, `read()` and `write()` it instead of
+ * `lock()`) whose read lock is taken by hits, and the cached values need to implement [`Sync`].
+ * Entries tracking metadata in [`Cell`](std::cell::Cell)s (`ExpireAfterAccess`, `ExpireWith`,
+ * `MaxAge`, `EntryInfo`, `ValidateEvery`, `CompactEntries`) keep the `Mutex`.
+ *
+ * `Eviction: ARC` selects the Adaptive Replacement Cache policy, which tunes itself between recency
+ * and frequency, and suits workloads alternating between scans and hot-spot phases.
  *
- * You can also specify a custom hasher: `#[memoize(CustomHasher: ahash::HashMap)]`, as some hashers don't use a `new()` method to initialize them, you can also specifiy a `HasherInit` parameter, like this: `#[memoize(CustomHasher: FxHashMap, HasherInit: FxHashMap::default())]`, so it will initialize your `FxHashMap` with `FxHashMap::default()` insteado of `FxHashMap::new()`
+ * `#[memoize(Capacity: 100, PartitionBy: |key| key.0.is_premium(): bool)]` routes entries into
+ * separate partitions, each with its own capacity of 100 entries, so that churn in one partition
+ * can't evict the entries of another one. The function maps a reference to the key tuple to the
+ * partition, whose type (here `bool`) must be given after a colon.
+ *
+ * You can also specify a custom hasher: `#[memoize(CustomHasher: ahash::HashMap)]`, as some hashers don't use a `new()` method to initialize them, you can also specifiy a `HasherInit` parameter, like this: `#[memoize(CustomHasher: FxHashMap, HasherInit: FxHashMap::default())]`, so it will initialize your `FxHashMap` with `FxHashMap::default()` insteado of `FxHashMap::new()`. The map must implement `memoize::CacheStore`, like the `HashMap`s of `std` and `hashbrown` do.
+ *
+ * Instead of a `CustomHasher` per function, the `fxhash` or `ahash` feature sets the hasher of all
+ * unbounded and LRU stores to FxHash or aHash, respectively (see `memoize::DefaultHashBuilder`).
+ *
+ * `#[memoize(HashOnce)]` hashes the key only once on a miss: the store is a `memoize::PrehashedMap`,
+ * and the computed value is inserted with the hash of the lookup. It can't be combined with
+ * `Capacity`, `AutoCapacity`, `CustomHasher`, `PartitionBy` or `UseStore`, nor with `PerElement`,
+ * `WriteBack`, `CollectStream`, `CollectCoroutine` and `ValidateWith`, which look up keys their own
+ * way.
  *
  * This mechanism can, in principle, be extended (in the source code) to any other cache mechanism.
  *
  * `memoized_flush_<function name>()` allows you to clear the underlying memoization cache of a
  * function. This function is generated with the same visibility as the memoized function.
  *
+ * `#[memoize(UseStore: MY_CACHE)]` makes the function use a store declared by the user instead of
+ * generating one, e.g. to share it between several functions, or with other code. It must be declared
+ * like a generated store: a `std::sync::Mutex` (or `memoize::AsyncMutex`, in a `lazy_static` or
+ * `LazyLock`, for async functions) with `SharedCache`, and a `thread_local!` `RefCell` otherwise,
+ * around a type implementing `memoize::CacheStore`, like a `HashMap` or `lru::LruCache`. It can't be
+ * combined with options configuring the store, like `Capacity` or `CustomHasher`.
+ *
+ * `memoized_insert_<function name>(key, value)` inserts a value for a key (the memoized argument, or
+ * the tuple of the memoized arguments) without calling the function. The function may call it
+ * itself, to cache values of other keys it derives as a by-product (e.g. the neighbors of a page).
+ *
+ * For functions with several memoized arguments, `memoized_remove_prefix_<function name>(&first)`
+ * removes all entries whose first argument equals `first` (e.g. all entries of a user), regardless of
+ * the other arguments, and returns how many were removed.
+ *
+ * `memoized_find_<function name>(|key| ...)` returns the cached (and still fresh) entries whose key,
+ * the tuple of memoized arguments, satisfies a predicate, as `(key, value)` pairs in no particular
+ * order; e.g. `memoized_find_report(|(_, month)| *month == 3)` collects every report cached for March.
+ * Conversely, `memoized_keys_where_<function name>(|value| ...)` returns the keys of the cached entries
+ * whose value satisfies a predicate, e.g. to remove the entries derived from a stale source.
+ *
+ * `#[memoize(IndexBy: |key, value| value.folder: FolderId)]` maintains a reverse index from a
+ * secondary key, computed from each cached entry, to the keys of the entries, and generates
+ * `memoized_remove_by_index_<function name>(&secondary_key)`, which removes the entries with the given
+ * secondary key and returns how many there were; e.g. all documents of a folder, without knowing
+ * their ids. The type of the secondary key must be given after the function, as with `PartitionBy`.
+ *
+ * With the `jemalloc` or `mimalloc` feature, `memoize::set_memory_high_water_mark(bytes)` starts a
+ * background thread comparing the resident memory reported by the allocator to the given mark once
+ * per second. Whenever it is crossed, every `SharedCache` evicts the same share of its entries (the
+ * share of resident memory above the mark, but at least a tenth); LRU caches evict their least
+ * recently used entries. `memoize::shrink_caches(fraction)` does so on demand.
+ *
+ * Instead of tuning the `Capacity` of an LRU cache by hand, `#[memoize(AutoCapacity: 100..=10000)]`
+ * adapts it within the given range: once per window of requests, a cache where many misses evict
+ * another entry (while the hit rate is below 90 %) grows by half, and a cache that evicted nothing
+ * while less than half full shrinks by a quarter. It starts at the minimum, or the given `Capacity`.
+ *
+ * For LRU caches (with `Capacity`, but no other eviction policy), `memoized_lru_front_<function name>()`
+ * and `memoized_lru_back_<function name>()` return the key of the most and the least recently used
+ * entry, respectively, without updating their recency; the back entry is the next to be evicted.
+ * Unless the cache has an `AutoCapacity`, `memoized_resize_<function name>(capacity)` changes its
+ * capacity at runtime (to at least 1), evicting the least recently used entries if it shrinks. Like
+ * other generated functions, it resizes the cache of the calling thread for thread-local caches;
+ * caches of other threads keep the `Capacity` given to `#[memoize]`.
+ *
+ * With `#[memoize(EntryInfo)]`, every entry also tracks when it was last read and how many times,
+ * and `memoized_entry_info_<function name>(args...)` returns a `memoize::EntryInfo` with these, the
+ * time it was inserted, and when it expires, for the entry of the given arguments (if any). It
+ * requires the arguments to be stored in the key as they are, i.e. not ignored or transformed.
+ *
+ * For operations that the generated functions don't cover, `#[memoize(PublicStore)]` gives the store
+ * itself the visibility of the memoized function, as `MEMOIZED_MAPPING_<FUNCTION NAME>`: a
+ * `lazy_static` `std::sync::Mutex` (or `memoize::AsyncMutex` for async functions) around the cache
+ * with `SharedCache`, and a `thread_local!` `RefCell` around it otherwise. The cache maps tuples of
+ * the memoized arguments to the return values, unless options like `TimeToLive` or `StoreWith`
+ * change what is stored. It can't be combined with `PartitionBy`.
+ *
+ * With the `opentelemetry` feature, hits, misses and evictions are also counted in the
+ * `memoize.hits`, `memoize.misses` and `memoize.evictions` counters of the global meter provider,
+ * and the active span gets a `cache.hit` attribute.
+ *
+ * Every memoized function also calls the hooks registered with `memoize::set_hooks()`, an
+ * implementation of the `memoize::Hooks` trait (`on_hit`, `on_miss`, `on_insert`, `on_evict`,
+ * `on_flush` and `on_stale`), which instruments all memoized functions of a process uniformly.
+ *
+ * With `#[memoize(Instrument)]`, the cache sends events (hits, misses, insertions, evictions and
+ * flushes, with a hash of the key) to the subscribers of `memoize::subscribe_events()`.
+ *
+ * `memoized_stats_<function name>()` returns the numbers of cache hits and misses as a
+ * `memoize::Stats`. Threads count their calls in a few slots of the function, and the counts of all
+ * threads (including ones that have exited) are summed up, even if every thread has its own cache.
+ *
+ * `memoized_cache_handle_<function name>()` returns a handle implementing `memoize::CacheControl`,
+ * which flushes the cache, and returns its size and statistics, without naming the function; with
+ * the `serde` feature, it also removes entries by their key serialized with `bincode`. The trait is
+ * object safe, so that the handles of functions with different types can be collected in a
+ * `Vec<Box<dyn memoize::CacheControl>>`, e.g. for an admin endpoint or a periodic report.
+ *
  */
 #[proc_macro_attribute]
 pub fn memoize(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand(proc_macro2::TokenStream::new(), attr.into(), item)
+}
+
+/// Expands `#[memoize]` with the given default and explicit options.
+fn expand(
+    defaults: proc_macro2::TokenStream,
+    attr: proc_macro2::TokenStream,
+    item: TokenStream,
+) -> TokenStream {
     let func = parse_macro_input!(item as ItemFn);
+    match expand_fn(defaults, attr, &func) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Expands `#[memoize]` on `func`, or returns the error of an invalid combination of options.
+fn expand_fn(
+    defaults: proc_macro2::TokenStream,
+    attr: proc_macro2::TokenStream,
+    func: &ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
     let sig = &func.sig;
 
+    let vis = &func.vis;
     let fn_name = &sig.ident.to_string();
     let renamed_name = format!("memoized_original_{}", fn_name);
-    let flush_name = syn::Ident::new(format!("memoized_flush_{}", fn_name).as_str(), sig.span());
-    let size_name = syn::Ident::new(format!("memoized_size_{}", fn_name).as_str(), sig.span());
     let map_name = format!("memoized_mapping_{}", fn_name);
 
     if let Some(syn::FnArg::Receiver(_)) = sig.inputs.first() {
-        return quote::quote! { compile_error!("Cannot memoize methods!"); }.into();
+        return Err(syn::Error::new(sig.span(), "Cannot memoize methods!"));
     }
 
-    // Parse options from macro attributes
-    let options: CacheOptions = syn::parse(attr.clone()).unwrap();
+    // Parse options from macro attributes, and `defaults!`
+    let (mut options, options_source) = parse_options(defaults, attr)?;
+    resolve_store_mode(&mut options, sig)?;
 
     // Extracted from the function signature.
-    let input_params = match check_signature(sig, &options) {
-        Ok(p) => p,
-        Err(e) => return e.to_compile_error().into(),
+    let pyfunction = func.attrs.iter().any(is_pyfunction);
+    let input_params = check_signature(sig, &options, pyfunction)?;
+
+    // `impl Into<T>` arguments are converted first. The original function is then called with the
+    // converted values, which implement `Into<T>` as well.
+    let convert_into: Vec<_> = input_params
+        .iter()
+        .filter(|p| p.is_into)
+        .map(|p| {
+            let (name, ty) = (&p.arg_name, &p.arg_type);
+            quote::quote! { let #name: #ty = ::std::convert::Into::into(#name); }
+        })
+        .collect();
+
+    let Key {
+        types: memoized_input_types,
+        names: memoized_input_names,
+        bind: bind_extracted,
+        bind_scope,
+        slices: slice_names,
+    } = build_key(&options, sig, &input_params)?;
+
+    // Keys made of small `Copy` values (integers, `char`s, arrays and tuples of them) are copied
+    // instead of cloned; `SmallKey` asserts that a key of other `Copy` types is small as well.
+    let small_key = options.small_key || memoized_input_types.iter().all(is_small_copy);
+
+    // For each input, expression to be passe through to the original function.
+    // Cached arguments are cloned, original arguments are forwarded as-is
+    let fn_forwarded_exprs: Vec<_> = input_params
+        .iter()
+        .map(|p| {
+            let ident = p.arg_name.clone();
+            if p.is_memoized && !small_key && !options.serde_keys && !options.debug_keys {
+                quote::quote! { #ident.clone() }
+            } else {
+                quote::quote! { #ident }
+            }
+        })
+        .collect();
+
+    let Values {
+        input_tuple_type,
+        return_type,
+        future_output,
+        per_element,
+        stream_item,
+        coroutine_types,
+    } = value_types(&options, sig, &input_params, &memoized_input_types)?;
+
+    let (intern_func, value_type, encode_value, decode_value) =
+        value_encoding(&mut options, sig, &return_type)?;
+
+    // With `HashOnce`, the memoizing wrapper hashes the key once, and inserts a computed value with
+    // that hash. Other lookups, e.g. by `memoized_get_<fn>()`, go through the map as usual.
+    if options.hash_once
+        && (options.lru_max_entries.is_some()
+            || options.auto_capacity.is_some()
+            || options.custom_hasher.is_some()
+            || options.partition_by.is_some()
+            || options.use_store.is_some()
+            || options.per_element
+            || options.write_back.is_some()
+            || options.collect_stream
+            || options.collect_coroutine
+            || options.validate_with.is_some())
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "HashOnce cannot be combined with Capacity, AutoCapacity, CustomHasher, PartitionBy, UseStore, PerElement, WriteBack, CollectStream, CollectCoroutine or ValidateWith",
+        ));
+    }
+    let stored_type = stored_type(&options, sig, &value_type)?;
+
+    let bounded_store = bounded_store(
+        &mut options,
+        sig,
+        &input_tuple_type,
+        &return_type,
+        &stored_type,
+        &decode_value,
+    )?;
+    // Whether the store is a `HashMap` or an `LruCache`, whose keys can be looked up by a borrowed
+    // form.
+    let borrowed_lookup = options.priority.is_none()
+        && options.eviction.is_none()
+        && options.persist.is_none()
+        && options.partition_by.is_none()
+        && options.use_store.is_none();
+
+    let store_ident: Path = match &options.use_store {
+        Some(store) => store.clone(),
+        None => syn::Ident::new(&map_name.to_uppercase(), sig.span()).into(),
+    };
+    let (cache_type, cache_init, partitions) = cache_type(
+        &options,
+        sig,
+        &options_source,
+        &input_tuple_type,
+        &stored_type,
+        bounded_store,
+    )?;
+    let (_, get_fn, remove_fn) = store::cache_access_methods(&options);
+
+    // The original function is awaited by async memoizers. With the `tokio` or `async` feature,
+    // their shared store is guarded by an async mutex, so waiting for the lock doesn't block a
+    // worker thread.
+    let is_async = sig.asyncness.is_some() && !options.shared_future;
+    let async_store =
+        is_async && options.shared_cache && (cfg!(feature = "tokio") || cfg!(feature = "async"));
+    // With `Eviction: ClockPro`, hits only set a flag of their entry, so that a shared store is
+    // guarded by a `RwLock`, and hits only take its read lock. Entries tracking metadata in `Cell`s
+    // can't be shared by readers, and keep the `Mutex`.
+    let tracks_metadata = options.compact_entries
+        || options.expire_after_access.is_some()
+        || options.entry_info
+        || options.expire_with.is_some()
+        || options.max_age.is_some()
+        || options.validate_every.is_some();
+    let read_locked = options.shared_cache
+        && !async_store
+        && options.use_store.is_none()
+        && options.partition_by.is_none()
+        && options
+            .eviction
+            .as_ref()
+            .is_some_and(|policy| policy == "ClockPro")
+        && !tracks_metadata;
+    let lock_method = if read_locked {
+        quote::quote! { write }
+    } else {
+        quote::quote! { lock }
+    };
+    let (lock_store, async_helper) = if async_store {
+        (
+            quote::quote! { ::memoize::AsyncLock::lock(&*#store_ident).await },
+            quote::quote! { async },
+        )
+    } else {
+        (
+            quote::quote! { #store_ident.#lock_method().unwrap() },
+            quote::quote! {},
+        )
+    };
+    let store = store_static(
+        &options,
+        sig,
+        vis,
+        &store_ident,
+        &cache_type,
+        &cache_init,
+        async_store,
+        read_locked,
+    )?;
+
+    // The type implementing `memoize::memoized::Memoized` for the function, through which the
+    // generic functions of `memoize::memoized` reach its store.
+    let memo_type = syn::Ident::new(format!("memoized_type_{}", fn_name).as_str(), sig.span());
+    // Hits and misses are counted in slots shared by few threads, and summed up by
+    // `memoized_stats_<fn>()`.
+    let stats_name = syn::Ident::new(format!("memoized_stats_{}", fn_name).as_str(), sig.span());
+    let stats = quote::quote! {
+        #vis fn #stats_name() -> ::memoize::Stats {
+            ::memoize::memoized::stats::<#memo_type>()
+        }
+    };
+    let memo = Memo {
+        options: &options,
+        sig,
+        vis,
+        memo_type: &memo_type,
+        store_ident: &store_ident,
+        input_tuple_type: &input_tuple_type,
+        return_type: &return_type,
+        future_output: &future_output,
+        stored_type: &stored_type,
+        decode_value: &decode_value,
+        get_fn: &get_fn,
+        remove_fn: &remove_fn,
+        is_async,
+        async_store,
+        read_locked,
+        lock_store: &lock_store,
+        lock_method: &lock_method,
+        async_helper: &async_helper,
+    };
+    let (auto_capacity, adjust_capacity) = auto_capacity(&memo);
+    let (reverse_index, update_index, remove_by_index_func) = reverse_index(&memo)?;
+    // Rename original function.
+    let mut renamed_fn = func.clone();
+    renamed_fn.sig.ident = syn::Ident::new(&renamed_name, func.sig.span());
+    // With `BatchWith`, the original function is never called by the wrapper.
+    if options.batch_with.is_some() {
+        renamed_fn
+            .attrs
+            .push(syn::parse_quote! { #[allow(dead_code)] });
+    }
+    let memoized_id = &renamed_fn.sig.ident;
+    // Attributes exporting the function, like `#[wasm_bindgen]`, `#[pyfunction]` (with its
+    // `#[pyo3(...)]` options) or `#[export_name]`, are moved to the memoized function which callers
+    // see, and its doc comments are copied there.
+    let (export_attrs, kept_attrs): (Vec<_>, Vec<_>) =
+        renamed_fn.attrs.iter().cloned().partition(is_export_attr);
+    renamed_fn.attrs = kept_attrs;
+    // Argument attributes like `#[pyo3(from_py_with = ...)]` are only understood by `#[pyfunction]`,
+    // so they stay on the arguments of the memoized function.
+    for input in renamed_fn.sig.inputs.iter_mut() {
+        if let syn::FnArg::Typed(arg) = input {
+            arg.attrs.retain(|attr| !is_export_attr(attr));
+        }
+    }
+    // `#[track_caller]` is copied as well, so that the original function sees the caller of the
+    // memoized one.
+    let public_attrs = {
+        let copied_attrs = func
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc") || attr.path().is_ident("track_caller"));
+        quote::quote! { #(#copied_attrs)* #(#export_attrs)* }
+    };
+    let (wrapper_attrs, shared_attrs, const_attrs) = if options.shared_future {
+        (quote::quote! {}, public_attrs, quote::quote! {})
+    } else if options.keep_const {
+        (quote::quote! {}, quote::quote! {}, public_attrs)
+    } else {
+        (public_attrs, quote::quote! {}, quote::quote! {})
+    };
+
+    let call = build_call(
+        &memo,
+        &memoized_input_names,
+        &slice_names,
+        small_key,
+        borrowed_lookup,
+        &encode_value,
+    )?;
+    let Call {
+        syntax_names_tuple,
+        names_refs_tuple,
+        owned_names_tuple,
+        record_miss,
+        read_memo,
+        stored_value,
+        ..
+    } = &call;
+    let forwarding_tuple = quote::quote! { (#(#fn_forwarded_exprs),*) };
+    // With `CoarseClock`, entries are checked against the time of `memoize::coarse_now()`, which is
+    // read once per lookup.
+    if options.coarse_clock && !expires(&options) {
+        return Err(syn::Error::new(
+            sig.span(),
+            "CoarseClock requires TimeToLive, ExpireAfterAccess, ExpireWith or MaxAge",
+        ));
+    }
+    let (admit_func, admit_time) = admit_value(&memo)?;
+    let admit_names = admit_key(&memo, &admit_time, quote::quote! { &#names_refs_tuple });
+    let insert = if options.hash_once {
+        memo.insert_hashed_entry(
+            owned_names_tuple,
+            stored_value,
+            Some(&quote::quote! { ATTR_MEMOIZE_HASH__ }),
+        )
+    } else {
+        memo.insert_entry(owned_names_tuple, stored_value)
+    };
+    let (read_negative, memoize) = negative_filter(&memo, &call, &admit_names, &insert)?;
+    let (read_secondary, write_secondary) = secondary(&memo, &call, &insert)?;
+    let revalidate = revalidate(&memo, &call, &insert)?;
+    let (breaker, check_breaker, record_breaker) = circuit_breaker(&memo, &call)?;
+    let serve_stale = serve_stale(&memo, &call)?;
+    // `Err`s are not cached with `CircuitBreaker` or `StaleOnError`.
+    let handle_errors = if options.circuit_breaker.is_some() || options.stale_on_error.is_some() {
+        quote::quote! {
+            let ATTR_MEMOIZE_FAILED__ = ATTR_MEMOIZE_RETURN__.is_err();
+            #record_breaker
+            #serve_stale
+            if ATTR_MEMOIZE_FAILED__ {
+                return ATTR_MEMOIZE_RETURN__;
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
+    if options.max_refresh_rate.is_some() && !expires(&options) {
+        return Err(syn::Error::new(
+            sig.span(),
+            "MaxRefreshRate requires TimeToLive, ExpireAfterAccess, ExpireWith or MaxAge",
+        ));
+    }
+    let validated_hit = validated_hit(&memo, &call)?;
+    let discard_func = discard_func(&memo);
+    let (batcher, compute_return) = batcher(
+        &memo,
+        &call,
+        quote::quote! { #memoized_id #forwarding_tuple },
+        per_element.is_some(),
+    )?;
+
+    // With `WriteBack`, new entries are first collected in a thread-local buffer, which is merged
+    // into the shared store under a single lock acquisition.
+    let write_back = if let Some(threshold) = options.write_back {
+        if !options.shared_cache {
+            return Err(syn::Error::new(
+                sig.span(),
+                "WriteBack requires SharedCache",
+            ));
+        }
+        if is_async {
+            return Err(syn::Error::new(
+                sig.span(),
+                "WriteBack cannot be used with async functions",
+            ));
+        }
+        let every = match &options.write_back_every {
+            None => quote::quote! { None },
+            Some(every) => quote::quote! { Some(#every) },
+        };
+        Some((threshold, every))
+    } else {
+        None
+    };
+
+    let (precomputed_table, read_precomputed) = precomputed(
+        &memo,
+        &call,
+        &input_params,
+        &memoized_input_names,
+        memoized_id,
+    )?;
+
+    let (warmed_flag, read_warm, reset_warm) = warm_list(&memo, stream_item.is_some())?;
+
+    // The miss path of synchronous functions is moved into a closure run by the `#[cold]`
+    // `memoize::cold::miss()`, so that only the lookup is inlined into callers. `#[track_caller]`
+    // doesn't pass through closures, so its functions keep a single path.
+    let split_miss = sig.asyncness.is_none()
+        && !func
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("track_caller"));
+    // The wrapper itself is then small enough to inline, unless it is exported.
+    let inline_attr = if split_miss
+        && export_attrs.is_empty()
+        && per_element.is_none()
+        && stream_item.is_none()
+        && coroutine_types.is_none()
+        && write_back.is_none()
+    {
+        quote::quote! { #[inline] }
+    } else {
+        quote::quote! {}
+    };
+    let cold_miss = |miss: proc_macro2::TokenStream| {
+        if split_miss {
+            quote::quote! { ::memoize::cold::miss(move || { #miss }) }
+        } else {
+            miss
+        }
+    };
+    // Construct memoizer function, which calls the original function.
+    let memoizer = if let Some((_, _, is_slice)) = &per_element {
+        if write_back.is_some() || !options.precompute.is_empty() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "PerElement cannot be combined with WriteBack or Precompute",
+            ));
+        }
+        let elements = &memoized_input_names[0];
+        per_element_memoizer(
+            &memo,
+            &call,
+            &input_params,
+            memoized_id,
+            elements,
+            *is_slice,
+            &admit_time,
+        )
+    } else if stream_item.is_some() {
+        stream_memoizer(
+            &memo,
+            &call,
+            &memoize,
+            quote::quote! { #memoized_id #forwarding_tuple },
+        )
+    } else if coroutine_types.is_some() {
+        coroutine_memoizer(
+            &memo,
+            &call,
+            &memoize,
+            quote::quote! { #memoized_id #forwarding_tuple },
+        )
+    } else if let Some((threshold, every)) = &write_back {
+        write_back_memoizer(
+            &memo,
+            &call,
+            &compute_return,
+            &admit_names,
+            &insert,
+            *threshold,
+            every,
+        )
+    } else {
+        let read_hit = read_hit(&memo, &call, validated_hit);
+        // The store is only locked after the value has been computed, and released before
+        // returning it.
+        let store_value = memo.with_store(memoize.clone());
+        let miss = cold_miss(quote::quote! {
+            #read_negative
+            #revalidate
+            #read_secondary
+            #check_breaker
+            #record_miss
+            #compute_return
+            #handle_errors
+            #write_secondary
+            #store_value;
+            ATTR_MEMOIZE_RETURN__
+        });
+        quote::quote! {
+            #read_hit
+            #miss
+        }
+    };
+
+    // Lint attributes of the function apply to the wrapper as well, e.g. to allow the warning
+    // emitted by `DebugKeys`.
+    let lint_attrs: Vec<_> = func
+        .attrs
+        .iter()
+        .filter(|attr| {
+            ["allow", "warn", "deny"]
+                .iter()
+                .any(|lint| attr.path().is_ident(lint))
+        })
+        .collect();
+
+    // The memoizing wrapper accesses a runtime cache, so it can never be `const`. With `KeepConst`, a
+    // `const fn` keeps its name for a `const` function that evaluates the original function in const
+    // contexts, and calls the (renamed) memoizing wrapper at runtime.
+    let mut wrapper_sig = sig.clone();
+    wrapper_sig.constness = None;
+    let mut wrapper_vis = vis.clone();
+    if options.keep_const && sig.constness.is_none() {
+        return Err(syn::Error::new(
+            sig.span(),
+            "KeepConst requires a `const fn`",
+        ));
+    }
+    let const_wrapper = if options.keep_const {
+        wrapper_sig.ident = syn::Ident::new(&format!("memoized_runtime_{}", fn_name), sig.span());
+        wrapper_vis = syn::Visibility::Inherited;
+        let runtime_id = &wrapper_sig.ident;
+        let all_names = input_params.iter().map(|p| &p.arg_name);
+        quote::quote! {
+            #(#lint_attrs)*
+            #const_attrs
+            #vis #sig {
+                ::core::intrinsics::const_eval_select((#(#all_names,)*), #memoized_id, #runtime_id)
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
+    // With `SharedFuture`, the memoizing function returns the shared future, which the async wrapper
+    // awaits.
+    let shared_name = memo.ident("shared");
+    let shared_wrapper = if options.shared_future {
+        let all_names = input_params.iter().map(|p| &p.arg_name);
+        let mut shared_sig = wrapper_sig.clone();
+        shared_sig.asyncness = None;
+        shared_sig.ident = shared_name.clone();
+        shared_sig.output = syn::parse_quote! { -> #return_type };
+        // The future is only kept in the cache if the caller that created it awaits it to the end.
+        let body = quote::quote! {
+            let (ATTR_MEMOIZE_FUTURE__, ATTR_MEMOIZE_CREATED__) =
+                ::memoize::shared_future::track_created(move || #shared_name(#(#all_names),*));
+            let ATTR_MEMOIZE_RETURN__ = ATTR_MEMOIZE_FUTURE__.await;
+            if let Some(ATTR_MEMOIZE_CREATED__) = ATTR_MEMOIZE_CREATED__ {
+                ATTR_MEMOIZE_CREATED__.complete();
+            }
+            ATTR_MEMOIZE_RETURN__
+        };
+        wrapper_sig = shared_sig;
+        quote::quote! {
+            #shared_attrs
+            #vis #sig {
+                #body
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
+
+    let write_back_buffer = if write_back.is_some() {
+        write_back_buffer(&memo)
+    } else {
+        quote::quote! {}
+    };
+
+    let flusher = flush_helpers(&memo);
+
+    // Every cache has a handle implementing `memoize::CacheControl`.
+    let handle_name = syn::Ident::new(
+        format!("memoized_cache_handle_{}", fn_name).as_str(),
+        sig.span(),
+    );
+    let handle_func = quote::quote! {
+        #vis fn #handle_name() -> impl ::memoize::CacheControl + Copy {
+            ::memoize::memoized::CacheHandle::<#memo_type>::new()
+        }
+    };
+
+    let (scheduled_flush, register_scheduled_flush, check_flush_generation) =
+        scheduled_flusher(&memo);
+    let (subscribed_funcs, register_subscriber) = subscribed_invalidation(&memo)?;
+
+    let (register_func, register) = registration(
+        &memo,
+        &register_scheduled_flush,
+        &register_subscriber,
+        &check_flush_generation,
+    );
+
+    let (shrink_func, register_shrink) = memory_shrinker(&memo);
+    let (flush_timer, check_flush_timer) = periodic_flush(&memo);
+
+    // Bulk warming takes tuples of all arguments, which must thus be part of the key (and not
+    // borrowed slices).
+    let borrows_slice = per_element
+        .as_ref()
+        .is_some_and(|(_, _, is_slice)| *is_slice)
+        || !slice_names.is_empty();
+    let warm_func =
+        if input_params.iter().all(|p| p.is_memoized) && stream_item.is_none() && !borrows_slice {
+            bulk_warm(&memo, &input_params)
+        } else {
+            quote::quote! {}
+        };
+
+    // Buffered entries are published first, so that they are found by bulk lookups.
+    let publish_buffer = if write_back.is_some() {
+        let write_back_name = memo.ident("write_back");
+        quote::quote! { #write_back_name(); }
+    } else {
+        quote::quote! {}
+    };
+    // Keys which are used as they are can be looked up by the generic functions.
+    let plain_keys = input_params.iter().all(|p| p.is_memoized)
+        && per_element.is_none()
+        && stream_item.is_none()
+        && coroutine_types.is_none()
+        && options.scope_by.is_none()
+        && options.normalize_key.is_none()
+        && !options.nfc
+        && !options.serde_keys
+        && !options.debug_keys
+        && slice_names.is_empty();
+    let miss = Miss {
+        names: syntax_names_tuple,
+        names_refs: names_refs_tuple,
+        compute_return: &compute_return,
+        admit: &admit_names,
+        stored_value,
+        register: &register,
+    };
+    let get_many = bulk_lookups(&memo, &miss, read_memo, &publish_buffer, plain_keys);
+
+    let load_seed = if options.seed {
+        if options.shared_future {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Seed cannot be combined with SharedFuture",
+            ));
+        }
+        seed_loader(&memo, &register)
+    } else {
+        quote::quote! {}
+    };
+    let insert_func = if stores_returned_values(&options) {
+        insert_helper(&memo, &register)
+    } else {
+        quote::quote! {}
+    };
+
+    let bytes_func = if options.serialize_values {
+        bytes_helper(&memo)
+    } else {
+        quote::quote! {}
+    };
+
+    let size_func = size_helper(&memo);
+
+    // Keys of several components can be removed by their first component.
+    let remove_prefix_func = if memoized_input_types.len() >= 2 && options.use_store.is_none() {
+        remove_prefix_helper(&memo, &memoized_input_types[0])
+    } else {
+        quote::quote! {}
+    };
+
+    let query_funcs = if stores_returned_values(&options) && options.use_store.is_none() {
+        query_helpers(&memo)
+    } else {
+        quote::quote! {}
+    };
+
+    let export_funcs = if options.export {
+        if !stores_returned_values(&options) || options.use_store.is_some() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Export cannot be combined with PerElement, CollectStream, SharedFuture or UseStore",
+            ));
+        }
+        export_helpers(&memo, &register)
+    } else {
+        quote::quote! {}
+    };
+
+    let lru_funcs = if cfg!(feature = "full")
+        && options.lru_max_entries.is_some()
+        && options.priority.is_none()
+        && options.eviction.is_none()
+        && options.partition_by.is_none()
+    {
+        lru_helpers(&memo)
+    } else {
+        quote::quote! {}
+    };
+
+    // With `EntryInfo`, the metadata of an entry can be looked up by the arguments.
+    let entry_info_func = if options.entry_info {
+        if !memoized_input_names
+            .iter()
+            .eq(input_params.iter().map(|p| &p.arg_name))
+            || per_element.is_some()
+            || stream_item.is_some()
+            || options.use_store.is_some()
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "EntryInfo requires all arguments to be memoized as they are, and cannot be combined with PerElement, CollectStream or UseStore",
+            ));
+        }
+        entry_info_helper(&memo, &call, &input_params)
+    } else {
+        quote::quote! {}
+    };
+
+    // The glue between the function and the generic functions of `memoize::memoized`. Hooks are only
+    // implemented by functions with options that need them.
+    let hooks = memo_hooks(&memo, &update_index, &adjust_capacity, &reset_warm);
+    let memo_impl = memo_impl(&memo, &cache_type, stored_value, hooks);
+
+    // An explicit `SmallKey` is checked to be `Copy`, for a clearer error than a moved argument.
+    let small_key_check = if options.small_key {
+        quote::quote! {
+            fn memoize_small_key_is_copy<K: Copy>() {}
+            memoize_small_key_is_copy::<#input_tuple_type>();
+        }
+    } else {
+        quote::quote! {}
+    };
+
+    Ok(quote::quote! {
+        #renamed_fn
+        #memo_impl
+        #intern_func
+        #admit_func
+        #flush_timer
+        #auto_capacity
+        #shrink_func
+        #scheduled_flush
+        #subscribed_funcs
+        #register_func
+        #flusher
+        #handle_func
+        #size_func
+        #remove_prefix_func
+        #remove_by_index_func
+        #query_funcs
+        #entry_info_func
+        #lru_funcs
+        #bytes_func
+        #warm_func
+        #get_many
+        #insert_func
+        #load_seed
+        #export_funcs
+        #partitions
+        #store
+        #stats
+        #write_back_buffer
+        #precomputed_table
+        #batcher
+        #breaker
+        #reverse_index
+        #warmed_flag
+
+        #(#lint_attrs)*
+        #wrapper_attrs
+        #inline_attr
+        #[allow(unused_variables, unused_mut)]
+        #wrapper_vis #wrapper_sig {
+            #(#convert_into)*
+            #bind_scope
+            #(#bind_extracted)*
+            #small_key_check
+            #check_flush_timer
+            #register
+            #register_shrink
+            #read_warm
+            #read_precomputed
+            #memoizer
+        }
+
+        #shared_wrapper
+        #const_wrapper
+        #discard_func
+    })
+}
+
+/// Checks how the store is shared between threads (`SharedCache`, `ThreadLocal`, `TaskLocal`,
+/// `Scoped` and `RayonShared`), applying the `shared` and `thread-local` features.
+fn resolve_store_mode(options: &mut CacheOptions, sig: &syn::Signature) -> syn::Result<()> {
+    if options.shared_cache && options.thread_local {
+        return Err(syn::Error::new(
+            sig.span(),
+            "SharedCache cannot be combined with ThreadLocal",
+        ));
+    }
+    if (options.task_local || options.scoped)
+        && (options.task_local && options.scoped
+            || options.shared_cache
+            || options.thread_local
+            || options.rayon_shared
+            || options.public_store
+            || options.use_store.is_some()
+            || options.persist.is_some())
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "TaskLocal and Scoped cannot be combined with each other, or with SharedCache, ThreadLocal, RayonShared, PublicStore, UseStore or Persist",
+        ));
+    }
+    // The `shared` feature makes caches shared unless they are explicitly `ThreadLocal` (or
+    // `TaskLocal` or `Scoped`, or use a store of their own), while the `thread-local` feature keeps
+    // caches thread-local unless they are explicitly `SharedCache`.
+    if cfg!(feature = "shared")
+        && !options.thread_local
+        && !options.task_local
+        && !options.scoped
+        && options.use_store.is_none()
+    {
+        options.shared_cache = true;
+    }
+    // With `RayonShared`, rayon worker threads buffer their entries (which also serves as their own
+    // cache) and publish them to the shared cache every 64 entries or 100 ms, unless configured
+    // otherwise; other threads use the shared cache directly.
+    if options.rayon_shared && (options.shared_cache || !cfg!(feature = "thread-local")) {
+        if options.thread_local {
+            return Err(syn::Error::new(
+                sig.span(),
+                "RayonShared cannot be combined with ThreadLocal",
+            ));
+        }
+        options.shared_cache = true;
+        options.write_back.get_or_insert(64);
+        options
+            .write_back_every
+            .get_or_insert_with(|| syn::parse_quote! { std::time::Duration::from_millis(100) });
+    }
+
+    Ok(())
+}
+
+/// The key of a memoized function: the memoized arguments, or the values taking their place.
+struct Key {
+    types: Vec<syn::Type>,
+    /// The names the components of the key are bound to in the memoizing wrapper.
+    names: Vec<syn::Ident>,
+    /// Binds the components which aren't arguments, e.g. with `Extract`.
+    bind: Vec<proc_macro2::TokenStream>,
+    /// With `ScopeBy`, binds the scope, which is evaluated first.
+    bind_scope: proc_macro2::TokenStream,
+    /// Slice arguments, which are stored as a `Vec` (unless handled by `PerElement`).
+    slices: Vec<syn::Ident>,
+}
+
+/// Builds the key from the arguments, and the options transforming it.
+fn build_key(
+    options: &CacheOptions,
+    sig: &syn::Signature,
+    input_params: &[FnArgument],
+) -> syn::Result<Key> {
+    // Input types and names that are actually stored in the cache.
+    // With `Extract`, a value extracted from an otherwise ignored argument takes its place in the key.
+    let mut memoized_input_types: Vec<Box<syn::Type>> = vec![];
+    let mut memoized_input_names: Vec<syn::Ident> = vec![];
+    let mut bind_extracted = vec![];
+    // Slice arguments, which are stored as a `Vec` (unless handled by `PerElement`).
+    let mut slice_names: Vec<syn::Ident> = vec![];
+    for p in input_params {
+        if p.is_memoized {
+            match slice_element(&p.arg_type).filter(|_| !options.per_element) {
+                Some(element) => {
+                    memoized_input_types.push(Box::new(syn::parse_quote! { Vec<#element> }));
+                    slice_names.push(p.arg_name.clone());
+                }
+                None => memoized_input_types.push(p.arg_type.clone()),
+            }
+            memoized_input_names.push(p.arg_name.clone());
+        } else if let Some((_, extract, extract_type)) = options
+            .extract
+            .iter()
+            .find(|(argument, _, _)| *argument == p.arg_name)
+        {
+            let extracted_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_EXTRACTED_{}__", p.arg_name),
+                proc_macro2::Span::call_site(),
+            );
+            bind_extracted.push(quote::quote! {
+                let #extracted_name: #extract_type = ::std::clone::Clone::clone(&(#extract));
+            });
+            memoized_input_types.push(Box::new(extract_type.clone()));
+            memoized_input_names.push(extracted_name);
+        } else if options.canonical.contains(&p.arg_name) {
+            let collection = match &*p.arg_type {
+                syn::Type::Reference(reference) => (*reference.elem).clone(),
+                ty => ty.clone(),
+            };
+            let canonical_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_CANONICAL_{}__", p.arg_name),
+                proc_macro2::Span::call_site(),
+            );
+            let arg_name = &p.arg_name;
+            let canonical_type: syn::Type =
+                syn::parse_quote! { <#collection as ::memoize::Canonicalize>::Canonical };
+            bind_extracted.push(quote::quote! {
+                let #canonical_name: #canonical_type = <#collection as ::memoize::Canonicalize>::canonicalize(
+                    ::std::borrow::Borrow::<#collection>::borrow(&#arg_name),
+                );
+            });
+            memoized_input_types.push(Box::new(canonical_type));
+            memoized_input_names.push(canonical_name);
+        } else if let Some((_, quantize, bucket_type)) = options
+            .quantize
+            .iter()
+            .find(|(argument, _, _)| *argument == p.arg_name)
+        {
+            let bucket_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_QUANTIZED_{}__", p.arg_name),
+                proc_macro2::Span::call_site(),
+            );
+            bind_extracted.push(quote::quote! {
+                let #bucket_name: #bucket_type = #quantize;
+            });
+            memoized_input_types.push(Box::new(bucket_type.clone()));
+            memoized_input_names.push(bucket_name);
+        } else if let Some((_, steps, key_type)) = options
+            .key_pipeline
+            .iter()
+            .find(|(argument, _, _)| *argument == p.arg_name)
+        {
+            let key_type = match (key_type, &*p.arg_type) {
+                (Some(key_type), _) => key_type.clone(),
+                (None, syn::Type::Reference(_)) => {
+                    return Err(syn::Error::new(
+                        p.arg_name.span(),
+                        "KeyPipeline needs the type of the key for a reference argument, e.g. `KeyPipeline: arg => [...]: String`",
+                    ));
+                }
+                (None, arg_type) => arg_type.clone(),
+            };
+            let key_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_PIPELINE_{}__", p.arg_name),
+                proc_macro2::Span::call_site(),
+            );
+            let arg_name = &p.arg_name;
+            // Each step is passed through a function, so that the parameter types of closures can
+            // be inferred.
+            bind_extracted.push(quote::quote! {
+                let #key_name: #key_type = {
+                    fn memoize_key_step<A, B>(value: A, step: impl FnOnce(A) -> B) -> B {
+                        step(value)
+                    }
+                    let ATTR_MEMOIZE_VALUE__ = ::std::clone::Clone::clone(&#arg_name);
+                    #(let ATTR_MEMOIZE_VALUE__ = memoize_key_step(ATTR_MEMOIZE_VALUE__, #steps);)*
+                    ATTR_MEMOIZE_VALUE__
+                };
+            });
+            memoized_input_types.push(Box::new(key_type));
+            memoized_input_names.push(key_name);
+        } else if options.key_by_ptr.contains(&p.arg_name) {
+            let arc = match &*p.arg_type {
+                syn::Type::Reference(reference) => (*reference.elem).clone(),
+                ty => ty.clone(),
+            };
+            let ptr_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_PTR_{}__", p.arg_name),
+                proc_macro2::Span::call_site(),
+            );
+            let arg_name = &p.arg_name;
+            let ptr_type: syn::Type =
+                syn::parse_quote! { ::memoize::PtrKey<<#arc as ::std::ops::Deref>::Target> };
+            bind_extracted.push(quote::quote! {
+                let #ptr_name: #ptr_type =
+                    ::memoize::PtrKey::new(::std::borrow::Borrow::<#arc>::borrow(&#arg_name));
+            });
+            memoized_input_types.push(Box::new(ptr_type));
+            memoized_input_names.push(ptr_name);
+        }
+    }
+    if let Some((argument, _, _)) = options
+        .extract
+        .iter()
+        .find(|(argument, _, _)| !input_params.iter().any(|p| p.arg_name == *argument))
+    {
+        return Err(syn::Error::new(
+            argument.span(),
+            "Extract names an unknown argument",
+        ));
+    }
+    if let Some(argument) = options
+        .canonical
+        .iter()
+        .find(|argument| !input_params.iter().any(|p| p.arg_name == **argument))
+    {
+        return Err(syn::Error::new(
+            argument.span(),
+            "Canonical names an unknown argument",
+        ));
+    }
+    if let Some((argument, _, _)) = options
+        .quantize
+        .iter()
+        .find(|(argument, _, _)| !input_params.iter().any(|p| p.arg_name == *argument))
+    {
+        return Err(syn::Error::new(
+            argument.span(),
+            "Quantize names an unknown argument",
+        ));
+    }
+    if let Some((argument, _, _)) = options
+        .key_pipeline
+        .iter()
+        .find(|(argument, _, _)| !input_params.iter().any(|p| p.arg_name == *argument))
+    {
+        return Err(syn::Error::new(
+            argument.span(),
+            "KeyPipeline names an unknown argument",
+        ));
+    }
+    if let Some(argument) = options
+        .key_by_ptr
+        .iter()
+        .find(|argument| !input_params.iter().any(|p| p.arg_name == **argument))
+    {
+        return Err(syn::Error::new(
+            argument.span(),
+            "KeyByPtr names an unknown argument",
+        ));
+    }
+    if (!options.extract.is_empty()
+        || !options.canonical.is_empty()
+        || !options.key_by_ptr.is_empty()
+        || !options.quantize.is_empty()
+        || !options.key_pipeline.is_empty())
+        && (options.per_element || !options.precompute.is_empty())
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "Extract, Canonical, KeyByPtr, Quantize and KeyPipeline cannot be combined with PerElement or Precompute",
+        ));
+    }
+
+    if !slice_names.is_empty()
+        && (!options.precompute.is_empty() || options.normalize_key.is_some())
+    {
+        return Err(syn::Error::new(
+            slice_names[0].span(),
+            "Slice arguments cannot be combined with Precompute or NormalizeKey",
+        ));
+    }
+
+    // With `Nfc`, `String` arguments are stored in Unicode Normalization Form C, ahead of any other
+    // normalization.
+    if options.nfc {
+        if options.per_element || !options.precompute.is_empty() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Nfc cannot be combined with PerElement or Precompute",
+            ));
+        }
+        for (ty, name) in memoized_input_types
+            .iter()
+            .zip(memoized_input_names.iter_mut())
+        {
+            let is_string = match &**ty {
+                syn::Type::Path(path) => path.qself.is_none() && path.path.is_ident("String"),
+                _ => false,
+            };
+            if !is_string {
+                continue;
+            }
+            let nfc_name = syn::Ident::new(
+                &format!("ATTR_MEMOIZE_NFC_{}__", name),
+                proc_macro2::Span::call_site(),
+            );
+            bind_extracted.push(quote::quote! {
+                let #nfc_name: String = ::memoize::nfc(::std::clone::Clone::clone(&#name));
+            });
+            *name = nfc_name;
+        }
+    }
+
+    // With `NormalizeKey`, the key is passed through the given function before every lookup and
+    // insertion, so that equivalent arguments share an entry.
+    if let Some(normalize) = &options.normalize_key {
+        if options.per_element || !options.precompute.is_empty() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "NormalizeKey cannot be combined with PerElement or Precompute",
+            ));
+        }
+        let normalized_name = syn::Ident::new(
+            "ATTR_MEMOIZE_NORMALIZED_KEY__",
+            proc_macro2::Span::call_site(),
+        );
+        let key_type: syn::Type = syn::parse_quote! { (#(#memoized_input_types),*) };
+        bind_extracted.push(quote::quote! {
+            let #normalized_name: #key_type =
+                #normalize((#(::std::clone::Clone::clone(&#memoized_input_names)),*));
+        });
+        memoized_input_types = vec![Box::new(key_type)];
+        memoized_input_names = vec![normalized_name];
+    }
+
+    // With `SerdeKeys`, the key is the serialization of the arguments, which then only need to
+    // implement `serde::Serialize`.
+    if options.serde_keys {
+        if options.per_element || !options.precompute.is_empty() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "SerdeKeys cannot be combined with PerElement or Precompute",
+            ));
+        }
+        let serialized_name = syn::Ident::new(
+            "ATTR_MEMOIZE_SERIALIZED_KEY__",
+            proc_macro2::Span::call_site(),
+        );
+        bind_extracted.push(quote::quote! {
+            let #serialized_name: Vec<u8> =
+                ::memoize::serialized::to_bytes(&(#(&#memoized_input_names),*));
+        });
+        memoized_input_types = vec![Box::new(syn::parse_quote! { Vec<u8> })];
+        memoized_input_names = vec![serialized_name];
+        slice_names.clear();
+    }
+
+    // With `DebugKeys`, the key is the `Debug` representation of the arguments. As this is only
+    // correct if it is stable and distinguishes all unequal arguments, every use is flagged by a
+    // deprecation warning.
+    if options.debug_keys {
+        if options.serde_keys || options.per_element || !options.precompute.is_empty() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "DebugKeys cannot be combined with SerdeKeys, PerElement or Precompute",
+            ));
+        }
+        let formatted_name =
+            syn::Ident::new("ATTR_MEMOIZE_DEBUG_KEY__", proc_macro2::Span::call_site());
+        bind_extracted.push(quote::quote! {
+            {
+                #[deprecated(
+                    note = "memoize: DebugKeys keys the cache on the `Debug` output of the arguments, which must be stable and unique"
+                )]
+                fn debug_keys() {}
+                debug_keys();
+            }
+            let #formatted_name: String = format!("{:?}", (#(&#memoized_input_names),*));
+        });
+        memoized_input_types = vec![Box::new(syn::parse_quote! { String })];
+        memoized_input_names = vec![formatted_name];
+        slice_names.clear();
+    }
+
+    // With `ScopeBy`, the scope is evaluated at the start of every call, and stored as the first
+    // component of the key, as if it was an additional argument.
+    let bind_scope = match &options.scope_by {
+        None => quote::quote! {},
+        Some((scope, scope_type)) => {
+            if options.per_element || !options.precompute.is_empty() {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "ScopeBy cannot be combined with PerElement or Precompute",
+                ));
+            }
+            let scope_name =
+                syn::Ident::new("ATTR_MEMOIZE_SCOPE__", proc_macro2::Span::call_site());
+            memoized_input_types.insert(0, Box::new(scope_type.clone()));
+            memoized_input_names.insert(0, scope_name.clone());
+            quote::quote! { let #scope_name: #scope_type = #scope; }
+        }
+    };
+
+    Ok(Key {
+        types: memoized_input_types.into_iter().map(|ty| *ty).collect(),
+        names: memoized_input_names,
+        bind: bind_extracted,
+        bind_scope,
+        slices: slice_names,
+    })
+}
+
+/// The types of the key and of the values of a memoized function, as seen by the cache.
+struct Values {
+    input_tuple_type: proc_macro2::TokenStream,
+    /// The type of the cached values.
+    return_type: proc_macro2::TokenStream,
+    /// With `SharedFuture`, the output of the future held by the cache.
+    future_output: proc_macro2::TokenStream,
+    /// With `PerElement`, the types of an element and of its value, and whether the argument is a
+    /// slice.
+    per_element: Option<(syn::Type, syn::Type, bool)>,
+    /// With `CollectStream`, the type of the items.
+    stream_item: Option<syn::Type>,
+    /// With `CollectCoroutine`, the types of the yielded and returned values.
+    coroutine_types: Option<(syn::Type, syn::Type)>,
+}
+
+/// Derives the types of the key and the values from the signature, and the options changing what
+/// is cached.
+fn value_types(
+    options: &CacheOptions,
+    sig: &syn::Signature,
+    input_params: &[FnArgument],
+    memoized_input_types: &[syn::Type],
+) -> syn::Result<Values> {
+    let input_tuple_type = quote::quote! { (#(#memoized_input_types),*) };
+    let return_type = match &sig.output {
+        syn::ReturnType::Default => quote::quote! { () },
+        syn::ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+
+    // With `PerElement`, the cache maps single elements of the (only) memoized argument, a slice or
+    // `Vec`, to single elements of the returned `Vec`.
+    let per_element = if options.per_element {
+        Some(check_per_element(sig, input_params)?)
+    } else {
+        None
+    };
+    let (input_tuple_type, return_type) = match &per_element {
+        Some((key_type, value_type, _)) => {
+            (key_type.to_token_stream(), value_type.to_token_stream())
+        }
+        None => (input_tuple_type, return_type),
+    };
+
+    // With `SharedFuture`, the cache holds the (shared) future of the original function instead of
+    // its result, so that concurrent callers await the same computation.
+    let future_output = return_type.clone();
+    let return_type = if options.shared_future {
+        if sig.asyncness.is_none() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "SharedFuture requires an async function",
+            ));
+        }
+        if per_element.is_some() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "SharedFuture cannot be combined with PerElement",
+            ));
+        }
+        quote::quote! {
+            ::memoize::futures::future::Shared<
+                ::memoize::futures::future::BoxFuture<'static, #return_type>
+            >
+        }
+    } else {
+        return_type
+    };
+
+    // With `CollectStream`, the function returns a stream, and the cache holds its collected items.
+    let stream_item = if options.collect_stream {
+        if sig.asyncness.is_some()
+            || per_element.is_some()
+            || options.shared_future
+            || options.write_back.is_some()
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "CollectStream cannot be combined with async functions, PerElement, SharedFuture or WriteBack",
+            ));
+        }
+        match stream_item(&sig.output) {
+            Some(item) => Some(item),
+            None => {
+                return Err(syn::Error::new(
+                    sig.output.span(),
+                    "CollectStream requires a function returning `impl Stream<Item = T>`",
+                ))
+            }
+        }
+    } else {
+        None
+    };
+    // With `CollectCoroutine`, the function returns a coroutine, and the cache holds the values it
+    // yielded along with the value it returned.
+    let coroutine_types = if options.collect_coroutine {
+        if sig.asyncness.is_some()
+            || per_element.is_some()
+            || options.shared_future
+            || options.write_back.is_some()
+            || options.collect_stream
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "CollectCoroutine cannot be combined with async functions, PerElement, SharedFuture, WriteBack or CollectStream",
+            ));
+        }
+        match coroutine_types(&sig.output) {
+            Some(types) => Some(types),
+            None => {
+                return Err(syn::Error::new(
+                    sig.output.span(),
+                    "CollectCoroutine requires a function returning `impl Coroutine<Yield = Y, Return = R>`",
+                ))
+            }
+        }
+    } else {
+        None
+    };
+    let return_type = match (&stream_item, &coroutine_types) {
+        (Some(item), _) => quote::quote! { Vec<#item> },
+        (None, Some((yield_type, complete_type))) => {
+            quote::quote! { (Vec<#yield_type>, #complete_type) }
+        }
+        (None, None) => return_type,
+    };
+
+    Ok(Values {
+        input_tuple_type,
+        return_type,
+        future_output,
+        per_element,
+        stream_item,
+        coroutine_types,
+    })
+}
+
+/// Resolves the options changing how values are stored (`PersistXdg`, `SerializeValues` and
+/// `DedupValues`). Returns the interner of `DedupValues`, the stored type of the values, and the
+/// expressions encoding and decoding `ATTR_MEMOIZE_RETURN__`.
+fn value_encoding(
+    options: &mut CacheOptions,
+    sig: &syn::Signature,
+    return_type: &proc_macro2::TokenStream,
+) -> syn::Result<(
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)> {
+    let fn_name = sig.ident.to_string();
+    // `PersistXdg` is a special case of `Persist`, with one file per function in the platform's cache
+    // directory.
+    if let Some(tool) = &options.persist_xdg {
+        if options.persist.is_some() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "PersistXdg cannot be combined with Persist",
+            ));
+        }
+        let function = fn_name.as_str();
+        options.persist = Some(syn::parse_quote! {
+            ::memoize::cache_file(#tool, concat!(module_path!(), "::", #function))
+        });
+    }
+
+    // With `StoreWith`/`LoadWith`, the cache holds a different representation of the return value.
+    // `SerializeValues` is a special case of this.
+    if options.serialize_values {
+        if options.store_with.is_some() || options.load_with.is_some() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "SerializeValues cannot be combined with StoreWith or LoadWith",
+            ));
+        }
+        options.store_with = Some((
+            syn::parse_quote! { ::memoize::serialized::to_bytes },
+            syn::parse_quote! { Vec<u8> },
+        ));
+        options.load_with = Some(syn::parse_quote! { ::memoize::serialized::from_bytes });
+    }
+    // `DedupValues` stores values as `Arc`s, interned so that equal values share one allocation.
+    let intern_name = syn::Ident::new(&format!("memoized_intern_{}", fn_name), sig.span());
+    let intern_func = if options.dedup_values {
+        if options.store_with.is_some()
+            || options.load_with.is_some()
+            || options.persist.is_some()
+            || options.shared_future
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "DedupValues cannot be combined with StoreWith, LoadWith, SerializeValues, Persist or SharedFuture",
+            ));
+        }
+        options.store_with = Some((
+            syn::parse_quote! { #intern_name },
+            syn::parse_quote! { ::std::sync::Arc<#return_type> },
+        ));
+        options.load_with = Some(syn::parse_quote! { ::memoize::dedup::resolve });
+        quote::quote! {
+            fn #intern_name(value: &#return_type) -> ::std::sync::Arc<#return_type> {
+                static ATTR_MEMOIZE_INTERNER__: ::memoize::dedup::Interner<#return_type> =
+                    ::memoize::dedup::Interner::new();
+                ATTR_MEMOIZE_INTERNER__.intern(value)
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
+    let (value_type, encode_value, decode_value) = match (&options.store_with, &options.load_with) {
+        (None, None) => (
+            return_type.clone(),
+            quote::quote! { ATTR_MEMOIZE_RETURN__.clone() },
+            quote::quote! { ATTR_MEMOIZE_RETURN__.clone() },
+        ),
+        (Some((encode, stored_type)), Some(decode)) => (
+            stored_type.to_token_stream(),
+            quote::quote! { #encode(::std::borrow::Borrow::borrow(&ATTR_MEMOIZE_RETURN__)) },
+            quote::quote! { #decode(ATTR_MEMOIZE_RETURN__) },
+        ),
+        _ => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "StoreWith and LoadWith must be used together",
+            ))
+        }
+    };
+
+    Ok((intern_func, value_type, encode_value, decode_value))
+}
+
+/// The type of the stored entries: `memoize::CompactEntry`s with `CompactEntries`, `memoize::Entry`s
+/// tracking the metadata needed by the options, or the values themselves.
+fn stored_type(
+    options: &CacheOptions,
+    sig: &syn::Signature,
+    value_type: &proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // With `CompactEntries`, entries only hold millisecond timestamps relative to the epoch of the
+    // cache, which is enough for `TimeToLive` and `ExpireAfterAccess`.
+    if options.compact_entries {
+        if options.time_to_live.is_none() && options.expire_after_access.is_none() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "CompactEntries requires TimeToLive or ExpireAfterAccess",
+            ));
+        }
+        if options.expire_with.is_some()
+            || options.max_age.is_some()
+            || options.entry_info
+            || options.validate_every.is_some()
+            || options.revalidate.is_some()
+            || options.use_store.is_some()
+            || measures_cost(options)
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "CompactEntries cannot be combined with ExpireWith, MaxAge, EntryInfo, ValidateEvery, Revalidate, UseStore or Eviction: GreedyDual",
+            ));
+        }
+    }
+    let stored_type = if options.compact_entries {
+        quote::quote! { ::memoize::CompactEntry<#value_type> }
+    } else if uses_entry(options) {
+        // Entries only track the metadata needed by the options.
+        let tracked = |needed: bool, ty: proc_macro2::TokenStream| {
+            if needed {
+                quote::quote! { ::std::cell::Cell<#ty> }
+            } else {
+                quote::quote! { () }
+            }
+        };
+        let accessed = tracked(
+            options.expire_after_access.is_some() || options.entry_info,
+            quote::quote! { ::std::time::Instant },
+        );
+        let expires = tracked(
+            options.expire_with.is_some() || options.max_age.is_some(),
+            quote::quote! { Option<::std::time::Instant> },
+        );
+        let cost = tracked(
+            measures_cost(options),
+            quote::quote! { ::std::time::Duration },
+        );
+        let hits = tracked(options.entry_info, quote::quote! { u64 });
+        let validated = tracked(
+            options.validate_every.is_some(),
+            quote::quote! { ::std::time::Instant },
+        );
+        quote::quote! { ::memoize::Entry<#value_type, #accessed, #expires, #cost, #hits, #validated> }
+    } else {
+        value_type.clone()
+    };
+
+    Ok(stored_type)
+}
+
+/// With `AutoCapacity`, `Priority` or `Eviction`, checks the bounds of the store. Returns the store
+/// type and the arguments of its `new()` after the capacity, if a bounded store uses another eviction
+/// policy than LRU.
+fn bounded_store(
+    options: &mut CacheOptions,
+    sig: &syn::Signature,
+    input_tuple_type: &proc_macro2::TokenStream,
+    return_type: &proc_macro2::TokenStream,
+    stored_type: &proc_macro2::TokenStream,
+    decode_value: &proc_macro2::TokenStream,
+) -> syn::Result<Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)>> {
+    // With `AutoCapacity`, an LRU cache starts at its minimum capacity (or the given `Capacity`), and
+    // is resized as it observes its hit rate and evictions.
+    if let Some((min, max)) = options.auto_capacity {
+        if options.priority.is_some()
+            || options.eviction.is_some()
+            || options.partition_by.is_some()
+            || options.persist.is_some()
+            || options.use_store.is_some()
+        {
+            return Err(syn::Error::new(
+                sig.span(),
+                "AutoCapacity cannot be combined with Priority, Eviction, PartitionBy, Persist or UseStore",
+            ));
+        }
+        match options.lru_max_entries {
+            None => options.lru_max_entries = Some(min),
+            Some(cap) if cap < min || cap > max => {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "Capacity must be within the AutoCapacity range",
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    // With `Priority` or `Eviction`, the bounded cache uses another eviction policy than LRU, possibly
+    // ranking entries by a function of the key and return value, or by their cost.
+    if (options.priority.is_some() || options.eviction.is_some())
+        && options.lru_max_entries.is_none()
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "Priority and Eviction require Capacity",
+        ));
+    }
+    let bounded_store = match (&options.priority, &options.eviction) {
+        (None, None) => None,
+        (Some(_), Some(_)) => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Priority cannot be combined with Eviction",
+            ))
+        }
+        (None, Some(_)) if measures_cost(options) => Some((
+            quote::quote! { ::memoize::GreedyDualCache },
+            quote::quote! { , |ATTR_MEMOIZE_STORED__: &#stored_type| ATTR_MEMOIZE_STORED__.cost() },
+        )),
+        (None, Some(policy)) if policy == "ARC" => {
+            Some((quote::quote! { ::memoize::ArcCache }, quote::quote! {}))
+        }
+        (None, Some(_)) => Some((quote::quote! { ::memoize::ClockProCache }, quote::quote! {})),
+        (Some(priority), None) => {
+            let stored_return = if uses_entry(options) {
+                quote::quote! { &ATTR_MEMOIZE_STORED__.value }
+            } else {
+                quote::quote! { ATTR_MEMOIZE_STORED__ }
+            };
+            let return_value = if options.store_with.is_some() {
+                quote::quote! { ::std::borrow::Borrow::borrow(&#decode_value) }
+            } else {
+                quote::quote! { ATTR_MEMOIZE_RETURN__ }
+            };
+            Some((
+                quote::quote! { ::memoize::PriorityLruCache },
+                quote::quote! {
+                    , |ATTR_MEMOIZE_KEY__: &#input_tuple_type, ATTR_MEMOIZE_STORED__: &#stored_type| -> u8 {
+                        let ATTR_MEMOIZE_PRIORITY__: fn(&#input_tuple_type, &#return_type) -> u8 = #priority;
+                        let ATTR_MEMOIZE_RETURN__ = #stored_return;
+                        ATTR_MEMOIZE_PRIORITY__(ATTR_MEMOIZE_KEY__, #return_value)
+                    }
+                },
+            ))
+        }
+    };
+    Ok(bounded_store)
+}
+
+/// The type of the store, and the expression initializing it: a `HashMap`, a bounded store, a
+/// `memoize::DiskStore` with `Persist`, or a map of partitions with `PartitionBy` (whose declaration
+/// is returned as well).
+fn cache_type(
+    options: &CacheOptions,
+    sig: &syn::Signature,
+    options_source: &str,
+    input_tuple_type: &proc_macro2::TokenStream,
+    stored_type: &proc_macro2::TokenStream,
+    bounded_store: Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)>,
+) -> syn::Result<(
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)> {
+    let fn_name = sig.ident.to_string();
+    // Construct storage for the memoized keys and return values.
+    // With `UseStore`, the store is a static declared by the user.
+    if options.use_store.is_some()
+        && (options.lru_max_entries.is_some()
+            || options.custom_hasher.is_some()
+            || options.partition_by.is_some()
+            || options.persist.is_some()
+            || options.public_store)
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "UseStore cannot be combined with Capacity, CustomHasher, PartitionBy, Persist or PublicStore",
+        ));
+    }
+    let (cache_type, cache_init) = store::construct_cache(
+        options,
+        input_tuple_type.clone(),
+        stored_type.clone(),
+        bounded_store,
+    );
+
+    // With `Persist`, the store is backed by a file, which is shared with other processes. The file
+    // is stamped with a fingerprint of the signature and the options (including `PersistSalt`), so
+    // that files written by an incompatible version of the function are not loaded.
+    if (options.persist_salt.is_some() || options.persist_key.is_some())
+        && options.persist.is_none()
+    {
+        return Err(syn::Error::new(
+            sig.span(),
+            "PersistSalt and PersistKey require Persist or PersistXdg",
+        ));
+    }
+    let (insert_fn, get_fn, remove_fn) = store::cache_access_methods(options);
+
+    let (cache_type, cache_init) = match &options.persist {
+        None => (cache_type, cache_init),
+        Some(path) => {
+            if options.lru_max_entries.is_some()
+                || options.custom_hasher.is_some()
+                || options.partition_by.is_some()
+                || uses_entry(options)
+            {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "Persist cannot be combined with Capacity, CustomHasher, PartitionBy or expiring entries",
+                ));
+            }
+            (
+                quote::quote! { ::memoize::DiskStore<#input_tuple_type, #stored_type> },
+                {
+                    let fingerprint =
+                        fingerprint(&[&sig.to_token_stream().to_string(), options_source]);
+                    match &options.persist_key {
+                        None => quote::quote! { ::memoize::DiskStore::open(#path, #fingerprint) },
+                        // The key is only known at runtime.
+                        Some(key) => quote::quote! {{
+                            let ATTR_MEMOIZE_KEY__: fn() -> [u8; 32] = #key;
+                            ::memoize::DiskStore::open_encrypted(#path, #fingerprint, ATTR_MEMOIZE_KEY__())
+                        }},
+                    }
+                },
+            )
+        }
+    };
+
+    // With `PartitionBy`, the store is a map from partitions to stores of the configured type, each
+    // with its own capacity.
+    let partitions_type = syn::Ident::new(
+        format!("memoized_partitions_{}", fn_name).as_str(),
+        sig.span(),
+    );
+    Ok(
+        if let Some((partition_by, partition_type)) = &options.partition_by {
+            // Only bounded stores are asked whether they contain a key.
+            let partition_contains = if options.lru_max_entries.is_some() {
+                quote::quote! {
+                    fn contains(&self, key: &#input_tuple_type) -> bool {
+                        self.0
+                            .get(&Self::partition(key))
+                            .map_or(false, |partition| partition.contains(key))
+                    }
+                }
+            } else {
+                quote::quote! {}
+            };
+            let partitions = quote::quote! {
+                #[allow(non_camel_case_types)]
+                struct #partitions_type(std::collections::HashMap<#partition_type, #cache_type>);
+
+                #[allow(dead_code)]
+                impl #partitions_type {
+                    fn partition(key: &#input_tuple_type) -> #partition_type {
+                        let ATTR_MEMOIZE_PARTITION__: fn(&#input_tuple_type) -> #partition_type = #partition_by;
+                        ATTR_MEMOIZE_PARTITION__(key)
+                    }
+
+                    fn #get_fn(&mut self, key: &#input_tuple_type) -> Option<&#stored_type> {
+                        self.0.get_mut(&Self::partition(key))?.#get_fn(key)
+                    }
+
+                    fn #insert_fn(&mut self, key: #input_tuple_type, value: #stored_type) {
+                        self.0
+                            .entry(Self::partition(&key))
+                            .or_insert_with(|| #cache_init)
+                            .#insert_fn(key, value);
+                    }
+
+                    fn #remove_fn(&mut self, key: &#input_tuple_type) -> Option<#stored_type> {
+                        self.0.get_mut(&Self::partition(key))?.#remove_fn(key)
+                    }
+
+                    fn len(&self) -> usize {
+                        self.0.values().map(|partition| partition.len()).sum()
+                    }
+
+                    #partition_contains
+
+                    fn clear(&mut self) {
+                        self.0.clear();
+                    }
+
+                    fn iter(&self) -> impl Iterator<Item = (&#input_tuple_type, &#stored_type)> {
+                        self.0.values().flat_map(|partition| partition.iter())
+                    }
+                }
+
+                impl ::memoize::CacheStore<#input_tuple_type, #stored_type> for #partitions_type {
+                    fn get(&mut self, key: &#input_tuple_type) -> Option<&#stored_type> {
+                        #partitions_type::#get_fn(self, key)
+                    }
+
+                    fn insert(&mut self, key: #input_tuple_type, value: #stored_type) {
+                        #partitions_type::#insert_fn(self, key, value)
+                    }
+
+                    fn remove(&mut self, key: &#input_tuple_type) -> Option<#stored_type> {
+                        #partitions_type::#remove_fn(self, key)
+                    }
+
+                    fn len(&self) -> usize {
+                        #partitions_type::len(self)
+                    }
+
+                    fn clear(&mut self) {
+                        #partitions_type::clear(self)
+                    }
+                }
+
+                impl ::memoize::memoized::Entries<#input_tuple_type, #stored_type> for #partitions_type {
+                    fn for_each_entry(&self, mut f: impl FnMut(&#input_tuple_type, &#stored_type)) {
+                        self.iter().for_each(|(key, value)| f(key, value))
+                    }
+                }
+            };
+            (
+                quote::quote! { #partitions_type },
+                quote::quote! { #partitions_type(std::collections::HashMap::new()) },
+                partitions,
+            )
+        } else {
+            (cache_type, cache_init, quote::quote! {})
+        },
+    )
+}
+
+/// The static holding the store, unless it is declared by the user (`UseStore`).
+#[allow(clippy::too_many_arguments)]
+fn store_static(
+    options: &CacheOptions,
+    sig: &syn::Signature,
+    vis: &syn::Visibility,
+    store_ident: &Path,
+    cache_type: &proc_macro2::TokenStream,
+    cache_init: &proc_macro2::TokenStream,
+    async_store: bool,
+    read_locked: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let fn_name = sig.ident.to_string();
+    // With `PublicStore`, the store has the visibility of the function, so that it can be accessed
+    // directly by other code.
+    let store_vis = if options.public_store {
+        if options.partition_by.is_some() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "PublicStore cannot be combined with PartitionBy",
+            ));
+        }
+        let doc = format!(" The cache of [`{}`], generated by `#[memoize]`.", fn_name);
+        quote::quote! { #[doc = #doc] #vis }
+    } else {
+        quote::quote! {}
+    };
+    let store = if options.use_store.is_some() {
+        quote::quote! {
+            #[allow(unused_imports)]
+            use ::memoize::CacheStore as _;
+        }
+    } else if async_store {
+        quote::quote! {
+            ::memoize::lazy_static::lazy_static! {
+                #store_vis static ref #store_ident : ::memoize::AsyncMutex<#cache_type> =
+                    ::memoize::AsyncMutex::new(#cache_init);
+            }
+        }
+    } else if read_locked {
+        quote::quote! {
+            ::memoize::lazy_static::lazy_static! {
+                #store_vis static ref #store_ident : std::sync::RwLock<#cache_type> =
+                    std::sync::RwLock::new(#cache_init);
+            }
+        }
+    } else if options.shared_cache {
+        quote::quote! {
+            ::memoize::lazy_static::lazy_static! {
+                #store_vis static ref #store_ident : std::sync::Mutex<#cache_type> =
+                    std::sync::Mutex::new(#cache_init);
+            }
+        }
+    } else if options.scoped {
+        // Accessed like a thread-local store, but holds a cache per `memoize::scope()`.
+        quote::quote! {
+            static #store_ident : ::memoize::scope::ScopedStore<#cache_type> =
+                ::memoize::scope::ScopedStore::new(|| #cache_init);
+        }
+    } else if options.task_local {
+        // Accessed like a thread-local store, but holds a cache per `memoize::task_scope()`.
+        quote::quote! {
+            static #store_ident : ::memoize::scope::TaskLocalStore<#cache_type> =
+                ::memoize::scope::TaskLocalStore::new(|| #cache_init);
+        }
+    } else {
+        quote::quote! {
+            std::thread_local! {
+                #store_vis static #store_ident : std::cell::RefCell<#cache_type> =
+                    std::cell::RefCell::new(#cache_init);
+            }
+        }
+    };
+    Ok(store)
+}
+
+/// With `AutoCapacity`, an LRU store counts hits, misses and evictions, and resizes itself when
+/// an insertion tells it to. Returns the statistics, and the resizing of the store after an
+/// insertion.
+fn auto_capacity(memo: &Memo) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let options = memo.options;
+    let ident = memo.static_ident("auto_capacity");
+    let auto_capacity = match options.auto_capacity {
+        None => quote::quote! {},
+        Some((min, max)) if options.shared_cache => quote::quote! {
+            static #ident: ::memoize::auto_capacity::AutoCapacity =
+                ::memoize::auto_capacity::AutoCapacity::new(#min, #max);
+        },
+        Some((min, max)) => quote::quote! {
+            std::thread_local! {
+                static #ident: ::memoize::auto_capacity::AutoCapacity =
+                    const { ::memoize::auto_capacity::AutoCapacity::new(#min, #max) };
+            }
+        },
+    };
+    let adjust_capacity = if options.auto_capacity.is_some() {
+        let record_insert = memo.with_auto_capacity(quote::quote! {
+            ATTR_MEMOIZE_AUTO__.record_insert(
+                ATTR_MEMOIZE_EVICTED__,
+                ATTR_MEMOIZE_HM__.len(),
+                ATTR_MEMOIZE_HM__.cap().get(),
+            )
+        });
+        quote::quote! {
+            if let Some(ATTR_MEMOIZE_CAPACITY__) = #record_insert {
+                ATTR_MEMOIZE_HM__.resize(std::num::NonZeroUsize::new(ATTR_MEMOIZE_CAPACITY__).unwrap());
+            }
+        }
+    } else {
+        quote::quote! {}
+    };
+    (auto_capacity, adjust_capacity)
+}
+
+/// With `IndexBy`, entries can be removed by their secondary key, as found by a reverse index which
+/// is updated before an entry is inserted. Returns the reverse index, its update, and
+/// `memoized_remove_by_index_<fn>()`.
+fn reverse_index(
+    memo: &Memo,
+) -> syn::Result<(
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)> {
+    let Memo {
+        options,
+        sig,
+        vis,
+        input_tuple_type,
+        get_fn,
+        remove_fn,
+        async_helper,
+        ..
+    } = memo;
+    let index_ident = memo.static_ident("index");
+    let remove_by_index_name = memo.ident("remove_by_index");
+    // The index is rebuilt from the store once it has grown too much, as it isn't told about
+    // evictions and removals. It is updated before the entry is inserted.
+    let update_index = match memo.index_of(
+        quote::quote! { ATTR_MEMOIZE_INSERTED__ },
+        quote::quote! { ATTR_MEMOIZE_STORED__ },
+    ) {
+        None => quote::quote! {},
+        Some(index) => {
+            let index_stored = memo.index_of(
+                quote::quote! { ATTR_MEMOIZE_KEY__ },
+                quote::quote! { ATTR_MEMOIZE_STORED__ },
+            );
+            let update_index = memo.with_index(quote::quote! {
+                if ATTR_MEMOIZE_INDEX__.needs_rebuild(ATTR_MEMOIZE_HM__.len() + 1) {
+                    ATTR_MEMOIZE_INDEX__.clear();
+                    for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_STORED__) in ATTR_MEMOIZE_HM__.iter() {
+                        ATTR_MEMOIZE_INDEX__.insert(#index_stored, ATTR_MEMOIZE_KEY__.clone());
+                    }
+                }
+                ATTR_MEMOIZE_INDEX__.insert(ATTR_MEMOIZE_SECONDARY__, ATTR_MEMOIZE_INSERTED__.clone());
+            });
+            quote::quote! {
+                let ATTR_MEMOIZE_SECONDARY__ = #index;
+                #update_index;
+            }
+        }
+    };
+    Ok(match &options.index_by {
+        None => (quote::quote! {}, quote::quote! {}, quote::quote! {}),
+        Some((_, index_type)) => {
+            if !stores_returned_values(options)
+                || options.write_back.is_some()
+                || options.use_store.is_some()
+            {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "IndexBy cannot be combined with PerElement, CollectStream, WriteBack, SharedFuture or UseStore",
+                ));
+            }
+            let index = if options.shared_cache {
+                quote::quote! {
+                    ::memoize::lazy_static::lazy_static! {
+                        static ref #index_ident: std::sync::Mutex<::memoize::index::ReverseIndex<#index_type, #input_tuple_type>> =
+                            std::sync::Mutex::new(::memoize::index::ReverseIndex::new());
+                    }
+                }
+            } else {
+                quote::quote! {
+                    std::thread_local! {
+                        static #index_ident: std::cell::RefCell<::memoize::index::ReverseIndex<#index_type, #input_tuple_type>> =
+                            std::cell::RefCell::new(::memoize::index::ReverseIndex::new());
+                    }
+                }
+            };
+            let take_keys = memo.with_index(quote::quote! { ATTR_MEMOIZE_INDEX__.take(index) });
+            let index_stored = memo.index_of(
+                quote::quote! { &ATTR_MEMOIZE_KEY__ },
+                quote::quote! { ATTR_MEMOIZE_STORED__ },
+            );
+            // Keys whose entry was replaced by one with another secondary key are kept.
+            let remove_by_index = memo.with_store(quote::quote! {
+                let ATTR_MEMOIZE_KEYS__ = #take_keys;
+                let mut ATTR_MEMOIZE_REMOVED__ = 0;
+                for ATTR_MEMOIZE_KEY__ in ATTR_MEMOIZE_KEYS__ {
+                    let ATTR_MEMOIZE_MATCHES__ = ATTR_MEMOIZE_HM__
+                        .#get_fn(&ATTR_MEMOIZE_KEY__)
+                        .is_some_and(|ATTR_MEMOIZE_STORED__| #index_stored == *index);
+                    if ATTR_MEMOIZE_MATCHES__ {
+                        ATTR_MEMOIZE_HM__.#remove_fn(&ATTR_MEMOIZE_KEY__);
+                        ATTR_MEMOIZE_REMOVED__ += 1;
+                    }
+                }
+                ATTR_MEMOIZE_REMOVED__
+            });
+            (
+                index,
+                update_index,
+                quote::quote! {
+                    #vis #async_helper fn #remove_by_index_name(index: &#index_type) -> usize {
+                        #remove_by_index
+                    }
+                },
+            )
+        }
+    })
+}
+
+/// Builds the code of the memoizing wrapper which is shared by its options.
+fn build_call(
+    memo: &Memo,
+    memoized_input_names: &[syn::Ident],
+    slice_names: &[syn::Ident],
+    small_key: bool,
+    borrowed_lookup: bool,
+    encode_value: &proc_macro2::TokenStream,
+) -> syn::Result<Call> {
+    let Memo {
+        options,
+        sig,
+        memo_type,
+        return_type,
+        stored_type,
+        ..
+    } = memo;
+    let names_refs_tuple = quote::quote! { (#(&#memoized_input_names),*) };
+    let (record_hit, record_miss) = if options.instrument {
+        (
+            quote::quote! { ::memoize::memoized::hit_key::<#memo_type, _>(&#names_refs_tuple); },
+            quote::quote! { ::memoize::memoized::miss_key::<#memo_type, _>(&#names_refs_tuple); },
+        )
+    } else {
+        (
+            quote::quote! { ::memoize::memoized::hit::<#memo_type>(); },
+            quote::quote! { ::memoize::memoized::miss::<#memo_type>(); },
+        )
+    };
+    let syntax_names_tuple = quote::quote! { (#(#memoized_input_names),*) };
+    let syntax_names_tuple_cloned = if small_key {
+        syntax_names_tuple.clone()
+    } else {
+        let cloned = memoized_input_names.iter().map(|name| {
+            if slice_names.contains(name) {
+                quote::quote! { #name.to_vec() }
+            } else {
+                quote::quote! { #name.clone() }
+            }
+        });
+        quote::quote! { (#(#cloned),*) }
+    };
+    // The key tuple, owning slices (which are borrowed by the wrapper).
+    let owned_names_tuple = {
+        let owned = memoized_input_names.iter().map(|name| {
+            if slice_names.contains(name) {
+                quote::quote! { #name.to_vec() }
+            } else {
+                quote::quote! { #name }
+            }
+        });
+        quote::quote! { (#(#owned),*) }
+    };
+    // A key consisting of a single slice is looked up without building the `Vec`.
+    let lookup_key = if borrowed_lookup
+        && memoized_input_names.len() == 1
+        && slice_names.contains(&memoized_input_names[0])
+    {
+        let name = &memoized_input_names[0];
+        quote::quote! { *#name }
+    } else {
+        syntax_names_tuple_cloned.clone()
+    };
+    let read_memo = memo.read_key(&lookup_key);
+    // With `HashOnce`, the wrapper's lookup evaluates to the hash of the key along with the value.
+    let read_hashed = {
+        let read = memo.read(quote::quote! {
+            ATTR_MEMOIZE_HM__.get_hashed(ATTR_MEMOIZE_HASH__, &#lookup_key)
+        });
+        quote::quote! {{
+            let ATTR_MEMOIZE_HASH__ = ATTR_MEMOIZE_HM__.hash_key(&#lookup_key);
+            (ATTR_MEMOIZE_HASH__, #read)
+        }}
+    };
+    let stored_value = if options.compact_entries {
+        // A value computed after the epoch ran out is stale until the epoch is renewed.
+        quote::quote! {
+            ::memoize::CompactEntry::new(
+                #encode_value,
+                ::memoize::memoized::ticks::<#memo_type>().unwrap_or(u32::MAX),
+            )
+        }
+    } else if uses_entry(options) {
+        let expires = match (&options.expire_with, &options.max_age) {
+            (None, None) => quote::quote! { None },
+            (Some(expire_with), None) => quote::quote! {{
+                let ATTR_MEMOIZE_EXPIRE__: fn(&#return_type) -> Option<std::time::Instant> = #expire_with;
+                ATTR_MEMOIZE_EXPIRE__(::std::borrow::Borrow::borrow(&ATTR_MEMOIZE_RETURN__))
+            }},
+            // Without a max-age, a value is stale right away.
+            (None, Some(max_age)) => quote::quote! {{
+                let ATTR_MEMOIZE_MAX_AGE__: fn(&#return_type) -> Option<std::time::Duration> = #max_age;
+                let ATTR_MEMOIZE_MAX_AGE__ =
+                    ATTR_MEMOIZE_MAX_AGE__(::std::borrow::Borrow::borrow(&ATTR_MEMOIZE_RETURN__));
+                Some(std::time::Instant::now() + ATTR_MEMOIZE_MAX_AGE__.unwrap_or_default())
+            }},
+            (Some(_), Some(_)) => {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "ExpireWith cannot be combined with MaxAge",
+                ))
+            }
+        };
+        if measures_cost(options) {
+            quote::quote! { <#stored_type>::new(#encode_value, #expires).with_cost(ATTR_MEMOIZE_COST__) }
+        } else {
+            quote::quote! { <#stored_type>::new(#encode_value, #expires) }
+        }
+    } else {
+        encode_value.clone()
+    };
+    Ok(Call {
+        syntax_names_tuple,
+        names_refs_tuple,
+        syntax_names_tuple_cloned,
+        owned_names_tuple,
+        lookup_key,
+        record_hit,
+        record_miss,
+        read_memo,
+        read_hashed,
+        stored_value,
+    })
+}
+
+/// Whether a computed `ATTR_MEMOIZE_RETURN__` is stored at all. With `MaxValueWeight`, values
+/// weighing more than the limit are returned, but not cached; with `MinComputeTime`, neither are
+/// values computed faster than the threshold. Returns the check of `AdmitAfter` (see [`admit_key`]),
+/// and the condition on the value.
+fn admit_value(memo: &Memo) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Memo {
+        options,
+        sig,
+        return_type,
+        ..
+    } = memo;
+    let is_async = memo.is_async;
+    let admit_weight = match (&options.max_value_weight, &options.weigher) {
+        (None, None) => quote::quote! { true },
+        (Some(max_weight), Some(weigher)) => {
+            if options.shared_future {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "MaxValueWeight cannot be combined with SharedFuture",
+                ));
+            }
+            quote::quote! {{
+                let ATTR_MEMOIZE_WEIGHER__: fn(&#return_type) -> usize = #weigher;
+                ATTR_MEMOIZE_WEIGHER__(::std::borrow::Borrow::borrow(&ATTR_MEMOIZE_RETURN__))
+                    <= #max_weight
+            }}
+        }
+        _ => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "MaxValueWeight and Weigher must be used together",
+            ))
+        }
+    };
+    // Values computed by plain async functions are only admitted if no flush started in the
+    // meantime.
+    let admit_weight = if is_async {
+        quote::quote! { #admit_weight && ATTR_MEMOIZE_RUNNING__.is_current() }
+    } else {
+        admit_weight
+    };
+    // With `IgnoreErrors`, `Err`s are returned, but not cached. A `SharedFuture` resolving to an
+    // `Err` is discarded anyway.
+    let admit_weight = if options.ignore_errors {
+        if !returns_result(&sig.output) {
+            return Err(syn::Error::new(
+                sig.output.span(),
+                "IgnoreErrors requires a function returning a `Result`",
+            ));
+        }
+        if options.shared_future {
+            admit_weight
+        } else {
+            quote::quote! {
+                #admit_weight && {
+                    let ATTR_MEMOIZE_RESULT__: &#return_type =
+                        ::std::borrow::Borrow::borrow(&ATTR_MEMOIZE_RETURN__);
+                    ATTR_MEMOIZE_RESULT__.is_ok()
+                }
+            }
+        }
+    } else {
+        admit_weight
+    };
+    let admit_time = match &options.min_compute_time {
+        None => admit_weight,
+        Some(min_time) => {
+            if options.shared_future {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "MinComputeTime cannot be combined with SharedFuture",
+                ));
+            }
+            quote::quote! { ATTR_MEMOIZE_COST__ >= #min_time && #admit_weight }
+        }
+    };
+    // With `AdmitAfter`, a key is only admitted once it has been requested often enough, as
+    // estimated by a frequency sketch. Takes a reference to the key.
+    let admit_name = memo.ident("admit");
+    let admit_func = match options.admit_after {
+        None => quote::quote! {},
+        Some(requests) => quote::quote! {
+            fn #admit_name<Q: ::std::hash::Hash + ?Sized>(key: &Q) -> bool {
+                static ATTR_MEMOIZE_SKETCH__: ::std::sync::Mutex<::memoize::FrequencySketch> =
+                    ::std::sync::Mutex::new(::memoize::FrequencySketch::new());
+                match ATTR_MEMOIZE_SKETCH__.lock() {
+                    Ok(mut sketch) => sketch.record(key) >= #requests,
+                    Err(_) => true,
+                }
+            }
+        },
+    };
+    Ok((admit_func, admit_time))
+}
+
+/// Whether the computed `ATTR_MEMOIZE_RETURN__` of `key` (a reference) is stored, given the condition
+/// `admit_time` on the value.
+fn admit_key(
+    memo: &Memo,
+    admit_time: &proc_macro2::TokenStream,
+    key: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match memo.options.admit_after {
+        None => admit_time.clone(),
+        Some(_) => {
+            let admit_name = memo.ident("admit");
+            quote::quote! { #admit_time && #admit_name(#key) }
+        }
+    }
+}
+
+/// With `NegativeFilter`, keys whose result equals the given negative value are only recorded in a
+/// `memoize::NegativeFilter`, which is consulted after the cache. Returns the check of the filter, and
+/// the storing of a computed value (by `insert`, if admitted by `admit_names`).
+fn negative_filter(
+    memo: &Memo,
+    call: &Call,
+    admit_names: &proc_macro2::TokenStream,
+    insert: &proc_macro2::TokenStream,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Memo {
+        options,
+        sig,
+        memo_type,
+        ..
+    } = memo;
+    let names_refs_tuple = &call.names_refs_tuple;
+    Ok(match &options.negative_filter {
+        None => {
+            if options.negative_filter_capacity.is_some() {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "NegativeFilterCapacity requires NegativeFilter",
+                ));
+            }
+            (
+                quote::quote! {},
+                quote::quote! {
+                    if #admit_names {
+                        #insert
+                    }
+                },
+            )
+        }
+        Some(negative) => {
+            reject_with_wrapping_modes("NegativeFilter", options, sig.span())?;
+            (
+                quote::quote! {
+                    if ::memoize::memoized::negative_hit::<#memo_type, _>(&#names_refs_tuple) {
+                        return #negative;
+                    }
+                },
+                quote::quote! {
+                    if !::memoize::memoized::filter_negative::<#memo_type, _>(
+                        &#names_refs_tuple,
+                        &ATTR_MEMOIZE_RETURN__,
+                    ) && #admit_names
+                    {
+                        #insert
+                    }
+                },
+            )
+        }
+    })
+}
+
+/// With `Secondary`, misses consult an external store before computing the value, and computed
+/// values are written through to it. With `WriteBehind`, the values are written by a background
+/// thread instead. Returns the lookup in the secondary store, and the write to it.
+fn secondary(
+    memo: &Memo,
+    call: &Call,
+    insert: &proc_macro2::TokenStream,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Memo {
+        options,
+        sig,
+        return_type,
+        ..
+    } = memo;
+    let Call {
+        names_refs_tuple,
+        record_hit,
+        ..
+    } = call;
+    Ok(match &options.secondary {
+        None => {
+            if options.write_behind {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "WriteBehind requires Secondary",
+                ));
+            }
+            (quote::quote! {}, quote::quote! {})
+        }
+        Some(secondary) => {
+            reject_with_wrapping_modes("Secondary", options, sig.span())?;
+            let fn_name = sig.ident.to_string();
+            let path = quote::quote! { concat!(module_path!(), "::", #fn_name) };
+            // Nothing is known about the cost of values found in the secondary store.
+            let cost = if measures_cost(options) {
+                quote::quote! { let ATTR_MEMOIZE_COST__ = std::time::Duration::ZERO; }
+            } else {
+                quote::quote! {}
+            };
+            let insert_found = memo.with_store(insert.clone());
+            let put = if options.write_behind {
+                quote::quote! { ::memoize::secondary::put_behind }
+            } else {
+                quote::quote! { ::memoize::secondary::put }
+            };
+            (
+                quote::quote! {
+                    let ATTR_MEMOIZE_FOUND__ = ::memoize::secondary::get::<_, _, #return_type>(
+                        #secondary,
+                        #path,
+                        &#names_refs_tuple,
+                    );
+                    if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_FOUND__ {
+                        #cost
+                        #insert_found;
+                        #record_hit
+                        return ATTR_MEMOIZE_RETURN__;
+                    }
+                },
+                quote::quote! {
+                    #put(#secondary, #path, &#names_refs_tuple, &ATTR_MEMOIZE_RETURN__);
+                },
+            )
+        }
+    })
+}
+
+/// With `Revalidate`, a stale value is served (and renewed by `insert`) if the validator confirms
+/// that it is still current, instead of recomputing it.
+fn revalidate(
+    memo: &Memo,
+    call: &Call,
+    insert: &proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let Memo {
+        options,
+        sig,
+        input_tuple_type,
+        return_type,
+        decode_value,
+        get_fn,
+        ..
+    } = memo;
+    let Call {
+        syntax_names_tuple_cloned,
+        lookup_key,
+        record_hit,
+        ..
+    } = call;
+    Ok(match &options.revalidate {
+        None => quote::quote! {},
+        Some(revalidate) => {
+            if !expires(options) {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "Revalidate requires MaxAge, ExpireWith, TimeToLive or ExpireAfterAccess",
+                ));
+            }
+            reject_with_wrapping_modes("Revalidate", options, sig.span())?;
+            let read_stale = memo.with_store(quote::quote! {
+                ATTR_MEMOIZE_HM__.#get_fn(&#lookup_key).map(|ATTR_MEMOIZE_ENTRY__| {
+                    let ATTR_MEMOIZE_RETURN__ = &ATTR_MEMOIZE_ENTRY__.value;
+                    (#decode_value, ATTR_MEMOIZE_ENTRY__.cost())
+                })
+            });
+            // The stale value was admitted when it was computed.
+            let renew = memo.with_store(insert.clone());
+            quote::quote! {
+                let ATTR_MEMOIZE_STALE__ = #read_stale;
+                if let Some((ATTR_MEMOIZE_RETURN__, ATTR_MEMOIZE_COST__)) = ATTR_MEMOIZE_STALE__ {
+                    let ATTR_MEMOIZE_REVALIDATE__: fn(&#input_tuple_type, &#return_type) -> bool = #revalidate;
+                    if ATTR_MEMOIZE_REVALIDATE__(&#syntax_names_tuple_cloned, &ATTR_MEMOIZE_RETURN__) {
+                        #renew;
+                        #record_hit
+                        return ATTR_MEMOIZE_RETURN__;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// With `CircuitBreaker`, misses are short-circuited with the last error while the circuit is open,
+/// and errors are counted instead of cached. Returns the breaker, the check of the circuit, and the
+/// recording of a computed `ATTR_MEMOIZE_RETURN__`.
+fn circuit_breaker(
+    memo: &Memo,
+    call: &Call,
+) -> syn::Result<(
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)> {
+    let Memo {
+        options,
+        sig,
+        return_type,
+        ..
+    } = memo;
+    let input_tuple_type = memo.input_tuple_type;
+    let syntax_names_tuple_cloned = &call.syntax_names_tuple_cloned;
+    let breaker_ident = memo.static_ident("breaker");
+    Ok(match &options.circuit_breaker {
+        None => (quote::quote! {}, quote::quote! {}, quote::quote! {}),
+        Some((failures, cooldown, per_key)) => {
+            if !returns_result(&sig.output) {
+                return Err(syn::Error::new(
+                    sig.output.span(),
+                    "CircuitBreaker requires a function returning a `Result`",
+                ));
+            }
+            reject_with_wrapping_modes("CircuitBreaker", options, sig.span())?;
+            let (key_type, key) = if *per_key {
+                (input_tuple_type.clone(), syntax_names_tuple_cloned.clone())
+            } else {
+                (quote::quote! { () }, quote::quote! { () })
+            };
+            (
+                quote::quote! {
+                    ::memoize::lazy_static::lazy_static! {
+                        static ref #breaker_ident: ::memoize::breaker::CircuitBreaker<#key_type, #return_type> =
+                            ::memoize::breaker::CircuitBreaker::new(#failures, #cooldown);
+                    }
+                },
+                quote::quote! {
+                    if let Some(ATTR_MEMOIZE_RETURN__) = #breaker_ident.check(&#key) {
+                        return ATTR_MEMOIZE_RETURN__;
+                    }
+                },
+                quote::quote! {
+                    #breaker_ident.record(#key, &ATTR_MEMOIZE_RETURN__, ATTR_MEMOIZE_FAILED__);
+                },
+            )
+        }
+    })
+}
+
+/// With `StaleOnError`, a failed recomputation returns the expired value, if it is an `Ok`.
+fn serve_stale(memo: &Memo, call: &Call) -> syn::Result<proc_macro2::TokenStream> {
+    let Memo {
+        options,
+        sig,
+        input_tuple_type,
+        return_type,
+        decode_value,
+        get_fn,
+        ..
+    } = memo;
+    let Call {
+        syntax_names_tuple_cloned,
+        lookup_key,
+        ..
+    } = call;
+    Ok(match &options.stale_on_error {
+        None => quote::quote! {},
+        Some(report) => {
+            if !returns_result(&sig.output) {
+                return Err(syn::Error::new(
+                    sig.output.span(),
+                    "StaleOnError requires a function returning a `Result`",
+                ));
+            }
+            if !expires(options) {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "StaleOnError requires TimeToLive, ExpireAfterAccess, ExpireWith or MaxAge",
+                ));
+            }
+            reject_with_wrapping_modes("StaleOnError", options, sig.span())?;
+            let read_stale = memo.with_store(quote::quote! {
+                ATTR_MEMOIZE_HM__.#get_fn(&#lookup_key).map(|ATTR_MEMOIZE_ENTRY__| {
+                    let ATTR_MEMOIZE_RETURN__ = &ATTR_MEMOIZE_ENTRY__.value;
+                    #decode_value
+                })
+            });
+            let report = match report {
+                None => quote::quote! {},
+                Some(report) => quote::quote! {
+                    let ATTR_MEMOIZE_REPORT__: fn(&#input_tuple_type, &#return_type) = #report;
+                    ATTR_MEMOIZE_REPORT__(&#syntax_names_tuple_cloned, &ATTR_MEMOIZE_RETURN__);
+                },
+            };
+            let fn_name = sig.ident.to_string();
+            quote::quote! {
+                if ATTR_MEMOIZE_FAILED__ {
+                    if let Some(ATTR_MEMOIZE_STALE__) = #read_stale {
+                        if ATTR_MEMOIZE_STALE__.is_ok() {
+                            ::memoize::hooks::record_stale(#fn_name);
+                            #report
+                            return ATTR_MEMOIZE_STALE__;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// With `ValidateWith`, hits are only served if the validator confirms them (with `ValidateEvery`,
+/// once per period); otherwise the entry is removed, and the call continues as a miss.
+fn validated_hit(memo: &Memo, call: &Call) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let Memo {
+        options,
+        sig,
+        memo_type,
+        input_tuple_type,
+        return_type,
+        get_fn,
+        remove_fn,
+        ..
+    } = memo;
+    let Call {
+        syntax_names_tuple_cloned,
+        lookup_key,
+        record_hit,
+        read_memo,
+        ..
+    } = call;
+    Ok(match &options.validate_with {
+        None => {
+            if options.validate_every.is_some() {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "ValidateEvery requires ValidateWith",
+                ));
+            }
+            None
+        }
+        Some(validate) => {
+            reject_with_wrapping_modes("ValidateWith", options, sig.span())?;
+            // Evaluates to the cached value, and whether it needs to be validated.
+            let read_due = match &options.validate_every {
+                None => {
+                    quote::quote! { #read_memo.map(|ATTR_MEMOIZE_RETURN__| (ATTR_MEMOIZE_RETURN__, true)) }
+                }
+                Some(every) => quote::quote! {
+                    ATTR_MEMOIZE_HM__
+                        .#get_fn(&#lookup_key)
+                        .filter(|ATTR_MEMOIZE_ENTRY__| <#memo_type as ::memoize::memoized::Memoized>::is_fresh(ATTR_MEMOIZE_ENTRY__))
+                        .map(|ATTR_MEMOIZE_ENTRY__| {
+                            <#memo_type as ::memoize::memoized::Memoized>::touch(ATTR_MEMOIZE_ENTRY__);
+                            let ATTR_MEMOIZE_DUE__ = ATTR_MEMOIZE_ENTRY__.validation_due(#every);
+                            (<#memo_type as ::memoize::memoized::Memoized>::load(ATTR_MEMOIZE_ENTRY__), ATTR_MEMOIZE_DUE__)
+                        })
+                },
+            };
+            let read_due = memo.with_store(read_due);
+            let remove_invalid = memo.with_store(quote::quote! {
+                ATTR_MEMOIZE_HM__.#remove_fn(&#syntax_names_tuple_cloned);
+            });
+            Some(quote::quote! {
+                let ATTR_MEMOIZE_FOUND__ = #read_due;
+                if let Some((ATTR_MEMOIZE_RETURN__, ATTR_MEMOIZE_DUE__)) = ATTR_MEMOIZE_FOUND__ {
+                    let ATTR_MEMOIZE_VALIDATE__: fn(&#input_tuple_type, &#return_type) -> bool = #validate;
+                    if !ATTR_MEMOIZE_DUE__
+                        || ATTR_MEMOIZE_VALIDATE__(&#syntax_names_tuple_cloned, &ATTR_MEMOIZE_RETURN__)
+                    {
+                        #record_hit
+                        return ATTR_MEMOIZE_RETURN__;
+                    }
+                    #remove_invalid;
+                }
+            })
+        }
+    })
+}
+
+/// With `SharedFuture`, removes a failed future from the cache.
+fn discard_func(memo: &Memo) -> proc_macro2::TokenStream {
+    let Memo {
+        options,
+        input_tuple_type,
+        store_ident,
+        lock_method,
+        remove_fn,
+        ..
+    } = memo;
+    let discard_name = memo.ident("discard");
+    if !options.shared_future {
+        quote::quote! {}
+    } else if options.shared_cache {
+        quote::quote! {
+            fn #discard_name(key: &#input_tuple_type) {
+                if let Ok(mut ATTR_MEMOIZE_HM__) = #store_ident.#lock_method() {
+                    ATTR_MEMOIZE_HM__.#remove_fn(key);
+                }
+            }
+        }
+    } else {
+        quote::quote! {
+            fn #discard_name(key: &#input_tuple_type) {
+                let _ = #store_ident.try_with(|ATTR_MEMOIZE_HM__| {
+                    if let Ok(mut ATTR_MEMOIZE_HM__) = ATTR_MEMOIZE_HM__.try_borrow_mut() {
+                        ATTR_MEMOIZE_HM__.#remove_fn(key);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Calls the original function by `call`, binding `result` (and `ATTR_MEMOIZE_COST__`). A
+/// `SharedFuture` which fails (resolves to an `Err`, or panics) is removed from the cache, so that
+/// the next caller retries the computation.
+fn compute(
+    memo: &Memo,
+    keys: &Call,
+    result: proc_macro2::TokenStream,
+    call: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let Memo {
+        options,
+        sig,
+        future_output,
+        ..
+    } = memo;
+    let is_async = memo.is_async;
+    let syntax_names_tuple_cloned = &keys.syntax_names_tuple_cloned;
+    let discard_name = memo.ident("discard");
+    let inflight_ident = memo.static_ident("inflight");
+    let discard_error = if returns_result(&sig.output) {
+        quote::quote! {
+            if ATTR_MEMOIZE_RETURN__.is_err() {
+                ATTR_MEMOIZE_DISCARD__.discard();
+            }
+        }
+    } else {
+        quote::quote! { drop(ATTR_MEMOIZE_DISCARD__); }
+    };
+    let call = if options.shared_future {
+        quote::quote! {{
+            let ATTR_MEMOIZE_FUTURE__ = #call;
+            let ATTR_MEMOIZE_DISCARD__ =
+                ::memoize::shared_future::DiscardOnUnwind::new(#syntax_names_tuple_cloned, #discard_name);
+            ::memoize::shared_future::mark_created(#syntax_names_tuple_cloned, #discard_name);
+            let ATTR_MEMOIZE_RUNNING__ = #inflight_ident.start();
+            ::memoize::futures::FutureExt::shared(
+                ::std::boxed::Box::pin(async move {
+                    let ATTR_MEMOIZE_RETURN__ = ATTR_MEMOIZE_FUTURE__.await;
+                    #discard_error
+                    ::std::mem::drop(ATTR_MEMOIZE_RUNNING__);
+                    ATTR_MEMOIZE_RETURN__
+                }) as ::memoize::futures::future::BoxFuture<'static, #future_output>
+            )
+        }}
+    } else if is_async {
+        quote::quote! { #call.await }
+    } else {
+        call
+    };
+    let start_running = if is_async {
+        quote::quote! { let ATTR_MEMOIZE_RUNNING__ = #inflight_ident.start(); }
+    } else {
+        quote::quote! {}
+    };
+    if times_compute(options) {
+        quote::quote! {
+            #start_running
+            let ATTR_MEMOIZE_START__ = std::time::Instant::now();
+            let #result = #call;
+            let ATTR_MEMOIZE_COST__ = ATTR_MEMOIZE_START__.elapsed();
+        }
+    } else {
+        quote::quote! {
+            #start_running
+            let #result = #call;
+        }
+    }
+}
+
+/// With `BatchWith`, misses are loaded in batches by the given function instead of the `original`
+/// call. Returns the batcher, and the computation binding `ATTR_MEMOIZE_RETURN__`.
+fn batcher(
+    memo: &Memo,
+    call: &Call,
+    original: proc_macro2::TokenStream,
+    per_element: bool,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Memo {
+        options,
+        sig,
+        input_tuple_type,
+        return_type,
+        ..
+    } = memo;
+    let is_async = memo.is_async;
+    let syntax_names_tuple_cloned = &call.syntax_names_tuple_cloned;
+    let batcher_ident = memo.static_ident("batcher");
+    Ok(match &options.batch_with {
+        None => {
+            if options.batch_window.is_some() {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "BatchWindow requires BatchWith",
+                ));
+            }
+            (
+                quote::quote! {},
+                compute(
+                    memo,
+                    call,
+                    quote::quote! { ATTR_MEMOIZE_RETURN__ },
+                    original,
+                ),
+            )
+        }
+        Some(load_many) => {
+            if !is_async || per_element || options.collect_stream || options.write_back.is_some() {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "BatchWith requires an async function, and cannot be combined with PerElement, SharedFuture, CollectStream or WriteBack",
+                ));
+            }
+            let window = match &options.batch_window {
+                Some(window) => quote::quote! { #window },
+                None => quote::quote! { std::time::Duration::from_millis(1) },
+            };
+            (
+                quote::quote! {
+                    static #batcher_ident: ::memoize::batch::Batcher<#input_tuple_type, #return_type> =
+                        ::memoize::batch::Batcher::new();
+                },
+                compute(
+                    memo,
+                    call,
+                    quote::quote! { ATTR_MEMOIZE_RETURN__ },
+                    quote::quote! { #batcher_ident.load(#syntax_names_tuple_cloned, #window, #load_many) },
+                ),
+            )
+        }
+    })
+}
+
+/// With `Precompute`, the listed inputs are evaluated at compile time (which requires a `const fn`),
+/// and the results are consulted before the runtime cache. Returns the table of results, and its
+/// lookup.
+fn precomputed(
+    memo: &Memo,
+    call: &Call,
+    input_params: &[FnArgument],
+    memoized_input_names: &[syn::Ident],
+    memoized_id: &syn::Ident,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Memo {
+        options,
+        sig,
+        input_tuple_type,
+        return_type,
+        ..
+    } = memo;
+    let record_hit = &call.record_hit;
+    let precomputed_ident = memo.static_ident("precomputed");
+    Ok(if options.precompute.is_empty() {
+        (quote::quote! {}, quote::quote! {})
+    } else {
+        if sig.constness.is_none() {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Precompute requires a `const fn`",
+            ));
+        }
+        if input_params.iter().any(|p| !p.is_memoized) {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Precompute cannot be combined with Ignore",
+            ));
+        }
+        let entries: Vec<_> = options
+            .precompute
+            .iter()
+            .map(|input| {
+                let args = argument_list(input);
+                quote::quote! { ((#(#args),*), #memoized_id(#(#args),*)) }
+            })
+            .collect();
+        let n_entries = entries.len();
+        let key_matches = if memoized_input_names.len() == 1 {
+            let name = &memoized_input_names[0];
+            quote::quote! { *ATTR_MEMOIZE_KEY__ == #name }
+        } else {
+            let indices = (0..memoized_input_names.len()).map(syn::Index::from);
+            quote::quote! { true #(&& ATTR_MEMOIZE_KEY__.#indices == #memoized_input_names)* }
+        };
+        (
+            quote::quote! {
+                static #precomputed_ident : [(#input_tuple_type, #return_type); #n_entries] = [#(#entries),*];
+            },
+            quote::quote! {
+                for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_RETURN__) in #precomputed_ident.iter() {
+                    if #key_matches {
+                        #record_hit
+                        return ATTR_MEMOIZE_RETURN__.clone();
+                    }
+                }
+            },
+        )
+    })
+}
+
+/// With `Warm`, the listed inputs are computed (through the memoized function itself) when the
+/// cache is first touched, and again after it has been flushed. Returns the warm list, its call, and
+/// the reset of its flag.
+fn warm_list(
+    memo: &Memo,
+    collects_stream: bool,
+) -> syn::Result<(
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)> {
+    let Memo { options, sig, .. } = memo;
+    let is_async = memo.is_async;
+    let warmed_ident = memo.static_ident("warmed");
+    // With `SharedFuture`, warming inserts the futures without awaiting them.
+    let fn_ident = if options.shared_future {
+        memo.ident("shared")
+    } else {
+        sig.ident.clone()
+    };
+    let warm_list_name = memo.ident("warm_list");
+    let (async_fn, await_call) = if is_async {
+        (quote::quote! { async }, quote::quote! { .await })
+    } else {
+        (quote::quote! {}, quote::quote! {})
+    };
+    Ok(if options.warm.is_empty() {
+        if options.warm_at_startup {
+            return Err(syn::Error::new(
+                sig.span(),
+                "WarmAtStartup requires a Warm list",
+            ));
+        }
+        (quote::quote! {}, quote::quote! {}, quote::quote! {})
+    } else {
+        let calls: Vec<_> = options
+            .warm
+            .iter()
+            .map(|input| {
+                let args = argument_list(input);
+                quote::quote! { let _ = #fn_ident(#(#args),*)#await_call; }
+            })
+            .collect();
+        if collects_stream {
+            return Err(syn::Error::new(
+                sig.span(),
+                "Warm cannot be combined with CollectStream",
+            ));
+        }
+        if options.warm_at_startup && is_async {
+            return Err(syn::Error::new(
+                sig.span(),
+                "WarmAtStartup cannot be used with async functions",
+            ));
+        }
+        let register = if options.warm_at_startup {
+            quote::quote! {
+                #[::memoize::ctor::ctor(anonymous, crate_path = ::memoize::ctor)]
+                unsafe fn register_warm_list() {
+                    ::memoize::register_warmer(#warm_list_name);
+                }
+            }
+        } else {
+            quote::quote! {}
+        };
+        let (flag, set_flag, reset_flag) = if options.shared_cache {
+            (
+                quote::quote! {
+                    static #warmed_ident: std::sync::atomic::AtomicBool =
+                        std::sync::atomic::AtomicBool::new(false);
+                },
+                quote::quote! { #warmed_ident.swap(true, std::sync::atomic::Ordering::SeqCst) },
+                quote::quote! {
+                    #warmed_ident.store(false, std::sync::atomic::Ordering::SeqCst);
+                },
+            )
+        } else {
+            (
+                quote::quote! {
+                    std::thread_local! {
+                        static #warmed_ident: std::cell::Cell<bool> = std::cell::Cell::new(false);
+                    }
+                },
+                quote::quote! {
+                    #warmed_ident.with(|ATTR_MEMOIZE_WARMED__| ATTR_MEMOIZE_WARMED__.replace(true))
+                },
+                quote::quote! {
+                    #warmed_ident.with(|ATTR_MEMOIZE_WARMED__| ATTR_MEMOIZE_WARMED__.set(false));
+                },
+            )
+        };
+        (
+            quote::quote! {
+                #flag
+
+                #async_fn fn #warm_list_name() {
+                    if !#set_flag {
+                        #(#calls)*
+                    }
+                }
+
+                #register
+            },
+            quote::quote! { #warm_list_name()#await_call; },
+            reset_flag,
+        )
+    })
+}
+
+/// With `PerElement`, looks up the `elements` one by one, and computes the missing ones in a single
+/// call of the original function, whose `elements` argument is a slice (`is_slice`) or a `Vec`.
+fn per_element_memoizer(
+    memo: &Memo,
+    call: &Call,
+    input_params: &[FnArgument],
+    memoized_id: &syn::Ident,
+    elements: &syn::Ident,
+    is_slice: bool,
+    admit_time: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let options = memo.options;
+    let stored_value = &call.stored_value;
+    let missing = if is_slice {
+        quote::quote! { &ATTR_MEMOIZE_MISSING__[..] }
+    } else {
+        quote::quote! { ATTR_MEMOIZE_MISSING__.clone() }
+    };
+    let forwarded_exprs = input_params.iter().map(|p| {
+        let ident = &p.arg_name;
+        if p.is_memoized {
+            missing.clone()
+        } else {
+            quote::quote! { #ident }
+        }
+    });
+    let compute_missing = compute(
+        memo,
+        call,
+        quote::quote! { ATTR_MEMOIZE_COMPUTED__ },
+        quote::quote! { #memoized_id(#(#forwarded_exprs),*) },
+    );
+    // The cost of computing all missing elements is shared equally between them.
+    let share_cost = if times_compute(options) {
+        quote::quote! {
+            let ATTR_MEMOIZE_COST__ = ATTR_MEMOIZE_COST__ / ATTR_MEMOIZE_MISSING__.len() as u32;
+        }
+    } else {
+        quote::quote! {}
+    };
+    let record_elements = memo.record_stats(
+        quote::quote! { ATTR_MEMOIZE_FOUND__.len() - ATTR_MEMOIZE_MISSING__.len() },
+        quote::quote! { ATTR_MEMOIZE_MISSING__.len() },
+    );
+    let record_elements = if options.instrument {
+        let emit_hit = memo.emit_event("Hit", Some(quote::quote! { ATTR_MEMOIZE_KEY__ }));
+        let emit_miss = memo.emit_event("Miss", Some(quote::quote! { ATTR_MEMOIZE_KEY__ }));
+        quote::quote! {
+            #record_elements
+            for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_RETURN__) in #elements.iter().zip(ATTR_MEMOIZE_FOUND__.iter()) {
+                if ATTR_MEMOIZE_RETURN__.is_some() {
+                    #emit_hit
+                } else {
+                    #emit_miss
+                }
+            }
+        }
+    } else {
+        record_elements
+    };
+    let read_element = memo.read_key(&quote::quote! { ATTR_MEMOIZE_KEY__ });
+    let read_elements = memo.with_store(quote::quote! {
+        #elements
+            .iter()
+            .map(|ATTR_MEMOIZE_KEY__| #read_element)
+            .collect::<Vec<_>>()
+    });
+    let admit_element = admit_key(memo, admit_time, quote::quote! { ATTR_MEMOIZE_KEY__ });
+    let insert_element =
+        memo.insert_entry(&quote::quote! { ATTR_MEMOIZE_KEY__.clone() }, stored_value);
+    let insert_elements = memo.with_store(quote::quote! {
+        #share_cost
+        for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_RETURN__) in
+            ATTR_MEMOIZE_MISSING__.iter().zip(ATTR_MEMOIZE_COMPUTED__.iter())
+        {
+            if #admit_element {
+                #insert_element
+            }
+        }
+    });
+    quote::quote! {
+        let ATTR_MEMOIZE_FOUND__ = #read_elements;
+        let ATTR_MEMOIZE_MISSING__: Vec<_> = #elements
+            .iter()
+            .zip(ATTR_MEMOIZE_FOUND__.iter())
+            .filter(|(_, ATTR_MEMOIZE_RETURN__)| ATTR_MEMOIZE_RETURN__.is_none())
+            .map(|(ATTR_MEMOIZE_KEY__, _)| ATTR_MEMOIZE_KEY__.clone())
+            .collect();
+        #record_elements
+        if ATTR_MEMOIZE_MISSING__.is_empty() {
+            return ATTR_MEMOIZE_FOUND__.into_iter().flatten().collect();
+        }
+
+        #compute_missing
+        assert_eq!(
+            ATTR_MEMOIZE_COMPUTED__.len(),
+            ATTR_MEMOIZE_MISSING__.len(),
+            "memoize: PerElement function must return one value per element",
+        );
+        #insert_elements;
+
+        // Merge cached and computed values, in order.
+        let mut ATTR_MEMOIZE_COMPUTED__ = ATTR_MEMOIZE_COMPUTED__.into_iter();
+        ATTR_MEMOIZE_FOUND__
+            .into_iter()
+            .map(|ATTR_MEMOIZE_RETURN__| match ATTR_MEMOIZE_RETURN__ {
+                Some(ATTR_MEMOIZE_RETURN__) => ATTR_MEMOIZE_RETURN__,
+                None => ATTR_MEMOIZE_COMPUTED__.next().unwrap(),
+            })
+            .collect()
+    }
+}
+
+/// With `CollectStream`, hits replay the collected items; on a miss, the items are collected while
+/// the `original` stream is consumed, and cached by `memoize` once it is exhausted.
+fn stream_memoizer(
+    memo: &Memo,
+    call: &Call,
+    memoize: &proc_macro2::TokenStream,
+    original: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let Memo {
+        options,
+        return_type,
+        ..
+    } = memo;
+    let Call {
+        syntax_names_tuple,
+        syntax_names_tuple_cloned,
+        record_hit,
+        record_miss,
+        read_memo,
+        ..
+    } = call;
+    let read_items = memo.with_store(read_memo.clone());
+    let start = if times_compute(options) {
+        quote::quote! { let ATTR_MEMOIZE_START__ = std::time::Instant::now(); }
+    } else {
+        quote::quote! {}
+    };
+    let cost = if times_compute(options) {
+        quote::quote! { let ATTR_MEMOIZE_COST__ = ATTR_MEMOIZE_START__.elapsed(); }
+    } else {
+        quote::quote! {}
+    };
+    let store_items = memo.with_store(memoize.clone());
+    quote::quote! {
+        let ATTR_MEMOIZE_RETURN__ = #read_items;
+        if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_RETURN__ {
+            #record_hit
+            return ::memoize::futures::future::Either::Left(
+                ::memoize::futures::stream::iter(ATTR_MEMOIZE_RETURN__),
+            );
+        }
+        #record_miss
+
+        let ATTR_MEMOIZE_KEY__ = #syntax_names_tuple_cloned;
+        #start
+        ::memoize::futures::future::Either::Right(::memoize::stream::collect_into(
+            #original,
+            move |ATTR_MEMOIZE_RETURN__: #return_type| {
+                let #syntax_names_tuple = ATTR_MEMOIZE_KEY__;
+                #cost
+                #store_items
+            },
+        ))
+    }
+}
+
+/// With `CollectCoroutine`, hits replay the stored values. On a miss, the `original` coroutine is
+/// run to completion first, so that its values can be stored by `memoize`.
+fn coroutine_memoizer(
+    memo: &Memo,
+    call: &Call,
+    memoize: &proc_macro2::TokenStream,
+    original: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let Memo {
+        options,
+        return_type,
+        ..
+    } = memo;
+    let Call {
+        record_hit,
+        record_miss,
+        read_memo,
+        ..
+    } = call;
+    let read_values = memo.with_store(read_memo.clone());
+    let start = if times_compute(options) {
+        quote::quote! { let ATTR_MEMOIZE_START__ = std::time::Instant::now(); }
+    } else {
+        quote::quote! {}
+    };
+    let cost = if times_compute(options) {
+        quote::quote! { let ATTR_MEMOIZE_COST__ = ATTR_MEMOIZE_START__.elapsed(); }
+    } else {
+        quote::quote! {}
+    };
+    let store_values = memo.with_store(memoize.clone());
+    quote::quote! {
+        let ATTR_MEMOIZE_RETURN__ = #read_values;
+        if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_RETURN__ {
+            #record_hit
+            return ::memoize::coroutine::Replay::new(ATTR_MEMOIZE_RETURN__);
+        }
+        #record_miss
+
+        #start
+        let ATTR_MEMOIZE_RETURN__: #return_type =
+            ::memoize::coroutine::materialize(#original);
+        #cost
+        #store_values;
+        ::memoize::coroutine::Replay::new(ATTR_MEMOIZE_RETURN__)
+    }
+}
+
+/// With `WriteBack`, hits are looked up in the thread-local buffer first, and computed values are
+/// buffered, unless they are computed outside of the rayon pool with `RayonShared` (in which case they
+/// are inserted by `insert`). The buffer is written back once it holds `threshold` entries (or after
+/// `every`).
+#[allow(clippy::too_many_arguments)]
+fn write_back_memoizer(
+    memo: &Memo,
+    call: &Call,
+    compute_return: &proc_macro2::TokenStream,
+    admit_names: &proc_macro2::TokenStream,
+    insert: &proc_macro2::TokenStream,
+    threshold: usize,
+    every: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let options = memo.options;
+    let Call {
+        owned_names_tuple,
+        lookup_key,
+        record_hit,
+        record_miss,
+        read_memo,
+        stored_value,
+        ..
+    } = call;
+    let write_back_ident = memo.static_ident("write_back_buffer");
+    let buffers = if options.rayon_shared {
+        quote::quote! { ::memoize::rayon::current_thread_index().is_some() }
+    } else {
+        quote::quote! { true }
+    };
+    let read_store = memo.with_store(read_memo.clone());
+    let insert_store = memo.with_store(insert.clone());
+    quote::quote! {
+        let ATTR_MEMOIZE_RETURN__ = #write_back_ident.with(|ATTR_MEMOIZE_WB__| {
+            ATTR_MEMOIZE_WB__.borrow_mut().get(&#lookup_key)
+        });
+        if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_RETURN__ {
+            #record_hit
+            return ATTR_MEMOIZE_RETURN__;
+        }
+        if let Some(ATTR_MEMOIZE_RETURN__) = #read_store {
+            #record_hit
+            return ATTR_MEMOIZE_RETURN__;
+        }
+        #record_miss
+        #compute_return
+
+        if #admit_names {
+            if #buffers {
+                #write_back_ident.with(|ATTR_MEMOIZE_WB__| {
+                    ATTR_MEMOIZE_WB__.borrow_mut().insert(
+                        #owned_names_tuple,
+                        #stored_value,
+                        #threshold,
+                        #every,
+                    )
+                });
+            } else {
+                #insert_store;
+            }
+        }
+
+        ATTR_MEMOIZE_RETURN__
+    }
+}
+
+/// Looks up the key, returning a hit. With `ValidateWith`, this is the `validated_hit`.
+fn read_hit(
+    memo: &Memo,
+    call: &Call,
+    validated_hit: Option<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let Call {
+        record_hit,
+        read_memo,
+        read_hashed,
+        ..
+    } = call;
+    let options = memo.options;
+    match validated_hit {
+        Some(validated_hit) => validated_hit,
+        None if options.hash_once => {
+            let read_hashed = memo.with_store(read_hashed.clone());
+            quote::quote! {
+                let (ATTR_MEMOIZE_HASH__, ATTR_MEMOIZE_RETURN__) = #read_hashed;
+                if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_RETURN__ {
+                    #record_hit
+                    return ATTR_MEMOIZE_RETURN__;
+                }
+            }
+        }
+        // A store guarded by a `RwLock` is only read-locked by hits.
+        None if memo.read_locked => {
+            let read_memo = memo.with_store_ref(read_memo.clone());
+            quote::quote! {
+                if let Some(ATTR_MEMOIZE_RETURN__) = #read_memo {
+                    #record_hit
+                    return ATTR_MEMOIZE_RETURN__;
+                }
+            }
+        }
+        None => {
+            let read_memo = memo.with_store(read_memo.clone());
+            quote::quote! {
+                if let Some(ATTR_MEMOIZE_RETURN__) = #read_memo {
+                    #record_hit
+                    return ATTR_MEMOIZE_RETURN__;
+                }
+            }
+        }
+    }
+}
+
+/// With `WriteBack`, the thread-local buffer of new entries, and `memoized_write_back_<fn>()` writing
+/// it back to the store.
+fn write_back_buffer(memo: &Memo) -> proc_macro2::TokenStream {
+    let Memo { vis, memo_type, .. } = memo;
+    let write_back_ident = memo.static_ident("write_back_buffer");
+    let write_back_name = memo.ident("write_back");
+    quote::quote! {
+        std::thread_local! {
+            static #write_back_ident: std::cell::RefCell<
+                ::memoize::memoized::WriteBackBuffer<#memo_type>,
+            > = std::cell::RefCell::new(::memoize::memoized::WriteBackBuffer::new());
+        }
+
+        #vis fn #write_back_name() {
+            #write_back_ident.with(|ATTR_MEMOIZE_WB__| ATTR_MEMOIZE_WB__.borrow_mut().write_back());
+        }
+    }
+}
+
+/// `memoized_flush_<fn>()`, and `memoized_flush_<fn>_async()` for async functions.
+fn flush_helpers(memo: &Memo) -> proc_macro2::TokenStream {
+    let Memo {
+        sig,
+        vis,
+        memo_type,
+        async_store,
+        lock_store,
+        ..
+    } = memo;
+    let async_store = *async_store;
+    let flush_name = memo.ident("flush");
+    let flusher = if async_store {
+        quote::quote! {
+            #vis async fn #flush_name() {
+                <#memo_type as ::memoize::memoized::Memoized>::clear(&mut *#lock_store);
+                ::memoize::memoized::flushed::<#memo_type>();
+            }
+        }
+    } else {
+        quote::quote! {
+            #vis fn #flush_name() {
+                ::memoize::memoized::flush::<#memo_type>();
+            }
+        }
+    };
+    // Async functions can also be flushed by waiting for the computations which are running
+    // meanwhile, so that no value computed before the flush is returned afterwards.
+    let flush_async_name = syn::Ident::new(&format!("{}_async", flush_name), sig.span());
+    if sig.asyncness.is_some() {
+        let inflight_ident = memo.static_ident("inflight");
+        let await_flush = if async_store {
+            quote::quote! { .await }
+        } else {
+            quote::quote! {}
+        };
+        quote::quote! {
+            #flusher
+
+            static #inflight_ident: ::memoize::inflight::InFlight = ::memoize::inflight::InFlight::new();
+
+            #vis async fn #flush_async_name() {
+                let ATTR_MEMOIZE_DRAINED__ = #inflight_ident.flush();
+                #flush_name()#await_flush;
+                ATTR_MEMOIZE_DRAINED__.await;
+            }
+        }
+    } else {
+        flusher
+    }
+}
+
+/// The registrations of `Schedule` and `InvalidateOn` happen once, from the memoized function and
+/// from every helper writing the store, so that entries inserted before the first call are reached
+/// as well. Returns the registering function, and its call.
+fn registration(
+    memo: &Memo,
+    register_scheduled_flush: &proc_macro2::TokenStream,
+    register_subscriber: &proc_macro2::TokenStream,
+    check_flush_generation: &proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let register_name = memo.ident("register");
+    if register_scheduled_flush.is_empty() && register_subscriber.is_empty() {
+        (quote::quote! {}, quote::quote! {})
+    } else {
+        (
+            quote::quote! {
+                fn #register_name() {
+                    static ATTR_MEMOIZE_REGISTERED__: std::sync::Once = std::sync::Once::new();
+                    ATTR_MEMOIZE_REGISTERED__.call_once(|| {
+                        #register_scheduled_flush
+                        #register_subscriber
+                    });
+                    #check_flush_generation
+                }
+            },
+            quote::quote! { #register_name(); },
+        )
+    }
+}
+
+/// With serialized values, `memoized_bytes_<fn>()` measures the memory used by the values.
+fn bytes_helper(memo: &Memo) -> proc_macro2::TokenStream {
+    let Memo {
+        vis, async_helper, ..
+    } = memo;
+    let bytes_name = memo.ident("bytes");
+    let stored_bytes = if uses_entry(memo.options) {
+        quote::quote! { ATTR_MEMOIZE_VALUE__.value.len() }
+    } else {
+        quote::quote! { ATTR_MEMOIZE_VALUE__.len() }
+    };
+    let sum_bytes = memo.with_store(quote::quote! {
+        ATTR_MEMOIZE_HM__
+            .iter()
+            .map(|(_, ATTR_MEMOIZE_VALUE__)| #stored_bytes)
+            .sum()
+    });
+    quote::quote! {
+        #vis #async_helper fn #bytes_name() -> usize {
+            #sum_bytes
+        }
+    }
+}
+
+/// `memoized_size_<fn>()`.
+fn size_helper(memo: &Memo) -> proc_macro2::TokenStream {
+    let Memo {
+        vis,
+        memo_type,
+        lock_store,
+        ..
+    } = memo;
+    let size_name = memo.ident("size");
+    if memo.async_store {
+        quote::quote! {
+            #vis async fn #size_name() -> usize {
+                #lock_store.len()
+            }
+        }
+    } else {
+        quote::quote! {
+            #vis fn #size_name() -> usize {
+                ::memoize::memoized::size::<#memo_type>()
+            }
+        }
+    }
+}
+
+/// `memoized_remove_prefix_<fn>()` removes the entries of keys of several components by their first
+/// component, of type `prefix_type`.
+fn remove_prefix_helper(memo: &Memo, prefix_type: &syn::Type) -> proc_macro2::TokenStream {
+    let Memo {
+        vis,
+        memo_type,
+        async_helper,
+        ..
+    } = memo;
+    let remove_prefix_name = memo.ident("remove_prefix");
+    let remove_prefix = memo.with_store(quote::quote! {
+        ::memoize::memoized::remove_where::<#memo_type>(
+            &mut *ATTR_MEMOIZE_HM__,
+            |ATTR_MEMOIZE_KEY__| ATTR_MEMOIZE_KEY__.0 == *prefix,
+        )
+    });
+    quote::quote! {
+        #vis #async_helper fn #remove_prefix_name(prefix: &#prefix_type) -> usize {
+            #remove_prefix
+        }
+    }
+}
+
+/// With `EntryInfo`, `memoized_entry_info_<fn>()` looks up the metadata of an entry by the arguments.
+fn entry_info_helper(
+    memo: &Memo,
+    call: &Call,
+    input_params: &[FnArgument],
+) -> proc_macro2::TokenStream {
+    let Memo {
+        vis,
+        get_fn,
+        async_helper,
+        ..
+    } = memo;
+    let lookup_key = &call.lookup_key;
+    let entry_info_name = memo.ident("entry_info");
+    let params: Vec<_> = input_params
+        .iter()
+        .map(|p| {
+            let (name, ty) = (&p.arg_name, &p.arg_type);
+            quote::quote! { #name: #ty }
+        })
+        .collect();
+    let read_info = memo.with_store(quote::quote! {
+        ATTR_MEMOIZE_HM__
+            .#get_fn(&#lookup_key)
+            .map(|ATTR_MEMOIZE_ENTRY__| ATTR_MEMOIZE_ENTRY__.info())
+    });
+    quote::quote! {
+        #vis #async_helper fn #entry_info_name(#(#params),*) -> Option<::memoize::EntryInfo> {
+            #read_info
+        }
+    }
+}
+
+/// The glue between the function and the generic functions of `memoize::memoized`, implementing
+/// `Memoized` for the store of type `cache_type` (unless declared with `UseStore`). `stored_value` is
+/// the value stored for `ATTR_MEMOIZE_RETURN__`.
+fn memo_impl(
+    memo: &Memo,
+    cache_type: &proc_macro2::TokenStream,
+    stored_value: &proc_macro2::TokenStream,
+    hooks: Vec<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let Memo {
+        options,
+        sig,
+        memo_type,
+        store_ident,
+        input_tuple_type,
+        return_type,
+        stored_type,
+        decode_value,
+        ..
+    } = memo;
+    let fn_name = sig.ident.to_string();
+    let store_type = if options.use_store.is_some() {
+        quote::quote! { dyn ::memoize::CacheStore<#input_tuple_type, #stored_type> }
+    } else {
+        cache_type.clone()
+    };
+    // A store declared with `UseStore` is accessed as a `dyn CacheStore`.
+    let (access, access_ref) = if options.use_store.is_some() {
+        (
+            quote::quote! { #store_ident.with_store(|ATTR_MEMOIZE_HM__| f(ATTR_MEMOIZE_HM__)) },
+            quote::quote! { #store_ident.with_store_ref(|ATTR_MEMOIZE_HM__| f(ATTR_MEMOIZE_HM__)) },
+        )
+    } else {
+        (
+            quote::quote! { #store_ident.with_store(f) },
+            quote::quote! { #store_ident.with_store_ref(f) },
+        )
+    };
+    // Borrowed keys are never deserialized.
+    let decode_key = if borrows(input_tuple_type) {
+        quote::quote! { None }
+    } else {
+        quote::quote! {
+            use ::memoize::control::{DecodeKey as _, SkipKey as _};
+            (&::memoize::control::KeyDecoder::<#input_tuple_type>::new()).decode(key)
+        }
+    };
+    let stored_ref = if uses_entry(options) {
+        quote::quote! { &ATTR_MEMOIZE_STORED__.value }
+    } else {
+        quote::quote! { ATTR_MEMOIZE_STORED__ }
+    };
+    let value_ref = if options.store_with.is_some() {
+        quote::quote! { &#decode_value }
+    } else {
+        quote::quote! { ATTR_MEMOIZE_RETURN__ }
+    };
+    // Nothing is known about the cost of values which weren't computed by the function.
+    let cost = if measures_cost(options) {
+        quote::quote! { let ATTR_MEMOIZE_COST__ = std::time::Duration::ZERO; }
+    } else {
+        quote::quote! {}
+    };
+    quote::quote! {
+        #[allow(non_camel_case_types)]
+        enum #memo_type {}
+
+        #[allow(unused_variables)]
+        impl ::memoize::memoized::Memoized for #memo_type {
+            type Key = #input_tuple_type;
+            type Value = #return_type;
+            type Stored = #stored_type;
+            type Store = #store_type;
+
+            const NAME: &'static str = #fn_name;
+
+            fn stats() -> &'static ::memoize::StatsRegistry {
+                static ATTR_MEMOIZE_STATS__: ::memoize::StatsRegistry = ::memoize::StatsRegistry::new();
+                &ATTR_MEMOIZE_STATS__
+            }
+
+            fn with_store<R>(f: impl FnOnce(&mut Self::Store) -> R) -> R {
+                use ::memoize::memoized::StoreCell as _;
+                #access
+            }
+
+            fn with_store_ref<R>(f: impl FnOnce(&Self::Store) -> R) -> R {
+                use ::memoize::memoized::StoreCell as _;
+                #access_ref
+            }
+
+            fn decode_key(key: &[u8]) -> Option<Self::Key> {
+                #decode_key
+            }
+
+            fn load(ATTR_MEMOIZE_STORED__: &Self::Stored) -> Self::Value {
+                let ATTR_MEMOIZE_RETURN__ = #stored_ref;
+                #decode_value
+            }
+
+            fn inspect<R>(ATTR_MEMOIZE_STORED__: &Self::Stored, f: impl FnOnce(&Self::Value) -> R) -> R {
+                let ATTR_MEMOIZE_RETURN__ = #stored_ref;
+                f(#value_ref)
+            }
+
+            fn store(ATTR_MEMOIZE_RETURN__: Self::Value) -> Self::Stored {
+                #cost
+                #stored_value
+            }
+
+            #(#hooks)*
+        }
+    }
+}
+
+/// The hooks of `Memoized` which are only implemented by functions with options that need them:
+/// `update_index` and `adjust_capacity` are run before and after an insertion, and `reset_warm` after
+/// a flush.
+fn memo_hooks(
+    memo: &Memo,
+    update_index: &proc_macro2::TokenStream,
+    adjust_capacity: &proc_macro2::TokenStream,
+    reset_warm: &proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    let Memo {
+        options,
+        sig,
+        store_ident,
+        ..
+    } = memo;
+    let mut hooks = vec![];
+    if options.instrument {
+        hooks.push(quote::quote! {
+            const INSTRUMENT: bool = true;
+
+            fn key_hash(key: &Self::Key) -> Option<u64> {
+                Some(::memoize::events::key_hash(key))
+            }
+        });
+    }
+    if options.lru_max_entries.is_some() {
+        hooks.push(quote::quote! {
+            const BOUNDED: bool = true;
+
+            fn contains(store: &Self::Store, key: &Self::Key) -> bool {
+                store.contains(key)
+            }
+        });
+    }
+    if options.auto_capacity.is_some() || cfg!(feature = "opentelemetry") {
+        hooks.push(quote::quote! { const TRACK_EVICTIONS: bool = true; });
+    }
+    if uses_entry(options) {
+        hooks.push(quote::quote! {
+            fn is_fresh(stored: &Self::Stored) -> bool {
+                ::memoize::memoized::Expiring::is_fresh::<Self>(stored)
+            }
+
+            fn touch(stored: &Self::Stored) {
+                ::memoize::memoized::Expiring::touch::<Self>(stored)
+            }
+        });
+    }
+    // Entries are checked by `is_fresh_at()` of `memoize::Entry` or `memoize::CompactEntry`, given
+    // the durations of the options.
+    let expiry = [
+        ("time_to_live", &options.time_to_live),
+        ("time_to_idle", &options.expire_after_access),
+        ("max_refresh_rate", &options.max_refresh_rate),
+    ]
+    .iter()
+    .filter_map(|&(field, duration)| {
+        let field = syn::Ident::new(field, sig.span());
+        let duration = duration.as_ref()?;
+        Some(quote::quote! { #field: Some(#duration), })
+    })
+    .chain(
+        options
+            .coarse_clock
+            .then(|| quote::quote! { coarse_clock: true, }),
+    )
+    .collect::<Vec<_>>();
+    if !expiry.is_empty() {
+        hooks.push(quote::quote! {
+            fn expiry() -> ::memoize::memoized::Expiry {
+                ::memoize::memoized::Expiry {
+                    #(#expiry)*
+                    ..::memoize::memoized::Expiry::NEVER
+                }
+            }
+        });
+    }
+    // The epoch of the timestamps of `CompactEntries`, one per store.
+    if options.compact_entries {
+        let epoch = if options.shared_cache {
+            quote::quote! {
+                static ATTR_MEMOIZE_EPOCH__: ::memoize::compact::Epoch = ::memoize::compact::Epoch::new();
+                Some(f(&ATTR_MEMOIZE_EPOCH__))
+            }
+        } else {
+            quote::quote! {
+                std::thread_local! {
+                    static ATTR_MEMOIZE_EPOCH__: ::memoize::compact::Epoch =
+                        const { ::memoize::compact::Epoch::new() };
+                }
+                Some(ATTR_MEMOIZE_EPOCH__.with(f))
+            }
+        };
+        hooks.push(quote::quote! {
+            fn with_epoch<R>(f: impl FnOnce(&::memoize::compact::Epoch) -> R) -> Option<R> {
+                #epoch
+            }
+        });
+    }
+    if options.auto_capacity.is_some() {
+        let record_hit =
+            memo.with_auto_capacity(quote::quote! { ATTR_MEMOIZE_AUTO__.record_hit() });
+        hooks.push(quote::quote! {
+            fn hit() {
+                #record_hit
+            }
+        });
+    }
+    if !update_index.is_empty() {
+        hooks.push(quote::quote! {
+            fn inserting(
+                ATTR_MEMOIZE_HM__: &mut Self::Store,
+                ATTR_MEMOIZE_INSERTED__: &Self::Key,
+                ATTR_MEMOIZE_STORED__: &Self::Stored,
+            ) {
+                #update_index
+            }
+        });
+    }
+    if !adjust_capacity.is_empty() {
+        hooks.push(quote::quote! {
+            fn inserted(ATTR_MEMOIZE_HM__: &mut Self::Store, ATTR_MEMOIZE_EVICTED__: bool) {
+                #adjust_capacity
+            }
+        });
+    }
+    if let Some(negative) = &options.negative_filter {
+        let capacity = options.negative_filter_capacity.unwrap_or(100_000);
+        hooks.push(quote::quote! {
+            fn negative_filter() -> Option<&'static ::std::sync::RwLock<::memoize::NegativeFilter>> {
+                static ATTR_MEMOIZE_FILTER__: ::std::sync::RwLock<::memoize::NegativeFilter> =
+                    ::std::sync::RwLock::new(::memoize::NegativeFilter::new(#capacity));
+                Some(&ATTR_MEMOIZE_FILTER__)
+            }
+
+            fn is_negative(ATTR_MEMOIZE_RETURN__: &Self::Value) -> bool {
+                *ATTR_MEMOIZE_RETURN__ == #negative
+            }
+        });
+    }
+    // Buffers filled before a flush are discarded instead of being written back.
+    if options.write_back.is_some() {
+        let try_access = if options.use_store.is_some() {
+            quote::quote! { #store_ident.try_with_store(|ATTR_MEMOIZE_HM__| f(ATTR_MEMOIZE_HM__)) }
+        } else {
+            quote::quote! { #store_ident.try_with_store(f) }
+        };
+        hooks.push(quote::quote! {
+            fn try_with_store<R>(f: impl FnOnce(&mut Self::Store) -> R) -> Option<R> {
+                use ::memoize::memoized::StoreCell as _;
+                #try_access
+            }
+
+            fn write_back_generation() -> Option<&'static std::sync::atomic::AtomicUsize> {
+                static ATTR_MEMOIZE_GENERATION__: std::sync::atomic::AtomicUsize =
+                    std::sync::atomic::AtomicUsize::new(0);
+                Some(&ATTR_MEMOIZE_GENERATION__)
+            }
+        });
+    }
+    if !reset_warm.is_empty() {
+        hooks.push(quote::quote! {
+            fn flushed() {
+                #reset_warm
+            }
+        });
+    }
+    hooks
+}
+
+/// A memoized function, as seen by the code generated for its options: the names and types of its
+/// store, and how to reach it.
+struct Memo<'a> {
+    options: &'a CacheOptions,
+    sig: &'a syn::Signature,
+    vis: &'a syn::Visibility,
+    /// The type implementing `memoize::memoized::Memoized` for the function.
+    memo_type: &'a syn::Ident,
+    store_ident: &'a Path,
+    input_tuple_type: &'a proc_macro2::TokenStream,
+    return_type: &'a proc_macro2::TokenStream,
+    /// The output of the original future, with `SharedFuture`.
+    future_output: &'a proc_macro2::TokenStream,
+    /// The type of the stored values, e.g. `memoize::Entry<V>`.
+    stored_type: &'a proc_macro2::TokenStream,
+    /// Evaluates to the returned value, given a reference to the encoded value in
+    /// `ATTR_MEMOIZE_RETURN__`.
+    decode_value: &'a proc_macro2::TokenStream,
+    get_fn: &'a proc_macro2::TokenStream,
+    remove_fn: &'a proc_macro2::TokenStream,
+    /// Whether the original function is awaited.
+    is_async: bool,
+    /// Whether the store is guarded by an async mutex, which is locked by awaiting `lock_store`.
+    async_store: bool,
+    /// Whether hits only take the read lock of the store.
+    read_locked: bool,
+    lock_store: &'a proc_macro2::TokenStream,
+    /// `write` for a store guarded by a `RwLock`, `lock` otherwise.
+    lock_method: &'a proc_macro2::TokenStream,
+    /// `async` for helpers locking an async store.
+    async_helper: &'a proc_macro2::TokenStream,
+}
+
+impl Memo<'_> {
+    /// The name of a generated item, `memoized_<what>_<function name>`.
+    fn ident(&self, what: &str) -> syn::Ident {
+        syn::Ident::new(
+            &format!("memoized_{}_{}", what, self.sig.ident),
+            self.sig.span(),
+        )
+    }
+
+    /// The name of a generated static, `MEMOIZED_<WHAT>_<FUNCTION NAME>`.
+    fn static_ident(&self, what: &str) -> syn::Ident {
+        syn::Ident::new(
+            &format!("memoized_{}_{}", what, self.sig.ident).to_uppercase(),
+            self.sig.span(),
+        )
+    }
+
+    /// Evaluates `body` with `ATTR_MEMOIZE_HM__` bound to the (locked or borrowed) store. The store
+    /// of an async function is locked by awaiting, and thus in the async function itself.
+    fn with_store(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let (memo_type, lock_store) = (self.memo_type, self.lock_store);
+        if self.async_store {
+            quote::quote! {{
+                let mut ATTR_MEMOIZE_HM__ = #lock_store;
+                #body
+            }}
+        } else {
+            quote::quote! {
+                <#memo_type as ::memoize::memoized::Memoized>::with_store(|ATTR_MEMOIZE_HM__| { #body })
+            }
+        }
+    }
+
+    /// ... for reading only, so that a thread-local store is only borrowed immutably.
+    fn with_store_ref(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let (memo_type, lock_store) = (self.memo_type, self.lock_store);
+        if self.async_store {
+            quote::quote! {{
+                let ATTR_MEMOIZE_HM__ = #lock_store;
+                #body
+            }}
+        } else {
+            quote::quote! {
+                <#memo_type as ::memoize::memoized::Memoized>::with_store_ref(|ATTR_MEMOIZE_HM__| { #body })
+            }
+        }
+    }
+
+    /// Evaluates `body` with `ATTR_MEMOIZE_AUTO__` bound to the `AutoCapacity` statistics of the
+    /// store. Empty without `AutoCapacity`.
+    fn with_auto_capacity(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let ident = self.static_ident("auto_capacity");
+        if self.options.auto_capacity.is_none() {
+            quote::quote! {}
+        } else if self.options.shared_cache {
+            quote::quote! {{
+                let ATTR_MEMOIZE_AUTO__ = &#ident;
+                #body
+            }}
+        } else {
+            quote::quote! {
+                #ident.with(|ATTR_MEMOIZE_AUTO__| #body)
+            }
+        }
+    }
+
+    /// Evaluates `body` with `ATTR_MEMOIZE_INDEX__` bound to the (locked or borrowed) reverse index
+    /// of `IndexBy`, which maps the secondary keys of the entries to their keys. It is kept next to
+    /// the store, and locked (or borrowed) after it.
+    fn with_index(&self, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let ident = self.static_ident("index");
+        if self.options.shared_cache {
+            quote::quote! {{
+                let mut ATTR_MEMOIZE_INDEX__ = #ident.lock().unwrap();
+                #body
+            }}
+        } else {
+            quote::quote! {
+                #ident.with(|ATTR_MEMOIZE_INDEX__| {
+                    let mut ATTR_MEMOIZE_INDEX__ = ATTR_MEMOIZE_INDEX__.borrow_mut();
+                    #body
+                })
+            }
+        }
+    }
+
+    /// The secondary key of the entry of `key`, given a reference to its stored value. `None`
+    /// without `IndexBy`.
+    fn index_of(
+        &self,
+        key: proc_macro2::TokenStream,
+        stored: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let (index_by, index_type) = self.options.index_by.as_ref()?;
+        let (input_tuple_type, return_type, decode_value) =
+            (self.input_tuple_type, self.return_type, self.decode_value);
+        let value = if uses_entry(self.options) {
+            quote::quote! { &(#stored).value }
+        } else {
+            stored
+        };
+        Some(quote::quote! {{
+            let ATTR_MEMOIZE_RETURN__ = #value;
+            let ATTR_MEMOIZE_RETURN__: #return_type = #decode_value;
+            let ATTR_MEMOIZE_INDEX_BY__: fn(&#input_tuple_type, &#return_type) -> #index_type = #index_by;
+            ATTR_MEMOIZE_INDEX_BY__(#key, &ATTR_MEMOIZE_RETURN__)
+        }})
+    }
+
+    /// Evaluates to an `Option` of the cached value, given the lookup `get` of its entry in
+    /// `ATTR_MEMOIZE_HM__`.
+    fn read(&self, get: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let memo_type = self.memo_type;
+        quote::quote! { ::memoize::memoized::read::<#memo_type>(#get) }
+    }
+
+    /// Looks up `key` in `ATTR_MEMOIZE_HM__`, evaluating to an `Option` of the cached value.
+    fn read_key(&self, key: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let get_fn = self.get_fn;
+        self.read(quote::quote! { ATTR_MEMOIZE_HM__.#get_fn(&#key) })
+    }
+
+    /// Locks the shared store from synchronous code, e.g. from another thread. `poisoned` is
+    /// evaluated if its mutex is poisoned.
+    fn lock_blocking(&self, poisoned: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let (store_ident, lock_method) = (self.store_ident, self.lock_method);
+        if self.async_store {
+            quote::quote! { ::memoize::AsyncLock::lock_blocking(&*#store_ident) }
+        } else {
+            quote::quote! {
+                match #store_ident.#lock_method() {
+                    Ok(ATTR_MEMOIZE_HM__) => ATTR_MEMOIZE_HM__,
+                    Err(_) => #poisoned,
+                }
+            }
+        }
+    }
+
+    /// Flushes the shared store from synchronous code.
+    fn flush_blocking(&self) -> proc_macro2::TokenStream {
+        let memo_type = self.memo_type;
+        if self.async_store {
+            let lock = self.lock_blocking(quote::quote! {});
+            quote::quote! {
+                <#memo_type as ::memoize::memoized::Memoized>::clear(&mut *#lock);
+                ::memoize::memoized::flushed::<#memo_type>();
+            }
+        } else {
+            let flush_name = self.ident("flush");
+            quote::quote! { #flush_name(); }
+        }
+    }
+
+    /// With `Instrument`, sends a cache event to the subscribers of `memoize::subscribe_events()`.
+    fn emit_event(
+        &self,
+        kind: &str,
+        key: Option<proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        if !self.options.instrument {
+            return quote::quote! {};
+        }
+        let fn_name = self.sig.ident.to_string();
+        let kind = syn::Ident::new(kind, self.sig.span());
+        let key_hash = match key {
+            None => quote::quote! { None },
+            Some(key) => quote::quote! { Some(::memoize::events::key_hash(&#key)) },
+        };
+        quote::quote! {
+            if ::memoize::events::enabled() {
+                ::memoize::events::emit(#fn_name, ::memoize::CacheEventKind::#kind, #key_hash);
+            }
+        }
+    }
+
+    fn record_stats(
+        &self,
+        hits: proc_macro2::TokenStream,
+        misses: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let memo_type = self.memo_type;
+        quote::quote! {
+            ::memoize::memoized::record::<#memo_type>((#hits) as u64, (#misses) as u64);
+        }
+    }
+
+    /// Inserts `value` for `key` into `ATTR_MEMOIZE_HM__`. With `HashOnce`, the wrapper passes the
+    /// `hash` of the key it computed for the lookup.
+    fn insert_hashed_entry(
+        &self,
+        key: &proc_macro2::TokenStream,
+        value: &proc_macro2::TokenStream,
+        hash: Option<&proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        let memo_type = self.memo_type;
+        let insert = match hash {
+            Some(hash) => quote::quote! {
+                ::memoize::memoized::insert_with::<#memo_type>(
+                    &mut *ATTR_MEMOIZE_HM__,
+                    ATTR_MEMOIZE_INSERTED__,
+                    ATTR_MEMOIZE_STORED__,
+                    |ATTR_MEMOIZE_HM__, ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_STORED__| {
+                        ATTR_MEMOIZE_HM__.insert_hashed(#hash, ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_STORED__)
+                    },
+                );
+            },
+            None => quote::quote! {
+                ::memoize::memoized::insert::<#memo_type>(
+                    &mut *ATTR_MEMOIZE_HM__,
+                    ATTR_MEMOIZE_INSERTED__,
+                    ATTR_MEMOIZE_STORED__,
+                );
+            },
+        };
+        quote::quote! {
+            let ATTR_MEMOIZE_INSERTED__ = #key;
+            let ATTR_MEMOIZE_STORED__ = #value;
+            #insert
+        }
+    }
+
+    fn insert_entry(
+        &self,
+        key: &proc_macro2::TokenStream,
+        value: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        self.insert_hashed_entry(key, value, None)
+    }
+    /// Inserts `(key, value)` pairs which weren't computed by the function, e.g. seeded or imported.
+    fn insert_values(&self, entries: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let memo_type = self.memo_type;
+        if self.async_store {
+            self.with_store(quote::quote! {
+                for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_RETURN__) in #entries {
+                    ::memoize::memoized::insert::<#memo_type>(
+                        &mut *ATTR_MEMOIZE_HM__,
+                        ATTR_MEMOIZE_KEY__,
+                        <#memo_type as ::memoize::memoized::Memoized>::store(ATTR_MEMOIZE_RETURN__),
+                    );
+                }
+            })
+        } else {
+            quote::quote! { ::memoize::memoized::insert_values::<#memo_type>(#entries) }
+        }
+    }
+}
+
+/// The code of the memoizing wrapper which is shared by its options. It is only valid within the
+/// wrapper, where the memoized arguments are in scope.
+struct Call {
+    /// The key tuple of the memoized arguments, which is also a pattern binding them.
+    syntax_names_tuple: proc_macro2::TokenStream,
+    /// References to the memoized arguments.
+    names_refs_tuple: proc_macro2::TokenStream,
+    /// The key tuple, cloning the arguments (unless the key is small).
+    syntax_names_tuple_cloned: proc_macro2::TokenStream,
+    /// The key tuple, owning slices (which are borrowed by the wrapper).
+    owned_names_tuple: proc_macro2::TokenStream,
+    /// The key looked up by the wrapper.
+    lookup_key: proc_macro2::TokenStream,
+    record_hit: proc_macro2::TokenStream,
+    record_miss: proc_macro2::TokenStream,
+    /// Looks up the key in `ATTR_MEMOIZE_HM__`, evaluating to an `Option` of the cached value.
+    read_memo: proc_macro2::TokenStream,
+    /// With `HashOnce`, the lookup evaluates to the hash of the key along with the value.
+    read_hashed: proc_macro2::TokenStream,
+    /// The value stored for `ATTR_MEMOIZE_RETURN__`.
+    stored_value: proc_macro2::TokenStream,
+}
+
+/// How generated functions compute the value of a missing key, with the memoized arguments bound by
+/// the pattern `names`.
+struct Miss<'a> {
+    names: &'a proc_macro2::TokenStream,
+    /// References to the memoized arguments.
+    names_refs: &'a proc_macro2::TokenStream,
+    /// Binds `ATTR_MEMOIZE_RETURN__` to the computed value.
+    compute_return: &'a proc_macro2::TokenStream,
+    /// Whether the computed value is cached.
+    admit: &'a proc_macro2::TokenStream,
+    /// The stored representation of `ATTR_MEMOIZE_RETURN__`.
+    stored_value: &'a proc_macro2::TokenStream,
+    /// Registers the function before its store is first written.
+    register: &'a proc_macro2::TokenStream,
+}
+
+/// With the `schedule` feature, a function registers a flusher for `memoize::schedule_flush()`
+/// before its store is first written. The shared store is flushed from the scheduler thread, while
+/// thread-local stores are flushed on their next access once the flush generation has changed.
+///
+/// Returns the flusher, its registration, and the check of the flush generation.
+fn scheduled_flusher(
+    memo: &Memo,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    if !cfg!(feature = "schedule") {
+        return (quote::quote! {}, quote::quote! {}, quote::quote! {});
+    }
+    let scheduled_flush_name = memo.ident("flush_scheduled");
+    let flush_generation_ident = memo.static_ident("flush_generation");
+    let flush_seen_ident = memo.static_ident("flush_seen");
+    let flush_name = memo.ident("flush");
+    let fn_name_str = memo.sig.ident.to_string();
+    let scheduled_flush = if !memo.options.shared_cache {
+        quote::quote! {
+            static #flush_generation_ident: std::sync::atomic::AtomicUsize =
+                std::sync::atomic::AtomicUsize::new(0);
+            std::thread_local! {
+                static #flush_seen_ident: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+            }
+
+            fn #scheduled_flush_name() {
+                #flush_generation_ident.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+    } else {
+        let flush = memo.flush_blocking();
+        quote::quote! {
+            fn #scheduled_flush_name() {
+                #flush
+            }
+        }
+    };
+    let register = quote::quote! {
+        ::memoize::schedule::register_flusher(
+            concat!(module_path!(), "::", #fn_name_str),
+            #scheduled_flush_name,
+        );
+    };
+    let check_generation = if memo.options.shared_cache {
+        quote::quote! {}
+    } else {
+        quote::quote! {
+            let ATTR_MEMOIZE_GENERATION__ =
+                #flush_generation_ident.load(std::sync::atomic::Ordering::SeqCst);
+            if #flush_seen_ident
+                .with(|ATTR_MEMOIZE_SEEN__| ATTR_MEMOIZE_SEEN__.replace(ATTR_MEMOIZE_GENERATION__))
+                != ATTR_MEMOIZE_GENERATION__
+            {
+                #flush_name();
+            }
+        }
     };
+    (scheduled_flush, register, check_generation)
+}
 
-    // Input types and names that are actually stored in the cache.
-    let memoized_input_types: Vec<Box<syn::Type>> = input_params
-        .iter()
-        .filter_map(|p| {
-            if p.is_memoized {
-                Some(p.arg_type.clone())
-            } else {
-                None
-            }
-        })
-        .collect();
-    let memoized_input_names: Vec<syn::Ident> = input_params
-        .iter()
-        .filter_map(|p| {
-            if p.is_memoized {
-                Some(p.arg_name.clone())
-            } else {
-                None
+/// With `Subscribe`, a function registers functions flushing its cache and removing a serialized key
+/// before its store is first written, which apply the invalidations published to the bus.
+///
+/// Returns the functions and their registration.
+fn subscribed_invalidation(
+    memo: &Memo,
+) -> Result<(proc_macro2::TokenStream, proc_macro2::TokenStream), syn::Error> {
+    if !memo.options.subscribe {
+        return Ok((quote::quote! {}, quote::quote! {}));
+    }
+    if !memo.options.shared_cache {
+        return Err(syn::Error::new(
+            memo.sig.span(),
+            "Subscribe requires SharedCache",
+        ));
+    }
+    let subscribed_flush_name = memo.ident("flush_subscribed");
+    let subscribed_remove_name = memo.ident("remove_subscribed");
+    let fn_name_str = memo.sig.ident.to_string();
+    let (input_tuple_type, remove_fn) = (memo.input_tuple_type, memo.remove_fn);
+    let flush = memo.flush_blocking();
+    let lock = memo.lock_blocking(quote::quote! { return });
+    Ok((
+        quote::quote! {
+            fn #subscribed_flush_name() {
+                #flush
             }
-        })
-        .collect();
 
-    // For each input, expression to be passe through to the original function.
-    // Cached arguments are cloned, original arguments are forwarded as-is
-    let fn_forwarded_exprs: Vec<_> = input_params
-        .iter()
-        .map(|p| {
-            let ident = p.arg_name.clone();
-            if p.is_memoized {
-                quote::quote! { #ident.clone() }
-            } else {
-                quote::quote! { #ident }
+            fn #subscribed_remove_name(key: &[u8]) {
+                if let Some(ATTR_MEMOIZE_KEY__) = ::memoize::bus::decode_key::<#input_tuple_type>(key) {
+                    let mut ATTR_MEMOIZE_HM__ = #lock;
+                    ATTR_MEMOIZE_HM__.#remove_fn(&ATTR_MEMOIZE_KEY__);
+                }
             }
-        })
-        .collect();
-
-    let input_tuple_type = quote::quote! { (#(#memoized_input_types),*) };
-    let return_type = match &sig.output {
-        syn::ReturnType::Default => quote::quote! { () },
-        syn::ReturnType::Type(_, ty) => ty.to_token_stream(),
-    };
+        },
+        quote::quote! {
+            ::memoize::bus::register_subscriber(
+                concat!(module_path!(), "::", #fn_name_str),
+                #subscribed_flush_name,
+                #subscribed_remove_name,
+            );
+        },
+    ))
+}
 
-    // Construct storage for the memoized keys and return values.
-    let store_ident = syn::Ident::new(&map_name.to_uppercase(), sig.span());
-    let (cache_type, cache_init) =
-        store::construct_cache(&options, input_tuple_type, return_type.clone());
-    let store = if options.shared_cache {
+/// With the `jemalloc` or `mimalloc` feature, shared caches register a function evicting a fraction
+/// of their entries, which is called when the process uses too much memory.
+///
+/// Returns the function and its registration.
+fn memory_shrinker(memo: &Memo) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let options = memo.options;
+    if !(cfg!(feature = "jemalloc") || cfg!(feature = "mimalloc"))
+        || !options.shared_cache
+        || options.use_store.is_some()
+    {
+        return (quote::quote! {}, quote::quote! {});
+    }
+    let shrink_name = memo.ident("shrink");
+    let fn_name_str = memo.sig.ident.to_string();
+    let (input_tuple_type, remove_fn) = (memo.input_tuple_type, memo.remove_fn);
+    let lock = memo.lock_blocking(quote::quote! { return 0 });
+    let evict = if cfg!(feature = "full")
+        && options.lru_max_entries.is_some()
+        && options.priority.is_none()
+        && options.eviction.is_none()
+        && options.partition_by.is_none()
+    {
         quote::quote! {
-            ::memoize::lazy_static::lazy_static! {
-                static ref #store_ident : std::sync::Mutex<#cache_type> =
-                    std::sync::Mutex::new(#cache_init);
+            for _ in 0..ATTR_MEMOIZE_N__ {
+                ATTR_MEMOIZE_HM__.pop_lru();
             }
         }
     } else {
         quote::quote! {
-            std::thread_local! {
-                static #store_ident : std::cell::RefCell<#cache_type> =
-                    std::cell::RefCell::new(#cache_init);
+            let ATTR_MEMOIZE_KEYS__: Vec<#input_tuple_type> = ATTR_MEMOIZE_HM__
+                .iter()
+                .take(ATTR_MEMOIZE_N__)
+                .map(|(ATTR_MEMOIZE_KEY__, _)| ATTR_MEMOIZE_KEY__.clone())
+                .collect();
+            for ATTR_MEMOIZE_KEY__ in &ATTR_MEMOIZE_KEYS__ {
+                ATTR_MEMOIZE_HM__.#remove_fn(ATTR_MEMOIZE_KEY__);
             }
         }
     };
+    (
+        quote::quote! {
+            fn #shrink_name(fraction: f64) -> usize {
+                let mut ATTR_MEMOIZE_HM__ = #lock;
+                let ATTR_MEMOIZE_N__ = (ATTR_MEMOIZE_HM__.len() as f64 * fraction).ceil() as usize;
+                #evict
+                ATTR_MEMOIZE_N__
+            }
+        },
+        quote::quote! {
+            static ATTR_MEMOIZE_SHRINK_REGISTERED__: std::sync::Once = std::sync::Once::new();
+            ATTR_MEMOIZE_SHRINK_REGISTERED__.call_once(|| {
+                ::memoize::memory::register_shrinker(
+                    concat!(module_path!(), "::", #fn_name_str),
+                    #shrink_name,
+                );
+            });
+        },
+    )
+}
 
-    // Rename original function.
-    let mut renamed_fn = func.clone();
-    renamed_fn.sig.ident = syn::Ident::new(&renamed_name, func.sig.span());
-    let memoized_id = &renamed_fn.sig.ident;
-
-    // Construct memoizer function, which calls the original function.
-    let syntax_names_tuple = quote::quote! { (#(#memoized_input_names),*) };
-    let syntax_names_tuple_cloned = quote::quote! { (#(#memoized_input_names.clone()),*) };
-    let forwarding_tuple = quote::quote! { (#(#fn_forwarded_exprs),*) };
-    let (insert_fn, get_fn) = store::cache_access_methods(&options);
-    let (read_memo, memoize) = match options.time_to_live {
-        None => (
-            quote::quote!(ATTR_MEMOIZE_HM__.#get_fn(&#syntax_names_tuple_cloned).cloned()),
-            quote::quote!(ATTR_MEMOIZE_HM__.#insert_fn(#syntax_names_tuple, ATTR_MEMOIZE_RETURN__.clone());),
-        ),
-        Some(ttl) => (
+/// With `FlushEvery`, calls check whether the period has passed, and flush the whole cache if so.
+/// Thread-local caches keep their own timer per thread.
+///
+/// Returns the timer and the check.
+fn periodic_flush(memo: &Memo) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let every = match &memo.options.flush_every {
+        None => return (quote::quote! {}, quote::quote! {}),
+        Some(every) => every,
+    };
+    let flush_timer_ident = memo.static_ident("flush_timer");
+    let flush_name = memo.ident("flush");
+    let flush_call = if memo.async_store {
+        quote::quote! { #flush_name().await }
+    } else {
+        quote::quote! { #flush_name() }
+    };
+    if memo.options.shared_cache {
+        (
             quote::quote! {
-                ATTR_MEMOIZE_HM__.#get_fn(&#syntax_names_tuple_cloned).and_then(|(last_updated, ATTR_MEMOIZE_RETURN__)|
-                    (last_updated.elapsed() < #ttl).then(|| ATTR_MEMOIZE_RETURN__.clone())
-                )
+                static #flush_timer_ident: ::memoize::schedule::FlushTimer =
+                    ::memoize::schedule::FlushTimer::new();
             },
-            quote::quote!(ATTR_MEMOIZE_HM__.#insert_fn(#syntax_names_tuple, (std::time::Instant::now(), ATTR_MEMOIZE_RETURN__.clone()));),
-        ),
+            quote::quote! {
+                if #flush_timer_ident.due(#every) {
+                    #flush_call;
+                }
+            },
+        )
+    } else {
+        (
+            quote::quote! {
+                thread_local! {
+                    static #flush_timer_ident: ::memoize::schedule::FlushTimer =
+                        const { ::memoize::schedule::FlushTimer::new() };
+                }
+            },
+            quote::quote! {
+                if #flush_timer_ident.with(|ATTR_MEMOIZE_TIMER__| ATTR_MEMOIZE_TIMER__.due(#every)) {
+                    #flush_call;
+                }
+            },
+        )
+    }
+}
+
+/// `memoized_warm_<fn>()`, which computes and inserts all given inputs, tuples of all arguments.
+/// With the `rayon` feature, the inputs of a shared cache are computed in parallel.
+fn bulk_warm(memo: &Memo, input_params: &[FnArgument]) -> proc_macro2::TokenStream {
+    let warm_name = memo.ident("warm");
+    let vis = memo.vis;
+    // With `SharedFuture`, warming inserts the futures without awaiting them.
+    let fn_ident = if memo.options.shared_future {
+        memo.ident("shared")
+    } else {
+        memo.sig.ident.clone()
+    };
+    let (async_fn, await_call) = if memo.is_async {
+        (quote::quote! { async }, quote::quote! { .await })
+    } else {
+        (quote::quote! {}, quote::quote! {})
+    };
+    let all_types: Vec<_> = input_params.iter().map(|p| &p.arg_type).collect();
+    let all_names: Vec<_> = input_params.iter().map(|p| &p.arg_name).collect();
+    let item_type = quote::quote! { (#(#all_types),*) };
+    let sequential = quote::quote! {
+        for (#(#all_names),*) in inputs {
+            let _ = #fn_ident(#(#all_names),*)#await_call;
+        }
     };
+    // Warming in parallel only pays off if all threads share the same cache.
+    let (send_bound, warm_body) =
+        if cfg!(feature = "rayon") && memo.options.shared_cache && !memo.is_async {
+            (
+                quote::quote! { I::IntoIter: Send, },
+                quote::quote! {
+                    use ::memoize::rayon::iter::{ParallelBridge, ParallelIterator};
+                    inputs.into_iter().par_bridge().for_each(|(#(#all_names),*)| {
+                        let _ = #fn_ident(#(#all_names),*);
+                    });
+                },
+            )
+        } else {
+            (quote::quote! {}, sequential)
+        };
+    quote::quote! {
+        #vis #async_fn fn #warm_name<I>(inputs: I)
+        where
+            I: IntoIterator<Item = #item_type>,
+            #send_bound
+        {
+            #warm_body
+        }
+    }
+}
 
-    let memoizer = if options.shared_cache {
+/// Bulk lookups, taking the lock only once (twice, if missing values are computed):
+/// `memoized_get_many_<fn>()`, and `memoized_get_or_compute_many_<fn>()` if the keys are `plain_keys`,
+/// used as they are.
+///
+/// `read_memo` looks up the memoized arguments bound by `miss.names`, and `publish_buffer` is run
+/// first.
+fn bulk_lookups(
+    memo: &Memo,
+    miss: &Miss,
+    read_memo: &proc_macro2::TokenStream,
+    publish_buffer: &proc_macro2::TokenStream,
+    plain_keys: bool,
+) -> proc_macro2::TokenStream {
+    let get_many_name = memo.ident("get_many");
+    let get_or_compute_many_name = memo.ident("get_or_compute_many");
+    let (vis, async_helper, memo_type) = (memo.vis, memo.async_helper, memo.memo_type);
+    let (input_tuple_type, return_type) = (memo.input_tuple_type, memo.return_type);
+    let (names, compute_return, admit, stored_value, register) = (
+        miss.names,
+        miss.compute_return,
+        miss.admit,
+        miss.stored_value,
+        miss.register,
+    );
+    let lock_store = memo.lock_store;
+    // An async store is locked for a bounded number of keys at a time, yielding in between, so that
+    // many keys don't stall the other tasks using the cache.
+    let read_many = if memo.async_store {
+        quote::quote! {{
+            let keys: Vec<#input_tuple_type> = keys.into_iter().collect();
+            let mut ATTR_MEMOIZE_FOUND__ = Vec::with_capacity(keys.len());
+            for ATTR_MEMOIZE_CHUNK__ in keys.chunks(::memoize::MAINTENANCE_CHUNK) {
+                {
+                    let mut ATTR_MEMOIZE_HM__ = #lock_store;
+                    for #names in ATTR_MEMOIZE_CHUNK__.iter().cloned() {
+                        ATTR_MEMOIZE_FOUND__.push(#read_memo);
+                    }
+                }
+                ::memoize::yield_now().await;
+            }
+            ATTR_MEMOIZE_FOUND__
+        }}
+    } else if plain_keys {
+        quote::quote! { ::memoize::memoized::get_many::<#memo_type>(keys) }
+    } else {
+        memo.with_store(quote::quote! {
+            keys.into_iter()
+                .map(|#names| #read_memo)
+                .collect::<Vec<Option<#return_type>>>()
+        })
+    };
+    let get_or_compute_many = if !plain_keys
+        || memo.options.negative_filter.is_some()
+        || memo.options.secondary.is_some()
+    {
+        quote::quote! {}
+    } else if !memo.is_async {
         quote::quote! {
+            #vis fn #get_or_compute_many_name<I>(keys: I) -> Vec<#return_type>
+            where
+                I: IntoIterator<Item = #input_tuple_type>,
             {
-                let mut ATTR_MEMOIZE_HM__ = #store_ident.lock().unwrap();
-                if let Some(ATTR_MEMOIZE_RETURN__) = #read_memo {
-                    return ATTR_MEMOIZE_RETURN__
-                }
+                #publish_buffer
+                #register
+                ::memoize::memoized::get_or_compute_many::<#memo_type>(keys, |#names| {
+                    #compute_return
+                    let ATTR_MEMOIZE_ENTRY__ = if #admit {
+                        Some((#names, #stored_value))
+                    } else {
+                        None
+                    };
+                    (ATTR_MEMOIZE_RETURN__, ATTR_MEMOIZE_ENTRY__)
+                })
             }
-            let ATTR_MEMOIZE_RETURN__ = #memoized_id #forwarding_tuple;
-
-            let mut ATTR_MEMOIZE_HM__ = #store_ident.lock().unwrap();
-            #memoize
-
-            ATTR_MEMOIZE_RETURN__
         }
     } else {
+        let record_many = memo.record_stats(
+            quote::quote! { ATTR_MEMOIZE_N_KEYS__ - ATTR_MEMOIZE_COMPUTED__.len() },
+            quote::quote! { ATTR_MEMOIZE_COMPUTED__.len() },
+        );
+        let emit_hit = memo.emit_event("Hit", Some(miss.names_refs.clone()));
+        let emit_miss = memo.emit_event("Miss", Some(miss.names_refs.clone()));
+        let insert_computed = memo.insert_entry(
+            &quote::quote! { ATTR_MEMOIZE_KEY__ },
+            &quote::quote! { ATTR_MEMOIZE_VALUE__ },
+        );
+        // An async store is locked for a bounded number of entries at a time, like for the lookups.
+        let insert_many = if memo.async_store {
+            quote::quote! {
+                let mut ATTR_MEMOIZE_COMPUTED__ = ATTR_MEMOIZE_COMPUTED__.into_iter().peekable();
+                while ATTR_MEMOIZE_COMPUTED__.peek().is_some() {
+                    {
+                        let mut ATTR_MEMOIZE_HM__ = #lock_store;
+                        for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_VALUE__) in
+                            ATTR_MEMOIZE_COMPUTED__.by_ref().take(::memoize::MAINTENANCE_CHUNK)
+                        {
+                            #insert_computed
+                        }
+                    }
+                    ::memoize::yield_now().await;
+                }
+            }
+        } else {
+            memo.with_store(quote::quote! {
+                for (ATTR_MEMOIZE_KEY__, ATTR_MEMOIZE_VALUE__) in ATTR_MEMOIZE_COMPUTED__ {
+                    #insert_computed
+                }
+            })
+        };
         quote::quote! {
-            let ATTR_MEMOIZE_RETURN__ = #store_ident.with(|ATTR_MEMOIZE_HM__| {
-                let mut ATTR_MEMOIZE_HM__ = ATTR_MEMOIZE_HM__.borrow_mut();
-                #read_memo
-            });
-            if let Some(ATTR_MEMOIZE_RETURN__) = ATTR_MEMOIZE_RETURN__ {
-                return ATTR_MEMOIZE_RETURN__;
+            #vis async fn #get_or_compute_many_name<I>(keys: I) -> Vec<#return_type>
+            where
+                I: IntoIterator<Item = #input_tuple_type>,
+            {
+                #publish_buffer
+                #register
+                let keys: Vec<#input_tuple_type> = keys.into_iter().collect();
+                let ATTR_MEMOIZE_N_KEYS__ = keys.len();
+                let found = {
+                    let keys = keys.iter().cloned();
+                    #read_many
+                };
+                let mut ATTR_MEMOIZE_COMPUTED__ = vec![];
+                let mut results = Vec::with_capacity(ATTR_MEMOIZE_N_KEYS__);
+                for (#names, found) in keys.into_iter().zip(found) {
+                    match found {
+                        Some(ATTR_MEMOIZE_RETURN__) => {
+                            #emit_hit
+                            results.push(ATTR_MEMOIZE_RETURN__);
+                        }
+                        None => {
+                            #emit_miss
+                            #compute_return
+                            if #admit {
+                                ATTR_MEMOIZE_COMPUTED__.push((#names, #stored_value));
+                            }
+                            results.push(ATTR_MEMOIZE_RETURN__);
+                        }
+                    }
+                }
+                #record_many
+                if !ATTR_MEMOIZE_COMPUTED__.is_empty() {
+                    #insert_many;
+                }
+                results
             }
+        }
+    };
+    quote::quote! {
+        #vis #async_helper fn #get_many_name<I>(keys: I) -> Vec<Option<#return_type>>
+        where
+            I: IntoIterator<Item = #input_tuple_type>,
+        {
+            #publish_buffer
+            #read_many
+        }
 
-            let ATTR_MEMOIZE_RETURN__ = #memoized_id #forwarding_tuple;
+        #get_or_compute_many
+    }
+}
 
-            #store_ident.with(|ATTR_MEMOIZE_HM__| {
-                let mut ATTR_MEMOIZE_HM__ = ATTR_MEMOIZE_HM__.borrow_mut();
-                #memoize
-            });
+/// `memoized_insert_<fn>()`, which inserts a value without calling the function, e.g. called by the
+/// function itself for related keys it computed as a by-product.
+fn insert_helper(memo: &Memo, register: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let insert_name = memo.ident("insert");
+    let (vis, async_helper, memo_type) = (memo.vis, memo.async_helper, memo.memo_type);
+    let (input_tuple_type, return_type) = (memo.input_tuple_type, memo.return_type);
+    let insert = if memo.async_store {
+        memo.with_store(quote::quote! {
+            ::memoize::memoized::insert::<#memo_type>(
+                &mut *ATTR_MEMOIZE_HM__,
+                key,
+                <#memo_type as ::memoize::memoized::Memoized>::store(value),
+            );
+        })
+    } else {
+        quote::quote! { ::memoize::memoized::insert_value::<#memo_type>(key, value); }
+    };
+    quote::quote! {
+        #vis #async_helper fn #insert_name(key: #input_tuple_type, value: #return_type) {
+            #register
+            #insert
+        }
+    }
+}
 
-            ATTR_MEMOIZE_RETURN__
+/// With `Seed`, `memoized_load_seed_<fn>()` loads precomputed entries from a file written by
+/// `memoize::write_seed()`.
+fn seed_loader(memo: &Memo, register: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let load_seed_name = memo.ident("load_seed");
+    let (vis, async_helper) = (memo.vis, memo.async_helper);
+    let (input_tuple_type, return_type) = (memo.input_tuple_type, memo.return_type);
+    let insert_seed = memo.insert_values(quote::quote! { ATTR_MEMOIZE_SEED__ });
+    quote::quote! {
+        #vis #async_helper fn #load_seed_name<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<usize> {
+            let ATTR_MEMOIZE_SEED__: Vec<(#input_tuple_type, #return_type)> = ::memoize::read_seed(path)?;
+            let ATTR_MEMOIZE_N_SEEDED__ = ATTR_MEMOIZE_SEED__.len();
+            #register
+            #insert_seed;
+            Ok(ATTR_MEMOIZE_N_SEEDED__)
         }
-    };
+    }
+}
 
-    let vis = &func.vis;
+/// Cached entries can be queried by a predicate on their keys with `memoized_find_<fn>()`, and by a
+/// predicate on their values with `memoized_keys_where_<fn>()`, which only returns the keys.
+fn query_helpers(memo: &Memo) -> proc_macro2::TokenStream {
+    let find_name = memo.ident("find");
+    let keys_where_name = memo.ident("keys_where");
+    let (vis, async_helper, memo_type) = (memo.vis, memo.async_helper, memo.memo_type);
+    let (input_tuple_type, return_type) = (memo.input_tuple_type, memo.return_type);
+    let find = memo.with_store_ref(quote::quote! {
+        ::memoize::memoized::find::<#memo_type>(&*ATTR_MEMOIZE_HM__, predicate)
+    });
+    let keys_where = memo.with_store_ref(quote::quote! {
+        ::memoize::memoized::keys_where::<#memo_type>(&*ATTR_MEMOIZE_HM__, predicate)
+    });
+    quote::quote! {
+        #vis #async_helper fn #find_name(
+            predicate: impl Fn(&#input_tuple_type) -> bool,
+        ) -> Vec<(#input_tuple_type, #return_type)> {
+            #find
+        }
 
-    let flusher = if options.shared_cache {
-        quote::quote! {
-            #vis fn #flush_name() {
-                #store_ident.lock().unwrap().clear();
-            }
+        #vis #async_helper fn #keys_where_name(
+            predicate: impl Fn(&#return_type) -> bool,
+        ) -> Vec<#input_tuple_type> {
+            #keys_where
         }
-    } else {
+    }
+}
+
+/// With `Export`, the cached entries can be handed to another process, e.g. from a warmed parent
+/// process to the workers it spawns.
+fn export_helpers(memo: &Memo, register: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let export_name = memo.ident("export");
+    let import_name = memo.ident("import");
+    let (vis, async_helper, memo_type) = (memo.vis, memo.async_helper, memo.memo_type);
+    let (input_tuple_type, return_type) = (memo.input_tuple_type, memo.return_type);
+    let export = memo.with_store_ref(quote::quote! {
+        ::memoize::memoized::find::<#memo_type>(&*ATTR_MEMOIZE_HM__, |_| true)
+    });
+    let import = memo.insert_values(quote::quote! { ATTR_MEMOIZE_IMPORTED__ });
+    quote::quote! {
+        #vis #async_helper fn #export_name() -> Vec<u8> {
+            let mut ATTR_MEMOIZE_EXPORTED__: Vec<(#input_tuple_type, #return_type)> = #export;
+            // LRU caches iterate from the most recently used entry; importing the least recently
+            // used entry first keeps their order.
+            ATTR_MEMOIZE_EXPORTED__.reverse();
+            ::memoize::export_entries(&ATTR_MEMOIZE_EXPORTED__)
+        }
+
+        #vis #async_helper fn #import_name(bytes: &[u8]) -> std::io::Result<usize> {
+            let ATTR_MEMOIZE_IMPORTED__: Vec<(#input_tuple_type, #return_type)> =
+                ::memoize::import_entries(bytes)?;
+            let ATTR_MEMOIZE_N_IMPORTED__ = ATTR_MEMOIZE_IMPORTED__.len();
+            #register
+            #import;
+            Ok(ATTR_MEMOIZE_N_IMPORTED__)
+        }
+    }
+}
+
+/// LRU caches expose their most and least recently used keys, and can be resized (unless
+/// `AutoCapacity` resizes them itself).
+fn lru_helpers(memo: &Memo) -> proc_macro2::TokenStream {
+    let lru_front_name = memo.ident("lru_front");
+    let lru_back_name = memo.ident("lru_back");
+    let resize_name = memo.ident("resize");
+    let (vis, async_helper, memo_type) = (memo.vis, memo.async_helper, memo.memo_type);
+    let input_tuple_type = memo.input_tuple_type;
+    let front = memo.with_store(quote::quote! {
+        ATTR_MEMOIZE_HM__.iter().next().map(|(ATTR_MEMOIZE_KEY__, _)| ATTR_MEMOIZE_KEY__.clone())
+    });
+    let back = memo.with_store(quote::quote! {
+        ATTR_MEMOIZE_HM__.peek_lru().map(|(ATTR_MEMOIZE_KEY__, _)| ATTR_MEMOIZE_KEY__.clone())
+    });
+    let resize_func = if memo.options.auto_capacity.is_none() {
+        let resize = memo.with_store(quote::quote! {
+            let ATTR_MEMOIZE_LEN__ = ATTR_MEMOIZE_HM__.len();
+            ATTR_MEMOIZE_HM__.resize(std::num::NonZeroUsize::new(capacity.max(1)).unwrap());
+            ::memoize::memoized::record_evictions::<#memo_type>(
+                ATTR_MEMOIZE_LEN__ - ATTR_MEMOIZE_HM__.len(),
+            );
+        });
         quote::quote! {
-            #vis fn #flush_name() {
-                #store_ident.with(|ATTR_MEMOIZE_HM__| ATTR_MEMOIZE_HM__.borrow_mut().clear());
+            #vis #async_helper fn #resize_name(capacity: usize) {
+                #resize
             }
         }
+    } else {
+        quote::quote! {}
     };
+    quote::quote! {
+        #vis #async_helper fn #lru_front_name() -> Option<#input_tuple_type> {
+            #front
+        }
 
-    let size_func = if options.shared_cache {
-        quote::quote! {
-            #vis fn #size_name() -> usize {
-                #store_ident.lock().unwrap().len()
+        #vis #async_helper fn #lru_back_name() -> Option<#input_tuple_type> {
+            #back
+        }
+
+        #resize_func
+    }
+}
+
+/// Rejects the option `name` if the cache is used through `PerElement`, `WriteBack`, `SharedFuture`,
+/// `CollectStream` or `CollectCoroutine`, which look up and insert entries their own way.
+fn reject_with_wrapping_modes(
+    name: &str,
+    options: &CacheOptions,
+    span: proc_macro2::Span,
+) -> Result<(), syn::Error> {
+    if options.per_element
+        || options.write_back.is_some()
+        || options.shared_future
+        || options.collect_stream
+        || options.collect_coroutine
+    {
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "{} cannot be combined with PerElement, WriteBack, SharedFuture, CollectStream or CollectCoroutine",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether entries expire, by `TimeToLive`, `ExpireAfterAccess`, `ExpireWith` or `MaxAge`.
+fn expires(options: &CacheOptions) -> bool {
+    options.time_to_live.is_some()
+        || options.expire_after_access.is_some()
+        || options.expire_with.is_some()
+        || options.max_age.is_some()
+}
+
+/// Whether `Eviction: GreedyDual` measures how long it takes to compute each value.
+fn measures_cost(options: &CacheOptions) -> bool {
+    options
+        .eviction
+        .as_ref()
+        .is_some_and(|policy| policy == "GreedyDual")
+}
+
+/// Whether options that need to know when a value was computed or expires store `memoize::Entry`s
+/// instead of plain values.
+fn uses_entry(options: &CacheOptions) -> bool {
+    expires(options)
+        || options.entry_info
+        || options.validate_every.is_some()
+        || measures_cost(options)
+}
+
+/// Whether computations are timed: `MinComputeTime` only caches values that were slow to compute.
+fn times_compute(options: &CacheOptions) -> bool {
+    measures_cost(options) || options.min_compute_time.is_some()
+}
+
+/// Whether the cache holds the values returned by the function as they are, i.e. neither their
+/// elements (`PerElement`), collected streams (`CollectStream`) nor shared futures (`SharedFuture`).
+fn stores_returned_values(options: &CacheOptions) -> bool {
+    !options.per_element && !options.collect_stream && !options.shared_future
+}
+
+/// Checks that a `PerElement` function takes exactly one memoized argument, a slice reference or a
+/// `Vec`, and returns a `Vec`.
+///
+/// Returns the element types of the argument and of the return value, and whether the argument is a
+/// slice.
+fn check_per_element(
+    sig: &syn::Signature,
+    params: &[FnArgument],
+) -> Result<(syn::Type, syn::Type, bool), syn::Error> {
+    let memoized: Vec<&FnArgument> = params.iter().filter(|p| p.is_memoized).collect();
+    if memoized.len() != 1 {
+        return Err(syn::Error::new(
+            sig.span(),
+            "PerElement requires exactly one memoized argument",
+        ));
+    }
+    let (key_type, is_slice) = match &*memoized[0].arg_type {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(slice) => ((*slice.elem).clone(), true),
+            _ => {
+                return Err(syn::Error::new(
+                    reference.span(),
+                    "PerElement requires a slice",
+                ))
             }
+        },
+        other => match vec_element(other) {
+            Some(elem) => (elem, false),
+            None => return Err(syn::Error::new(other.span(), "PerElement requires a slice")),
+        },
+    };
+    let value_type = match &sig.output {
+        syn::ReturnType::Type(_, ty) => vec_element(ty),
+        syn::ReturnType::Default => None,
+    };
+    match value_type {
+        Some(value_type) => Ok((key_type, value_type, is_slice)),
+        None => Err(syn::Error::new(
+            sig.output.span(),
+            "PerElement requires a function returning a `Vec`",
+        )),
+    }
+}
+
+/// Hashes the given strings (with 64-bit FNV-1a, which unlike `std`'s hashers is stable across
+/// compiler versions).
+fn fingerprint(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
         }
-    } else {
-        quote::quote! {
-            #vis fn #size_name() -> usize {
-                #store_ident.with(|ATTR_MEMOIZE_HM__| ATTR_MEMOIZE_HM__.borrow().len())
+    }
+    hash
+}
+
+/// Returns `T` if a function returns `impl Stream<Item = T>`.
+fn stream_item(output: &syn::ReturnType) -> Option<syn::Type> {
+    let ty = match output {
+        syn::ReturnType::Type(_, ty) => ty,
+        syn::ReturnType::Default => return None,
+    };
+    if let syn::Type::ImplTrait(impl_trait) = &**ty {
+        for bound in &impl_trait.bounds {
+            if let syn::TypeParamBound::Trait(bound) = bound {
+                let last = bound.path.segments.last()?;
+                if last.ident != "Stream" {
+                    continue;
+                }
+                if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::AssocType(assoc) = arg {
+                            if assoc.ident == "Item" {
+                                return Some(assoc.ty.clone());
+                            }
+                        }
+                    }
+                }
             }
         }
+    }
+    None
+}
+
+/// Returns `Y` and `R` if a function returns `impl Coroutine<Yield = Y, Return = R>`.
+fn coroutine_types(output: &syn::ReturnType) -> Option<(syn::Type, syn::Type)> {
+    let ty = match output {
+        syn::ReturnType::Type(_, ty) => ty,
+        syn::ReturnType::Default => return None,
     };
+    if let syn::Type::ImplTrait(impl_trait) = &**ty {
+        for bound in &impl_trait.bounds {
+            if let syn::TypeParamBound::Trait(bound) = bound {
+                let last = bound.path.segments.last()?;
+                if last.ident != "Coroutine" {
+                    continue;
+                }
+                if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                    let (mut yield_type, mut complete_type) = (None, None);
+                    for arg in &args.args {
+                        match arg {
+                            syn::GenericArgument::AssocType(assoc) if assoc.ident == "Yield" => {
+                                yield_type = Some(assoc.ty.clone())
+                            }
+                            syn::GenericArgument::AssocType(assoc) if assoc.ident == "Return" => {
+                                complete_type = Some(assoc.ty.clone())
+                            }
+                            _ => {}
+                        }
+                    }
+                    return Some((yield_type?, complete_type?));
+                }
+            }
+        }
+    }
+    None
+}
 
-    quote::quote! {
-        #renamed_fn
-        #flusher
-        #size_func
-        #store
+/// Whether a function returns a `Result` (or an alias like `io::Result`).
+fn returns_result(output: &syn::ReturnType) -> bool {
+    match output {
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|last| last.ident == "Result"),
+            _ => false,
+        },
+        syn::ReturnType::Default => false,
+    }
+}
 
-        #[allow(unused_variables, unused_mut)]
-        #vis #sig {
-            #memoizer
+/// Whether `ty` is a primitive `Copy` type which is cheap to copy: an integer, `bool`, `char`, a
+/// float, or a small array or tuple of those.
+fn is_small_copy(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => {
+            path.qself.is_none()
+                && path.path.get_ident().is_some_and(|ident| {
+                    [
+                        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+                        "i128", "isize", "bool", "char", "f32", "f64",
+                    ]
+                    .iter()
+                    .any(|primitive| ident == primitive)
+                })
         }
+        syn::Type::Array(array) => {
+            let small_len = match &array.len {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }) => len.base10_parse::<usize>().is_ok_and(|len| len <= 16),
+                _ => false,
+            };
+            small_len && is_small_copy(&array.elem)
+        }
+        syn::Type::Tuple(tuple) => tuple.elems.iter().all(is_small_copy),
+        syn::Type::Paren(paren) => is_small_copy(&paren.elem),
+        _ => false,
+    }
+}
+
+/// Returns `T` if `ty` is `Vec<T>`.
+fn vec_element(ty: &syn::Type) -> Option<syn::Type> {
+    if let syn::Type::Path(path) = ty {
+        let last = path.path.segments.last()?;
+        if last.ident == "Vec" {
+            if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                if let Some(syn::GenericArgument::Type(elem)) = args.args.first() {
+                    return Some(elem.clone());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Splits an input given as a parenthesized tuple (`(a, b)`, or `(a)` for a single argument) into
+/// the expressions of its arguments.
+fn argument_list(input: &Expr) -> Vec<&Expr> {
+    match input {
+        Expr::Tuple(tuple) => tuple.elems.iter().collect(),
+        Expr::Paren(paren) => vec![&*paren.expr],
+        other => vec![other],
     }
-    .into()
 }
 
 /// An argument of the memoized function.
@@ -469,11 +6693,16 @@ struct FnArgument {
 
     /// Whether or not this specific argument is included in the memoization.
     is_memoized: bool,
+
+    /// Whether the argument is declared as `impl Into<T>`, and converted to `T` (the `arg_type`)
+    /// before it is used as a key.
+    is_into: bool,
 }
 
 fn check_signature(
     sig: &syn::Signature,
     options: &CacheOptions,
+    pyfunction: bool,
 ) -> Result<Vec<FnArgument>, syn::Error> {
     if sig.inputs.is_empty() {
         return Ok(vec![]);
@@ -483,15 +6712,37 @@ fn check_signature(
 
     for a in &sig.inputs {
         if let syn::FnArg::Typed(ref arg) = a {
-            let arg_type = arg.ty.clone();
+            let into_type = into_target(&arg.ty);
+            let is_into = into_type.is_some();
+            let arg_type = into_type.map(Box::new).unwrap_or_else(|| arg.ty.clone());
 
             if let syn::Pat::Ident(patident) = &*arg.pat {
                 let arg_name = patident.ident.clone();
-                let is_memoized = !options.ignore.contains(&arg_name);
+                let is_dyn_ignored = options.auto_ignore_dyn && is_trait_object(&arg_type);
+                // The GIL token of a `#[pyfunction]` is not an input.
+                let is_gil_token = pyfunction && is_python_token(&arg_type);
+                let is_memoized = !options.ignore.contains(&arg_name)
+                    && !is_dyn_ignored
+                    && !is_gil_token
+                    && !options
+                        .extract
+                        .iter()
+                        .any(|(argument, _, _)| *argument == arg_name)
+                    && !options.canonical.contains(&arg_name)
+                    && !options.key_by_ptr.contains(&arg_name)
+                    && !options
+                        .quantize
+                        .iter()
+                        .any(|(argument, _, _)| *argument == arg_name)
+                    && !options
+                        .key_pipeline
+                        .iter()
+                        .any(|(argument, _, _)| *argument == arg_name);
                 params.push(FnArgument {
                     arg_type,
                     arg_name,
                     is_memoized,
+                    is_into,
                 });
             } else {
                 return Err(syn::Error::new(
@@ -504,5 +6755,116 @@ fn check_signature(
     Ok(params)
 }
 
+/// Whether the type is a trait object behind a reference or a smart pointer, like `&dyn Trait` or
+/// `Box<dyn Trait>`.
+fn is_trait_object(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::TraitObject(_) => true,
+        syn::Type::Reference(reference) => is_trait_object(&reference.elem),
+        syn::Type::Paren(paren) => is_trait_object(&paren.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last().unwrap();
+            match &last.arguments {
+                syn::PathArguments::AngleBracketed(args)
+                    if ["Box", "Rc", "Arc"]
+                        .iter()
+                        .any(|pointer| last.ident == pointer)
+                        && args.args.len() == 1 =>
+                {
+                    match &args.args[0] {
+                        syn::GenericArgument::Type(pointee) => is_trait_object(pointee),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Whether the type is PyO3's GIL token `Python<'py>`: a type named `Python` with a single lifetime
+/// argument.
+fn is_python_token(ty: &syn::Type) -> bool {
+    let segment = match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path.path.segments.last().unwrap(),
+        _ => return false,
+    };
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) if segment.ident == "Python" => {
+            arguments.args.len() == 1
+                && matches!(arguments.args[0], syn::GenericArgument::Lifetime(_))
+        }
+        _ => false,
+    }
+}
+
+/// Whether an attribute is PyO3's `#[pyfunction]`.
+fn is_pyfunction(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "pyfunction")
+}
+
+/// Whether an attribute exports a function, and so belongs on the memoized function callers see: e.g.
+/// `#[wasm_bindgen]`, `#[pyfunction]`, `#[export_name = "..."]` or `#[no_mangle]`.
+fn is_export_attr(attr: &syn::Attribute) -> bool {
+    let path = attr.path();
+    path.segments.last().is_some_and(|segment| {
+        ["wasm_bindgen", "pyfunction", "pyo3"]
+            .iter()
+            .any(|name| segment.ident == name)
+    }) || ["export_name", "no_mangle", "unsafe"]
+        .iter()
+        .any(|name| path.is_ident(name))
+}
+
+/// Whether a type contains a reference or a lifetime, so that it can't be deserialized into an owned
+/// value.
+fn borrows(ty: &proc_macro2::TokenStream) -> bool {
+    ty.clone().into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Punct(punct) => punct.as_char() == '&' || punct.as_char() == '\'',
+        proc_macro2::TokenTree::Group(group) => borrows(&group.stream()),
+        _ => false,
+    })
+}
+
+/// Returns `T` if the type is a slice reference `&[T]`.
+fn slice_element(ty: &syn::Type) -> Option<syn::Type> {
+    match ty {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(slice) => Some((*slice.elem).clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `T` if the type is `impl Into<T>`.
+fn into_target(ty: &syn::Type) -> Option<syn::Type> {
+    let bound = match ty {
+        syn::Type::ImplTrait(impl_trait) if impl_trait.bounds.len() == 1 => {
+            match &impl_trait.bounds[0] {
+                syn::TypeParamBound::Trait(bound) => bound,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let last = bound.path.segments.last()?;
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args)
+            if last.ident == "Into" && args.args.len() == 1 =>
+        {
+            match &args.args[0] {
+                syn::GenericArgument::Type(target) => Some(target.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {}
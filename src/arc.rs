@@ -0,0 +1,295 @@
+//! A bounded cache using the Adaptive Replacement Cache (ARC) policy.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum List {
+    // Resident entries used once recently.
+    T1,
+    // Resident entries used at least twice recently.
+    T2,
+    // Keys recently evicted from T1.
+    B1,
+    // Keys recently evicted from T2.
+    B2,
+}
+
+struct Slot<V> {
+    value: Option<V>,
+    list: List,
+    tick: u64,
+}
+
+/// A bounded cache implementing the Adaptive Replacement Cache policy. It keeps entries used once
+/// and entries used repeatedly in separate LRU lists, and remembers recently evicted keys to tune
+/// the share of the capacity given to each list: it adapts to workloads alternating between scans
+/// and hot spots, which a plain LRU cache handles poorly.
+///
+/// This is the store used by `#[memoize(Capacity: N, Eviction: ARC)]`; it offers the same `get` and
+/// `put` methods as [`lru::LruCache`].
+pub struct ArcCache<K, V> {
+    map: HashMap<K, Slot<V>>,
+    // The keys of each list, least recently used first.
+    lists: [BTreeMap<u64, K>; 4],
+    cap: usize,
+    // The target size of T1.
+    p: usize,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> ArcCache<K, V> {
+    /// Creates a cache holding at most `cap` entries (and remembering up to `cap` evicted keys).
+    pub fn new(cap: NonZeroUsize) -> Self {
+        ArcCache {
+            map: HashMap::new(),
+            lists: Default::default(),
+            cap: cap.get(),
+            p: 0,
+            tick: 0,
+        }
+    }
+
+    fn list_len(&self, list: List) -> usize {
+        self.lists[list as usize].len()
+    }
+
+    // Moves the entry for `key` to the most recently used end of `to`.
+    fn move_to(&mut self, key: &K, to: List) {
+        self.tick += 1;
+        let slot = self.map.get_mut(key).unwrap();
+        let key = self.lists[slot.list as usize].remove(&slot.tick).unwrap();
+        slot.list = to;
+        slot.tick = self.tick;
+        self.lists[to as usize].insert(self.tick, key);
+    }
+
+    // Removes the least recently used key of `list` entirely.
+    fn remove_lru(&mut self, list: List) {
+        if let Some((_, key)) = self.lists[list as usize].pop_first() {
+            self.map.remove(&key);
+        }
+    }
+
+    // Evicts the least recently used entry of T1 or T2 into the corresponding ghost list, if the
+    // cache is full.
+    fn replace(&mut self, in_b2: bool) {
+        if self.len() < self.cap {
+            return;
+        }
+        let t1 = self.list_len(List::T1);
+        let (from, to) = if t1 > 0 && (t1 > self.p || (in_b2 && t1 == self.p)) {
+            (List::T1, List::B1)
+        } else {
+            (List::T2, List::B2)
+        };
+        if let Some(key) = self.lists[from as usize].values().next().cloned() {
+            self.map.get_mut(&key).unwrap().value = None;
+            self.move_to(&key, to);
+        }
+    }
+
+    /// Returns the value for `key`, marking it as used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        match self.map.get(key)?.list {
+            List::T1 | List::T2 => self.move_to(key, List::T2),
+            List::B1 | List::B2 => return None,
+        }
+        self.map.get(key).unwrap().value.as_ref()
+    }
+
+    /// Returns the value for `key` without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key)?.value.as_ref()
+    }
+
+    /// Returns whether the cache holds a value for `key`, without marking it as used.
+    pub fn contains(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Inserts a value, evicting an entry if the cache is full. Returns the previous value for
+    /// `key`, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let (b1, b2) = (self.list_len(List::B1), self.list_len(List::B2));
+        match self.map.get(&key).map(|slot| slot.list) {
+            Some(List::T1) | Some(List::T2) => {
+                self.move_to(&key, List::T2);
+                return self.map.get_mut(&key).unwrap().value.replace(value);
+            }
+            Some(List::B1) => {
+                // T1 was too small.
+                self.p = self.cap.min(self.p + (b2 / b1).max(1));
+                self.replace(false);
+                self.move_to(&key, List::T2);
+            }
+            Some(List::B2) => {
+                // T2 was too small.
+                self.p = self.p.saturating_sub((b1 / b2).max(1));
+                self.replace(true);
+                self.move_to(&key, List::T2);
+            }
+            None => {
+                let t1 = self.list_len(List::T1);
+                let total = t1 + self.list_len(List::T2) + b1 + b2;
+                if t1 + b1 >= self.cap {
+                    if t1 < self.cap {
+                        self.remove_lru(List::B1);
+                        self.replace(false);
+                    } else {
+                        self.remove_lru(List::T1);
+                    }
+                } else if total >= self.cap {
+                    if total >= 2 * self.cap {
+                        self.remove_lru(List::B2);
+                    }
+                    self.replace(false);
+                }
+                self.tick += 1;
+                self.lists[List::T1 as usize].insert(self.tick, key.clone());
+                self.map.insert(
+                    key,
+                    Slot {
+                        value: Some(value),
+                        list: List::T1,
+                        tick: self.tick,
+                    },
+                );
+                return None;
+            }
+        }
+        self.map.get_mut(&key).unwrap().value = Some(value);
+        None
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let slot = self.map.remove(key)?;
+        self.lists[slot.list as usize].remove(&slot.tick);
+        slot.value
+    }
+
+    /// Iterates over all entries, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map
+            .iter()
+            .filter_map(|(key, slot)| slot.value.as_ref().map(|value| (key, value)))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.list_len(List::T1) + self.list_len(List::T2)
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn cap(&self) -> NonZeroUsize {
+        NonZeroUsize::new(self.cap).unwrap()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        *self = ArcCache::new(self.cap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(cap: usize) -> ArcCache<u32, u32> {
+        ArcCache::new(NonZeroUsize::new(cap).unwrap())
+    }
+
+    fn list(cache: &ArcCache<u32, u32>, key: u32) -> Option<List> {
+        cache.map.get(&key).map(|slot| slot.list)
+    }
+
+    #[test]
+    fn repeated_use_promotes_to_t2() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        assert!(list(&cache, 1) == Some(List::T1));
+        assert_eq!(cache.get(&1), Some(&10));
+        assert!(list(&cache, 1) == Some(List::T2));
+        // Updating a resident entry uses it as well.
+        cache.put(2, 20);
+        assert_eq!(cache.put(2, 21), Some(20));
+        assert!(list(&cache, 2) == Some(List::T2));
+    }
+
+    #[test]
+    fn evicted_keys_become_ghosts() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.get(&1);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        // 2 was evicted from T1, and is remembered in B1.
+        assert!(list(&cache, 2) == Some(List::B1));
+        assert!(!cache.contains(&2));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.peek(&2), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.iter().count(), 2);
+    }
+
+    #[test]
+    fn ghost_hits_adapt_the_target_size() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.get(&1);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        assert_eq!(cache.p, 0);
+
+        // A hit in B1 grows T1's target, and evicts from T2 into B2.
+        cache.put(2, 21);
+        assert_eq!(cache.p, 1);
+        assert!(list(&cache, 2) == Some(List::T2));
+        assert!(list(&cache, 1) == Some(List::B2));
+        assert_eq!(cache.peek(&2), Some(&21));
+
+        // A hit in B2 shrinks it again, and evicts from T1 into B1.
+        cache.put(1, 11);
+        assert_eq!(cache.p, 0);
+        assert!(list(&cache, 1) == Some(List::T2));
+        assert!(list(&cache, 3) == Some(List::B1));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn frequently_used_entries_survive_a_scan() {
+        let mut cache = cache(4);
+        for key in [1, 2] {
+            cache.put(key, key);
+            cache.get(&key);
+        }
+        for key in 100..200 {
+            cache.put(key, key);
+            assert!(cache.len() <= 4);
+            // Ghost lists remember at most as many keys as the capacity.
+            assert!(cache.map.len() <= 8);
+        }
+        assert!(cache.contains(&1) && cache.contains(&2));
+        assert!(cache.contains(&199));
+    }
+
+    #[test]
+    fn pop_and_clear() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.put(2, 20);
+        assert_eq!(cache.pop(&1), Some(10));
+        assert_eq!(cache.pop(&1), None);
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.map.is_empty());
+    }
+}
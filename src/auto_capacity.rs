@@ -0,0 +1,65 @@
+//! Adaptive sizing of LRU caches, used by `AutoCapacity`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Observes the hits, misses and evictions of a bounded cache, and periodically proposes a new
+/// capacity within `min..=max`.
+///
+/// Decisions are taken once per window of requests (as many as the current capacity, but at least
+/// 64): if at least a quarter of the misses evicted another entry while the hit rate stayed below
+/// 90 %, the cache is thrashing and grows by half; if nothing was evicted and less than half of the
+/// cache is used, it shrinks by a quarter.
+pub struct AutoCapacity {
+    min: usize,
+    max: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+impl AutoCapacity {
+    pub const fn new(min: usize, max: usize) -> Self {
+        AutoCapacity {
+            min,
+            max,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records a cache hit.
+    pub fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the insertion of a computed value (a miss), which may have evicted another entry,
+    /// into a cache holding `len` of `capacity` entries. Returns the new capacity, if it should
+    /// change.
+    pub fn record_insert(&self, evicted: bool, len: usize, capacity: usize) -> Option<usize> {
+        let misses = self.misses.fetch_add(1, Ordering::Relaxed) + 1;
+        let evictions = self
+            .evictions
+            .fetch_add(evicted as usize, Ordering::Relaxed)
+            + evicted as usize;
+        let hits = self.hits.load(Ordering::Relaxed);
+        if hits + misses < capacity.max(64) {
+            return None;
+        }
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.evictions.store(0, Ordering::Relaxed);
+
+        let hit_rate = hits as f64 / (hits + misses) as f64;
+        let churn = evictions as f64 / misses as f64;
+        let proposed = if churn >= 0.25 && hit_rate < 0.9 {
+            capacity + capacity.div_ceil(2)
+        } else if evictions == 0 && len < capacity / 2 {
+            capacity - capacity / 4
+        } else {
+            capacity
+        };
+        let proposed = proposed.clamp(self.min, self.max);
+        (proposed != capacity).then_some(proposed)
+    }
+}
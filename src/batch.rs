@@ -0,0 +1,86 @@
+//! Support for `BatchWith` caches.
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Batch<V> = Shared<BoxFuture<'static, Arc<Vec<V>>>>;
+
+/// Collects the keys missed by concurrent calls within a time window, and loads all of them with a
+/// single call of the batch function.
+///
+/// The first miss opens a batch, and the keys missed until its window has passed join it. The batch
+/// is loaded once the window has passed, and every caller receives the value of its key.
+pub struct Batcher<K, V> {
+    pending: Mutex<Option<(Vec<K>, Batch<V>)>>,
+}
+
+impl<K, V> Batcher<K, V>
+where
+    K: PartialEq + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub const fn new() -> Self {
+        Batcher {
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Loads the value of `key` as part of the open batch, or of a new one.
+    pub async fn load<F, Fut>(&'static self, key: K, window: Duration, load_many: F) -> V
+    where
+        F: FnOnce(Vec<K>) -> Fut + Send + 'static,
+        Fut: Future<Output = Vec<V>> + Send + 'static,
+    {
+        let (index, batch) = {
+            let mut pending = self.pending.lock().unwrap();
+            match &mut *pending {
+                Some((keys, batch)) => {
+                    let index = match keys.iter().position(|pending| *pending == key) {
+                        Some(index) => index,
+                        None => {
+                            keys.push(key);
+                            keys.len() - 1
+                        }
+                    };
+                    (index, batch.clone())
+                }
+                None => {
+                    let batch = async move {
+                        ::tokio::time::sleep(window).await;
+                        // Close the batch, so that later misses open a new one.
+                        let keys = match self.pending.lock().unwrap().take() {
+                            Some((keys, _)) => keys,
+                            None => vec![],
+                        };
+                        let n_keys = keys.len();
+                        let values = load_many(keys).await;
+                        assert_eq!(
+                            values.len(),
+                            n_keys,
+                            "memoize: BatchWith function must return one value per key",
+                        );
+                        Arc::new(values)
+                    }
+                    .boxed()
+                    .shared();
+                    *pending = Some((vec![key], batch.clone()));
+                    (0, batch)
+                }
+            }
+        };
+        batch.await[index].clone()
+    }
+}
+
+impl<K, V> Default for Batcher<K, V>
+where
+    K: PartialEq + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,232 @@
+//! A Bloom filter of keys with a negative result, used by `NegativeFilter`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+// With about 9.6 bits per key and 7 hash functions, the false positive rate is 1%.
+const BITS_PER_KEY: usize = 10;
+const HASHES: u64 = 7;
+
+/// A set of keys which answers "definitely not contained" exactly, and "contained" with a false
+/// positive rate of about 1% while it holds at most `capacity` keys.
+///
+/// The filter uses about 10 bits per key of capacity, regardless of the size of the keys. Once
+/// `capacity` keys have been inserted, it is cleared, so that the false positive rate doesn't grow.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    capacity: usize,
+    len: usize,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter for up to `capacity` keys; memory is only allocated on the first
+    /// insertion.
+    pub const fn new(capacity: usize) -> Self {
+        BloomFilter {
+            bits: Vec::new(),
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// Adds `key` to the filter.
+    pub fn insert<Q: Hash + ?Sized>(&mut self, key: &Q) {
+        if self.len >= self.capacity {
+            self.clear();
+        }
+        if self.bits.is_empty() {
+            self.bits = vec![0; (self.capacity.max(1) * BITS_PER_KEY).div_ceil(64)];
+        }
+        for bit in Self::bits(key, self.bits.len() as u64 * 64) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+
+    /// Whether `key` may have been inserted. `false` is always correct.
+    pub fn contains<Q: Hash + ?Sized>(&self, key: &Q) -> bool {
+        !self.bits.is_empty()
+            && Self::bits(key, self.bits.len() as u64 * 64)
+                .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Number of insertions since the filter was last cleared.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all keys.
+    pub fn clear(&mut self) {
+        self.bits = Vec::new();
+        self.len = 0;
+    }
+
+    // Positions of the bits of `key`, by double hashing.
+    fn bits<Q: Hash + ?Sized>(key: &Q, n_bits: u64) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (first, step) = (hash(0), hash(1) | 1);
+        (0..HASHES).map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % n_bits) as usize)
+    }
+}
+
+/// The keys with a negative result of a `NegativeFilter` function: a [`BloomFilter`] in front of the
+/// set of the keys' 64-bit hashes, so that keys which were never inserted are not found, and most
+/// of them are rejected by the Bloom filter alone.
+///
+/// Keys take about 10 bits in the Bloom filter and a hash of 8 bytes in the set, regardless of their
+/// size. Once `capacity` keys have been inserted, the filter is cleared.
+pub struct NegativeFilter {
+    bloom: BloomFilter,
+    hashes: Option<HashSet<u64>>,
+    capacity: usize,
+}
+
+impl NegativeFilter {
+    /// Creates an empty filter for up to `capacity` keys; memory is only allocated on the first
+    /// insertion.
+    pub const fn new(capacity: usize) -> Self {
+        NegativeFilter {
+            bloom: BloomFilter::new(capacity),
+            hashes: None,
+            capacity,
+        }
+    }
+
+    /// Adds `key` to the filter.
+    pub fn insert<Q: Hash + ?Sized>(&mut self, key: &Q) {
+        let hash = Self::hash(key);
+        if self
+            .hashes
+            .as_ref()
+            .is_some_and(|hashes| hashes.contains(&hash))
+        {
+            return;
+        }
+        if self.len() >= self.capacity {
+            self.clear();
+        }
+        self.hashes.get_or_insert_with(HashSet::new).insert(hash);
+        self.bloom.insert(&hash);
+    }
+
+    /// Whether `key` has been inserted (since the filter was last cleared).
+    pub fn contains<Q: Hash + ?Sized>(&self, key: &Q) -> bool {
+        let hash = Self::hash(key);
+        self.bloom.contains(&hash)
+            && self
+                .hashes
+                .as_ref()
+                .is_some_and(|hashes| hashes.contains(&hash))
+    }
+
+    /// Number of keys since the filter was last cleared.
+    pub fn len(&self) -> usize {
+        self.hashes.as_ref().map_or(0, HashSet::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all keys.
+    pub fn clear(&mut self) {
+        self.bloom.clear();
+        self.hashes = None;
+    }
+
+    fn hash<Q: Hash + ?Sized>(key: &Q) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_false_negatives() {
+        let mut filter = BloomFilter::new(1000);
+        assert!(!filter.contains(&0));
+        for key in 0..1000 {
+            filter.insert(&key);
+        }
+        assert_eq!(filter.len(), 1000);
+        assert!((0..1000).all(|key| filter.contains(&key)));
+    }
+
+    #[test]
+    fn false_positive_rate_is_low() {
+        let mut filter = BloomFilter::new(1000);
+        for key in 0..1000 {
+            filter.insert(&key);
+        }
+        let false_positives = (1000..11_000).filter(|key| filter.contains(key)).count();
+        // About 1% of 10,000 keys; allow for some variance.
+        assert!(false_positives < 300, "{}", false_positives);
+    }
+
+    #[test]
+    fn full_filter_is_cleared() {
+        let mut filter = BloomFilter::new(10);
+        for key in 0..10 {
+            filter.insert(&key);
+        }
+        filter.insert(&10);
+        assert_eq!(filter.len(), 1);
+        assert!(filter.contains(&10));
+        filter.clear();
+        assert!(filter.is_empty());
+        assert!(!filter.contains(&10));
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut filter = BloomFilter::new(0);
+        filter.insert("key");
+        assert!(filter.contains("key"));
+        filter.insert("other");
+        assert!(filter.contains("other"));
+    }
+
+    #[test]
+    fn negative_filter_has_no_false_positives() {
+        let mut filter = NegativeFilter::new(1000);
+        for key in 0..1000 {
+            filter.insert(&key);
+        }
+        assert_eq!(filter.len(), 1000);
+        assert!((0..1000).all(|key| filter.contains(&key)));
+        // The Bloom filter alone would let about 1% of these through.
+        assert!(!(1000..11_000).any(|key| filter.contains(&key)));
+    }
+
+    #[test]
+    fn full_negative_filter_is_cleared() {
+        let mut filter = NegativeFilter::new(10);
+        for key in 0..10 {
+            filter.insert(&key);
+        }
+        // Keys inserted again aren't counted twice.
+        filter.insert(&0);
+        assert_eq!(filter.len(), 10);
+        filter.insert(&10);
+        assert_eq!(filter.len(), 1);
+        assert!(filter.contains(&10));
+        assert!(!filter.contains(&0));
+        filter.clear();
+        assert!(filter.is_empty());
+        assert!(!filter.contains(&10));
+    }
+}
@@ -0,0 +1,68 @@
+//! Support for `CircuitBreaker`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Circuit<R> {
+    // Consecutive failures.
+    failures: u32,
+    open_until: Option<Instant>,
+    last_error: R,
+}
+
+/// Counts the consecutive failures of a memoized function, per key (or for all keys, with `()` as
+/// the key), and opens the circuit once there are too many of them.
+///
+/// While the circuit is open, calls return the last error without calling the function. After the
+/// cooldown, the next call tries again: its failure opens the circuit right away, and its success
+/// closes it.
+pub struct CircuitBreaker<K, R> {
+    failures: u32,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<K, Circuit<R>>>,
+}
+
+impl<K: Hash + Eq, R: Clone> CircuitBreaker<K, R> {
+    pub fn new(failures: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            failures,
+            cooldown,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the last error if the circuit of `key` is open.
+    pub fn check(&self, key: &K) -> Option<R> {
+        let circuits = self.circuits.lock().ok()?;
+        let circuit = circuits.get(key)?;
+        match circuit.open_until {
+            Some(open_until) if Instant::now() < open_until => Some(circuit.last_error.clone()),
+            _ => None,
+        }
+    }
+
+    /// Records the outcome of a call: a success closes the circuit of `key`, and a failure (with the
+    /// returned error) counts towards opening it.
+    pub fn record(&self, key: K, result: &R, failed: bool) {
+        let mut circuits = match self.circuits.lock() {
+            Ok(circuits) => circuits,
+            Err(_) => return,
+        };
+        if !failed {
+            circuits.remove(&key);
+            return;
+        }
+        let circuit = circuits.entry(key).or_insert_with(|| Circuit {
+            failures: 0,
+            open_until: None,
+            last_error: result.clone(),
+        });
+        circuit.failures = circuit.failures.saturating_add(1);
+        circuit.last_error = result.clone();
+        if circuit.failures >= self.failures {
+            circuit.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Invalidation of caches in every process of a deployment, used by `Subscribe`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::convert::TryFrom;
+use std::sync::{Mutex, RwLock};
+
+/// The transport of invalidation messages between processes, e.g. a Redis pub/sub channel, or
+/// [`UnixSocketBus`] for processes on the same host.
+///
+/// `publish` sends a message to all other processes, which pass it to [`deliver_invalidation`] when
+/// they receive it. A message need not be delivered back to its sender, which has applied it already.
+pub trait InvalidationBus: Send + Sync {
+    /// Sends a message to the other processes. Errors are up to the implementation: a message
+    /// which is lost leaves stale entries in the other processes.
+    fn publish(&self, message: &[u8]);
+}
+
+#[derive(Clone, Copy)]
+struct Subscriber {
+    path: &'static str,
+    flush: fn(),
+    remove: fn(&[u8]),
+}
+
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+static BUS: RwLock<Option<Box<dyn InvalidationBus>>> = RwLock::new(None);
+
+// The kinds of messages: the tag is followed by nothing, or by the serialized key.
+const FLUSH: u8 = 0;
+const REMOVE: u8 = 1;
+
+/// Registers the functions flushing a memoized function's cache and removing a serialized key from
+/// it, under the path of the function (`module_path!()::name`). Called once by the code generated for
+/// `Subscribe` functions, before their store is first written by the function or by one of its
+/// helpers.
+#[doc(hidden)]
+pub fn register_subscriber(path: &'static str, flush: fn(), remove: fn(&[u8])) {
+    SUBSCRIBERS.lock().unwrap().push(Subscriber {
+        path,
+        flush,
+        remove,
+    });
+}
+
+/// Deserializes a key published with [`publish_key_invalidation`]. Keys which can't be
+/// deserialized (e.g. of another version of the function) are ignored.
+#[doc(hidden)]
+pub fn decode_key<K: DeserializeOwned>(key: &[u8]) -> Option<K> {
+    bincode::deserialize(key).ok()
+}
+
+/// Sets the bus on which invalidations are published, and replaces any previous one.
+pub fn set_invalidation_bus<B: InvalidationBus + 'static>(bus: B) {
+    *BUS.write().unwrap() = Some(Box::new(bus));
+}
+
+/// Flushes the caches of the `Subscribe` functions matching `fn_tag` (their name or full path, as
+/// with `flush_by_tag`) in this process, and publishes the invalidation to all other processes.
+pub fn publish_invalidation(fn_tag: &str) {
+    publish(encode(FLUSH, fn_tag, &[]));
+}
+
+/// Removes the entry of `key` (the tuple of memoized arguments, or the bare argument of
+/// single-argument functions) from the caches of the `Subscribe` functions matching `fn_tag`, in this
+/// process and all other processes.
+pub fn publish_key_invalidation<K: Serialize + ?Sized>(fn_tag: &str, key: &K) {
+    let key = bincode::serialize(key).expect("memoize: failed to serialize key");
+    publish(encode(REMOVE, fn_tag, &key));
+}
+
+fn publish(message: Vec<u8>) {
+    deliver_invalidation(&message);
+    if let Some(bus) = BUS.read().unwrap().as_ref() {
+        bus.publish(&message);
+    }
+}
+
+fn encode(kind: u8, fn_tag: &str, key: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(fn_tag.len()).expect("memoize: tag too long");
+    let mut message = vec![kind];
+    message.extend_from_slice(&len.to_le_bytes());
+    message.extend_from_slice(fn_tag.as_bytes());
+    message.extend_from_slice(key);
+    message
+}
+
+/// Applies a message received from the [`InvalidationBus`] to the caches of this process. Malformed
+/// messages are ignored.
+pub fn deliver_invalidation(message: &[u8]) {
+    if message.len() < 5 {
+        return;
+    }
+    let (kind, len) = (message[0], &message[1..5]);
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let (fn_tag, key) = match message.get(5..5 + len) {
+        Some(fn_tag) => (fn_tag, &message[5 + len..]),
+        None => return,
+    };
+    let fn_tag = match std::str::from_utf8(fn_tag) {
+        Ok(fn_tag) => fn_tag,
+        Err(_) => return,
+    };
+    // Copy the subscribers, so that invalidating may memoize (and register) further functions.
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS.lock().unwrap().clone();
+    for subscriber in subscribers {
+        let path = subscriber.path;
+        let name = path.rsplit("::").next().unwrap_or(path);
+        if fn_tag != path && fn_tag != name {
+            continue;
+        }
+        match kind {
+            FLUSH => (subscriber.flush)(),
+            REMOVE => (subscriber.remove)(key),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix::UnixSocketBus;
+
+#[cfg(unix)]
+mod unix {
+    use super::{deliver_invalidation, InvalidationBus};
+
+    use std::fs;
+    use std::io;
+    use std::os::unix::net::UnixDatagram;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+
+    /// An [`InvalidationBus`] connecting the processes on one host through Unix datagram sockets in a
+    /// shared directory.
+    ///
+    /// Each process binds a socket in the directory, and a background thread delivers the messages it
+    /// receives. Messages are published to every other socket in the directory; sockets of processes
+    /// which have exited are removed.
+    pub struct UnixSocketBus {
+        socket: UnixDatagram,
+        dir: PathBuf,
+        path: PathBuf,
+    }
+
+    impl UnixSocketBus {
+        /// Joins the bus of the processes using `dir`, which is created if necessary.
+        pub fn join<P: AsRef<Path>>(dir: P) -> io::Result<UnixSocketBus> {
+            let dir = dir.as_ref().to_path_buf();
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(format!("{}.sock", std::process::id()));
+            // A leftover of an earlier process with the same id.
+            let _ = fs::remove_file(&path);
+            let socket = UnixDatagram::bind(&path)?;
+            let receiver = socket.try_clone()?;
+            thread::Builder::new()
+                .name("memoize-bus".to_string())
+                .spawn(move || {
+                    let mut buf = vec![0; 64 * 1024];
+                    while let Ok(len) = receiver.recv(&mut buf) {
+                        deliver_invalidation(&buf[..len]);
+                    }
+                })?;
+            Ok(UnixSocketBus { socket, dir, path })
+        }
+    }
+
+    impl InvalidationBus for UnixSocketBus {
+        fn publish(&self, message: &[u8]) {
+            let peers = match fs::read_dir(&self.dir) {
+                Ok(peers) => peers,
+                Err(_) => return,
+            };
+            for peer in peers.flatten() {
+                let peer = peer.path();
+                if peer == self.path || peer.extension() != Some("sock".as_ref()) {
+                    continue;
+                }
+                if let Err(error) = self.socket.send_to(message, &peer) {
+                    if error.kind() == io::ErrorKind::ConnectionRefused {
+                        let _ = fs::remove_file(&peer);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for UnixSocketBus {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
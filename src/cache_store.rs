@@ -0,0 +1,209 @@
+//! The interface of stores, implemented by the stores of `memoize` and used by `UseStore`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+/// The operations a memoized function performs on its store.
+///
+/// A store named with `#[memoize(UseStore: MY_CACHE)]` is accessed through these methods (or
+/// inherent methods of the same names), so that any map-like type can back a memoized function.
+/// Keys are tuples of the memoized arguments, and values are the return values (or `Entry`s of them,
+/// with options that need to know when a value was computed).
+pub trait CacheStore<K, V> {
+    /// Looks up the value of a key, possibly updating bookkeeping like recency.
+    fn get(&mut self, key: &K) -> Option<&V>;
+
+    /// Inserts a value, replacing any previous value of the key.
+    fn insert(&mut self, key: K, value: V);
+
+    /// Removes a key, returning its value.
+    fn remove(&mut self, key: &K) -> Option<V>;
+
+    /// The number of stored entries.
+    fn len(&self) -> usize;
+
+    /// Whether no entries are stored.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all entries.
+    fn clear(&mut self);
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> CacheStore<K, V> for HashMap<K, V, S> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        HashMap::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        HashMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self)
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: Hash + Eq, V, S: BuildHasher> CacheStore<K, V> for lru::LruCache<K, V, S> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        lru::LruCache::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        lru::LruCache::put(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        lru::LruCache::pop(self, key)
+    }
+
+    fn len(&self) -> usize {
+        lru::LruCache::len(self)
+    }
+
+    fn clear(&mut self) {
+        lru::LruCache::clear(self)
+    }
+}
+
+impl<K: Ord, V> CacheStore<K, V> for BTreeMap<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        BTreeMap::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        BTreeMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        BTreeMap::clear(self)
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: Hash + Eq, V, S: BuildHasher> CacheStore<K, V> for hashbrown::HashMap<K, V, S> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        hashbrown::HashMap::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        hashbrown::HashMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        hashbrown::HashMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        hashbrown::HashMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        hashbrown::HashMap::clear(self)
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: Hash + Eq, V, S: BuildHasher> CacheStore<K, V> for crate::PrehashedMap<K, V, S> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        hashbrown::HashMap::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        hashbrown::HashMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        hashbrown::HashMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        hashbrown::HashMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        hashbrown::HashMap::clear(self)
+    }
+}
+
+// The bounded stores of `Priority` and `Eviction` offer the methods of `lru::LruCache`.
+#[cfg(feature = "full")]
+macro_rules! bounded_cache_store {
+    ($store:ident) => {
+        impl<K: Hash + Eq + Clone, V> CacheStore<K, V> for crate::$store<K, V> {
+            fn get(&mut self, key: &K) -> Option<&V> {
+                crate::$store::get(self, key)
+            }
+
+            fn insert(&mut self, key: K, value: V) {
+                crate::$store::put(self, key, value);
+            }
+
+            fn remove(&mut self, key: &K) -> Option<V> {
+                crate::$store::pop(self, key)
+            }
+
+            fn len(&self) -> usize {
+                crate::$store::len(self)
+            }
+
+            fn clear(&mut self) {
+                crate::$store::clear(self)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "full")]
+bounded_cache_store!(ArcCache);
+#[cfg(feature = "full")]
+bounded_cache_store!(ClockProCache);
+#[cfg(feature = "full")]
+bounded_cache_store!(GreedyDualCache);
+#[cfg(feature = "full")]
+bounded_cache_store!(PriorityLruCache);
+
+#[cfg(feature = "serde")]
+impl<K, V> CacheStore<K, V> for crate::DiskStore<K, V>
+where
+    K: Hash + Eq + serde::Serialize + serde::de::DeserializeOwned,
+    V: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn get(&mut self, key: &K) -> Option<&V> {
+        crate::DiskStore::get(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        crate::DiskStore::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        crate::DiskStore::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        crate::DiskStore::len(self)
+    }
+
+    fn clear(&mut self) {
+        crate::DiskStore::clear(self)
+    }
+}
@@ -0,0 +1,80 @@
+//! Canonical key representations of unordered collections, used by `Canonical`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+/// A type whose logically equal values can have different representations (like the iteration
+/// order of a `HashSet`), and which can be converted into a canonical, hashable representation.
+///
+/// Arguments marked with `#[memoize(Canonical: argument)]` are stored in the cache in their
+/// canonical representation, so that logically equal arguments hit the same entry.
+pub trait Canonicalize {
+    type Canonical: Hash + Eq + Clone;
+
+    fn canonicalize(&self) -> Self::Canonical;
+}
+
+/// The elements, sorted.
+impl<T: Ord + Hash + Clone, S> Canonicalize for HashSet<T, S> {
+    type Canonical = Vec<T>;
+
+    fn canonicalize(&self) -> Vec<T> {
+        let mut elements: Vec<T> = self.iter().cloned().collect();
+        elements.sort_unstable();
+        elements
+    }
+}
+
+/// The entries, sorted by key.
+impl<K: Ord + Hash + Clone, V: Hash + Eq + Clone, S> Canonicalize for HashMap<K, V, S> {
+    type Canonical = Vec<(K, V)>;
+
+    fn canonicalize(&self) -> Vec<(K, V)> {
+        let mut entries: Vec<(K, V)> = self
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+}
+
+impl<T: Ord + Hash + Clone> Canonicalize for BTreeSet<T> {
+    type Canonical = Vec<T>;
+
+    fn canonicalize(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<K: Ord + Hash + Clone, V: Hash + Eq + Clone> Canonicalize for BTreeMap<K, V> {
+    type Canonical = Vec<(K, V)>;
+
+    fn canonicalize(&self) -> Vec<(K, V)> {
+        self.iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// The elements, sorted: the vector is treated as a multiset.
+impl<T: Ord + Hash + Clone> Canonicalize for Vec<T> {
+    type Canonical = Vec<T>;
+
+    fn canonicalize(&self) -> Vec<T> {
+        let mut elements = self.clone();
+        elements.sort();
+        elements
+    }
+}
+
+/// The elements, sorted: the slice is treated as a multiset.
+impl<T: Ord + Hash + Clone> Canonicalize for [T] {
+    type Canonical = Vec<T>;
+
+    fn canonicalize(&self) -> Vec<T> {
+        let mut elements = self.to_vec();
+        elements.sort();
+        elements
+    }
+}
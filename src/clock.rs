@@ -0,0 +1,119 @@
+//! A coarse clock, for caches comparing timestamps on every hit.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// How often the time returned by [`coarse_now`] advances.
+pub const COARSE_CLOCK_RESOLUTION: Duration = Duration::from_millis(1);
+
+// The number of ticks without a read after which the background thread parks.
+const IDLE_TICKS: u32 = 100;
+
+lazy_static::lazy_static! {
+    pub(crate) static ref START: Instant = Instant::now();
+}
+
+// The nanoseconds from `START` to the last tick.
+static TICKS: AtomicU64 = AtomicU64::new(0);
+// Whether the background thread is ticking, rather than parked.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// Whether the clock was read since the last tick.
+static READ: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref TICKER_THREAD: Thread = thread::Builder::new()
+        .name("memoize-clock".to_string())
+        .spawn(run_ticker)
+        .expect("memoize: failed to start the clock thread")
+        .thread()
+        .clone();
+}
+
+fn tick() {
+    // Ticks of the background thread and of a reader waking it may race, but never go backwards.
+    TICKS.fetch_max(START.elapsed().as_nanos() as u64, Ordering::Relaxed);
+}
+
+fn run_ticker() {
+    let mut idle = 0;
+    loop {
+        thread::sleep(COARSE_CLOCK_RESOLUTION);
+        tick();
+        if READ.swap(false, Ordering::Relaxed) {
+            idle = 0;
+            continue;
+        }
+        idle += 1;
+        if idle >= IDLE_TICKS {
+            idle = 0;
+            ACTIVE.store(false, Ordering::SeqCst);
+            // Woken by the next read.
+            while !ACTIVE.load(Ordering::SeqCst) {
+                thread::park();
+            }
+        }
+    }
+}
+
+/// The current time, as of the last tick of a background thread updating it every
+/// [`COARSE_CLOCK_RESOLUTION`].
+///
+/// Reading it is a lot cheaper than `Instant::now()`, but it lags behind by up to about the
+/// resolution, or more if the background thread isn't scheduled in time. It never goes backwards.
+///
+/// The thread is started by the first call. It parks once the clock hasn't been read for 100 ticks,
+/// so that idle programs aren't woken up every millisecond, and the next call (which reads the time
+/// itself) wakes it up again.
+pub fn coarse_now() -> Instant {
+    if !ACTIVE.load(Ordering::Acquire) {
+        wake();
+    }
+    if !READ.load(Ordering::Relaxed) {
+        READ.store(true, Ordering::Relaxed);
+    }
+    *START + Duration::from_nanos(TICKS.load(Ordering::Relaxed))
+}
+
+// Ticks before the thread is marked as active, so that concurrent readers seeing it active see the
+// tick as well.
+#[cold]
+fn wake() {
+    tick();
+    if ACTIVE
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+    {
+        TICKER_THREAD.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_and_parks_when_idle() {
+        let first = coarse_now();
+        thread::sleep(COARSE_CLOCK_RESOLUTION * 20);
+        let second = coarse_now();
+        assert!(second > first);
+        assert!(ACTIVE.load(Ordering::SeqCst));
+
+        // Without reads, the thread parks after `IDLE_TICKS`.
+        thread::sleep(COARSE_CLOCK_RESOLUTION * IDLE_TICKS * 5);
+        assert!(!ACTIVE.load(Ordering::SeqCst));
+        let parked = TICKS.load(Ordering::Relaxed);
+        thread::sleep(COARSE_CLOCK_RESOLUTION * 20);
+        assert_eq!(TICKS.load(Ordering::Relaxed), parked);
+
+        // The next read is up to date, and wakes the thread.
+        let before = Instant::now();
+        let woken = coarse_now();
+        assert!(woken >= before && woken <= Instant::now());
+        assert!(woken >= second);
+        assert!(ACTIVE.load(Ordering::SeqCst));
+        thread::sleep(COARSE_CLOCK_RESOLUTION * 20);
+        assert!(coarse_now() > woken);
+    }
+}
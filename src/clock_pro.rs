@@ -0,0 +1,387 @@
+//! A bounded cache using the CLOCK-Pro replacement policy.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Hot,
+    Cold,
+    // A recently evicted cold entry, whose key is remembered in order to detect reuse.
+    Test,
+}
+
+struct Node<K, V> {
+    key: K,
+    value: Option<V>,
+    kind: Kind,
+    referenced: AtomicBool,
+    prev: usize,
+    next: usize,
+}
+
+/// A bounded cache implementing the CLOCK-Pro policy, which approximates LIRS: entries are hot or
+/// cold, and cold entries that are used again soon after being inserted (or evicted) become hot.
+/// Unlike LRU, a full scan of new keys only evicts cold entries.
+///
+/// Hits only set a flag on the entry and don't reorder anything, which is why `get` takes `&self`.
+///
+/// This is the store used by `#[memoize(Capacity: N, Eviction: ClockPro)]`; it offers the same `get`
+/// and `put` methods as [`lru::LruCache`].
+pub struct ClockProCache<K, V> {
+    // The clock: a ring of nodes, linked by their indices.
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
+    map: HashMap<K, usize>,
+    hand_hot: Option<usize>,
+    hand_cold: Option<usize>,
+    hand_test: Option<usize>,
+    count_hot: usize,
+    count_cold: usize,
+    count_test: usize,
+    cap: usize,
+    // The adaptive target for the number of resident cold entries.
+    cold_target: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> ClockProCache<K, V> {
+    /// Creates a cache holding at most `cap` entries (and remembering up to `cap` evicted keys).
+    pub fn new(cap: NonZeroUsize) -> Self {
+        ClockProCache {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            map: HashMap::new(),
+            hand_hot: None,
+            hand_cold: None,
+            hand_test: None,
+            count_hot: 0,
+            count_cold: 0,
+            count_test: 0,
+            cap: cap.get(),
+            cold_target: 1,
+        }
+    }
+
+    fn node(&self, index: usize) -> &Node<K, V> {
+        self.nodes[index].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<K, V> {
+        self.nodes[index].as_mut().unwrap()
+    }
+
+    /// Returns the value for `key`, marking it as referenced.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let node = self.node(*self.map.get(key)?);
+        let value = node.value.as_ref()?;
+        node.referenced.store(true, Ordering::Relaxed);
+        Some(value)
+    }
+
+    /// Returns the value for `key` without marking it as referenced.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.node(*self.map.get(key)?).value.as_ref()
+    }
+
+    /// Returns whether the cache holds a value for `key`, without marking it as used.
+    pub fn contains(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Inserts a value, evicting an entry if the cache is full. Returns the previous value for
+    /// `key`, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let index = match self.map.get(&key) {
+            None => {
+                self.add(key, value, Kind::Cold);
+                self.count_cold += 1;
+                return None;
+            }
+            Some(index) => *index,
+        };
+        if self.node(index).value.is_some() {
+            let node = self.node_mut(index);
+            node.referenced.store(true, Ordering::Relaxed);
+            return node.value.replace(value);
+        }
+        // The key was evicted recently, so the cold target was too small.
+        if self.cold_target < self.cap {
+            self.cold_target += 1;
+        }
+        self.count_test -= 1;
+        self.unlink(index);
+        self.add(key, value, Kind::Hot);
+        self.count_hot += 1;
+        None
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let index = *self.map.get(key)?;
+        match self.node(index).kind {
+            Kind::Hot => self.count_hot -= 1,
+            Kind::Cold => self.count_cold -= 1,
+            Kind::Test => self.count_test -= 1,
+        }
+        self.unlink(index)
+    }
+
+    /// Iterates over all entries, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.nodes
+            .iter()
+            .flatten()
+            .filter_map(|node| node.value.as_ref().map(|value| (&node.key, value)))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.count_hot + self.count_cold
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn cap(&self) -> NonZeroUsize {
+        NonZeroUsize::new(self.cap).unwrap()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        *self = ClockProCache::new(self.cap());
+    }
+
+    // Inserts a new node at the head of the clock, i.e. just behind the hot hand.
+    fn add(&mut self, key: K, value: V, kind: Kind) {
+        while self.count_hot + self.count_cold >= self.cap {
+            self.run_hand_cold();
+        }
+        let mut node = Node {
+            key: key.clone(),
+            value: Some(value),
+            kind,
+            referenced: AtomicBool::new(false),
+            prev: 0,
+            next: 0,
+        };
+        let index = self.free.pop().unwrap_or(self.nodes.len());
+        match self.hand_hot {
+            None => {
+                node.prev = index;
+                node.next = index;
+                self.hand_hot = Some(index);
+                self.hand_cold = Some(index);
+                self.hand_test = Some(index);
+            }
+            Some(head) => {
+                let prev = self.node(head).prev;
+                node.prev = prev;
+                node.next = head;
+                self.node_mut(prev).next = index;
+                self.node_mut(head).prev = index;
+            }
+        }
+        if index == self.nodes.len() {
+            self.nodes.push(Some(node));
+        } else {
+            self.nodes[index] = Some(node);
+        }
+        self.map.insert(key, index);
+    }
+
+    // Removes a node from the clock, moving hands pointing at it back by one.
+    fn unlink(&mut self, index: usize) -> Option<V> {
+        let node = self.nodes[index].take().unwrap();
+        self.free.push(index);
+        self.map.remove(&node.key);
+        if node.next == index {
+            self.hand_hot = None;
+            self.hand_cold = None;
+            self.hand_test = None;
+        } else {
+            self.node_mut(node.prev).next = node.next;
+            self.node_mut(node.next).prev = node.prev;
+            for hand in [&mut self.hand_hot, &mut self.hand_cold, &mut self.hand_test] {
+                if *hand == Some(index) {
+                    *hand = Some(node.prev);
+                }
+            }
+        }
+        node.value
+    }
+
+    fn next(&self, hand: Option<usize>) -> Option<usize> {
+        hand.map(|index| self.node(index).next)
+    }
+
+    // Evicts or promotes the cold entry under the cold hand, then lets the other hands catch up.
+    fn run_hand_cold(&mut self) {
+        let index = match self.hand_cold {
+            None => return,
+            Some(index) => index,
+        };
+        let node = self.node_mut(index);
+        if node.kind == Kind::Cold {
+            if node.referenced.swap(false, Ordering::Relaxed) {
+                node.kind = Kind::Hot;
+                self.count_cold -= 1;
+                self.count_hot += 1;
+            } else {
+                // Forget the oldest evicted key first: the test hand is ahead of the cold hand, so
+                // that it doesn't stop at the key evicted right now.
+                while self.count_test >= self.cap {
+                    self.run_hand_test();
+                }
+                let node = self.node_mut(index);
+                node.kind = Kind::Test;
+                node.value = None;
+                self.count_cold -= 1;
+                self.count_test += 1;
+            }
+        }
+        self.hand_cold = self.next(self.hand_cold);
+        while self.count_hot > self.cap - self.cold_target {
+            self.run_hand_hot();
+        }
+    }
+
+    // Demotes the hot entry under the hot hand if it wasn't referenced since the last pass.
+    fn run_hand_hot(&mut self) {
+        let index = match self.hand_hot {
+            None => return,
+            Some(index) => index,
+        };
+        let node = self.node_mut(index);
+        if node.kind == Kind::Hot && !node.referenced.swap(false, Ordering::Relaxed) {
+            node.kind = Kind::Cold;
+            self.count_hot -= 1;
+            self.count_cold += 1;
+        }
+        self.hand_hot = self.next(self.hand_hot);
+    }
+
+    // Forgets the evicted key under the test hand.
+    fn run_hand_test(&mut self) {
+        let index = match self.hand_test {
+            None => return,
+            Some(index) => index,
+        };
+        if self.node(index).kind == Kind::Test {
+            // The key wasn't reused while it was remembered, so the cold target was too large.
+            self.unlink(index);
+            self.count_test -= 1;
+            if self.cold_target > 1 {
+                self.cold_target -= 1;
+            }
+        }
+        self.hand_test = self.next(self.hand_test);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(cap: usize) -> ClockProCache<u32, u32> {
+        ClockProCache::new(NonZeroUsize::new(cap).unwrap())
+    }
+
+    fn kind(cache: &ClockProCache<u32, u32>, key: u32) -> Option<Kind> {
+        cache.map.get(&key).map(|index| cache.node(*index).kind)
+    }
+
+    // Fills a cache of 3 entries with 1 (referenced), 2 and 3, and inserts 4.
+    fn filled() -> ClockProCache<u32, u32> {
+        let mut cache = cache(3);
+        cache.put(1, 1);
+        assert_eq!(cache.get(&1), Some(&1));
+        cache.put(2, 2);
+        cache.put(3, 3);
+        assert!(kind(&cache, 1) == Some(Kind::Cold));
+        cache.put(4, 4);
+        cache
+    }
+
+    #[test]
+    fn referenced_cold_entries_become_hot() {
+        let cache = filled();
+        assert!(kind(&cache, 1) == Some(Kind::Hot));
+        assert!(kind(&cache, 4) == Some(Kind::Cold));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn unreferenced_cold_entries_become_test_entries() {
+        let cache = filled();
+        assert!(kind(&cache, 2) == Some(Kind::Test));
+        assert!(!cache.contains(&2));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.iter().count(), 3);
+    }
+
+    #[test]
+    fn reused_test_entries_become_hot() {
+        let mut cache = filled();
+        assert_eq!(cache.put(2, 20), None);
+        assert!(kind(&cache, 2) == Some(Kind::Hot));
+        assert_eq!(cache.peek(&2), Some(&20));
+        // The cold target grew, as the key was evicted too early.
+        assert_eq!(cache.cold_target, 2);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn unreferenced_hot_entries_are_demoted() {
+        let mut cache = filled();
+        cache.put(2, 2);
+        // With two hot entries, only one is left for the target of two cold ones.
+        cache.put(5, 5);
+        assert!(kind(&cache, 1) == Some(Kind::Cold));
+        assert!(kind(&cache, 2) == Some(Kind::Hot));
+        assert_eq!(cache.count_hot, 1);
+    }
+
+    #[test]
+    fn hot_entries_survive_a_scan() {
+        let mut cache = filled();
+        for key in 100..200 {
+            cache.put(key, key);
+            assert!(cache.len() <= 3);
+            assert!(cache.count_test <= 3);
+        }
+        assert!(cache.contains(&1));
+        assert!(cache.contains(&199));
+    }
+
+    #[test]
+    fn test_entries_are_forgotten_oldest_first() {
+        let mut cache = filled();
+        for key in 100..200 {
+            cache.put(key, key);
+        }
+        // The keys evicted last are remembered, so that their reuse is detected.
+        assert!(kind(&cache, 198) == Some(Kind::Cold));
+        assert!(kind(&cache, 197) == Some(Kind::Test));
+        assert!(kind(&cache, 196) == Some(Kind::Test));
+        assert!(kind(&cache, 100).is_none());
+        cache.put(197, 197);
+        assert!(kind(&cache, 197) == Some(Kind::Hot));
+    }
+
+    #[test]
+    fn pop_and_clear() {
+        let mut cache = filled();
+        assert_eq!(cache.pop(&4), Some(4));
+        assert_eq!(cache.pop(&2), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.count_test, 0);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.map.is_empty());
+    }
+}
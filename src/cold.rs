@@ -0,0 +1,9 @@
+//! Keeps the miss path of memoized functions out of line.
+
+/// Runs the miss path `f` of a memoizing wrapper: looking up the cache is all that is left inlined
+/// in the callers, while computing and inserting values is compiled once, away from hot code.
+#[cold]
+#[inline(never)]
+pub fn miss<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
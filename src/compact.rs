@@ -0,0 +1,94 @@
+//! Support for `CompactEntries`.
+
+use crate::clock::START;
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A cached value with the timestamps needed by `TimeToLive` and `ExpireAfterAccess`, stored as
+/// milliseconds since the epoch of its cache (see [`Epoch`]).
+///
+/// Caches store `CompactEntry`s instead of [`Entry`](crate::Entry)s with `CompactEntries`, which
+/// saves a few dozen bytes per entry.
+#[derive(Clone, Debug)]
+pub struct CompactEntry<V> {
+    /// The (stored representation of the) value.
+    pub value: V,
+    /// When the value was computed.
+    pub inserted: u32,
+    /// When the value was last read from the cache (only tracked with `ExpireAfterAccess`).
+    pub accessed: Cell<u32>,
+}
+
+impl<V> CompactEntry<V> {
+    /// Creates an entry for a value that was computed at `ticks`.
+    pub fn new(value: V, ticks: u32) -> Self {
+        CompactEntry {
+            value,
+            inserted: ticks,
+            accessed: Cell::new(ticks),
+        }
+    }
+
+    /// Whether the value is still fresh at `now`, given the `ttl` since it was computed and the
+    /// `tti` since it was last read. Values younger than `refresh` are fresh anyway. Values of a
+    /// previous epoch are never fresh.
+    pub fn is_fresh_at(
+        &self,
+        now: u32,
+        ttl: Option<Duration>,
+        tti: Option<Duration>,
+        refresh: Option<Duration>,
+    ) -> bool {
+        let younger = |then: u32, limit: Duration| age(then, now).is_some_and(|age| age < limit);
+        let expired =
+            |then: u32, limit: Option<Duration>| limit.is_some_and(|limit| !younger(then, limit));
+        (!expired(self.inserted, ttl) && !expired(self.accessed.get(), tti))
+            || refresh.is_some_and(|refresh| younger(self.inserted, refresh))
+    }
+}
+
+/// The time since `then` at `now`, both in ticks of the same epoch. `None` if `then` is later, i.e.
+/// belongs to a previous epoch.
+fn age(then: u32, now: u32) -> Option<Duration> {
+    now.checked_sub(then)
+        .map(|ms| Duration::from_millis(ms as u64))
+}
+
+/// The start of the timestamps of a cache's entries.
+///
+/// Ticks run out after about 49 days. The cache is then cleared, and a new epoch is started.
+pub struct Epoch {
+    // Milliseconds from `START`.
+    start: AtomicU64,
+}
+
+fn millis(now: Instant) -> u64 {
+    now.saturating_duration_since(*START).as_millis() as u64
+}
+
+impl Epoch {
+    pub const fn new() -> Self {
+        Epoch {
+            start: AtomicU64::new(0),
+        }
+    }
+
+    /// The milliseconds from the start of the epoch to `now`, or `None` if the epoch has run out.
+    pub fn ticks(&self, now: Instant) -> Option<u32> {
+        u32::try_from(millis(now).saturating_sub(self.start.load(Ordering::Relaxed))).ok()
+    }
+
+    /// Starts a new epoch at `now`.
+    pub fn reset(&self, now: Instant) {
+        self.start.store(millis(now), Ordering::Relaxed);
+    }
+}
+
+impl Default for Epoch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
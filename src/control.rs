@@ -0,0 +1,115 @@
+//! Operations on caches, independent of their key and value types.
+
+use crate::Stats;
+
+use std::marker::PhantomData;
+
+/// A handle on the cache of a memoized function, as returned by
+/// `memoized_cache_handle_<function name>()`. It can be kept in application state and handed to
+/// administrative code, which doesn't need to know the function or its types.
+///
+/// Every memoized function has a handle, and the trait is object safe, so that generic tooling can
+/// operate on a `Vec<Box<dyn CacheControl>>` of caches with different key and value types.
+///
+/// For thread-local caches, the handle operates on the cache of the calling thread. For caches of
+/// async functions, the methods wait for the store without awaiting, so they can be called from
+/// async code as well.
+pub trait CacheControl: Send + Sync {
+    /// The name of the memoized function.
+    fn name(&self) -> &'static str;
+    /// Removes all entries.
+    fn flush(&self);
+    /// The number of cached entries.
+    fn size(&self) -> usize;
+    /// The hit and miss counts of the function.
+    fn stats(&self) -> Stats;
+    /// Removes the entry of a key serialized with `bincode` (as the tuple of memoized arguments,
+    /// or the bare argument for single-argument functions), and returns whether there was one.
+    ///
+    /// Requires the `serde` feature, and a key implementing `serde::Deserialize`; otherwise, nothing
+    /// is removed.
+    fn remove_serialized(&self, key: &[u8]) -> bool;
+}
+
+impl<C: CacheControl + ?Sized> CacheControl for &C {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn flush(&self) {
+        (**self).flush()
+    }
+
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn stats(&self) -> Stats {
+        (**self).stats()
+    }
+
+    fn remove_serialized(&self, key: &[u8]) -> bool {
+        (**self).remove_serialized(key)
+    }
+}
+
+impl<C: CacheControl + ?Sized> CacheControl for Box<C> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn flush(&self) {
+        (**self).flush()
+    }
+
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn stats(&self) -> Stats {
+        (**self).stats()
+    }
+
+    fn remove_serialized(&self, key: &[u8]) -> bool {
+        (**self).remove_serialized(key)
+    }
+}
+
+/// Deserializes keys of type `K` if it implements `serde::Deserialize`, and gives up otherwise. The
+/// code generated by `memoize` picks the implementation by method resolution, calling `decode()` on
+/// a `&KeyDecoder<K>` with both traits in scope.
+#[doc(hidden)]
+pub struct KeyDecoder<K>(PhantomData<K>);
+
+impl<K> KeyDecoder<K> {
+    pub const fn new() -> Self {
+        KeyDecoder(PhantomData)
+    }
+}
+
+impl<K> Default for KeyDecoder<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub trait DecodeKey<K> {
+    fn decode(&self, key: &[u8]) -> Option<K>;
+}
+
+#[cfg(feature = "serde")]
+impl<K: serde::de::DeserializeOwned> DecodeKey<K> for KeyDecoder<K> {
+    fn decode(&self, key: &[u8]) -> Option<K> {
+        bincode::deserialize(key).ok()
+    }
+}
+
+#[doc(hidden)]
+pub trait SkipKey<K> {
+    fn decode(&self, _key: &[u8]) -> Option<K> {
+        None
+    }
+}
+
+impl<K> SkipKey<K> for &KeyDecoder<K> {}
@@ -0,0 +1,55 @@
+//! Support for `CollectCoroutine` caches.
+
+use std::ops::{Coroutine, CoroutineState};
+use std::pin::Pin;
+
+/// Runs `coroutine` to completion, and returns the values it yielded, along with the value it
+/// returned.
+pub fn materialize<C, Y, R>(coroutine: C) -> (Vec<Y>, R)
+where
+    C: Coroutine<Yield = Y, Return = R>,
+{
+    let mut coroutine = Box::pin(coroutine);
+    let mut yielded = Vec::new();
+    loop {
+        match coroutine.as_mut().resume(()) {
+            CoroutineState::Yielded(value) => yielded.push(value),
+            CoroutineState::Complete(complete) => return (yielded, complete),
+        }
+    }
+}
+
+/// A coroutine yielding the values of a materialized coroutine, and then returning its value.
+pub struct Replay<Y, R> {
+    yielded: std::vec::IntoIter<Y>,
+    complete: Option<R>,
+}
+
+impl<Y, R> Replay<Y, R> {
+    pub fn new((yielded, complete): (Vec<Y>, R)) -> Self {
+        Replay {
+            yielded: yielded.into_iter(),
+            complete: Some(complete),
+        }
+    }
+}
+
+// The fields are never pinned.
+impl<Y, R> Unpin for Replay<Y, R> {}
+
+impl<Y, R> Coroutine for Replay<Y, R> {
+    type Yield = Y;
+    type Return = R;
+
+    fn resume(self: Pin<&mut Self>, _: ()) -> CoroutineState<Y, R> {
+        let this = self.get_mut();
+        match this.yielded.next() {
+            Some(value) => CoroutineState::Yielded(value),
+            None => CoroutineState::Complete(
+                this.complete
+                    .take()
+                    .expect("`Replay` coroutine resumed after completion"),
+            ),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Interning of cached values, used by `DedupValues`.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// A set of shared values, so that equal values computed for different keys are stored only once.
+///
+/// Values are dropped from the set once no cache entry refers to them anymore; dead values are
+/// pruned whenever the set has doubled in size since the last pruning.
+pub struct Interner<T> {
+    values: Mutex<Option<(HashSet<Arc<T>>, usize)>>,
+}
+
+impl<T: Hash + Eq + Clone> Interner<T> {
+    pub const fn new() -> Self {
+        Interner {
+            values: Mutex::new(None),
+        }
+    }
+
+    /// Returns the shared copy of `value`, adding one if there is none yet.
+    pub fn intern(&self, value: &T) -> Arc<T> {
+        let mut values = self.values.lock().unwrap();
+        let (values, prune_at) = values.get_or_insert_with(|| (HashSet::new(), 32));
+        if let Some(shared) = values.get(value) {
+            return shared.clone();
+        }
+        if values.len() >= *prune_at {
+            values.retain(|shared| Arc::strong_count(shared) > 1);
+            *prune_at = 2 * values.len().max(16);
+        }
+        let shared = Arc::new(value.clone());
+        values.insert(shared.clone());
+        shared
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns a copy of an interned value on a cache hit.
+pub fn resolve<T: Clone>(shared: &Arc<T>) -> T {
+    T::clone(shared)
+}
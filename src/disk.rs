@@ -0,0 +1,710 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hash;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::raw::c_int;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once, Weak};
+
+/// Number of records buffered before they are written to the file.
+const BUFFERED_RECORDS: usize = 64;
+
+/// Files start with this magic, followed by the fingerprint of the function and the generation of the
+/// file (both little-endian `u64`s), and then by the records, each prefixed with its length (as a
+/// little-endian `u32`). The generation is incremented whenever the file is cleared.
+const MAGIC: &[u8; 8] = b"memoize\x03";
+const HEADER_LEN: u64 = 24;
+
+lazy_static::lazy_static! {
+    static ref JOURNALS: Mutex<Vec<Weak<Mutex<Journal>>>> = Mutex::new(Vec::new());
+}
+
+/// Writes the entries of all persisted caches (`Persist`, `PersistXdg`) which are not on disk yet.
+///
+/// New entries are buffered, and written in batches. Buffered entries are also written when a
+/// thread-local cache is dropped (at thread exit), and when the process exits normally (by returning
+/// from `main()` or through `std::process::exit()`), but not if it is killed or aborts (e.g. on a
+/// panic with `panic = "abort"`); calling this function writes them right away.
+pub fn flush_persistence() {
+    flush_journals(true);
+}
+
+static REGISTER_AT_EXIT: Once = Once::new();
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> c_int;
+}
+
+/// Registered with `atexit()` when the first persisted cache is opened.
+extern "C" fn flush_at_exit() {
+    // Unwinding out of an `extern "C"` function aborts the process. At exit, a journal locked by
+    // the exiting thread itself (e.g. `exit()` called while a cache is being written) is skipped
+    // rather than waited for.
+    let _ = panic::catch_unwind(|| flush_journals(false));
+}
+
+fn flush_journals(wait: bool) {
+    let journals: Vec<_> = match JOURNALS.lock() {
+        Ok(mut journals) => {
+            journals.retain(|journal| journal.strong_count() > 0);
+            journals.iter().filter_map(Weak::upgrade).collect()
+        }
+        Err(_) => return,
+    };
+    for journal in journals {
+        let journal = if wait {
+            journal.lock().ok()
+        } else {
+            journal.try_lock().ok()
+        };
+        if let Some(mut journal) = journal {
+            journal.write_pending();
+        }
+    }
+}
+
+/// Returns a guard calling [`flush_persistence`] when it is dropped, e.g. at the end of a batch of work
+/// whose results should be on disk even if the process is killed later:
+///
+/// ```ignore
+/// {
+///     let _persistence = memoize::persistence_guard();
+///     // ...
+/// }
+/// ```
+pub fn persistence_guard() -> PersistenceGuard {
+    PersistenceGuard { _private: () }
+}
+
+/// Writes the entries of all persisted caches when dropped; see `persistence_guard`.
+#[must_use = "the entries are written when the guard is dropped"]
+#[derive(Debug)]
+pub struct PersistenceGuard {
+    _private: (),
+}
+
+impl Drop for PersistenceGuard {
+    fn drop(&mut self) {
+        flush_persistence();
+    }
+}
+
+/// The file of a `DiskStore`, and the records which haven't been written to it yet.
+struct Journal {
+    file: File,
+    fingerprint: u64,
+    // Generation of the file in which records have been read up to `offset`.
+    generation: u64,
+    // Length of the file up to which records have been read.
+    offset: u64,
+    pending: Vec<u8>,
+    pending_records: usize,
+}
+
+impl Journal {
+    /// Locks the file (shared or exclusively), and calls `f` while it is locked.
+    fn locked<R>(&mut self, exclusive: bool, f: impl FnOnce(&mut Self) -> R) -> Option<R> {
+        let locked = if exclusive {
+            fs2::FileExt::lock_exclusive(&self.file)
+        } else {
+            fs2::FileExt::lock_shared(&self.file)
+        };
+        locked.ok()?;
+        let result = f(self);
+        let _ = fs2::FileExt::unlock(&self.file);
+        Some(result)
+    }
+
+    /// Reads the header of the file, returning its fingerprint and generation if it has one.
+    fn read_header(&mut self) -> Option<(u64, u64)> {
+        let mut header = [0; HEADER_LEN as usize];
+        self.file.seek(SeekFrom::Start(0)).ok()?;
+        self.file.read_exact(&mut header).ok()?;
+        if &header[..8] != MAGIC {
+            return None;
+        }
+        let word = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        Some((word(8), word(16)))
+    }
+
+    /// The generation of the file, unless it was written for another fingerprint.
+    fn current_generation(&mut self) -> Option<u64> {
+        match self.read_header() {
+            Some((fingerprint, generation)) if fingerprint == self.fingerprint => Some(generation),
+            _ => None,
+        }
+    }
+
+    /// Empties the file, starting the given generation. The file must be locked exclusively.
+    fn reset(&mut self, generation: u64) -> bool {
+        let mut header = [0; HEADER_LEN as usize];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..16].copy_from_slice(&self.fingerprint.to_le_bytes());
+        header[16..].copy_from_slice(&generation.to_le_bytes());
+        if self.file.set_len(0).is_err() || self.file.write_all(&header).is_err() {
+            return false;
+        }
+        self.generation = generation;
+        self.offset = HEADER_LEN;
+        true
+    }
+
+    /// Checks the header of the file. A file which is empty, or was written for another fingerprint
+    /// (i.e. by an incompatible version of the function) is reset.
+    fn start(&mut self) -> bool {
+        self.locked(true, |journal| match journal.read_header() {
+            Some((fingerprint, generation)) if fingerprint == journal.fingerprint => {
+                journal.generation = generation;
+                true
+            }
+            // Processes still using the file with the other fingerprint stop reading it, but
+            // another generation keeps processes with this fingerprint from trusting their offset
+            // once it is reset again.
+            Some((_, generation)) => journal.reset(generation.wrapping_add(1)),
+            None => journal.reset(0),
+        })
+        .unwrap_or(false)
+    }
+
+    /// Reads the records appended by other processes since the last read. Returns whether the file
+    /// has been cleared in between, and the complete records (with their lengths).
+    ///
+    /// Returns `None` if the file has been reset for another fingerprint.
+    fn read_appended(&mut self) -> Option<(bool, Vec<u8>)> {
+        let generation = self.current_generation()?;
+        let len = self.file.metadata().map(|m| m.len()).unwrap_or(self.offset);
+        // The file may have been cleared and appended to beyond the offset since the last read.
+        let cleared = generation != self.generation || len < self.offset;
+        if cleared {
+            self.generation = generation;
+            self.offset = HEADER_LEN;
+        }
+        let mut records = vec![];
+        if len > self.offset
+            && self.file.seek(SeekFrom::Start(self.offset)).is_ok()
+            && self.file.read_to_end(&mut records).is_ok()
+        {
+            // A record being cut off is left for `truncate_torn()`.
+            records.truncate(complete_records(&records));
+            self.offset += records.len() as u64;
+        }
+        Some((cleared, records))
+    }
+
+    /// Whether records have been read up to `offset` in the current generation of the file, which is
+    /// `len` long.
+    fn offset_is_current(&mut self, len: u64) -> bool {
+        len >= self.offset && self.current_generation() == Some(self.generation)
+    }
+
+    /// Removes a record left incomplete at the end of the file (by a process which was killed while
+    /// writing it), so that the records appended after it can be read. Records are only written with
+    /// the file locked exclusively, which it must be here as well.
+    fn truncate_torn(&mut self) {
+        let len = match self.file.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return,
+        };
+        let start = if self.offset_is_current(len) {
+            self.offset
+        } else {
+            HEADER_LEN
+        };
+        let mut records = vec![];
+        if self.file.seek(SeekFrom::Start(start)).is_err()
+            || self.file.read_to_end(&mut records).is_err()
+        {
+            return;
+        }
+        let complete = start + complete_records(&records) as u64;
+        if complete < len {
+            let _ = self.file.set_len(complete);
+        }
+    }
+
+    /// Writes the buffered records.
+    fn write_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.locked(true, |journal| {
+            let pending = std::mem::take(&mut journal.pending);
+            journal.pending_records = 0;
+            // The file has been reset for another fingerprint: the records would be read as
+            // entries of the other version of the function, so they are dropped.
+            let generation = match journal.current_generation() {
+                Some(generation) => generation,
+                None => return,
+            };
+            journal.truncate_torn();
+            let len = journal.file.metadata().map(|m| m.len());
+            // Records of other processes appended in between, or a clear, are read by the next
+            // lookup.
+            let current =
+                len.as_ref().ok() == Some(&journal.offset) && generation == journal.generation;
+            if journal.file.write_all(&pending).is_ok() && current {
+                journal.offset += pending.len() as u64;
+            }
+        });
+    }
+}
+
+/// A cache store persisted to a file, which is shared by all processes using the same file.
+///
+/// Entries are held in memory, and new entries are appended to the file as records (using
+/// `bincode`, prefixed with their length), in batches; see [`flush_persistence`]. Records appended by other processes are read
+/// when a key is not found in memory. All file accesses are protected by advisory file locks, so
+/// that concurrently running processes (e.g. parallel invocations of a CLI tool) can share results
+/// without a daemon.
+///
+/// The cache is best-effort: if the file can't be opened, read or written, entries are only kept in
+/// memory.
+pub struct DiskStore<K, V> {
+    entries: HashMap<K, V>,
+    journal: Option<Arc<Mutex<Journal>>>,
+    cipher: Option<Cipher>,
+}
+
+impl<K: Hash + Eq + Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> DiskStore<K, V> {
+    /// Opens (or creates, with its parent directories) the file at `path`, and reads its entries.
+    ///
+    /// Files written with another `fingerprint` are not loaded, but reset.
+    pub fn open<P: AsRef<Path>>(path: P, fingerprint: u64) -> Self {
+        Self::open_with(path.as_ref(), fingerprint, None)
+    }
+
+    /// Like [`DiskStore::open`], but all records (keys and values) are encrypted with
+    /// ChaCha20-Poly1305, using the given 256-bit `key`.
+    ///
+    /// Records which can't be decrypted with the key are ignored.
+    #[cfg(feature = "encryption")]
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, fingerprint: u64, key: [u8; 32]) -> Self {
+        use chacha20poly1305::KeyInit;
+        let cipher = Cipher::new(&key.into());
+        Self::open_with(path.as_ref(), fingerprint, Some(cipher))
+    }
+
+    fn open_with(path: &Path, fingerprint: u64, cipher: Option<Cipher>) -> Self {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let journal = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .ok()
+            .map(|file| Journal {
+                file,
+                fingerprint,
+                generation: 0,
+                offset: HEADER_LEN,
+                pending: vec![],
+                pending_records: 0,
+            })
+            .and_then(|mut journal| journal.start().then_some(journal))
+            .map(|journal| Arc::new(Mutex::new(journal)));
+        if let Some(journal) = &journal {
+            if let Ok(mut journals) = JOURNALS.lock() {
+                journals.push(Arc::downgrade(journal));
+            }
+            REGISTER_AT_EXIT.call_once(|| unsafe {
+                atexit(flush_at_exit);
+            });
+        }
+        let mut store = DiskStore {
+            entries: HashMap::new(),
+            journal,
+            cipher,
+        };
+        store.locked(false, |_| ());
+        store
+    }
+
+    /// Locks the file, reads the records appended by other processes, and calls `f` with the
+    /// journal.
+    fn locked<R>(&mut self, exclusive: bool, f: impl FnOnce(&mut Journal) -> R) -> Option<R> {
+        let entries = &mut self.entries;
+        let cipher = self.cipher.as_ref();
+        let mut journal = self.journal.as_ref()?.lock().ok()?;
+        journal.locked(exclusive, |journal| {
+            let (cleared, records) = journal.read_appended()?;
+            if cleared {
+                entries.clear();
+            }
+            // Records which can't be decoded (e.g. written with another key) are skipped.
+            for record in RecordIter(&records) {
+                match decode_record::<K, V>(record, cipher) {
+                    Some((key, Some(value))) => {
+                        entries.insert(key, value);
+                    }
+                    Some((key, None)) => {
+                        entries.remove(&key);
+                    }
+                    None => {}
+                }
+            }
+            Some(f(journal))
+        })?
+    }
+
+    /// Buffers a record, which inserts or (without a value) removes an entry.
+    fn append(&mut self, key: &K, value: Option<&V>) {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return,
+        };
+        let mut journal = match journal.lock() {
+            Ok(journal) => journal,
+            Err(_) => return,
+        };
+        let record = match bincode::serialize(&(key, value)) {
+            Ok(record) => record,
+            Err(_) => return,
+        };
+        let record = match &self.cipher {
+            Some(cipher) => match seal(cipher, &record) {
+                Some(sealed) => sealed,
+                None => return,
+            },
+            None => record,
+        };
+        let len = match u32::try_from(record.len()) {
+            Ok(len) => len,
+            Err(_) => return,
+        };
+        journal.pending.extend_from_slice(&len.to_le_bytes());
+        journal.pending.extend_from_slice(&record);
+        journal.pending_records += 1;
+        if journal.pending_records >= BUFFERED_RECORDS {
+            journal.write_pending();
+        }
+    }
+
+    /// Returns the value for `key`, looking for entries added by other processes if it is not known
+    /// yet.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.entries.contains_key(key) {
+            self.locked(false, |_| ());
+        }
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.append(&key, Some(&value));
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.append(key, None);
+        self.entries.remove(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the buffered entries to the file.
+    pub fn flush(&mut self) {
+        if let Some(Ok(mut journal)) = self.journal.as_ref().map(|journal| journal.lock()) {
+            journal.write_pending();
+        }
+    }
+
+    /// Removes all entries, also from the file. Other processes notice the clear by the new
+    /// generation of the file.
+    pub fn clear(&mut self) {
+        self.locked(true, |journal| {
+            journal.pending.clear();
+            journal.pending_records = 0;
+            journal.reset(journal.generation.wrapping_add(1));
+        });
+        self.entries.clear();
+    }
+}
+
+impl<K, V> Drop for DiskStore<K, V> {
+    fn drop(&mut self) {
+        if let Some(Ok(mut journal)) = self.journal.as_ref().map(|journal| journal.lock()) {
+            journal.write_pending();
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+type Cipher = chacha20poly1305::ChaCha20Poly1305;
+// Without the `encryption` feature, there is no cipher.
+#[cfg(not(feature = "encryption"))]
+type Cipher = std::convert::Infallible;
+
+/// Returns the length of the complete records (with their lengths) at the start of `bytes`.
+fn complete_records(bytes: &[u8]) -> usize {
+    let mut complete = 0;
+    while let Some(len) = bytes.get(complete..complete + 4) {
+        let end = complete + 4 + u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if end > bytes.len() {
+            break;
+        }
+        complete = end;
+    }
+    complete
+}
+
+/// Iterates over the records returned by `Journal::read_appended()`, which are all complete.
+struct RecordIter<'a>(&'a [u8]);
+
+impl<'a> Iterator for RecordIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let len = self.0.get(..4)?;
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let (record, rest) = self.0[4..].split_at(len);
+        self.0 = rest;
+        Some(record)
+    }
+}
+
+/// Decodes a record, decrypting it first with a cipher.
+fn decode_record<K: DeserializeOwned, V: DeserializeOwned>(
+    record: &[u8],
+    cipher: Option<&Cipher>,
+) -> Option<(K, Option<V>)> {
+    match cipher {
+        None => bincode::deserialize(record).ok(),
+        Some(cipher) => bincode::deserialize(&unseal(cipher, record)?).ok(),
+    }
+}
+
+/// Encrypts a record, prefixed with its random nonce.
+#[cfg(feature = "encryption")]
+fn seal(cipher: &Cipher, record: &[u8]) -> Option<Vec<u8>> {
+    use chacha20poly1305::aead::{Aead, AeadCore, OsRng};
+    let nonce = Cipher::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.extend(cipher.encrypt(&nonce, record).ok()?);
+    Some(sealed)
+}
+
+#[cfg(feature = "encryption")]
+fn unseal(cipher: &Cipher, sealed: &[u8]) -> Option<Vec<u8>> {
+    use chacha20poly1305::aead::Aead;
+    use chacha20poly1305::Nonce;
+    if sealed.len() < 12 {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+#[cfg(not(feature = "encryption"))]
+fn seal(cipher: &Cipher, _record: &[u8]) -> Option<Vec<u8>> {
+    match *cipher {}
+}
+
+#[cfg(not(feature = "encryption"))]
+fn unseal(cipher: &Cipher, _sealed: &[u8]) -> Option<Vec<u8>> {
+    match *cipher {}
+}
+
+/// Returns the file in which `PersistXdg: "tool"` stores the cache of `function` (a path like
+/// `crate::module::function`).
+///
+/// The file is located in the platform's cache directory: `$XDG_CACHE_HOME` (or `~/.cache`) on Linux,
+/// `~/Library/Caches` on macOS, and `%LOCALAPPDATA%` on Windows; the temporary directory is used if
+/// none of those is known.
+pub fn cache_file(tool: &str, function: &str) -> PathBuf {
+    let file = format!("{}.bin", function.replace("::", "."));
+    cache_dir().join(tool).join(file)
+}
+
+fn cache_dir() -> PathBuf {
+    let var = |name| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let dir = if cfg!(windows) {
+        var("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| home.join(".cache")))
+    };
+    dir.unwrap_or_else(std::env::temp_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A path in the temporary directory which isn't used by another test.
+    fn temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        std::env::temp_dir().join(format!(
+            "memoize-disk-{}-{}-{}.bin",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            name
+        ))
+    }
+
+    fn file_len(path: &Path) -> u64 {
+        fs::metadata(path).unwrap().len()
+    }
+
+    fn append_bytes(path: &Path, bytes: &[u8]) {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(bytes)
+            .unwrap();
+    }
+
+    fn record(bytes: &[u8]) -> Vec<u8> {
+        let mut record = (bytes.len() as u32).to_le_bytes().to_vec();
+        record.extend_from_slice(bytes);
+        record
+    }
+
+    #[test]
+    fn complete_records_stops_at_a_torn_record() {
+        let mut bytes = record(b"abc");
+        bytes.extend(record(b""));
+        let complete = bytes.len();
+        assert_eq!(complete_records(&bytes), complete);
+        bytes.extend_from_slice(&record(b"defgh")[..6]);
+        assert_eq!(complete_records(&bytes), complete);
+        bytes.truncate(complete + 2);
+        assert_eq!(complete_records(&bytes), complete);
+        assert_eq!(
+            RecordIter(&bytes[..complete]).collect::<Vec<_>>(),
+            vec![&b"abc"[..], &b""[..]]
+        );
+    }
+
+    #[test]
+    fn truncate_torn_removes_a_partial_record() {
+        let path = temp_path("torn");
+        let mut store = DiskStore::<u32, String>::open(&path, 1);
+        store.insert(1, "one".to_string());
+        store.flush();
+        let len = file_len(&path);
+
+        // A process killed while writing a record.
+        append_bytes(&path, &record(b"a record which was cut off")[..10]);
+        let journal = store.journal.clone().unwrap();
+        journal.lock().unwrap().locked(true, Journal::truncate_torn);
+        assert_eq!(file_len(&path), len);
+
+        // Records appended afterwards are read by other processes.
+        store.insert(2, "two".to_string());
+        store.flush();
+        let mut other = DiskStore::<u32, String>::open(&path, 1);
+        assert_eq!(other.get(&1).map(String::as_str), Some("one"));
+        assert_eq!(other.get(&2).map(String::as_str), Some("two"));
+        drop((store, other));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn torn_record_is_not_read() {
+        let path = temp_path("torn-read");
+        let mut store = DiskStore::<u32, u32>::open(&path, 1);
+        store.insert(1, 10);
+        store.flush();
+        append_bytes(&path, &record(b"cut off")[..6]);
+
+        let mut other = DiskStore::<u32, u32>::open(&path, 1);
+        assert_eq!(other.get(&1), Some(&10));
+        assert_eq!(other.len(), 1);
+        drop((store, other));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn cleared_file_is_read_again_from_the_header() {
+        let path = temp_path("cleared");
+        let mut reader = DiskStore::<u32, u32>::open(&path, 1);
+        let mut writer = DiskStore::<u32, u32>::open(&path, 1);
+        writer.insert(1, 10);
+        writer.flush();
+        assert_eq!(reader.get(&1), Some(&10));
+        let offset = reader.journal.as_ref().unwrap().lock().unwrap().offset;
+
+        // The file is cleared, and grows beyond the offset of the reader before it looks again.
+        writer.clear();
+        for key in 2..20 {
+            writer.insert(key, key * 10);
+        }
+        writer.flush();
+        assert!(file_len(&path) > offset);
+
+        assert_eq!(reader.get(&19), Some(&190));
+        assert_eq!(reader.len(), 18);
+        assert!(!reader.contains(&1));
+        let journal = reader.journal.as_ref().unwrap().lock().unwrap();
+        assert_eq!(journal.offset, file_len(&path));
+        assert_eq!(journal.generation, 1);
+        drop(journal);
+        drop((reader, writer));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn file_of_another_fingerprint_is_reset() {
+        let path = temp_path("fingerprint");
+        let mut store = DiskStore::<u32, u32>::open(&path, 1);
+        store.insert(1, 10);
+        store.flush();
+        drop(store);
+
+        let mut other = DiskStore::<u32, u32>::open(&path, 2);
+        assert_eq!(other.get(&1), None);
+        assert_eq!(file_len(&path), HEADER_LEN);
+        drop(other);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn records_of_another_fingerprint_are_dropped() {
+        let path = temp_path("fingerprints");
+        let mut old = DiskStore::<u32, u32>::open(&path, 1);
+        old.insert(0, 0);
+        old.flush();
+
+        // A new version of the function resets the file while the old one is still running.
+        let mut new = DiskStore::<u32, i32>::open(&path, 2);
+        old.insert(1, 4_000_000_000);
+        old.flush();
+        assert_eq!(file_len(&path), HEADER_LEN);
+        assert_eq!(new.get(&1), None);
+
+        new.insert(2, -2);
+        new.flush();
+        let mut newer = DiskStore::<u32, i32>::open(&path, 2);
+        assert_eq!(newer.get(&2), Some(&-2));
+        assert_eq!(newer.len(), 1);
+        drop((old, new, newer));
+        let _ = fs::remove_file(&path);
+    }
+}
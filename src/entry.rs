@@ -0,0 +1,242 @@
+//! Cache entries carrying metadata about their value.
+
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A cached value, together with the metadata used to decide whether it is still fresh, and which
+/// entry to evict.
+///
+/// Caches only store `Entry`s if an option needs the metadata (e.g. `TimeToLive`); otherwise they
+/// store plain values. Besides when the value was computed, an entry only stores the metadata its
+/// cache's options need: each of the other fields is a `Cell` if it is tracked, and `()` otherwise.
+/// An `Entry<V>` thus only holds the value and its insertion time.
+#[derive(Clone, Debug)]
+pub struct Entry<V, A = (), X = (), C = (), H = (), T = ()> {
+    /// The (stored representation of the) value.
+    pub value: V,
+    /// When the value was computed.
+    pub inserted: Instant,
+    /// When the value was last read from the cache (tracked with `ExpireAfterAccess` and
+    /// `EntryInfo`).
+    pub accessed: A,
+    /// When the value expires, independently of the cache's `TimeToLive` (tracked with `ExpireWith`
+    /// and `MaxAge`).
+    pub expires: X,
+    /// How long it took to compute the value (tracked with `Eviction: GreedyDual`).
+    pub cost: C,
+    /// How many times the value was read from the cache (tracked with `EntryInfo`).
+    pub hits: H,
+    /// When the value was last confirmed by the validator (tracked with `ValidateEvery`).
+    pub validated: T,
+}
+
+/// A piece of metadata of an [`Entry`]: a `Cell` if it is tracked, or `()` if it isn't.
+pub trait EntryMeta<T>: Clone + fmt::Debug {
+    /// Whether the metadata is tracked.
+    const TRACKED: bool = true;
+
+    /// Starts tracking the metadata with `value`.
+    fn new(value: T) -> Self;
+    /// The metadata, or `None` if it isn't tracked.
+    fn get(&self) -> Option<T>;
+    /// Updates the metadata, if it is tracked.
+    fn set(&self, value: T);
+}
+
+impl<T> EntryMeta<T> for () {
+    const TRACKED: bool = false;
+
+    fn new(_value: T) -> Self {}
+
+    fn get(&self) -> Option<T> {
+        None
+    }
+
+    fn set(&self, _value: T) {}
+}
+
+impl<T: Copy + fmt::Debug> EntryMeta<T> for Cell<T> {
+    fn new(value: T) -> Self {
+        Cell::new(value)
+    }
+
+    fn get(&self) -> Option<T> {
+        Some(Cell::get(self))
+    }
+
+    fn set(&self, value: T) {
+        Cell::set(self, value)
+    }
+}
+
+/// Metadata about a cached entry, as returned by `memoized_entry_info_<function name>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// When the value was computed.
+    pub inserted: Instant,
+    /// When the value was last read from the cache, or computed if it wasn't read yet.
+    pub accessed: Instant,
+    /// How many times the value was read from the cache.
+    pub hits: u64,
+    /// When the value expires, independently of the cache's `TimeToLive`.
+    pub expires: Option<Instant>,
+}
+
+impl<V, A, X, C, H, T> Entry<V, A, X, C, H, T>
+where
+    A: EntryMeta<Instant>,
+    X: EntryMeta<Option<Instant>>,
+    C: EntryMeta<Duration>,
+    H: EntryMeta<u64>,
+    T: EntryMeta<Instant>,
+{
+    /// Creates an entry for a value that was just computed.
+    pub fn new(value: V, expires: Option<Instant>) -> Self {
+        let now = Instant::now();
+        Entry {
+            value,
+            inserted: now,
+            accessed: A::new(now),
+            expires: X::new(expires),
+            cost: C::new(Duration::ZERO),
+            hits: H::new(0),
+            validated: T::new(now),
+        }
+    }
+
+    /// Records how long it took to compute the value.
+    pub fn with_cost(self, cost: Duration) -> Self {
+        self.cost.set(cost);
+        self
+    }
+
+    /// How long it took to compute the value, or zero if it wasn't measured.
+    pub fn cost(&self) -> Duration {
+        self.cost.get().unwrap_or_default()
+    }
+
+    /// When the value was last read from the cache, or computed if that isn't tracked.
+    pub fn accessed_at(&self) -> Instant {
+        self.accessed.get().unwrap_or(self.inserted)
+    }
+
+    /// When the value expires, independently of the cache's `TimeToLive`.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires.get().flatten()
+    }
+
+    /// Records that the value was read from the cache.
+    pub fn touch(&self) {
+        self.touch_at(Instant::now());
+    }
+
+    /// Records that the value was read from the cache at the given time, e.g. read from
+    /// [`coarse_now`](crate::coarse_now).
+    pub fn touch_at(&self, now: Instant) {
+        self.accessed.set(now);
+    }
+
+    /// Records that the value was read from the cache, counting the hit.
+    pub fn record_hit(&self) {
+        self.touch();
+        if let Some(hits) = self.hits.get() {
+            self.hits.set(hits + 1);
+        }
+    }
+
+    /// Whether `every` has passed since the value was last validated. If so, the period restarts now.
+    /// Always true if validation isn't tracked.
+    pub fn validation_due(&self, every: Duration) -> bool {
+        let now = Instant::now();
+        if self
+            .validated
+            .get()
+            .is_some_and(|validated| now.duration_since(validated) < every)
+        {
+            return false;
+        }
+        self.validated.set(now);
+        true
+    }
+
+    /// Returns the metadata of the entry.
+    pub fn info(&self) -> EntryInfo {
+        EntryInfo {
+            inserted: self.inserted,
+            accessed: self.accessed_at(),
+            hits: self.hits.get().unwrap_or(0),
+            expires: self.expires_at(),
+        }
+    }
+
+    /// Returns whether the entry's own expiration time has passed.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Instant::now())
+    }
+
+    /// Returns whether the entry's own expiration time is at or before `now`.
+    pub fn is_expired_at(&self, now: Instant) -> bool {
+        self.expires_at().is_some_and(|expires| now >= expires)
+    }
+
+    /// Whether the value is still fresh at `now`, given the `ttl` since it was computed, the `tti`
+    /// since it was last read, and its own expiration time. Values younger than `refresh` are fresh
+    /// anyway.
+    pub fn is_fresh_at(
+        &self,
+        now: Instant,
+        ttl: Option<Duration>,
+        tti: Option<Duration>,
+        refresh: Option<Duration>,
+    ) -> bool {
+        let age = now.saturating_duration_since(self.inserted);
+        let exceeds = |elapsed: Duration, limit: Option<Duration>| {
+            limit.is_some_and(|limit| elapsed >= limit)
+        };
+        (!exceeds(age, ttl)
+            && !exceeds(now.saturating_duration_since(self.accessed_at()), tti)
+            && !self.is_expired_at(now))
+            || refresh.is_some_and(|refresh| age < refresh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untracked_metadata_takes_no_space() {
+        assert_eq!(
+            std::mem::size_of::<Entry<u64>>(),
+            std::mem::size_of::<(u64, Instant)>()
+        );
+        assert_eq!(
+            std::mem::size_of::<Entry<u64, Cell<Instant>>>(),
+            std::mem::size_of::<(u64, Instant, Instant)>()
+        );
+    }
+
+    #[test]
+    fn untracked_metadata_has_defaults() {
+        let entry: Entry<u64> = Entry::new(1, None).with_cost(Duration::from_secs(1));
+        entry.record_hit();
+        let info = entry.info();
+        assert_eq!(info.accessed, entry.inserted);
+        assert_eq!(info.hits, 0);
+        assert_eq!(entry.cost(), Duration::ZERO);
+        assert!(entry.validation_due(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn tracked_metadata_is_updated() {
+        let entry: Entry<u64, Cell<Instant>, (), Cell<Duration>, Cell<u64>, Cell<Instant>> =
+            Entry::new(1, None).with_cost(Duration::from_secs(1));
+        entry.record_hit();
+        entry.record_hit();
+        assert_eq!(entry.info().hits, 2);
+        assert!(entry.info().accessed >= entry.inserted);
+        assert_eq!(entry.cost(), Duration::from_secs(1));
+        assert!(!entry.validation_due(Duration::from_secs(60)));
+    }
+}
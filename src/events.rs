@@ -0,0 +1,78 @@
+//! A stream of events of instrumented caches, for external tooling like live cache dashboards.
+
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// What happened to a cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheEventKind {
+    /// A value was found in the cache.
+    Hit,
+    /// A value was not found in the cache, and will be computed.
+    Miss,
+    /// A value was inserted into the cache.
+    Insert,
+    /// An entry was evicted from a full bounded cache.
+    Evict,
+    /// The cache was flushed.
+    Flush,
+}
+
+/// An event of the cache of a function memoized with `Instrument`.
+#[derive(Clone, Debug)]
+pub struct CacheEvent {
+    /// The name of the memoized function.
+    pub function: &'static str,
+    pub kind: CacheEventKind,
+    /// A hash of the key tuple the event is about, if any (evictions and flushes have none). It is
+    /// computed with [`StableHasher`](crate::StableHasher), so that the events of several processes
+    /// and builds can be correlated.
+    pub key_hash: Option<u64>,
+    pub timestamp: SystemTime,
+}
+
+lazy_static::lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<Sender<CacheEvent>>> = Mutex::new(Vec::new());
+}
+// The number of subscribers, checked before doing any work for an event.
+static N_SUBSCRIBERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a channel receiving the events of all caches of functions memoized with `Instrument`,
+/// from all threads. Events are only generated while there are subscribers; dropping the receiver
+/// unsubscribes.
+pub fn subscribe_events() -> Receiver<CacheEvent> {
+    let (sender, receiver) = channel();
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.push(sender);
+    N_SUBSCRIBERS.store(subscribers.len(), Ordering::Relaxed);
+    receiver
+}
+
+/// Returns whether anybody is subscribed to events.
+#[doc(hidden)]
+pub fn enabled() -> bool {
+    N_SUBSCRIBERS.load(Ordering::Relaxed) > 0
+}
+
+/// Hashes a key tuple for a [`CacheEvent`].
+#[doc(hidden)]
+pub fn key_hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    crate::stable_hash(key)
+}
+
+/// Sends an event to all subscribers. Called by the code generated for `Instrument`.
+#[doc(hidden)]
+pub fn emit(function: &'static str, kind: CacheEventKind, key_hash: Option<u64>) {
+    let event = CacheEvent {
+        function,
+        kind,
+        key_hash,
+        timestamp: SystemTime::now(),
+    };
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    N_SUBSCRIBERS.store(subscribers.len(), Ordering::Relaxed);
+}
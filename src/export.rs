@@ -0,0 +1,73 @@
+//! The wire format of `memoized_export_<function name>()` and `memoized_import_<function name>()`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+/// Exports start with this magic, followed by the entries as records, each prefixed with its length
+/// (as a little-endian `u32`).
+const MAGIC: &[u8; 8] = b"memoexp\x01";
+
+/// Serializes entries for `memoized_import_<function name>()`, which is generated with
+/// `#[memoize(Export)]` as well.
+pub fn export_entries<K: Serialize, V: Serialize>(entries: &[(K, V)]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    for entry in entries {
+        let record = bincode::serialize(entry).expect("memoize: failed to serialize entry");
+        let len = u32::try_from(record.len()).expect("memoize: entry too large to export");
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&record);
+    }
+    bytes
+}
+
+/// Deserializes the entries exported by [`export_entries`].
+pub fn import_entries<K: DeserializeOwned, V: DeserializeOwned>(
+    mut bytes: &[u8],
+) -> io::Result<Vec<(K, V)>> {
+    let invalid = |error: &str| io::Error::new(io::ErrorKind::InvalidData, error.to_string());
+    if !bytes.starts_with(MAGIC) {
+        return Err(invalid("not a memoize export"));
+    }
+    bytes = &bytes[MAGIC.len()..];
+    let mut entries = vec![];
+    while !bytes.is_empty() {
+        if bytes.len() < 4 {
+            return Err(invalid("truncated memoize export"));
+        }
+        let (len, rest) = bytes.split_at(4);
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if rest.len() < len {
+            return Err(invalid("truncated memoize export"));
+        }
+        let (record, rest) = rest.split_at(len);
+        entries.push(bincode::deserialize(record).map_err(|error| invalid(&error.to_string()))?);
+        bytes = rest;
+    }
+    Ok(entries)
+}
+
+/// Writes `bytes` (e.g. an export) to a pipe or socket, prefixed with its length as a little-endian
+/// `u64`, so that the receiving process can read it with [`read_frame`].
+pub fn write_frame<W: Write>(mut writer: W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+/// Reads the bytes written by [`write_frame`].
+pub fn read_frame<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated frame",
+        ));
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,227 @@
+//! A bounded cache keeping expensive-to-compute entries longer (GreedyDual).
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+struct Slot<V> {
+    value: V,
+    cost: u64,
+    credit: u64,
+    tick: u64,
+}
+
+/// A bounded cache implementing the GreedyDual eviction policy.
+///
+/// Each entry has a credit, which is set to the current inflation value plus the entry's cost
+/// (the time it took to compute) whenever it is inserted or used. A full cache evicts the entry with
+/// the least credit, and raises the inflation value to that credit, so that entries which are not
+/// used any more eventually age out, however expensive they were to compute.
+///
+/// This is the store used by `#[memoize(Capacity: N, Eviction: GreedyDual)]`; it offers the same
+/// `get` and `put` methods as [`lru::LruCache`].
+pub struct GreedyDualCache<K, V> {
+    map: HashMap<K, Slot<V>>,
+    // Keys ordered by eviction order: least credit first, then least recently used first.
+    order: BTreeMap<(u64, u64), K>,
+    cap: NonZeroUsize,
+    inflation: u64,
+    tick: u64,
+    cost: fn(&V) -> Duration,
+}
+
+impl<K: Hash + Eq + Clone, V> GreedyDualCache<K, V> {
+    /// Creates a cache holding at most `cap` entries, where `cost` returns the computation time of a
+    /// value.
+    pub fn new(cap: NonZeroUsize, cost: fn(&V) -> Duration) -> Self {
+        GreedyDualCache {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            cap,
+            inflation: 0,
+            tick: 0,
+            cost,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Returns the value for `key`, renewing its credit.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let slot = self.map.get_mut(key)?;
+        let key = self.order.remove(&(slot.credit, slot.tick)).unwrap();
+        slot.credit = self.inflation.saturating_add(slot.cost);
+        slot.tick = tick;
+        self.order.insert((slot.credit, tick), key);
+        Some(&slot.value)
+    }
+
+    /// Returns the value for `key` without renewing its credit.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key).map(|slot| &slot.value)
+    }
+
+    /// Returns whether the cache holds a value for `key`, without marking it as used.
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a value, evicting an entry if the cache is full. Returns the previous value for
+    /// `key`, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let cost = u64::try_from((self.cost)(&value).as_nanos()).unwrap_or(u64::MAX);
+        let tick = self.next_tick();
+        if let Some(slot) = self.map.get_mut(&key) {
+            let key = self.order.remove(&(slot.credit, slot.tick)).unwrap();
+            slot.cost = cost;
+            slot.credit = self.inflation.saturating_add(cost);
+            slot.tick = tick;
+            self.order.insert((slot.credit, tick), key);
+            return Some(std::mem::replace(&mut slot.value, value));
+        }
+        if self.map.len() >= self.cap.get() {
+            if let Some(((credit, _), evicted)) = self.order.pop_first() {
+                self.inflation = credit;
+                self.map.remove(&evicted);
+            }
+        }
+        let credit = self.inflation.saturating_add(cost);
+        self.order.insert((credit, tick), key.clone());
+        self.map.insert(
+            key,
+            Slot {
+                value,
+                cost,
+                credit,
+                tick,
+            },
+        );
+        None
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let slot = self.map.remove(key)?;
+        self.order.remove(&(slot.credit, slot.tick));
+        Some(slot.value)
+    }
+
+    /// Iterates over all entries, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter().map(|(key, slot)| (key, &slot.value))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+        self.inflation = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values are their own cost, in nanoseconds.
+    fn cache(cap: usize) -> GreedyDualCache<u32, u64> {
+        GreedyDualCache::new(NonZeroUsize::new(cap).unwrap(), |cost| {
+            Duration::from_nanos(*cost)
+        })
+    }
+
+    #[test]
+    fn cheapest_entry_is_evicted() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.put(2, 5);
+        cache.put(3, 7);
+        assert!(cache.contains(&1) && cache.contains(&3));
+        assert!(!cache.contains(&2));
+        assert_eq!(cache.inflation, 5);
+    }
+
+    #[test]
+    fn equal_costs_evict_the_least_recently_used() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.put(2, 10);
+        assert_eq!(cache.get(&1), Some(&10));
+        cache.put(3, 10);
+        assert!(cache.contains(&1) && !cache.contains(&2));
+    }
+
+    #[test]
+    fn use_renews_credit_from_the_inflation() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        cache.put(2, 20);
+        // Evicts 1, so that new credits start at 10: 3 has the same credit as 2.
+        cache.put(3, 10);
+        assert_eq!(cache.inflation, 10);
+        // Without the renewal, 2 would be evicted first as the least recently used.
+        assert_eq!(cache.get(&2), Some(&20));
+        cache.put(4, 10);
+        assert!(cache.contains(&2) && !cache.contains(&3));
+        assert_eq!(cache.inflation, 20);
+    }
+
+    #[test]
+    fn expensive_entries_age_out() {
+        let mut cache = cache(2);
+        cache.put(1, 100);
+        for key in 2..10 {
+            cache.put(key, 10);
+            assert!(cache.contains(&1));
+        }
+        // Every eviction (from the insertion of 3 on) raised the inflation by 10.
+        assert_eq!(cache.inflation, 70);
+        cache.put(10, 10);
+        cache.put(11, 10);
+        // 11 has the same credit as 1, which is evicted as the least recently used.
+        assert!(cache.contains(&1));
+        cache.put(12, 10);
+        assert!(!cache.contains(&1));
+        assert_eq!(cache.inflation, 100);
+    }
+
+    #[test]
+    fn put_pop_and_clear() {
+        let mut cache = cache(2);
+        cache.put(1, 10);
+        assert_eq!(cache.put(1, 30), Some(10));
+        assert_eq!(cache.map[&1].credit, 30);
+        cache.put(2, 20);
+        assert_eq!(cache.pop(&2), Some(20));
+        assert_eq!(cache.pop(&2), None);
+        assert_eq!(cache.len(), 1);
+        cache.put(3, 5);
+        cache.put(4, 5);
+        assert_eq!(cache.inflation, 5);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.order.is_empty());
+        assert_eq!(cache.inflation, 0);
+    }
+}
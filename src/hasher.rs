@@ -0,0 +1,121 @@
+//! The hasher of the stores generated by `#[memoize]`, unless a `CustomHasher` is given, and a
+//! stable hasher for keys stored outside of the process.
+
+use std::hash::{Hash, Hasher};
+
+#[cfg(feature = "fxhash")]
+type Selected = rustc_hash::FxBuildHasher;
+#[cfg(all(feature = "ahash", not(feature = "fxhash")))]
+type Selected = ahash::RandomState;
+#[cfg(not(any(feature = "fxhash", feature = "ahash")))]
+type Selected = std::collections::hash_map::RandomState;
+
+/// Builds the hashers of all memoized functions' caches.
+///
+/// This is the standard library's `RandomState`, unless the `fxhash` feature selects
+/// `rustc_hash::FxBuildHasher`, or the `ahash` feature selects `ahash::RandomState` (`fxhash` takes
+/// precedence if both are enabled). Only the `HashMap` and LRU stores use it; other eviction policies
+/// and `CustomHasher` keep their own hashers.
+pub type DefaultHashBuilder = Selected;
+
+/// A hasher whose output only depends on the data hashed, and never changes for a given
+/// [`StableHasher::VERSION`]: unlike `std`'s `DefaultHasher`, whose algorithm may change between
+/// Rust releases, and `RandomState`, which is seeded per process, it can derive keys which are stored
+/// outside of the process (e.g. in files, or shared with other machines).
+///
+/// Version 1 is 64-bit FNV-1a. Integers are hashed as little-endian bytes, and `usize`/`isize` as
+/// 64-bit integers, so that the output is the same on all platforms. Note that the way a type feeds
+/// itself to the hasher is up to its `Hash` implementation, which for some `std` types is not
+/// guaranteed to stay the same either; with the `serde` feature, [`stable_key_hash`] hashes the
+/// `bincode` serialization of a key instead.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    /// The version of the hash algorithm, which changes whenever the output does.
+    pub const VERSION: u32 = 1;
+
+    pub const fn new() -> Self {
+        StableHasher {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64)
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16)
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32)
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64)
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128)
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as i64 as u64)
+    }
+}
+
+/// Hashes a key with [`StableHasher`].
+pub fn stable_hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = StableHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes the `bincode` serialization of a key with [`StableHasher`]. The result only changes with
+/// the serialized form of the key, i.e. when its type changes.
+#[cfg(feature = "serde")]
+pub fn stable_key_hash<K: serde::Serialize + ?Sized>(key: &K) -> u64 {
+    let mut hasher = StableHasher::new();
+    let serialized = bincode::serialize(key).expect("memoize: failed to serialize key");
+    hasher.write(&serialized);
+    hasher.finish()
+}
@@ -0,0 +1,101 @@
+//! Process-wide hooks instrumenting every memoized function.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Callbacks invoked by every memoized function, e.g. to export metrics in a uniform way. All
+/// methods do nothing by default, and receive the name of the memoized function.
+///
+/// Hooks are called while the cache is locked (or borrowed), so they must not call memoized
+/// functions themselves.
+pub trait Hooks: Send + Sync + 'static {
+    /// A value was found in the cache.
+    fn on_hit(&self, _function: &'static str) {}
+    /// A value was not found in the cache, and will be computed.
+    fn on_miss(&self, _function: &'static str) {}
+    /// A value was inserted into the cache.
+    fn on_insert(&self, _function: &'static str) {}
+    /// An entry was evicted from a full bounded cache.
+    fn on_evict(&self, _function: &'static str) {}
+    /// The cache was flushed.
+    fn on_flush(&self, _function: &'static str) {}
+    /// Recomputing an expired value failed, and the expired value was served instead (with
+    /// `StaleOnError`).
+    fn on_stale(&self, _function: &'static str) {}
+}
+
+lazy_static::lazy_static! {
+    static ref HOOKS: RwLock<Option<Arc<dyn Hooks>>> = RwLock::new(None);
+}
+// Whether hooks are set, checked before doing any work for them.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the hooks called by all memoized functions, replacing previously set hooks.
+pub fn set_hooks(hooks: impl Hooks) {
+    *HOOKS.write().unwrap() = Some(Arc::new(hooks));
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Removes the hooks set by [`set_hooks`].
+pub fn clear_hooks() {
+    ENABLED.store(false, Ordering::Release);
+    *HOOKS.write().unwrap() = None;
+}
+
+/// Returns whether hooks are set.
+#[doc(hidden)]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Calls `f` with the hooks, if they are set. Called by the code generated by `memoize`.
+#[doc(hidden)]
+pub fn call(f: impl FnOnce(&dyn Hooks)) {
+    if !enabled() {
+        return;
+    }
+    // Don't hold the lock while calling the hooks.
+    let hooks = HOOKS.read().unwrap().clone();
+    if let Some(hooks) = hooks {
+        f(&*hooks);
+    }
+}
+
+/// Reports an insertion to the hooks.
+#[doc(hidden)]
+pub fn record_insert(function: &'static str) {
+    call(|hooks| hooks.on_insert(function));
+}
+
+/// Reports an eviction to the hooks (and OpenTelemetry).
+#[doc(hidden)]
+pub fn record_eviction(function: &'static str) {
+    call(|hooks| hooks.on_evict(function));
+    #[cfg(feature = "opentelemetry")]
+    crate::otel::record_eviction(function);
+}
+
+/// Reports a flush to the hooks.
+#[doc(hidden)]
+pub fn record_flush(function: &'static str) {
+    call(|hooks| hooks.on_flush(function));
+}
+
+/// Reports that an expired value was served to the hooks.
+#[doc(hidden)]
+pub fn record_stale(function: &'static str) {
+    call(|hooks| hooks.on_stale(function));
+}
+
+/// Reports a number of hits and misses to the hooks.
+#[doc(hidden)]
+pub fn record(function: &'static str, hits: u64, misses: u64) {
+    call(|hooks| {
+        for _ in 0..hits {
+            hooks.on_hit(function);
+        }
+        for _ in 0..misses {
+            hooks.on_miss(function);
+        }
+    });
+}
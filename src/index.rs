@@ -0,0 +1,54 @@
+//! Support for `IndexBy`.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Maps secondary keys, computed from the cached entries, to the keys of the entries.
+///
+/// The index is only added to: entries which were removed or replaced in the meantime are skipped
+/// when removing by a secondary key, and the index is rebuilt once it has grown much larger than the
+/// cache.
+pub struct ReverseIndex<I, K> {
+    keys: HashMap<I, HashSet<K>>,
+    // The number of indexed keys, over all secondary keys.
+    len: usize,
+}
+
+impl<I: Hash + Eq, K: Hash + Eq> ReverseIndex<I, K> {
+    pub fn new() -> Self {
+        ReverseIndex {
+            keys: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Records that the entry of `key` has the secondary key `index`.
+    pub fn insert(&mut self, index: I, key: K) {
+        if self.keys.entry(index).or_default().insert(key) {
+            self.len += 1;
+        }
+    }
+
+    /// Removes and returns the keys recorded for the secondary key `index`.
+    pub fn take(&mut self, index: &I) -> HashSet<K> {
+        let keys = self.keys.remove(index).unwrap_or_default();
+        self.len -= keys.len();
+        keys
+    }
+
+    /// Whether the index should be rebuilt, given the number of entries of the cache.
+    pub fn needs_rebuild(&self, cache_len: usize) -> bool {
+        self.len > 2 * cache_len + 16
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.len = 0;
+    }
+}
+
+impl<I: Hash + Eq, K: Hash + Eq> Default for ReverseIndex<I, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,124 @@
+//! Tracking of the computations of async functions, for `memoized_flush_<function>_async()`.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// Counts the running computations of a memoized async function, by the flush epoch in which they
+/// started.
+pub struct InFlight {
+    state: Mutex<State>,
+}
+
+struct State {
+    epoch: u64,
+    // The number of running computations per epoch, without zeroes.
+    running: BTreeMap<u64, usize>,
+    waiters: Vec<Waker>,
+}
+
+impl InFlight {
+    pub const fn new() -> Self {
+        InFlight {
+            state: Mutex::new(State {
+                epoch: 0,
+                running: BTreeMap::new(),
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state is consistent after every operation, even if a waker panicked.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records that a computation starts. It is finished (or cancelled) when the returned guard is
+    /// dropped.
+    pub fn start(&self) -> Running<'_> {
+        let mut state = self.state();
+        let epoch = state.epoch;
+        *state.running.entry(epoch).or_default() += 1;
+        Running {
+            in_flight: self,
+            epoch,
+        }
+    }
+
+    /// Starts a new epoch, and returns a future resolving once all computations started before are
+    /// finished.
+    pub fn flush(&self) -> Drained<'_> {
+        let mut state = self.state();
+        state.epoch += 1;
+        Drained {
+            in_flight: self,
+            epoch: state.epoch,
+        }
+    }
+}
+
+impl Default for InFlight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A running computation.
+pub struct Running<'a> {
+    in_flight: &'a InFlight,
+    epoch: u64,
+}
+
+impl Running<'_> {
+    /// Whether no flush started since the computation started, i.e. whether its value can be cached.
+    pub fn is_current(&self) -> bool {
+        self.in_flight.state().epoch == self.epoch
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.in_flight.state();
+            let running = state.running.get_mut(&self.epoch).unwrap();
+            *running -= 1;
+            if *running > 0 {
+                return;
+            }
+            state.running.remove(&self.epoch);
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+/// Resolves once all computations started before a flush are finished.
+pub struct Drained<'a> {
+    in_flight: &'a InFlight,
+    epoch: u64,
+}
+
+impl Future for Drained<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.in_flight.state();
+        if state.running.range(..self.epoch).next().is_none() {
+            return Poll::Ready(());
+        }
+        if !state
+            .waiters
+            .iter()
+            .any(|waiter| waiter.will_wake(cx.waker()))
+        {
+            state.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
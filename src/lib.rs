@@ -1,5 +1,209 @@
+#![cfg_attr(feature = "nightly", feature(coroutine_trait))]
+
+#[cfg(all(feature = "shared", feature = "thread-local"))]
+compile_error!("The `shared` and `thread-local` features cannot be enabled at the same time");
+
 pub use ::lazy_static;
-pub use ::memoize_inner::memoize;
+pub use ::memoize_inner::{defaults, memoize};
+#[doc(hidden)]
+pub use ::memoize_inner::{
+    memoize_defaulted as __memoize_defaulted, memoize_with_defaults as __memoize_with_defaults,
+};
 
 #[cfg(feature = "full")]
 pub use ::lru;
+
+#[cfg(feature = "startup")]
+pub use ::ctor;
+
+#[cfg(feature = "rayon")]
+pub use ::rayon;
+
+#[cfg(feature = "tokio")]
+pub use ::tokio;
+
+#[cfg(any(feature = "tokio", feature = "async"))]
+pub use ::futures;
+
+mod canonical;
+pub use canonical::Canonicalize;
+
+mod ptr_key;
+pub use ptr_key::PtrKey;
+
+#[cfg(feature = "nfc")]
+mod nfc;
+#[cfg(feature = "nfc")]
+pub use nfc::nfc;
+
+mod entry;
+pub use entry::{Entry, EntryInfo, EntryMeta};
+
+mod clock;
+pub use clock::{coarse_now, COARSE_CLOCK_RESOLUTION};
+
+#[doc(hidden)]
+pub mod compact;
+pub use compact::CompactEntry;
+
+mod cache_store;
+pub use cache_store::CacheStore;
+
+#[doc(hidden)]
+pub mod memoized;
+
+mod hasher;
+#[cfg(feature = "serde")]
+pub use hasher::stable_key_hash;
+pub use hasher::{stable_hash, DefaultHashBuilder, StableHasher};
+
+#[cfg(feature = "full")]
+mod arc;
+#[cfg(feature = "full")]
+pub use arc::ArcCache;
+
+#[cfg(feature = "full")]
+mod clock_pro;
+#[cfg(feature = "full")]
+pub use clock_pro::ClockProCache;
+
+#[cfg(feature = "full")]
+mod greedy_dual;
+#[cfg(feature = "full")]
+pub use greedy_dual::GreedyDualCache;
+
+#[cfg(feature = "full")]
+mod prehashed;
+#[cfg(feature = "full")]
+pub use prehashed::PrehashedMap;
+
+#[cfg(feature = "full")]
+mod priority;
+#[cfg(feature = "full")]
+pub use priority::PriorityLruCache;
+
+mod bloom;
+pub use bloom::{BloomFilter, NegativeFilter};
+
+#[doc(hidden)]
+pub mod schedule;
+#[cfg(feature = "schedule")]
+pub use schedule::{
+    flush_by_tag, flush_request, flush_tags, schedule_flush, InvalidSchedule, RequestFlush,
+};
+
+mod sketch;
+pub use sketch::FrequencySketch;
+
+#[cfg(feature = "full")]
+#[doc(hidden)]
+pub mod auto_capacity;
+
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+#[doc(hidden)]
+pub mod memory;
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+pub use memory::{
+    check_memory, resident_memory, set_memory_high_water_mark, shrink_caches, shrinkable_caches,
+};
+
+mod stats;
+pub use stats::*;
+
+#[doc(hidden)]
+pub mod control;
+pub use control::CacheControl;
+
+#[doc(hidden)]
+pub mod hooks;
+pub use hooks::{clear_hooks, set_hooks, Hooks};
+
+#[doc(hidden)]
+pub mod events;
+pub use events::{subscribe_events, CacheEvent, CacheEventKind};
+
+mod warm;
+pub use warm::*;
+
+#[cfg(any(feature = "tokio", feature = "async"))]
+mod lock;
+#[cfg(any(feature = "tokio", feature = "async"))]
+pub use lock::{yield_now, AsyncLock, AsyncMutex, MAINTENANCE_CHUNK};
+
+#[cfg(any(feature = "tokio", feature = "async"))]
+#[doc(hidden)]
+pub mod shared_future;
+
+#[cfg(any(feature = "tokio", feature = "async"))]
+#[doc(hidden)]
+pub mod stream;
+
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+pub mod coroutine;
+
+#[doc(hidden)]
+pub mod inflight;
+
+#[doc(hidden)]
+pub mod cold;
+
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub mod batch;
+
+#[doc(hidden)]
+pub mod scope;
+pub use scope::scope;
+#[cfg(feature = "tokio")]
+pub use scope::task_scope;
+
+#[cfg(feature = "tower")]
+pub mod tower;
+
+#[doc(hidden)]
+pub mod dedup;
+
+#[doc(hidden)]
+pub mod breaker;
+
+#[doc(hidden)]
+pub mod index;
+
+#[cfg(feature = "serde")]
+pub mod serialized;
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub mod secondary;
+#[cfg(feature = "serde")]
+pub use secondary::{flush_write_behind, SecondaryStore};
+
+#[cfg(feature = "serde")]
+mod seed;
+#[cfg(feature = "serde")]
+pub use seed::{read_seed, write_seed};
+
+#[cfg(feature = "serde")]
+mod export;
+#[cfg(feature = "serde")]
+pub use export::{export_entries, import_entries, read_frame, write_frame};
+
+#[cfg(feature = "invalidation")]
+#[doc(hidden)]
+pub mod bus;
+#[cfg(all(feature = "invalidation", unix))]
+pub use bus::UnixSocketBus;
+#[cfg(feature = "invalidation")]
+pub use bus::{
+    deliver_invalidation, publish_invalidation, publish_key_invalidation, set_invalidation_bus,
+    InvalidationBus,
+};
+
+#[cfg(feature = "serde")]
+mod disk;
+#[cfg(feature = "serde")]
+pub use disk::{cache_file, flush_persistence, persistence_guard, DiskStore, PersistenceGuard};
+
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
@@ -0,0 +1,91 @@
+use std::future::Future;
+use std::ops::DerefMut;
+
+/// The mutex guarding the shared store of async memoized functions.
+///
+/// With the `tokio` feature, this is `tokio::sync::Mutex`; otherwise (with the `async` feature) it is
+/// the runtime-agnostic `async_lock::Mutex`, which works with async-std, smol, or any other executor.
+#[cfg(feature = "tokio")]
+pub type AsyncMutex<T> = ::tokio::sync::Mutex<T>;
+#[cfg(not(feature = "tokio"))]
+pub type AsyncMutex<T> = ::async_lock::Mutex<T>;
+
+/// A lock whose acquisition can be awaited, without blocking the executor's worker threads.
+///
+/// Memoized async functions only access their shared store through this trait, so that they don't
+/// depend on a particular async runtime.
+pub trait AsyncLock<T: Send>: Sync {
+    type Guard<'a>: DerefMut<Target = T> + Send
+    where
+        Self: 'a;
+
+    /// Waits until the lock is acquired.
+    fn lock(&self) -> impl Future<Output = Self::Guard<'_>> + Send;
+
+    /// Blocks the current thread until the lock is acquired. Must not be called from async code.
+    fn lock_blocking(&self) -> Self::Guard<'_>;
+
+    /// Acquires the lock if it is free.
+    fn try_lock(&self) -> Option<Self::Guard<'_>>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send> AsyncLock<T> for ::tokio::sync::Mutex<T> {
+    type Guard<'a>
+        = ::tokio::sync::MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    fn lock(&self) -> impl Future<Output = Self::Guard<'_>> + Send {
+        ::tokio::sync::Mutex::lock(self)
+    }
+
+    fn lock_blocking(&self) -> Self::Guard<'_> {
+        ::tokio::sync::Mutex::blocking_lock(self)
+    }
+
+    fn try_lock(&self) -> Option<Self::Guard<'_>> {
+        ::tokio::sync::Mutex::try_lock(self).ok()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: Send> AsyncLock<T> for ::async_lock::Mutex<T> {
+    type Guard<'a>
+        = ::async_lock::MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    fn lock(&self) -> impl Future<Output = Self::Guard<'_>> + Send {
+        ::async_lock::Mutex::lock(self)
+    }
+
+    fn lock_blocking(&self) -> Self::Guard<'_> {
+        ::async_lock::Mutex::lock_blocking(self)
+    }
+
+    fn try_lock(&self) -> Option<Self::Guard<'_>> {
+        ::async_lock::Mutex::try_lock(self)
+    }
+}
+
+/// Yields to the executor once, so that other tasks can run between the steps of long-running
+/// maintenance of a shared store, e.g. the batch insertion of many entries.
+#[doc(hidden)]
+pub fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    std::future::poll_fn(move |cx| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+}
+
+/// The number of entries handled under one lock of an async store by maintenance which yields in
+/// between.
+#[doc(hidden)]
+pub const MAINTENANCE_CHUNK: usize = 64;
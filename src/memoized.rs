@@ -0,0 +1,845 @@
+//! The parts of memoized functions that don't depend on the function.
+//!
+//! For every function, `memoize` generates a type implementing [`Memoized`], which names the key,
+//! value and store of the function, and how values are stored. The generated wrapper and helpers
+//! only build keys from the arguments; looking up, inserting and maintaining entries is done by the
+//! generic functions of this module.
+
+use crate::compact::Epoch;
+use crate::events::{self, CacheEventKind};
+use crate::{
+    coarse_now, hooks, CacheControl, CacheStore, CompactEntry, Entry, EntryMeta, NegativeFilter,
+    Stats, StatsRegistry,
+};
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::thread::LocalKey;
+use std::time::{Duration, Instant};
+
+/// The static holding a store: a `Mutex` (or `RwLock`) for shared caches, a `thread_local!` `RefCell`
+/// otherwise.
+pub trait StoreCell {
+    type Store;
+
+    /// Runs `f` on the locked (or mutably borrowed) store.
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut Self::Store) -> R) -> R;
+
+    /// Runs `f` on the store, which is only borrowed immutably if it is thread-local.
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&Self::Store) -> R) -> R;
+
+    /// Like [`with_store`](StoreCell::with_store), but returns `None` instead of panicking if the lock
+    /// is poisoned.
+    fn try_with_store<R>(&'static self, f: impl FnOnce(&mut Self::Store) -> R) -> Option<R> {
+        Some(self.with_store(f))
+    }
+}
+
+impl<S> StoreCell for Mutex<S> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.lock().unwrap())
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.lock().unwrap())
+    }
+
+    fn try_with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        self.lock().ok().map(|mut store| f(&mut store))
+    }
+}
+
+// The shared store of a `ClockPro` cache, whose hits only need `&self`.
+impl<S> StoreCell for RwLock<S> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.write().unwrap())
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.read().unwrap())
+    }
+
+    fn try_with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        self.write().ok().map(|mut store| f(&mut store))
+    }
+}
+
+impl<S: 'static> StoreCell for LocalKey<RefCell<S>> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        self.with(|store| f(&mut store.borrow_mut()))
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        self.with(|store| f(&store.borrow()))
+    }
+}
+
+impl<S: 'static> StoreCell for crate::scope::ScopedStore<S> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        self.with(|store| f(&mut store.borrow_mut()))
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        self.with(|store| f(&store.borrow()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: Send + 'static> StoreCell for crate::scope::TaskLocalStore<S> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        self.with(|store| f(&mut store.borrow_mut()))
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        self.with(|store| f(&store.borrow()))
+    }
+}
+
+// The store of an async function is locked without awaiting by the synchronous helpers, like the
+// methods of its `CacheControl`, which may be called from async code: the thread is parked until the
+// lock is released (unlike `AsyncLock::lock_blocking()`, which panics within a Tokio runtime). This
+// doesn't deadlock, as memoized functions don't hold the lock across an await point.
+#[cfg(any(feature = "tokio", feature = "async"))]
+impl<S: Send> StoreCell for crate::AsyncMutex<S> {
+    type Store = S;
+
+    fn with_store<R>(&'static self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut futures::executor::block_on(crate::AsyncLock::lock(
+            self,
+        )))
+    }
+
+    fn with_store_ref<R>(&'static self, f: impl FnOnce(&S) -> R) -> R {
+        f(&futures::executor::block_on(crate::AsyncLock::lock(self)))
+    }
+}
+
+/// Stores whose entries can be listed, by the helpers looking at all of them.
+pub trait Entries<K, V> {
+    /// Calls `f` with every entry, without marking it as used.
+    fn for_each_entry(&self, f: impl FnMut(&K, &V));
+}
+
+impl<K, V, S: BuildHasher> Entries<K, V> for HashMap<K, V, S> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+impl<K, V> Entries<K, V> for BTreeMap<K, V> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K, V, S> Entries<K, V> for hashbrown::HashMap<K, V, S> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K, V, S> Entries<K, V> for crate::PrehashedMap<K, V, S> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: std::hash::Hash + Eq, V, S: BuildHasher> Entries<K, V> for lru::LruCache<K, V, S> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: std::hash::Hash + Eq + Clone, V> Entries<K, V> for crate::ArcCache<K, V> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: std::hash::Hash + Eq + Clone, V> Entries<K, V> for crate::ClockProCache<K, V> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: std::hash::Hash + Eq + Clone, V> Entries<K, V> for crate::GreedyDualCache<K, V> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "full")]
+impl<K: std::hash::Hash + Eq + Clone, V> Entries<K, V> for crate::PriorityLruCache<K, V> {
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V> Entries<K, V> for crate::DiskStore<K, V>
+where
+    K: std::hash::Hash + Eq + serde::Serialize + serde::de::DeserializeOwned,
+    V: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn for_each_entry(&self, mut f: impl FnMut(&K, &V)) {
+        self.iter().for_each(|(key, value)| f(key, value))
+    }
+}
+
+/// A memoized function, as seen by the functions of this module. Implemented by `memoize` for a
+/// type generated next to the function.
+pub trait Memoized: Sized + 'static {
+    /// The memoized arguments: a single argument, or a tuple of them.
+    type Key;
+    /// The value returned by the function.
+    type Value;
+    /// The representation of a value in the store, e.g. a `memoize::Entry` of it.
+    type Stored;
+    /// The store, which is a `dyn CacheStore` with `UseStore`.
+    type Store: CacheStore<Self::Key, Self::Stored> + ?Sized;
+
+    /// The name of the function, as reported to hooks and event subscribers.
+    const NAME: &'static str;
+    /// Whether cache events are emitted (`Instrument`).
+    const INSTRUMENT: bool = false;
+    /// Whether the store is bounded, so that inserting an entry may evict another one.
+    const BOUNDED: bool = false;
+    /// Whether evictions are counted even if there are no hooks or event subscribers.
+    const TRACK_EVICTIONS: bool = false;
+
+    /// The hit and miss counts of the function.
+    fn stats() -> &'static StatsRegistry;
+
+    /// Runs `f` on the locked (or borrowed) store.
+    fn with_store<R>(f: impl FnOnce(&mut Self::Store) -> R) -> R;
+
+    /// Runs `f` on the store, which is only borrowed immutably if it is thread-local.
+    fn with_store_ref<R>(f: impl FnOnce(&Self::Store) -> R) -> R;
+
+    /// Like [`with_store`](Memoized::with_store), but returns `None` instead of panicking if the
+    /// lock is poisoned.
+    fn try_with_store<R>(f: impl FnOnce(&mut Self::Store) -> R) -> Option<R> {
+        Some(Self::with_store(f))
+    }
+
+    /// Deserializes a key, for `CacheControl::remove_serialized()`.
+    fn decode_key(key: &[u8]) -> Option<Self::Key>;
+
+    /// The value of a stored entry.
+    fn load(stored: &Self::Stored) -> Self::Value;
+
+    /// Calls `f` with (a reference to) the value of a stored entry, which is only decoded if it is
+    /// stored in another representation.
+    fn inspect<R>(stored: &Self::Stored, f: impl FnOnce(&Self::Value) -> R) -> R;
+
+    /// The stored representation of a value which wasn't computed by the function, e.g. one given to
+    /// `memoized_insert_<fn>()`.
+    fn store(value: Self::Value) -> Self::Stored;
+
+    /// Whether a stored entry hasn't expired, which [`Expiring`] entries check against
+    /// [`expiry`](Memoized::expiry).
+    fn is_fresh(_stored: &Self::Stored) -> bool {
+        true
+    }
+
+    /// Records a hit of a stored entry, e.g. for `ExpireAfterAccess`.
+    fn touch(_stored: &Self::Stored) {}
+
+    /// How the entries of the function expire.
+    fn expiry() -> Expiry {
+        Expiry::NEVER
+    }
+
+    /// Runs `f` on the epoch of the store, with `CompactEntries`.
+    fn with_epoch<R>(_f: impl FnOnce(&Epoch) -> R) -> Option<R> {
+        None
+    }
+
+    /// Called on every hit of the function, e.g. for `AutoCapacity`.
+    fn hit() {}
+
+    /// With `NegativeFilter`, the filter of the keys whose value is the negative one.
+    fn negative_filter() -> Option<&'static RwLock<NegativeFilter>> {
+        None
+    }
+
+    /// With `NegativeFilter`, whether a value is the negative one, which is recorded in the filter
+    /// instead of the store.
+    fn is_negative(_value: &Self::Value) -> bool {
+        false
+    }
+
+    /// With `WriteBack`, the generation of the buffers of all threads, which changes when the store is
+    /// cleared.
+    fn write_back_generation() -> Option<&'static AtomicUsize> {
+        None
+    }
+
+    /// The hash of a key sent along with cache events.
+    fn key_hash(_key: &Self::Key) -> Option<u64> {
+        None
+    }
+
+    /// Whether a bounded store holds an entry for a key, without marking it as used.
+    fn contains(_store: &Self::Store, _key: &Self::Key) -> bool {
+        false
+    }
+
+    /// Called before an entry is inserted, e.g. to index it.
+    fn inserting(_store: &mut Self::Store, _key: &Self::Key, _stored: &Self::Stored) {}
+
+    /// Called after an entry was inserted into a bounded store, with whether another entry was
+    /// evicted.
+    fn inserted(_store: &mut Self::Store, _evicted: bool) {}
+
+    /// Removes all entries.
+    fn clear(store: &mut Self::Store) {
+        // Buffers filled before a flush are discarded instead of being written back.
+        if let Some(generation) = Self::write_back_generation() {
+            generation.fetch_add(1, Ordering::SeqCst);
+        }
+        store.clear()
+    }
+
+    /// Called after the store was cleared, to reset the state kept besides it.
+    fn flushed() {}
+}
+
+/// Counts hits and misses.
+pub fn record<M: Memoized>(hits: u64, misses: u64) {
+    crate::record_stats(M::stats(), M::NAME, hits, misses)
+}
+
+/// Sends a cache event to the subscribers, if the function is instrumented.
+pub fn emit<M: Memoized>(kind: CacheEventKind, key: Option<&M::Key>) {
+    if M::INSTRUMENT && events::enabled() {
+        events::emit(M::NAME, kind, key.and_then(M::key_hash));
+    }
+}
+
+/// Like [`emit`], given another form of the key, e.g. references to the arguments.
+pub fn emit_key<M: Memoized, Q: Hash + ?Sized>(kind: CacheEventKind, key: &Q) {
+    if M::INSTRUMENT && events::enabled() {
+        events::emit(M::NAME, kind, Some(events::key_hash(key)));
+    }
+}
+
+/// Records a hit of the function.
+pub fn hit<M: Memoized>() {
+    record::<M>(1, 0);
+    M::hit();
+}
+
+/// Records a hit of the function, and sends an event with the hash of the key (`Instrument`).
+pub fn hit_key<M: Memoized, Q: Hash + ?Sized>(key: &Q) {
+    hit::<M>();
+    emit_key::<M, Q>(CacheEventKind::Hit, key);
+}
+
+/// Records a miss of the function.
+pub fn miss<M: Memoized>() {
+    record::<M>(0, 1);
+}
+
+/// Records a miss of the function, and sends an event with the hash of the key (`Instrument`).
+pub fn miss_key<M: Memoized, Q: Hash + ?Sized>(key: &Q) {
+    miss::<M>();
+    emit_key::<M, Q>(CacheEventKind::Miss, key);
+}
+
+/// How the entries of a function expire: the durations of `TimeToLive`, `ExpireAfterAccess` and
+/// `MaxRefreshRate`, and whether they are compared against `memoize::coarse_now()` (`CoarseClock`).
+#[derive(Clone, Copy, Debug)]
+pub struct Expiry {
+    pub time_to_live: Option<Duration>,
+    pub time_to_idle: Option<Duration>,
+    pub max_refresh_rate: Option<Duration>,
+    pub coarse_clock: bool,
+}
+
+impl Expiry {
+    /// Entries only expiring at their own expiration time, if any (`ExpireWith` and `MaxAge`).
+    pub const NEVER: Expiry = Expiry {
+        time_to_live: None,
+        time_to_idle: None,
+        max_refresh_rate: None,
+        coarse_clock: false,
+    };
+
+    /// The time entries are compared against.
+    pub fn now(&self) -> Instant {
+        if self.coarse_clock {
+            coarse_now()
+        } else {
+            Instant::now()
+        }
+    }
+}
+
+/// Stored entries that may expire: [`Entry`]s and [`CompactEntry`]s.
+pub trait Expiring {
+    /// Whether the entry of a function `M` hasn't expired.
+    fn is_fresh<M: Memoized>(&self) -> bool;
+
+    /// Records a hit of the entry of a function `M`, e.g. for `ExpireAfterAccess`.
+    fn touch<M: Memoized>(&self);
+}
+
+impl<V, A, X, C, H, T> Expiring for Entry<V, A, X, C, H, T>
+where
+    A: EntryMeta<Instant>,
+    X: EntryMeta<Option<Instant>>,
+    C: EntryMeta<Duration>,
+    H: EntryMeta<u64>,
+    T: EntryMeta<Instant>,
+{
+    fn is_fresh<M: Memoized>(&self) -> bool {
+        let expiry = M::expiry();
+        // Entries without expiration don't read the clock.
+        if expiry.time_to_live.is_none() && expiry.time_to_idle.is_none() && !X::TRACKED {
+            return true;
+        }
+        self.is_fresh_at(
+            expiry.now(),
+            expiry.time_to_live,
+            expiry.time_to_idle,
+            expiry.max_refresh_rate,
+        )
+    }
+
+    fn touch<M: Memoized>(&self) {
+        if A::TRACKED {
+            self.touch_at(M::expiry().now());
+        }
+        if let Some(hits) = self.hits.get() {
+            self.hits.set(hits + 1);
+        }
+    }
+}
+
+impl<V> Expiring for CompactEntry<V> {
+    fn is_fresh<M: Memoized>(&self) -> bool {
+        let expiry = M::expiry();
+        // Nothing is fresh once the epoch has run out.
+        ticks::<M>().is_some_and(|now| {
+            self.is_fresh_at(
+                now,
+                expiry.time_to_live,
+                expiry.time_to_idle,
+                expiry.max_refresh_rate,
+            )
+        })
+    }
+
+    fn touch<M: Memoized>(&self) {
+        if M::expiry().time_to_idle.is_some() {
+            self.accessed.set(ticks::<M>().unwrap_or(u32::MAX));
+        }
+    }
+}
+
+/// With `CompactEntries`, the current time in ticks of the epoch of the store, or `None` if it has
+/// run out.
+pub fn ticks<M: Memoized>() -> Option<u32> {
+    M::with_epoch(|epoch| epoch.ticks(M::expiry().now())).flatten()
+}
+
+/// The value of an entry found in the store, unless it has expired.
+pub fn read<M: Memoized>(stored: Option<&M::Stored>) -> Option<M::Value> {
+    let stored = stored.filter(|stored| M::is_fresh(stored))?;
+    M::touch(stored);
+    Some(M::load(stored))
+}
+
+/// Looks up the value of a key.
+pub fn get<M: Memoized>(store: &mut M::Store, key: &M::Key) -> Option<M::Value> {
+    read::<M>(store.get(key))
+}
+
+/// Inserts an entry, telling the hooks about it, and about the entry it evicted from a bounded
+/// store.
+pub fn insert<M: Memoized>(store: &mut M::Store, key: M::Key, stored: M::Stored) {
+    insert_with::<M>(store, key, stored, |store, key, stored| {
+        store.insert(key, stored)
+    })
+}
+
+/// Like [`insert`], but inserts the entry with `insert`, e.g. given the hash computed by the lookup.
+pub fn insert_with<M: Memoized>(
+    store: &mut M::Store,
+    key: M::Key,
+    stored: M::Stored,
+    insert: impl FnOnce(&mut M::Store, M::Key, M::Stored),
+) {
+    // Once the epoch of `CompactEntries` has run out, the entries of the store are dropped, as their
+    // timestamps can't be compared to the new epoch's.
+    M::with_epoch(|epoch| {
+        let now = M::expiry().now();
+        if epoch.ticks(now).is_none() {
+            store.clear();
+            epoch.reset(now);
+        }
+    });
+    M::inserting(store, &key, &stored);
+    hooks::record_insert(M::NAME);
+    emit::<M>(CacheEventKind::Insert, Some(&key));
+    if !M::BOUNDED {
+        insert(store, key, stored);
+        return;
+    }
+    // An insertion of a new key that doesn't grow the store has evicted another entry; this is only
+    // checked if somebody is interested.
+    let track = M::TRACK_EVICTIONS || hooks::enabled() || (M::INSTRUMENT && events::enabled());
+    let replaced = track && M::contains(store, &key);
+    let len = store.len();
+    insert(store, key, stored);
+    let evicted = track && !replaced && store.len() == len;
+    if evicted {
+        record_evictions::<M>(1);
+    }
+    M::inserted(store, evicted);
+}
+
+/// Inserts a value which wasn't computed by the function.
+pub fn insert_value<M: Memoized>(key: M::Key, value: M::Value) {
+    M::with_store(|store| insert::<M>(store, key, M::store(value)))
+}
+
+/// Inserts values which weren't computed by the function, e.g. loaded from a file.
+pub fn insert_values<M: Memoized>(entries: Vec<(M::Key, M::Value)>) {
+    M::with_store(|store| {
+        for (key, value) in entries {
+            insert::<M>(store, key, M::store(value));
+        }
+    })
+}
+
+/// Tells the hooks about entries evicted from the store, e.g. by resizing it.
+pub fn record_evictions<M: Memoized>(evicted: usize) {
+    for _ in 0..evicted {
+        hooks::record_eviction(M::NAME);
+        emit::<M>(CacheEventKind::Evict, None);
+    }
+}
+
+/// Looks up the values of several keys, locking the store once.
+pub fn get_many<M: Memoized>(keys: impl IntoIterator<Item = M::Key>) -> Vec<Option<M::Value>> {
+    M::with_store(|store| keys.into_iter().map(|key| get::<M>(store, &key)).collect())
+}
+
+/// Looks up the values of several keys, computing the missing ones with `compute`, which also
+/// returns the entry to insert if the value is admitted. The store is locked once for the lookups,
+/// and once more to insert the computed values.
+pub fn get_or_compute_many<M: Memoized>(
+    keys: impl IntoIterator<Item = M::Key>,
+    mut compute: impl FnMut(M::Key) -> (M::Value, Option<(M::Key, M::Stored)>),
+) -> Vec<M::Value> {
+    let keys: Vec<M::Key> = keys.into_iter().collect();
+    let n_keys = keys.len();
+    let found = M::with_store(|store| {
+        keys.iter()
+            .map(|key| get::<M>(store, key))
+            .collect::<Vec<_>>()
+    });
+    let mut computed = vec![];
+    let mut results = Vec::with_capacity(n_keys);
+    for (key, found) in keys.into_iter().zip(found) {
+        match found {
+            Some(value) => {
+                emit::<M>(CacheEventKind::Hit, Some(&key));
+                results.push(value);
+            }
+            None => {
+                emit::<M>(CacheEventKind::Miss, Some(&key));
+                let (value, entry) = compute(key);
+                computed.extend(entry);
+                results.push(value);
+            }
+        }
+    }
+    record::<M>((n_keys - computed.len()) as u64, computed.len() as u64);
+    if !computed.is_empty() {
+        M::with_store(|store| {
+            for (key, stored) in computed {
+                insert::<M>(store, key, stored);
+            }
+        });
+    }
+    results
+}
+
+/// The keys and values of the fresh entries whose key satisfies `predicate`.
+pub fn find<M: Memoized>(
+    store: &M::Store,
+    predicate: impl Fn(&M::Key) -> bool,
+) -> Vec<(M::Key, M::Value)>
+where
+    M::Key: Clone,
+    M::Store: Entries<M::Key, M::Stored>,
+{
+    let mut found = vec![];
+    store.for_each_entry(|key, stored| {
+        if predicate(key) && M::is_fresh(stored) {
+            found.push((key.clone(), M::load(stored)));
+        }
+    });
+    found
+}
+
+/// The keys of the fresh entries whose value satisfies `predicate`. Only the keys of the matching
+/// entries are cloned.
+pub fn keys_where<M: Memoized>(
+    store: &M::Store,
+    predicate: impl Fn(&M::Value) -> bool,
+) -> Vec<M::Key>
+where
+    M::Key: Clone,
+    M::Store: Entries<M::Key, M::Stored>,
+{
+    let mut keys = vec![];
+    store.for_each_entry(|key, stored| {
+        if M::is_fresh(stored) && M::inspect(stored, &predicate) {
+            keys.push(key.clone());
+        }
+    });
+    keys
+}
+
+/// Removes the entries whose key satisfies `predicate`, and returns how many there were.
+pub fn remove_where<M: Memoized>(store: &mut M::Store, predicate: impl Fn(&M::Key) -> bool) -> usize
+where
+    M::Key: Clone,
+    M::Store: Entries<M::Key, M::Stored>,
+{
+    let mut keys = vec![];
+    store.for_each_entry(|key, _| {
+        if predicate(key) {
+            keys.push(key.clone());
+        }
+    });
+    for key in &keys {
+        store.remove(key);
+    }
+    keys.len()
+}
+
+/// With `NegativeFilter`, whether the key was recorded with the negative value, which is then
+/// returned as a hit.
+pub fn negative_hit<M: Memoized, Q: Hash + ?Sized>(key: &Q) -> bool {
+    let found = M::negative_filter()
+        .is_some_and(|filter| filter.read().is_ok_and(|filter| filter.contains(key)));
+    if found {
+        hit_key::<M, Q>(key);
+    }
+    found
+}
+
+/// With `NegativeFilter`, records the key of a negative value in the filter instead of caching the
+/// value. Returns whether it did.
+pub fn filter_negative<M: Memoized, Q: Hash + ?Sized>(key: &Q, value: &M::Value) -> bool {
+    match M::negative_filter() {
+        Some(filter) if M::is_negative(value) => {
+            if let Ok(mut filter) = filter.write() {
+                filter.insert(key);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// With `WriteBack`, the entries computed by a thread, which are written to the shared store once
+/// there are enough of them, or once they are old enough.
+pub struct WriteBackBuffer<M: Memoized>
+where
+    M::Key: Hash + Eq,
+{
+    written: Instant,
+    entries: HashMap<M::Key, M::Stored>,
+    generation: usize,
+}
+
+fn write_back_generation<M: Memoized>() -> usize {
+    M::write_back_generation().map_or(0, |generation| generation.load(Ordering::SeqCst))
+}
+
+impl<M: Memoized> WriteBackBuffer<M>
+where
+    M::Key: Hash + Eq,
+{
+    pub fn new() -> Self {
+        WriteBackBuffer {
+            written: Instant::now(),
+            entries: HashMap::new(),
+            generation: write_back_generation::<M>(),
+        }
+    }
+
+    // Entries buffered before the store was cleared are dropped.
+    fn entries(&mut self) -> &mut HashMap<M::Key, M::Stored> {
+        let generation = write_back_generation::<M>();
+        if self.generation != generation {
+            self.entries.clear();
+            self.generation = generation;
+        }
+        &mut self.entries
+    }
+
+    /// Looks up the value of a buffered key.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<M::Value>
+    where
+        M::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        read::<M>(HashMap::get(self.entries(), key))
+    }
+
+    /// Buffers an entry, and writes the buffer back once it holds `threshold` entries, or was last
+    /// written back longer than `every` ago.
+    pub fn insert(
+        &mut self,
+        key: M::Key,
+        stored: M::Stored,
+        threshold: usize,
+        every: Option<Duration>,
+    ) {
+        self.entries().insert(key, stored);
+        if self.entries.len() >= threshold
+            || every.is_some_and(|every| self.written.elapsed() >= every)
+        {
+            self.write_back();
+        }
+    }
+
+    /// Inserts the buffered entries into the shared store.
+    pub fn write_back(&mut self) {
+        let entries = self.entries();
+        M::try_with_store(|store| {
+            for (key, stored) in entries.drain() {
+                insert::<M>(store, key, stored);
+            }
+        });
+        self.written = Instant::now();
+    }
+}
+
+impl<M: Memoized> Default for WriteBackBuffer<M>
+where
+    M::Key: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Entries still buffered when a thread exits are not lost.
+impl<M: Memoized> Drop for WriteBackBuffer<M>
+where
+    M::Key: Hash + Eq,
+{
+    fn drop(&mut self) {
+        self.write_back();
+    }
+}
+
+/// Removes all entries.
+pub fn flush<M: Memoized>() {
+    M::with_store(M::clear);
+    flushed::<M>();
+}
+
+/// Resets the state kept besides the store, and tells the hooks, once the store was cleared.
+pub fn flushed<M: Memoized>() {
+    M::flushed();
+    if let Some(filter) = M::negative_filter() {
+        if let Ok(mut filter) = filter.write() {
+            filter.clear();
+        }
+    }
+    hooks::record_flush(M::NAME);
+    emit::<M>(CacheEventKind::Flush, None);
+}
+
+/// The number of cached entries.
+pub fn size<M: Memoized>() -> usize {
+    M::with_store_ref(|store| store.len())
+}
+
+/// The hit and miss counts.
+pub fn stats<M: Memoized>() -> Stats {
+    M::stats().sum()
+}
+
+/// Removes the entry of a serialized key, and returns whether there was one.
+pub fn remove_serialized<M: Memoized>(key: &[u8]) -> bool {
+    match M::decode_key(key) {
+        Some(key) => M::with_store(|store| store.remove(&key).is_some()),
+        None => false,
+    }
+}
+
+/// The handle returned by `memoized_cache_handle_<fn>()`.
+pub struct CacheHandle<M>(PhantomData<fn() -> M>);
+
+impl<M> CacheHandle<M> {
+    pub const fn new() -> Self {
+        CacheHandle(PhantomData)
+    }
+}
+
+impl<M> Clone for CacheHandle<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for CacheHandle<M> {}
+
+impl<M> Default for CacheHandle<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Memoized> CacheControl for CacheHandle<M> {
+    fn name(&self) -> &'static str {
+        M::NAME
+    }
+
+    fn flush(&self) {
+        flush::<M>()
+    }
+
+    fn size(&self) -> usize {
+        size::<M>()
+    }
+
+    fn stats(&self) -> Stats {
+        stats::<M>()
+    }
+
+    fn remove_serialized(&self, key: &[u8]) -> bool {
+        remove_serialized::<M>(key)
+    }
+}
@@ -0,0 +1,117 @@
+//! Shrinking of caches when the process uses too much memory, with the `jemalloc` or `mimalloc`
+//! feature.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+struct Shrinker {
+    path: &'static str,
+    shrink: fn(f64) -> usize,
+}
+
+static SHRINKERS: Mutex<Vec<Shrinker>> = Mutex::new(Vec::new());
+static HIGH_WATER_MARK: AtomicUsize = AtomicUsize::new(usize::MAX);
+static MONITOR: Once = Once::new();
+
+/// Registers the function evicting a fraction of the entries of a memoized function's cache, under
+/// its path (`module_path!()::name`). Called by the code generated for memoized functions with
+/// shared caches on their first call.
+#[doc(hidden)]
+pub fn register_shrinker(path: &'static str, shrink: fn(f64) -> usize) {
+    SHRINKERS.lock().unwrap().push(Shrinker { path, shrink });
+}
+
+/// The number of bytes of resident memory, as reported by the allocator.
+///
+/// With the `jemalloc` feature, these are the resident pages of jemalloc, which must then be the
+/// global allocator (e.g. `tikv_jemallocator::Jemalloc`); otherwise, with the `mimalloc` feature, the
+/// resident set size of the process as reported by mimalloc.
+pub fn resident_memory() -> Option<usize> {
+    #[cfg(feature = "jemalloc")]
+    {
+        use tikv_jemalloc_ctl::{epoch, stats};
+        // The statistics are only refreshed when the epoch advances.
+        epoch::advance().ok()?;
+        stats::resident::read().ok()
+    }
+    #[cfg(not(feature = "jemalloc"))]
+    {
+        let mut current_rss = 0;
+        let mut ignored = [0usize; 7];
+        let [elapsed, user, system, peak_rss, current_commit, peak_commit, page_faults] =
+            &mut ignored;
+        // SAFETY: all pointers are valid for writes.
+        unsafe {
+            libmimalloc_sys::mi_process_info(
+                elapsed,
+                user,
+                system,
+                &mut current_rss,
+                peak_rss,
+                current_commit,
+                peak_commit,
+                page_faults,
+            );
+        }
+        Some(current_rss)
+    }
+}
+
+/// Evicts `fraction` (between 0 and 1) of the entries of every registered cache, i.e. of every
+/// memoized function with a `SharedCache` that was called at least once, and returns the number of
+/// evicted entries. LRU caches evict their least recently used entries; other caches arbitrary ones.
+///
+/// Must not be called from async code if async memoized functions use the `tokio` feature, as their
+/// stores are locked while blocking the current thread.
+pub fn shrink_caches(fraction: f64) -> usize {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let shrinkers: Vec<fn(f64) -> usize> = SHRINKERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|shrinker| shrinker.shrink)
+        .collect();
+    shrinkers.into_iter().map(|shrink| shrink(fraction)).sum()
+}
+
+/// The paths of the memoized functions whose caches are shrunk by [`shrink_caches`].
+pub fn shrinkable_caches() -> Vec<&'static str> {
+    SHRINKERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|shrinker| shrinker.path)
+        .collect()
+}
+
+/// Checks the resident memory against the high-water mark set with [`set_memory_high_water_mark`],
+/// and shrinks all caches (see [`shrink_caches`]) proportionally to the excess if it is crossed: by
+/// the share of resident memory above the mark, but at least a tenth. Returns the number of evicted
+/// entries.
+pub fn check_memory() -> usize {
+    let high_water_mark = HIGH_WATER_MARK.load(Ordering::Relaxed);
+    match resident_memory() {
+        Some(resident) if resident > high_water_mark => {
+            let excess = (resident - high_water_mark) as f64 / resident as f64;
+            shrink_caches(excess.max(0.1))
+        }
+        _ => 0,
+    }
+}
+
+/// Sets the number of bytes of resident memory above which caches are shrunk, and starts a
+/// background thread checking it once per second (see [`check_memory`]).
+pub fn set_memory_high_water_mark(bytes: usize) {
+    HIGH_WATER_MARK.store(bytes, Ordering::Relaxed);
+    MONITOR.call_once(|| {
+        thread::Builder::new()
+            .name("memoize-memory".to_string())
+            .spawn(|| loop {
+                thread::sleep(Duration::from_secs(1));
+                check_memory();
+            })
+            .expect("memoize: failed to start the memory monitor thread");
+    });
+}
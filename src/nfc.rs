@@ -0,0 +1,15 @@
+//! Unicode normalization of string keys, used by `Nfc`.
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Converts a string to Unicode Normalization Form C, so that canonically equivalent strings (e.g. a
+/// precomposed `é` and an `e` followed by a combining accent) compare equal.
+///
+/// Arguments of type `String` of functions with `#[memoize(Nfc)]` are stored in the cache in this
+/// form; it can also be used with `#[memoize(NormalizeKey: memoize::nfc)]` for a `String` key.
+pub fn nfc(text: String) -> String {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        return text;
+    }
+    text.nfc().collect()
+}
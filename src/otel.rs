@@ -0,0 +1,59 @@
+//! OpenTelemetry metrics and span attributes for memoized functions.
+//!
+//! With the `opentelemetry` feature, every memoized function counts its hits, misses and evictions
+//! in the `memoize.hits`, `memoize.misses` and `memoize.evictions` counters of the global meter
+//! provider (with a `function` attribute), and sets a `cache.hit` attribute on the active span.
+//! Install the meter provider before the first call of a memoized function; the counters are created
+//! from the global provider at that time.
+
+use opentelemetry::metrics::Counter;
+use opentelemetry::trace::get_active_span;
+use opentelemetry::{global, KeyValue};
+
+struct Instruments {
+    hits: Counter<u64>,
+    misses: Counter<u64>,
+    evictions: Counter<u64>,
+}
+
+lazy_static::lazy_static! {
+    static ref INSTRUMENTS: Instruments = {
+        let meter = global::meter("memoize");
+        Instruments {
+            hits: meter
+                .u64_counter("memoize.hits")
+                .with_description("Calls of memoized functions answered from the cache")
+                .build(),
+            misses: meter
+                .u64_counter("memoize.misses")
+                .with_description("Calls of memoized functions that computed the value")
+                .build(),
+            evictions: meter
+                .u64_counter("memoize.evictions")
+                .with_description("Entries evicted from bounded caches to make room")
+                .build(),
+        }
+    };
+}
+
+/// Records hits and misses of a memoized function. Called by the code generated by `memoize`.
+#[doc(hidden)]
+pub fn record(function: &'static str, hits: u64, misses: u64) {
+    let attributes = [KeyValue::new("function", function)];
+    if hits > 0 {
+        INSTRUMENTS.hits.add(hits, &attributes);
+    }
+    if misses > 0 {
+        INSTRUMENTS.misses.add(misses, &attributes);
+    }
+    get_active_span(|span| span.set_attribute(KeyValue::new("cache.hit", misses == 0)));
+}
+
+/// Records an eviction from the cache of a memoized function. Called by the code generated by
+/// `memoize`.
+#[doc(hidden)]
+pub fn record_eviction(function: &'static str) {
+    INSTRUMENTS
+        .evictions
+        .add(1, &[KeyValue::new("function", function)]);
+}
@@ -0,0 +1,71 @@
+//! The store of `HashOnce`.
+
+use crate::DefaultHashBuilder;
+
+use hashbrown::Equivalent;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+
+/// An unbounded store whose keys can be hashed once, before looking them up, and inserted with the
+/// same hash after a miss.
+///
+/// This is the store used by `#[memoize(HashOnce)]`. It dereferences to a `hashbrown::HashMap`,
+/// which offers the same methods as the standard library's `HashMap`.
+pub struct PrehashedMap<K, V, S = DefaultHashBuilder> {
+    map: hashbrown::HashMap<K, V, S>,
+}
+
+impl<K, V, S: Default> PrehashedMap<K, V, S> {
+    pub fn new() -> Self {
+        PrehashedMap {
+            map: hashbrown::HashMap::with_hasher(S::default()),
+        }
+    }
+}
+
+impl<K, V, S: Default> Default for PrehashedMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> PrehashedMap<K, V, S> {
+    /// Hashes `key` with the hasher of the map.
+    pub fn hash_key<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        self.map.hasher().hash_one(key)
+    }
+
+    /// Looks up `key`, whose hash was computed by `hash_key()`.
+    pub fn get_hashed<Q: Equivalent<K> + ?Sized>(&self, hash: u64, key: &Q) -> Option<&V> {
+        self.map
+            .raw_entry()
+            .from_key_hashed_nocheck(hash, key)
+            .map(|(_, value)| value)
+    }
+
+    /// Inserts or replaces the entry of `key`, whose hash was computed by `hash_key()`.
+    pub fn insert_hashed(&mut self, hash: u64, key: K, value: V) {
+        match self.map.raw_entry_mut().from_key_hashed_nocheck(hash, &key) {
+            hashbrown::hash_map::RawEntryMut::Occupied(mut entry) => {
+                entry.insert(value);
+            }
+            hashbrown::hash_map::RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, key, value);
+            }
+        }
+    }
+}
+
+impl<K, V, S> Deref for PrehashedMap<K, V, S> {
+    type Target = hashbrown::HashMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> DerefMut for PrehashedMap<K, V, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.map
+    }
+}
@@ -0,0 +1,205 @@
+//! A bounded cache evicting low-priority entries first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+
+struct Slot<V> {
+    value: V,
+    priority: u8,
+    tick: u64,
+}
+
+/// A bounded cache that, when full, evicts the least recently used entry among those with the
+/// lowest priority. The priority of an entry is computed once, when it is inserted.
+///
+/// This is the store used by `#[memoize(Capacity: N, Priority: ...)]`; it offers the same `get` and
+/// `put` methods as [`lru::LruCache`].
+pub struct PriorityLruCache<K, V> {
+    map: HashMap<K, Slot<V>>,
+    // Keys ordered by eviction order: lowest priority first, then least recently used first.
+    order: BTreeMap<(u8, u64), K>,
+    cap: NonZeroUsize,
+    tick: u64,
+    priority: fn(&K, &V) -> u8,
+}
+
+impl<K: Hash + Eq + Clone, V> PriorityLruCache<K, V> {
+    /// Creates a cache holding at most `cap` entries, ranked by `priority`.
+    pub fn new(cap: NonZeroUsize, priority: fn(&K, &V) -> u8) -> Self {
+        PriorityLruCache {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            cap,
+            tick: 0,
+            priority,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Returns the value for `key`, marking it as most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let slot = self.map.get_mut(key)?;
+        let key = self.order.remove(&(slot.priority, slot.tick)).unwrap();
+        slot.tick = tick;
+        self.order.insert((slot.priority, tick), key);
+        Some(&slot.value)
+    }
+
+    /// Returns the value for `key` without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key).map(|slot| &slot.value)
+    }
+
+    /// Returns whether the cache holds a value for `key`, without marking it as used.
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a value, evicting an entry if the cache is full. Returns the previous value for
+    /// `key`, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let priority = (self.priority)(&key, &value);
+        let tick = self.next_tick();
+        if let Some(slot) = self.map.get_mut(&key) {
+            let key = self.order.remove(&(slot.priority, slot.tick)).unwrap();
+            slot.priority = priority;
+            slot.tick = tick;
+            self.order.insert((priority, tick), key);
+            return Some(std::mem::replace(&mut slot.value, value));
+        }
+        if self.map.len() >= self.cap.get() {
+            if let Some((_, evicted)) = self.order.pop_first() {
+                self.map.remove(&evicted);
+            }
+        }
+        self.order.insert((priority, tick), key.clone());
+        self.map.insert(
+            key,
+            Slot {
+                value,
+                priority,
+                tick,
+            },
+        );
+        None
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let slot = self.map.remove(key)?;
+        self.order.remove(&(slot.priority, slot.tick));
+        Some(slot.value)
+    }
+
+    /// Iterates over all entries, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter().map(|(key, slot)| (key, &slot.value))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values are their own priority.
+    fn cache(cap: usize) -> PriorityLruCache<u32, u8> {
+        PriorityLruCache::new(NonZeroUsize::new(cap).unwrap(), |_, priority| *priority)
+    }
+
+    #[test]
+    fn low_priority_is_evicted_first() {
+        let mut cache = cache(2);
+        cache.put(1, 5);
+        cache.put(2, 1);
+        // 2 is used more recently, but has the lower priority.
+        assert_eq!(cache.get(&2), Some(&1));
+        cache.put(3, 5);
+        assert!(cache.contains(&1) && cache.contains(&3));
+        assert!(!cache.contains(&2));
+        // Among equal priorities, the least recently used entry is evicted; a new entry of the
+        // lowest priority is evicted next.
+        cache.put(4, 0);
+        assert!(!cache.contains(&1) && cache.contains(&3) && cache.contains(&4));
+        cache.put(5, 9);
+        assert!(!cache.contains(&4) && cache.contains(&3));
+    }
+
+    #[test]
+    fn lru_order_within_a_priority() {
+        let mut cache = cache(3);
+        cache.put(1, 1);
+        cache.put(2, 1);
+        cache.put(3, 1);
+        assert_eq!(cache.get(&1), Some(&1));
+        cache.put(4, 1);
+        assert!(!cache.contains(&2));
+        // `peek` doesn't mark an entry as used.
+        assert_eq!(cache.peek(&3), Some(&1));
+        cache.put(5, 1);
+        assert!(!cache.contains(&3));
+        assert!(cache.contains(&1) && cache.contains(&4) && cache.contains(&5));
+    }
+
+    #[test]
+    fn reinserting_changes_the_priority() {
+        let mut cache = cache(2);
+        cache.put(1, 1);
+        cache.put(2, 5);
+        // 1 moves up to the highest priority, so that 2 is now evicted first.
+        assert_eq!(cache.put(1, 9), Some(1));
+        assert_eq!(cache.len(), 2);
+        cache.put(3, 5);
+        assert!(cache.contains(&1) && cache.contains(&3));
+        assert!(!cache.contains(&2));
+        // And back down.
+        cache.put(1, 0);
+        cache.put(4, 5);
+        assert!(!cache.contains(&1));
+        assert_eq!(cache.order.len(), cache.len());
+    }
+
+    #[test]
+    fn capacity_of_one() {
+        let mut cache = cache(1);
+        cache.put(1, 9);
+        // However low its priority, a new entry replaces the only one.
+        cache.put(2, 0);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&2), Some(&0));
+        assert!(!cache.contains(&1));
+        assert_eq!(cache.put(2, 3), Some(0));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.pop(&2), Some(3));
+        assert!(cache.is_empty());
+        cache.put(3, 1);
+        cache.clear();
+        assert!(cache.is_empty() && cache.order.is_empty());
+    }
+}
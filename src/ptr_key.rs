@@ -0,0 +1,54 @@
+//! Keys comparing `Arc`s by pointer identity, used by `KeyByPtr`.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Weak};
+
+/// A cache key identifying the value behind an [`Arc`] by its address rather than its contents.
+///
+/// Only a [`Weak`] reference is held, so that the value itself is dropped along with its last `Arc`;
+/// the allocation remains reserved until the key is evicted though, so its address can't be reused
+/// by another value that would then be mistaken for the original.
+pub struct PtrKey<T: ?Sized> {
+    ptr: Weak<T>,
+}
+
+impl<T: ?Sized> PtrKey<T> {
+    pub fn new(arc: &Arc<T>) -> Self {
+        PtrKey {
+            ptr: Arc::downgrade(arc),
+        }
+    }
+
+    fn address(&self) -> usize {
+        Weak::as_ptr(&self.ptr) as *const () as usize
+    }
+}
+
+impl<T: ?Sized> Clone for PtrKey<T> {
+    fn clone(&self) -> Self {
+        PtrKey {
+            ptr: Weak::clone(&self.ptr),
+        }
+    }
+}
+
+impl<T: ?Sized> PartialEq for PtrKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.ptr, &other.ptr)
+    }
+}
+
+impl<T: ?Sized> Eq for PtrKey<T> {}
+
+impl<T: ?Sized> Hash for PtrKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for PtrKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PtrKey({:#x})", self.address())
+    }
+}
@@ -0,0 +1,422 @@
+//! Periodic flushing of whole caches, used by `FlushEvery`.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tracks when a cache was last flushed.
+///
+/// The period starts with the first access of the cache; memoized functions check it on every call,
+/// and flush their cache when it has passed.
+pub struct FlushTimer {
+    last_flush: Mutex<Option<Instant>>,
+}
+
+impl FlushTimer {
+    pub const fn new() -> Self {
+        FlushTimer {
+            last_flush: Mutex::new(None),
+        }
+    }
+
+    /// Whether `every` has passed since the last flush. If so, the period restarts now.
+    pub fn due(&self, every: Duration) -> bool {
+        let mut last_flush = match self.last_flush.lock() {
+            Ok(last_flush) => last_flush,
+            Err(_) => return false,
+        };
+        let now = Instant::now();
+        match *last_flush {
+            Some(last) if now.duration_since(last) < every => false,
+            Some(_) => {
+                *last_flush = Some(now);
+                true
+            }
+            None => {
+                *last_flush = Some(now);
+                false
+            }
+        }
+    }
+}
+
+impl Default for FlushTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "schedule")]
+pub use cron::{
+    flush_by_tag, flush_request, flush_tags, register_flusher, schedule_flush, InvalidSchedule,
+    RequestFlush,
+};
+
+#[cfg(feature = "schedule")]
+mod cron {
+    use std::error::Error;
+    use std::fmt;
+    use std::sync::{Mutex, Once};
+    use std::thread;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    struct Flusher {
+        path: &'static str,
+        flush: fn(),
+    }
+
+    struct Schedule {
+        tag: String,
+        spec: Spec,
+    }
+
+    static FLUSHERS: Mutex<Vec<Flusher>> = Mutex::new(Vec::new());
+    static SCHEDULES: Mutex<Vec<Schedule>> = Mutex::new(Vec::new());
+    static SCHEDULER: Once = Once::new();
+
+    /// Registers the flush function of a memoized function under its path
+    /// (`module_path!()::name`). Called once by the code generated for memoized functions, before
+    /// their store is first written by the function or by one of its helpers.
+    #[doc(hidden)]
+    pub fn register_flusher(path: &'static str, flush: fn()) {
+        FLUSHERS.lock().unwrap().push(Flusher { path, flush });
+    }
+
+    /// An invalid schedule given to [`schedule_flush`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct InvalidSchedule(String);
+
+    impl fmt::Display for InvalidSchedule {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "invalid schedule: {}", self.0)
+        }
+    }
+
+    impl Error for InvalidSchedule {}
+
+    /// Flushes the caches of the memoized functions matching `fn_tag` whenever the cron-like `spec`
+    /// matches the current minute (in UTC).
+    ///
+    /// `fn_tag` is the name of the memoized function, or its full path (`crate::module::name`) to tell
+    /// apart functions of the same name. `spec` has the five fields `minute hour day-of-month month
+    /// day-of-week` (with Sunday as 0 or 7), each of which is `*`, a number, a range `a-b`, a step
+    /// `*/n`, `a-b/n` or `a/n` (from `a` to the maximum), or a comma-separated list of those; `@hourly`,
+    /// `@daily` and `@weekly` are shorthands. As with cron, a day matches if either of the day fields
+    /// matches when both are restricted; a day field starting with `*` (e.g. `*/2`) is not.
+    ///
+    /// A background thread checks the schedules once a minute. Thread-local caches are flushed on the
+    /// next call of the memoized function in each thread.
+    pub fn schedule_flush(fn_tag: &str, spec: &str) -> Result<(), InvalidSchedule> {
+        let spec = Spec::parse(spec)?;
+        SCHEDULES.lock().unwrap().push(Schedule {
+            tag: fn_tag.to_string(),
+            spec,
+        });
+        SCHEDULER.call_once(|| {
+            thread::Builder::new()
+                .name("memoize-schedule".to_string())
+                .spawn(run_schedules)
+                .expect("memoize: failed to start the schedule thread");
+        });
+        Ok(())
+    }
+
+    fn run_schedules() {
+        loop {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            // Wake up at the start of the next minute.
+            thread::sleep(Duration::from_secs(60 - now % 60));
+            let minute = (now / 60 + 1) * 60;
+            let due: Vec<String> = SCHEDULES
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|schedule| schedule.spec.matches(minute))
+                .map(|schedule| schedule.tag.clone())
+                .collect();
+            for tag in due {
+                flush_by_tag(&tag);
+            }
+        }
+    }
+
+    /// Flushes the caches of the memoized functions matching `fn_tag` right away, as a scheduled
+    /// flush would.
+    pub fn flush_by_tag(fn_tag: &str) {
+        // Copy the flushers, so that flushing may memoize (and register) further functions.
+        let flushers: Vec<(&'static str, fn())> = FLUSHERS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|flusher| (flusher.path, flusher.flush))
+            .collect();
+        for (path, flush) in flushers {
+            let name = path.rsplit("::").next().unwrap_or(path);
+            if fn_tag == path || fn_tag == name {
+                flush();
+            }
+        }
+    }
+
+    /// Flushes the caches of the memoized functions matching any of `fn_tags`, as `flush_by_tag`.
+    pub fn flush_tags<T: AsRef<str>>(fn_tags: &[T]) {
+        for fn_tag in fn_tags {
+            flush_by_tag(fn_tag.as_ref());
+        }
+    }
+
+    /// Flushes the caches of the memoized functions matching any of `fn_tags` at the boundaries of a
+    /// request: right away, and again when the returned guard is dropped. Values computed while
+    /// handling the request are shared within it, but never seen by the next request.
+    ///
+    /// ```ignore
+    /// fn handle(request: Request) -> Response {
+    ///     let _flush = memoize::flush_request(&["account_balance", "pending_orders"]);
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// As with `flush_by_tag`, concurrent requests share the caches of `SharedCache` functions, and
+    /// thread-local caches are flushed on the next call in each thread; use `TaskLocal` (or
+    /// `RequestScoped`) functions to isolate concurrent requests from each other.
+    pub fn flush_request<T: AsRef<str>>(fn_tags: &[T]) -> RequestFlush {
+        let fn_tags: Vec<String> = fn_tags.iter().map(|tag| tag.as_ref().to_string()).collect();
+        flush_tags(&fn_tags);
+        RequestFlush { fn_tags }
+    }
+
+    /// Flushes its tags again when dropped; see `flush_request`.
+    #[must_use = "the tags are flushed again when the guard is dropped"]
+    #[derive(Debug)]
+    pub struct RequestFlush {
+        fn_tags: Vec<String>,
+    }
+
+    impl Drop for RequestFlush {
+        fn drop(&mut self) {
+            flush_tags(&self.fn_tags);
+        }
+    }
+
+    // The values allowed in each field, as bit sets.
+    struct Spec {
+        minutes: u64,
+        hours: u64,
+        days: u64,
+        months: u64,
+        weekdays: u64,
+        any_day: bool,
+        any_weekday: bool,
+    }
+
+    impl Spec {
+        fn parse(spec: &str) -> Result<Spec, InvalidSchedule> {
+            let spec = match spec.trim() {
+                "@hourly" => "0 * * * *",
+                "@daily" | "@midnight" => "0 0 * * *",
+                "@weekly" => "0 0 * * 0",
+                spec => spec,
+            };
+            let fields: Vec<&str> = spec.split_whitespace().collect();
+            if fields.len() != 5 {
+                return Err(InvalidSchedule(format!(
+                    "expected 5 fields, found {}",
+                    fields.len()
+                )));
+            }
+            Ok(Spec {
+                minutes: parse_field(fields[0], 0, 59)?,
+                hours: parse_field(fields[1], 0, 23)?,
+                days: parse_field(fields[2], 1, 31)?,
+                months: parse_field(fields[3], 1, 12)?,
+                // 7 is Sunday as well.
+                weekdays: {
+                    let weekdays = parse_field(fields[4], 0, 7)?;
+                    (weekdays | weekdays >> 7) & 0x7f
+                },
+                any_day: fields[2].starts_with('*'),
+                any_weekday: fields[4].starts_with('*'),
+            })
+        }
+
+        // Whether the minute starting at `secs` (since the Unix epoch) matches.
+        fn matches(&self, secs: u64) -> bool {
+            let (minute, hour) = ((secs / 60) % 60, (secs / 3600) % 24);
+            let days = secs / 86400;
+            let (_, month, day) = civil_from_days(days);
+            // 1970-01-01 was a Thursday.
+            let weekday = (days + 4) % 7;
+            let day_matches = match (self.any_day, self.any_weekday) {
+                (false, false) => self.days >> day & 1 == 1 || self.weekdays >> weekday & 1 == 1,
+                _ => self.days >> day & 1 == 1 && self.weekdays >> weekday & 1 == 1,
+            };
+            self.minutes >> minute & 1 == 1
+                && self.hours >> hour & 1 == 1
+                && self.months >> month & 1 == 1
+                && day_matches
+        }
+    }
+
+    fn parse_field(field: &str, min: u64, max: u64) -> Result<u64, InvalidSchedule> {
+        let invalid = || InvalidSchedule(format!("invalid field `{}`", field));
+        let number = |n: &str| match n.parse::<u64>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(invalid()),
+        };
+        let mut bits = 0;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, Some(step.parse::<u64>().map_err(|_| invalid())?)),
+                None => (part, None),
+            };
+            if step == Some(0) {
+                return Err(invalid());
+            }
+            let (from, to) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((from, to)) => (number(from)?, number(to)?),
+                    // With a step, a single value is the start of the range up to the maximum.
+                    None if step.is_some() => (number(range)?, max),
+                    None => (number(range)?, number(range)?),
+                },
+            };
+            if from > to {
+                return Err(invalid());
+            }
+            for value in (from..=to).step_by(step.unwrap_or(1) as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(bits)
+    }
+
+    // The (year, month, day) of a number of days since 1970-01-01, in the proleptic Gregorian
+    // calendar.
+    fn civil_from_days(days: u64) -> (u64, u64, u64) {
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        (year, month, day)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // The start of a minute, as seconds since the Unix epoch.
+        fn at(days: u64, hour: u64, minute: u64) -> u64 {
+            (days * 24 + hour) * 3600 + minute * 60
+        }
+
+        // Days since 1970-01-01 of some days of January 2024, which started on a Monday.
+        const MON_JAN_1: u64 = 19_723;
+        const FRI_JAN_5: u64 = MON_JAN_1 + 4;
+        const SUN_JAN_7: u64 = MON_JAN_1 + 6;
+        const SAT_JAN_13: u64 = MON_JAN_1 + 12;
+
+        fn values(bits: u64) -> Vec<u64> {
+            (0..64).filter(|value| bits >> value & 1 == 1).collect()
+        }
+
+        #[test]
+        fn fields() {
+            assert_eq!(
+                values(parse_field("*", 1, 12).unwrap()),
+                (1..=12).collect::<Vec<_>>()
+            );
+            assert_eq!(values(parse_field("7", 0, 59).unwrap()), [7]);
+            assert_eq!(values(parse_field("3-6", 0, 59).unwrap()), [3, 4, 5, 6]);
+            assert_eq!(values(parse_field("1,4-5,9", 0, 59).unwrap()), [1, 4, 5, 9]);
+        }
+
+        #[test]
+        fn steps() {
+            assert_eq!(values(parse_field("*/15", 0, 59).unwrap()), [0, 15, 30, 45]);
+            assert_eq!(
+                values(parse_field("10-30/10", 0, 59).unwrap()),
+                [10, 20, 30]
+            );
+            assert_eq!(values(parse_field("5/15", 0, 59).unwrap()), [5, 20, 35, 50]);
+            assert_eq!(values(parse_field("*/2", 1, 31).unwrap())[..3], [1, 3, 5]);
+        }
+
+        #[test]
+        fn invalid_specs() {
+            for field in ["", "60", "6-3", "*/0", "x", "1-", "5/"] {
+                assert!(parse_field(field, 0, 59).is_err(), "{:?}", field);
+            }
+            assert!(Spec::parse("* * * *").is_err());
+            assert!(Spec::parse("0 0 0 * *").is_err());
+            assert!(Spec::parse("0 0 * * 8").is_err());
+        }
+
+        #[test]
+        fn calendar() {
+            assert_eq!(civil_from_days(0), (1970, 1, 1));
+            assert_eq!(civil_from_days(MON_JAN_1), (2024, 1, 1));
+            assert_eq!(civil_from_days(MON_JAN_1 + 59), (2024, 2, 29));
+        }
+
+        #[test]
+        fn minutes_and_hours() {
+            let spec = Spec::parse("*/15 9-17 * * *").unwrap();
+            assert!(spec.matches(at(MON_JAN_1, 9, 0)));
+            assert!(spec.matches(at(MON_JAN_1, 17, 45)));
+            assert!(!spec.matches(at(MON_JAN_1, 9, 5)));
+            assert!(!spec.matches(at(MON_JAN_1, 18, 0)));
+
+            let daily = Spec::parse("@daily").unwrap();
+            assert!(daily.matches(at(FRI_JAN_5, 0, 0)));
+            assert!(!daily.matches(at(FRI_JAN_5, 1, 0)));
+        }
+
+        #[test]
+        fn sunday_is_0_and_7() {
+            for spec in ["0 0 * * 0", "0 0 * * 7", "0 0 * * 6-7", "@weekly"] {
+                let spec = Spec::parse(spec).unwrap();
+                assert!(spec.matches(at(SUN_JAN_7, 0, 0)));
+                assert!(!spec.matches(at(MON_JAN_1, 0, 0)));
+            }
+        }
+
+        #[test]
+        fn restricted_day_fields_match_either() {
+            // The 13th, or any Friday.
+            let spec = Spec::parse("0 0 13 * 5").unwrap();
+            assert!(spec.matches(at(FRI_JAN_5, 0, 0)));
+            assert!(spec.matches(at(SAT_JAN_13, 0, 0)));
+            assert!(!spec.matches(at(SUN_JAN_7, 0, 0)));
+        }
+
+        #[test]
+        fn starred_day_fields_match_both() {
+            // Only the 13th.
+            let spec = Spec::parse("0 0 13 * *").unwrap();
+            assert!(spec.matches(at(SAT_JAN_13, 0, 0)));
+            assert!(!spec.matches(at(FRI_JAN_5, 0, 0)));
+
+            // Mondays on odd days, as the day field starts with `*`.
+            let spec = Spec::parse("0 0 */2 * 1").unwrap();
+            assert!(spec.matches(at(MON_JAN_1, 0, 0)));
+            assert!(!spec.matches(at(MON_JAN_1 + 7, 0, 0)));
+            assert!(!spec.matches(at(MON_JAN_1 + 2, 0, 0)));
+
+            // The first of a month which is a Sunday, as the weekday field starts with `*`.
+            let spec = Spec::parse("0 0 1 * */7").unwrap();
+            let sun_sep_1 = MON_JAN_1 + 31 + 29 + 31 + 30 + 31 + 30 + 31 + 31;
+            assert!(spec.matches(at(sun_sep_1, 0, 0)));
+            assert!(!spec.matches(at(MON_JAN_1, 0, 0)));
+            assert!(!spec.matches(at(SUN_JAN_7, 0, 0)));
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Caches that live as long as a scope, rather than the program.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::rc::Rc;
+#[cfg(feature = "tokio")]
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockResult},
+};
+
+thread_local! {
+    /// The caches of all `Scoped` functions in the scopes of this thread, innermost last, by the
+    /// address of their store.
+    static SCOPES: RefCell<Vec<HashMap<usize, Rc<dyn Any>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` with fresh caches for all `Scoped` functions it calls on this thread, which are dropped
+/// once it returns.
+///
+/// Scopes can be nested; calls use the caches of the innermost one. Outside of a scope, `Scoped`
+/// functions don't cache anything.
+pub fn scope<R>(f: impl FnOnce() -> R) -> R {
+    struct Exit;
+
+    impl Drop for Exit {
+        fn drop(&mut self) {
+            // Dropped after releasing the borrow, in case a value's `drop()` calls a `Scoped`
+            // function.
+            let caches = SCOPES.try_with(|scopes| scopes.borrow_mut().pop());
+            drop(caches);
+        }
+    }
+
+    SCOPES.with(|scopes| scopes.borrow_mut().push(HashMap::new()));
+    let _exit = Exit;
+    f()
+}
+
+/// The store of a `Scoped` function: its cache within the innermost `scope()` of the current thread.
+pub struct ScopedStore<C> {
+    init: fn() -> C,
+}
+
+impl<C: 'static> ScopedStore<C> {
+    pub const fn new(init: fn() -> C) -> Self {
+        ScopedStore { init }
+    }
+
+    pub fn with<R>(&'static self, f: impl FnOnce(&RefCell<C>) -> R) -> R {
+        let id = self as *const Self as usize;
+        let cache = SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            let caches = scopes.last_mut()?;
+            let cache = caches
+                .entry(id)
+                .or_insert_with(|| Rc::new(RefCell::new((self.init)())));
+            // Every store has its own id, and thus a cache of its own type.
+            Some(Rc::clone(cache).downcast::<RefCell<C>>().unwrap())
+        });
+        match cache {
+            Some(cache) => f(&cache),
+            None => f(&RefCell::new((self.init)())),
+        }
+    }
+
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&RefCell<C>) -> R) -> Result<R, Infallible> {
+        Ok(self.with(f))
+    }
+}
+
+/// The caches of all `TaskLocal` functions in a task scope, by the address of their store.
+#[cfg(feature = "tokio")]
+pub type TaskCaches = Mutex<HashMap<usize, Arc<dyn Any + Send + Sync>>>;
+
+/// The cache of a function within a task scope, accessed like a `RefCell`.
+#[cfg(feature = "tokio")]
+pub struct ScopedCell<C>(Mutex<C>);
+
+#[cfg(feature = "tokio")]
+impl<C> ScopedCell<C> {
+    fn new(cache: C) -> Self {
+        ScopedCell(Mutex::new(cache))
+    }
+
+    pub fn borrow(&self) -> MutexGuard<'_, C> {
+        self.borrow_mut()
+    }
+
+    pub fn borrow_mut(&self) -> MutexGuard<'_, C> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn try_borrow_mut(&self) -> TryLockResult<MutexGuard<'_, C>> {
+        self.0.try_lock()
+    }
+}
+
+/// Returns the cache of the store `id` in `caches`, creating it if necessary.
+#[cfg(feature = "tokio")]
+fn cache_in<C: Send + 'static>(
+    caches: &TaskCaches,
+    id: usize,
+    init: fn() -> C,
+) -> Arc<ScopedCell<C>> {
+    let cache = Arc::clone(
+        caches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(id)
+            .or_insert_with(|| Arc::new(ScopedCell::new(init()))),
+    );
+    // Every store has its own id, and thus a cache of its own type.
+    cache.downcast::<ScopedCell<C>>().unwrap()
+}
+
+#[cfg(feature = "tokio")]
+tokio::task_local! {
+    static TASK_CACHES: TaskCaches;
+}
+
+/// Runs a future with fresh caches for all `TaskLocal` functions it calls, which are dropped once it
+/// completes.
+///
+/// Outside of such a scope, `TaskLocal` functions don't cache anything.
+#[cfg(feature = "tokio")]
+pub async fn task_scope<F: Future>(future: F) -> F::Output {
+    scoped_future(future).await
+}
+
+/// Wraps a future to run in a task scope of its own.
+#[cfg(feature = "tokio")]
+pub(crate) fn scoped_future<F: Future>(
+    future: F,
+) -> tokio::task::futures::TaskLocalFuture<TaskCaches, F> {
+    TASK_CACHES.scope(TaskCaches::default(), future)
+}
+
+/// The store of a `TaskLocal` function: its cache within the current `task_scope()`.
+#[cfg(feature = "tokio")]
+pub struct TaskLocalStore<C> {
+    init: fn() -> C,
+}
+
+#[cfg(feature = "tokio")]
+impl<C: Send + 'static> TaskLocalStore<C> {
+    pub const fn new(init: fn() -> C) -> Self {
+        TaskLocalStore { init }
+    }
+
+    pub fn with<R>(&'static self, f: impl FnOnce(&ScopedCell<C>) -> R) -> R {
+        let id = self as *const Self as usize;
+        match TASK_CACHES.try_with(|caches| cache_in(caches, id, self.init)) {
+            Ok(cache) => f(&cache),
+            Err(_) => f(&ScopedCell::new((self.init)())),
+        }
+    }
+
+    pub fn try_with<R>(
+        &'static self,
+        f: impl FnOnce(&ScopedCell<C>) -> R,
+    ) -> Result<R, Infallible> {
+        Ok(self.with(f))
+    }
+}
@@ -0,0 +1,124 @@
+//! External stores composed with the in-memory cache, used by `Secondary`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, Once};
+use std::thread;
+
+/// An external key-value store (e.g. Redis, or a directory on disk) consulted by memoized functions
+/// on cache misses, before computing a value, and written through when a value is computed.
+///
+/// Keys and values are serialized with `bincode`. Keys are prefixed with the path of the memoized
+/// function, so that one store can serve several functions. Errors are up to the implementation:
+/// a failing `get` can return `None`, in which case the value is computed.
+pub trait SecondaryStore {
+    /// Returns the value stored for `key`, if any.
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+    /// Stores `value` for `key`.
+    fn put(&self, key: &[u8], value: &[u8]);
+}
+
+fn store_key<K: Serialize + ?Sized>(function: &str, key: &K) -> Vec<u8> {
+    let mut store_key = function.as_bytes().to_vec();
+    store_key.push(0);
+    bincode::serialize_into(&mut store_key, key).expect("memoize: failed to serialize key");
+    store_key
+}
+
+/// Looks up the value of `function` for `key`. Values which can't be deserialized (e.g. written by
+/// an older version of the function) are ignored.
+pub fn get<S, K, V>(store: &S, function: &str, key: &K) -> Option<V>
+where
+    S: SecondaryStore + ?Sized,
+    K: Serialize + ?Sized,
+    V: DeserializeOwned,
+{
+    let value = store.get(&store_key(function, key))?;
+    bincode::deserialize(&value).ok()
+}
+
+/// Stores the value of `function` for `key`.
+pub fn put<S, K, V>(store: &S, function: &str, key: &K, value: &V)
+where
+    S: SecondaryStore + ?Sized,
+    K: Serialize + ?Sized,
+    V: Serialize + ?Sized,
+{
+    let value = bincode::serialize(value).expect("memoize: failed to serialize value");
+    store.put(&store_key(function, key), &value);
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    // Queued and running jobs.
+    pending: usize,
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    jobs: VecDeque::new(),
+    pending: 0,
+});
+static QUEUED: Condvar = Condvar::new();
+static DRAINED: Condvar = Condvar::new();
+static WORKER: Once = Once::new();
+
+/// Queues storing the value of `function` for `key`, for `WriteBehind`. The key and value are
+/// serialized right away, and written by a background thread.
+pub fn put_behind<S, K, V>(store: &'static S, function: &str, key: &K, value: &V)
+where
+    S: SecondaryStore + Sync + ?Sized,
+    K: Serialize + ?Sized,
+    V: Serialize + ?Sized,
+{
+    let key = store_key(function, key);
+    let value = bincode::serialize(value).expect("memoize: failed to serialize value");
+    WORKER.call_once(|| {
+        thread::Builder::new()
+            .name("memoize-write-behind".to_string())
+            .spawn(write_behind)
+            .expect("memoize: failed to start the write-behind thread");
+    });
+    let mut queue = QUEUE.lock().unwrap();
+    queue
+        .jobs
+        .push_back(Box::new(move || store.put(&key, &value)));
+    queue.pending += 1;
+    QUEUED.notify_one();
+}
+
+/// Waits until all values queued by `WriteBehind` functions have been written to their secondary
+/// stores.
+///
+/// Queued values are lost if the process exits before they are written; call this before exiting,
+/// e.g. at the end of `main`.
+pub fn flush_write_behind() {
+    let mut queue = QUEUE.lock().unwrap();
+    while queue.pending > 0 {
+        queue = DRAINED.wait(queue).unwrap();
+    }
+}
+
+fn write_behind() {
+    loop {
+        let job = {
+            let mut queue = QUEUE.lock().unwrap();
+            loop {
+                match queue.jobs.pop_front() {
+                    Some(job) => break job,
+                    None => queue = QUEUED.wait(queue).unwrap(),
+                }
+            }
+        };
+        // A panicking store loses the value, but not the thread.
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+        let mut queue = QUEUE.lock().unwrap();
+        queue.pending -= 1;
+        if queue.pending == 0 {
+            DRAINED.notify_all();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Seed files of precomputed cache entries, loaded by `memoized_load_seed_<function name>()`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Seed files start with this magic.
+const MAGIC: &[u8; 8] = b"memoseed";
+
+/// Writes a seed file of precomputed entries, e.g. from an offline batch job, for
+/// `memoized_load_seed_<function name>()` (generated with `#[memoize(Seed)]`).
+///
+/// Keys are given like the memoized arguments: as a tuple, or the bare argument for single-argument
+/// functions.
+pub fn write_seed<K, V, P, I>(path: P, entries: I) -> io::Result<()>
+where
+    K: Serialize,
+    V: Serialize,
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    for entry in entries {
+        bincode::serialize_into(&mut file, &entry).map_err(|error| into_io_error(*error))?;
+    }
+    file.flush()
+}
+
+/// Reads the entries of a seed file written by [`write_seed`].
+pub fn read_seed<K, V, P>(path: P) -> io::Result<Vec<(K, V)>>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a memoize seed file",
+        ));
+    }
+    let mut entries = vec![];
+    while !file.fill_buf()?.is_empty() {
+        entries.push(bincode::deserialize_from(&mut file).map_err(|error| into_io_error(*error))?);
+    }
+    Ok(entries)
+}
+
+fn into_io_error(error: bincode::ErrorKind) -> io::Error {
+    match error {
+        bincode::ErrorKind::Io(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
@@ -0,0 +1,13 @@
+//! Serialization of cached values and keys, used by `SerializeValues` and `SerdeKeys`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Serializes a value for storage in a cache.
+pub fn to_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+    bincode::serialize(value).expect("memoize: failed to serialize value")
+}
+
+/// Deserializes a value serialized by [`to_bytes`].
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> T {
+    bincode::deserialize(bytes).expect("memoize: failed to deserialize value")
+}
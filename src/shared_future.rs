@@ -0,0 +1,75 @@
+//! Support for `SharedFuture` caches.
+
+/// Discards a cached future if computing it panics, so that the next caller retries instead of
+/// awaiting a poisoned future.
+///
+/// Held by the cached future while it is computed; `discard` removes the entry for `key` from the
+/// cache.
+pub struct DiscardOnUnwind<K> {
+    key: K,
+    discard: fn(&K),
+}
+
+impl<K> DiscardOnUnwind<K> {
+    pub fn new(key: K, discard: fn(&K)) -> Self {
+        DiscardOnUnwind { key, discard }
+    }
+
+    /// Discards the cached future now, e.g. because it resolved to an error.
+    pub fn discard(&self) {
+        (self.discard)(&self.key)
+    }
+}
+
+impl<K> Drop for DiscardOnUnwind<K> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.discard();
+        }
+    }
+}
+
+/// Discards a cached future if the caller which created it stops awaiting it before it completes,
+/// e.g. because its task was cancelled, so that an abandoned computation isn't kept in the cache.
+/// Callers awaiting the same future meanwhile still complete it, but the value isn't cached.
+///
+/// Held by the creating caller while it awaits the future; `complete` disarms it.
+pub struct DiscardOnCancel(Option<Box<dyn FnOnce() + Send>>);
+
+impl DiscardOnCancel {
+    /// The future completed: it stays cached.
+    pub fn complete(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for DiscardOnCancel {
+    fn drop(&mut self) {
+        if let Some(discard) = self.0.take() {
+            discard();
+        }
+    }
+}
+
+std::thread_local! {
+    static CREATED: std::cell::RefCell<Option<DiscardOnCancel>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Records that the memoizing function created a new future for `key`, instead of finding one in
+/// the cache.
+pub fn mark_created<K: Send + 'static>(key: K, discard: fn(&K)) {
+    let guard = DiscardOnCancel(Some(Box::new(move || discard(&key))));
+    // Not a cancellation: the previous guard (e.g. of a warmed future) isn't dropped armed.
+    if let Some(previous) = CREATED.with(|created| created.replace(Some(guard))) {
+        previous.complete();
+    }
+}
+
+/// Calls the memoizing function `get`, and returns its future, with a guard if `get` created it.
+pub fn track_created<T>(get: impl FnOnce() -> T) -> (T, Option<DiscardOnCancel>) {
+    if let Some(previous) = CREATED.with(|created| created.take()) {
+        previous.complete();
+    }
+    let future = get();
+    (future, CREATED.with(|created| created.take()))
+}
@@ -0,0 +1,138 @@
+//! A count-min sketch estimating how often keys were requested, used by `AdmitAfter`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+const ROWS: usize = 4;
+const WIDTH: usize = 4096;
+
+/// Approximate request counts of keys, in a fixed amount of memory.
+///
+/// Each key increments one saturating counter in each of four rows; its estimated count is the
+/// minimum of its counters, which may overestimate, but never underestimates, the true count. To
+/// let the counts of formerly popular keys decay, all counters are halved once the sketch has
+/// recorded ten times as many requests as it has counters per row.
+pub struct FrequencySketch {
+    counters: Vec<u8>,
+    recorded: usize,
+}
+
+impl FrequencySketch {
+    /// Creates an empty sketch; memory is only allocated on the first request.
+    pub const fn new() -> Self {
+        FrequencySketch {
+            counters: Vec::new(),
+            recorded: 0,
+        }
+    }
+
+    /// Records a request for `key`, and returns its estimated number of requests so far.
+    pub fn record<Q: Hash + ?Sized>(&mut self, key: &Q) -> u8 {
+        if self.counters.is_empty() {
+            self.counters = vec![0; ROWS * WIDTH];
+        }
+        self.recorded += 1;
+        if self.recorded >= 10 * WIDTH {
+            for counter in &mut self.counters {
+                *counter /= 2;
+            }
+            self.recorded /= 2;
+        }
+        let mut estimate = u8::MAX;
+        for row in 0..ROWS {
+            let counter = &mut self.counters[row * WIDTH + Self::column(key, row)];
+            *counter = counter.saturating_add(1);
+            estimate = estimate.min(*counter);
+        }
+        estimate
+    }
+
+    /// Estimated number of requests for `key`.
+    pub fn estimate<Q: Hash + ?Sized>(&self, key: &Q) -> u8 {
+        if self.counters.is_empty() {
+            return 0;
+        }
+        (0..ROWS)
+            .map(|row| self.counters[row * WIDTH + Self::column(key, row)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Forgets all recorded requests.
+    pub fn clear(&mut self) {
+        self.counters = Vec::new();
+        self.recorded = 0;
+    }
+
+    fn column<Q: Hash + ?Sized>(key: &Q, row: usize) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        hasher.finish() as usize % WIDTH
+    }
+}
+
+impl Default for FrequencySketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn estimates_never_undercount() {
+        let mut sketch = FrequencySketch::new();
+        let mut counts = HashMap::new();
+        // Many more keys than counters per row, with skewed counts, so that counters collide.
+        for round in 0..20u32 {
+            for key in 0..(10_000 / (round + 1)) {
+                let count = counts.entry(key).or_insert(0u8);
+                *count += 1;
+                assert!(sketch.record(&key) >= *count);
+            }
+        }
+        assert!(sketch.recorded < 10 * WIDTH);
+        for (key, count) in &counts {
+            assert!(sketch.estimate(key) >= *count, "{}", key);
+        }
+    }
+
+    #[test]
+    fn counts_saturate() {
+        let mut sketch = FrequencySketch::new();
+        for _ in 0..300 {
+            sketch.record("key");
+        }
+        assert_eq!(sketch.estimate("key"), u8::MAX);
+    }
+
+    #[test]
+    fn counts_decay() {
+        let mut sketch = FrequencySketch::new();
+        for _ in 0..100 {
+            sketch.record("popular");
+        }
+        assert!(sketch.estimate("popular") >= 100);
+        // Halving happens once 10 * WIDTH requests were recorded in total.
+        for key in 0..10 * WIDTH {
+            sketch.record(&key);
+        }
+        let estimate = sketch.estimate("popular");
+        assert!((50..100).contains(&estimate), "{}", estimate);
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let mut sketch = FrequencySketch::new();
+        assert_eq!(sketch.estimate("key"), 0);
+        sketch.record("key");
+        assert!(sketch.estimate("key") >= 1);
+        sketch.clear();
+        assert_eq!(sketch.estimate("key"), 0);
+        assert!(sketch.counters.is_empty());
+    }
+}
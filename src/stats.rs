@@ -0,0 +1,104 @@
+//! Hit and miss statistics of memoized functions.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Hit and miss counts of a memoized function, as returned by `memoized_stats_<function name>()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Calls answered from the cache.
+    pub hits: u64,
+    /// Calls that computed the value.
+    pub misses: u64,
+}
+
+impl Stats {
+    /// Returns the share of calls answered from the cache, or 0 if there were no calls.
+    pub fn hit_rate(&self) -> f64 {
+        let calls = self.hits + self.misses;
+        if calls == 0 {
+            0.0
+        } else {
+            self.hits as f64 / calls as f64
+        }
+    }
+}
+
+// The number of counter slots of a registry. Threads are spread over the slots, so that counting
+// rarely contends on a cache line.
+const SLOTS: usize = 8;
+
+/// The counters of one slot.
+#[repr(align(64))]
+struct Slot {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Slot {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+}
+
+/// The slot of the calling thread, assigned round-robin on its first count.
+fn slot() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static SLOT: usize = NEXT.fetch_add(1, Ordering::Relaxed) % SLOTS;
+    }
+    // Threads that are exiting count in the first slot.
+    SLOT.try_with(|slot| *slot).unwrap_or(0)
+}
+
+/// Collects the counters of all threads calling a memoized function. It is a plain `static` of
+/// each function, and the counts of all threads are kept, including those which have exited.
+#[doc(hidden)]
+pub struct StatsRegistry {
+    slots: [Slot; SLOTS],
+}
+
+impl StatsRegistry {
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const SLOT: Slot = Slot::new();
+        StatsRegistry {
+            slots: [SLOT; SLOTS],
+        }
+    }
+
+    /// Counts hits and misses of the calling thread.
+    pub fn record(&self, hits: u64, misses: u64) {
+        let slot = &self.slots[slot()];
+        slot.hits.fetch_add(hits, Ordering::Relaxed);
+        slot.misses.fetch_add(misses, Ordering::Relaxed);
+    }
+
+    /// Sums the counters of all threads.
+    pub fn sum(&self) -> Stats {
+        let mut sum = Stats::default();
+        for slot in &self.slots {
+            sum.hits += slot.hits.load(Ordering::Relaxed);
+            sum.misses += slot.misses.load(Ordering::Relaxed);
+        }
+        sum
+    }
+}
+
+impl Default for StatsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts hits and misses of a memoized function, and reports them to the hooks (and
+/// OpenTelemetry). Called by the code generated by `memoize`.
+#[doc(hidden)]
+pub fn record_stats(stats: &StatsRegistry, function: &'static str, hits: u64, misses: u64) {
+    stats.record(hits, misses);
+    crate::hooks::record(function, hits, misses);
+    #[cfg(feature = "opentelemetry")]
+    crate::otel::record(function, hits, misses);
+}
@@ -0,0 +1,29 @@
+//! Support for `CollectStream` caches.
+
+use futures::stream::{self, Stream, StreamExt};
+
+/// Yields the items of `stream`, and passes all of them to `store` once it is exhausted.
+///
+/// A stream which is dropped before it is exhausted is not stored.
+pub fn collect_into<S, T, F>(stream: S, store: F) -> impl Stream<Item = T>
+where
+    S: Stream<Item = T>,
+    T: Clone,
+    F: FnOnce(Vec<T>),
+{
+    let state = (Box::pin(stream), Vec::new(), Some(store));
+    stream::unfold(state, |(mut stream, mut items, mut store)| async move {
+        match stream.next().await {
+            Some(item) => {
+                items.push(item.clone());
+                Some((item, (stream, items, store)))
+            }
+            None => {
+                if let Some(store) = store.take() {
+                    store(items);
+                }
+                None
+            }
+        }
+    })
+}
@@ -0,0 +1,141 @@
+//! Tower layers running every request in its own memoization scope, or flushing caches around it.
+
+use std::task::{Context, Poll};
+#[cfg(feature = "schedule")]
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::scope::{scoped_future, TaskCaches};
+
+pub use tower_layer::Layer;
+pub use tower_service::Service;
+
+/// Runs every request of the wrapped service in a `memoize::task_scope()`, so that the
+/// `RequestScoped` (or `TaskLocal`) functions called while handling it share a cache, which is
+/// dropped once the response is ready.
+///
+/// ```ignore
+/// let app = Router::new()
+///     .route("/users/{id}", get(show_user))
+///     .layer(memoize::tower::RequestScopeLayer);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestScopeLayer;
+
+impl<S> Layer<S> for RequestScopeLayer {
+    type Service = RequestScope<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestScope { inner }
+    }
+}
+
+/// A service running every request in its own memoization scope; see `RequestScopeLayer`.
+#[derive(Clone, Debug)]
+pub struct RequestScope<S> {
+    inner: S,
+}
+
+impl<S> RequestScope<S> {
+    pub fn new(inner: S) -> Self {
+        RequestScope { inner }
+    }
+}
+
+impl<S: Service<Request>, Request> Service<Request> for RequestScope<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = tokio::task::futures::TaskLocalFuture<TaskCaches, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The handler's future is created outside of the scope, but only runs within it.
+        scoped_future(self.inner.call(request))
+    }
+}
+
+/// Flushes the caches of the memoized functions matching the given tags (as
+/// `memoize::flush_by_tag`) before every request of the wrapped service, and again once its
+/// response is ready (or the request is cancelled): values are cached within a request, but never
+/// across requests. Requires the `schedule` feature.
+///
+/// ```ignore
+/// let app = Router::new()
+///     .route("/checkout", post(checkout))
+///     .layer(memoize::tower::FlushTagsLayer::new(["account_balance", "pending_orders"]));
+/// ```
+#[cfg(feature = "schedule")]
+#[derive(Clone, Debug)]
+pub struct FlushTagsLayer {
+    fn_tags: Arc<[String]>,
+}
+
+#[cfg(feature = "schedule")]
+impl FlushTagsLayer {
+    pub fn new<I: IntoIterator<Item = T>, T: Into<String>>(fn_tags: I) -> Self {
+        FlushTagsLayer {
+            fn_tags: fn_tags.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "schedule")]
+impl<S> Layer<S> for FlushTagsLayer {
+    type Service = FlushTags<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FlushTags {
+            inner,
+            fn_tags: self.fn_tags.clone(),
+        }
+    }
+}
+
+/// A service flushing tagged caches around every request; see `FlushTagsLayer`.
+#[cfg(feature = "schedule")]
+#[derive(Clone, Debug)]
+pub struct FlushTags<S> {
+    inner: S,
+    fn_tags: Arc<[String]>,
+}
+
+#[cfg(feature = "schedule")]
+impl<S: Service<Request>, Request> Service<Request> for FlushTags<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FlushTagsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let flush = crate::flush_request(&self.fn_tags);
+        FlushTagsFuture {
+            inner: Box::pin(self.inner.call(request)),
+            flush: Some(flush),
+        }
+    }
+}
+
+/// The response future of `FlushTags`, flushing the tags again once it is ready or dropped.
+#[cfg(feature = "schedule")]
+pub struct FlushTagsFuture<F> {
+    inner: Pin<Box<F>>,
+    flush: Option<crate::RequestFlush>,
+}
+
+#[cfg(feature = "schedule")]
+impl<F: Future> Future for FlushTagsFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let output = self.inner.as_mut().poll(cx);
+        if output.is_ready() {
+            self.flush = None;
+        }
+        output
+    }
+}
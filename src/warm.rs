@@ -0,0 +1,26 @@
+//! Warming of caches before serving traffic.
+
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref WARMERS: Mutex<Vec<fn()>> = Mutex::new(Vec::new());
+}
+
+/// Registers a function computing the warm list of a memoized function. Called by the code
+/// generated for `WarmAtStartup`.
+#[doc(hidden)]
+pub fn register_warmer(warmer: fn()) {
+    WARMERS.lock().unwrap().push(warmer);
+}
+
+/// Computes the `Warm` lists of all functions memoized with `WarmAtStartup`.
+///
+/// Call this from `main` before serving traffic. Thread-local caches are only warmed for the calling
+/// thread, so this is mostly useful together with `SharedCache`.
+pub fn warm_all() {
+    // Copy the list so that warming may memoize (and register) further functions.
+    let warmers = WARMERS.lock().unwrap().clone();
+    for warmer in warmers {
+        warmer();
+    }
+}